@@ -0,0 +1,74 @@
+use crate::{body::BodyId, universe::Universe};
+use cgmath::InnerSpace;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// States scanned per frame, jumping further than this only checks where time ended up
+pub const SCAN_LIMIT: usize = 10_000;
+
+const TOAST_DURATION: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Alarm {
+    pub a: BodyId,
+    pub b: BodyId,
+    // Between centers
+    pub distance: f64,
+    #[serde(default)]
+    pub pause: bool,
+    // Alarms only go off when a pair comes within the distance, not for as long as it stays there
+    #[serde(skip)]
+    pub inside: bool,
+}
+
+impl Alarm {
+    pub fn separation(&self, universe: &Universe) -> Option<f64> {
+        Some((universe.position3(self.a)? - universe.position3(self.b)?).magnitude())
+    }
+}
+
+// Indices of the alarms that went off in this state. Thinned states are skipped
+pub fn check(alarms: &mut [Alarm], universe: &Universe) -> Vec<usize> {
+    let mut triggered = vec![];
+    for (index, alarm) in alarms.iter_mut().enumerate() {
+        let Some(separation) = alarm.separation(universe) else {
+            continue;
+        };
+        let inside = separation <= alarm.distance;
+        if inside && !alarm.inside {
+            triggered.push(index);
+        }
+        alarm.inside = inside;
+    }
+    triggered
+}
+
+pub struct Toast {
+    pub message: String,
+    pub remaining: f64,
+}
+
+impl Toast {
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+            remaining: TOAST_DURATION,
+        }
+    }
+}
+
+pub fn show_toasts(ctx: &egui::Context, toasts: &[Toast]) {
+    if toasts.is_empty() {
+        return;
+    }
+    egui::Area::new(egui::Id::new("Toasts"))
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 48.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            for toast in toasts {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(&toast.message);
+                });
+            }
+        });
+}
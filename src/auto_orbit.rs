@@ -0,0 +1,100 @@
+use crate::{body::Body, conics};
+use cgmath::InnerSpace;
+use eframe::egui;
+
+// Puts the selected body in orbit around the focused body, from where it is or at a chosen altitude
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoOrbit {
+    pub enabled: bool,
+    pub eccentricity: f64,
+    pub retrograde: bool,
+}
+
+impl AutoOrbit {
+    // Returns whether the body was moved or given a new velocity
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        body: &mut Body,
+        focus: Option<&Body>,
+        gravity: f64,
+        three_d: bool,
+        parser: impl Fn(&str) -> Option<f64> + Copy,
+    ) -> bool {
+        // Only called inside the part of the window that is disabled when the state can not be edited
+        let can_edit = ui.is_enabled();
+        let mut changed = false;
+        ui.checkbox(&mut self.enabled, "Auto Orbit");
+        if three_d && self.enabled {
+            ui.label("Only available for worlds without 3D");
+        } else if focus.is_none() && self.enabled && can_edit {
+            ui.label("Focus a body for auto orbit");
+        }
+        if let Some(focus) = focus
+            && self.enabled
+            && can_edit
+            && !three_d
+        {
+            let focused_to_body = body.pos - focus.pos;
+            let mut altitude = focused_to_body.magnitude() - focus.radius;
+            let mut apply = false;
+            ui.horizontal(|ui| {
+                let label = ui.label("Orbit Altitude:");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut altitude)
+                            .range(0.0..=f64::INFINITY)
+                            .speed(0.1)
+                            .custom_parser(parser)
+                            .suffix("m"),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Above the surface of the focused body")
+                    .changed()
+                    && focused_to_body.magnitude() > 0.0
+                {
+                    body.pos = focused_to_body.normalize_to(altitude + focus.radius) + focus.pos;
+                    apply = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Eccentricity:");
+                apply |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.eccentricity)
+                            .range(0.0..=0.99)
+                            .speed(0.01),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Zero is circular, otherwise the body starts at periapsis")
+                    .changed();
+            });
+            // Prograde turns the same way as the focused body spins
+            ui.horizontal(|ui| {
+                apply |= ui
+                    .radio_value(&mut self.retrograde, false, "Prograde")
+                    .changed();
+                apply |= ui
+                    .radio_value(&mut self.retrograde, true, "Retrograde")
+                    .changed();
+            });
+            apply |= ui
+                .button("Set Orbit Velocity")
+                .on_hover_text("Put the selected body in orbit from where it is now")
+                .clicked();
+            if apply {
+                let clockwise = (focus.spin < 0.0) != self.retrograde;
+                body.vel = focus.vel
+                    + conics::periapsis_velocity(
+                        gravity * (focus.mass(false) + body.mass(false)),
+                        (body.pos - focus.pos).truncate(),
+                        self.eccentricity,
+                        clockwise,
+                    )
+                    .extend(0.0);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -1,24 +1,259 @@
 use cgmath::*;
-use serde::{Deserialize, Serialize};
-use std::{f64::consts::PI, num::NonZeroUsize, ptr::NonNull};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{f64::consts::PI, num::NonZeroUsize, ptr::NonNull, sync::Arc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Body {
+pub struct BodyMeta {
     pub name: String,
-    pub pos: Vector2<f64>,
-    pub vel: Vector2<f64>,
-    pub radius: f64,
     pub density: f64,
     pub color: Vector3<f64>,
+    #[serde(default)]
+    pub high_accuracy: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub parent: Option<BodyId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BurnKind {
+    Edit,
+    Nudge,
+    Transfer,
+    Maneuver,
+}
+
+impl BurnKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            BurnKind::Edit => "Velocity Edit",
+            BurnKind::Nudge => "Nudge",
+            BurnKind::Transfer => "Transfer Burn",
+            BurnKind::Maneuver => "Maneuver Node",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Burn {
+    pub time: f64,
+    pub kind: BurnKind,
+    #[serde(deserialize_with = "deserialize_vector")]
+    pub delta_v: Vector3<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Body {
+    #[serde(flatten)]
+    pub meta: Arc<BodyMeta>,
+    // z stays zero outside 3D worlds
+    #[serde(deserialize_with = "deserialize_vector")]
+    pub pos: Vector3<f64>,
+    #[serde(deserialize_with = "deserialize_vector")]
+    pub vel: Vector3<f64>,
+    pub radius: f64,
+    #[serde(default)]
+    pub thrust: Option<Thrust>,
+    // Counterclockwise in radians per second
+    #[serde(default)]
+    pub spin: f64,
+    #[serde(default)]
+    pub angle: f64,
+    #[serde(default)]
+    pub charge: f64,
+    #[serde(default)]
+    pub landed: Option<Landing>,
+    // Instantaneous velocity changes, oldest first, kept per state so scrubbing back hides later ones
+    #[serde(default)]
+    pub burns: Vec<Burn>,
+}
+
+// Saves from before 3D worlds only have x and y
+fn deserialize_vector<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vector3<f64>, D::Error> {
+    #[derive(Deserialize)]
+    struct Saved {
+        x: f64,
+        y: f64,
+        #[serde(default)]
+        z: f64,
+    }
+    let Saved { x, y, z } = Saved::deserialize(deserializer)?;
+    Ok(Vector3::new(x, y, z))
+}
+
+// Resting on another body's surface, carried along as it moves and spins
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Landing {
+    pub on: BodyId,
+    // Radians counterclockwise from the other body's own angle
+    pub angle: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ThrustDirection {
+    Prograde,
+    Retrograde,
+    // Radians counterclockwise from the x axis
+    Fixed { angle: f64 },
+    // Square to the line to this body, the way the orbit around it already turns, so the orbit
+    // slowly spirals outward
+    Tangential { around: BodyId },
+}
+
+impl ThrustDirection {
+    pub fn name(self) -> &'static str {
+        match self {
+            ThrustDirection::Prograde => "Prograde",
+            ThrustDirection::Retrograde => "Retrograde",
+            ThrustDirection::Fixed { .. } => "Fixed Angle",
+            ThrustDirection::Tangential { .. } => "Tangential",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Thrust {
+    pub direction: ThrustDirection,
+    pub acceleration: f64,
+    // Delta-v left to spend, unlimited when None
+    pub fuel: Option<f64>,
+    // Delta-v the engine has spent so far
+    #[serde(default)]
+    pub spent: f64,
+}
+
+impl Default for Thrust {
+    fn default() -> Self {
+        Self {
+            direction: ThrustDirection::Prograde,
+            acceleration: 0.01,
+            fuel: None,
+            spent: 0.0,
+        }
+    }
+}
+
+impl Thrust {
+    // Tangential thrust needs the offset and velocity relative to the body it goes around
+    pub fn direction(
+        &self,
+        vel: Vector2<f64>,
+        around: Option<(Vector2<f64>, Vector2<f64>)>,
+    ) -> Vector2<f64> {
+        match self.direction {
+            ThrustDirection::Prograde if vel.magnitude2() > 0.0 => vel.normalize(),
+            ThrustDirection::Retrograde if vel.magnitude2() > 0.0 => -vel.normalize(),
+            ThrustDirection::Prograde | ThrustDirection::Retrograde => Vector2::zero(),
+            ThrustDirection::Fixed { angle } => Vector2::new(angle.cos(), angle.sin()),
+            ThrustDirection::Tangential { .. } => match around {
+                Some((offset, relative_vel)) if offset.magnitude2() > 0.0 => {
+                    let tangent = Vector2::new(-offset.y, offset.x).normalize();
+                    if offset.perp_dot(relative_vel) < 0.0 {
+                        -tangent
+                    } else {
+                        tangent
+                    }
+                }
+                _ => Vector2::zero(),
+            },
+        }
+    }
+
+    // Prograde and retrograde follow the velocity out of the plane, the rest push within it
+    pub fn direction3(
+        &self,
+        vel: Vector3<f64>,
+        around: Option<(Vector2<f64>, Vector2<f64>)>,
+    ) -> Vector3<f64> {
+        match self.direction {
+            ThrustDirection::Prograde if vel.magnitude2() > 0.0 => vel.normalize(),
+            ThrustDirection::Retrograde if vel.magnitude2() > 0.0 => -vel.normalize(),
+            _ => self.direction(vel.truncate(), around).extend(0.0),
+        }
+    }
+}
+
+// Bodies in 3D worlds are balls, so their density is per volume instead of per area
+pub fn volume(radius: f64, three_d: bool) -> f64 {
+    if three_d {
+        4.0 / 3.0 * PI * radius.powi(3)
+    } else {
+        PI * radius * radius
+    }
+}
+
+pub fn radius_from_volume(volume: f64, three_d: bool) -> f64 {
+    if three_d {
+        (volume / (4.0 / 3.0 * PI)).cbrt()
+    } else {
+        (volume / PI).sqrt()
+    }
+}
+
+pub fn density_unit(three_d: bool) -> &'static str {
+    if three_d { "kg/m^3" } else { "kg/m^2" }
 }
 
 impl Body {
-    pub fn mass(&self) -> f64 {
-        self.density * PI * (self.radius * self.radius)
+    pub fn mass(&self, three_d: bool) -> f64 {
+        self.meta.density * volume(self.radius, three_d)
+    }
+
+    // Bodies are uniform discs or balls
+    pub fn moment_of_inertia(&self, three_d: bool) -> f64 {
+        let factor = if three_d { 0.4 } else { 0.5 };
+        factor * self.mass(three_d) * self.radius * self.radius
+    }
+
+    pub fn meta_mut(&mut self) -> &mut BodyMeta {
+        Arc::make_mut(&mut self.meta)
+    }
+
+    // Changes of the same kind at the same time are one burn, so dragging a value adds up to a single
+    // entry of the net change
+    pub fn record_burn(&mut self, time: f64, kind: BurnKind, delta_v: Vector3<f64>) {
+        if delta_v == Vector3::zero() {
+            return;
+        }
+        let burns = &mut self.burns;
+        match burns.last_mut() {
+            Some(last) if last.time == time && last.kind == kind => {
+                last.delta_v += delta_v;
+                if last.delta_v == Vector3::zero() {
+                    burns.pop();
+                }
+            }
+            _ => burns.push(Burn {
+                time,
+                kind,
+                delta_v,
+            }),
+        }
+    }
+
+    // Burns plus whatever the engine spent
+    pub fn total_delta_v(&self) -> f64 {
+        self.burns
+            .iter()
+            .map(|burn| burn.delta_v.magnitude())
+            .sum::<f64>()
+            + self.thrust.map_or(0.0, |thrust| thrust.spent)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+// Each state's share of the metadata, the Arc's allocation also holds its two reference counts
+pub fn meta_memory(meta: &Arc<BodyMeta>) -> usize {
+    (size_of::<BodyMeta>() + 2 * size_of::<usize>() + meta.name.capacity())
+        / Arc::strong_count(meta)
+}
+
+pub fn burns_memory(burns: &Vec<Burn>) -> usize {
+    burns.capacity() * size_of::<Burn>()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BodyId(NonZeroUsize);
 
 impl BodyId {
@@ -27,7 +262,7 @@ impl BodyId {
 
         static ID: AtomicUsize = AtomicUsize::new(1);
         let Some(next_id) = NonZeroUsize::new(ID.fetch_add(1, Ordering::Relaxed)) else {
-            eprintln!("BodyId counter somehow overflow, exiting");
+            tracing::error!("BodyId counter somehow overflow, exiting");
             std::process::abort()
         };
         Self(next_id)
@@ -38,6 +273,28 @@ impl BodyId {
     }
 }
 
+// Names a maneuver node so the state it lands on can record that it has been applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ManeuverId(NonZeroUsize);
+
+impl ManeuverId {
+    pub fn next_id() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static ID: AtomicUsize = AtomicUsize::new(1);
+        let Some(next_id) = NonZeroUsize::new(ID.fetch_add(1, Ordering::Relaxed)) else {
+            tracing::error!("ManeuverId counter somehow overflow, exiting");
+            std::process::abort()
+        };
+        Self(next_id)
+    }
+
+    pub fn get(self) -> usize {
+        self.0.get()
+    }
+}
+
+// Bodies are stored inline, only their metadata is shared between states until it is modified
 #[derive(Debug, Clone)]
 pub struct BodyList {
     bodies: Vec<(BodyId, Body)>,
@@ -131,6 +388,14 @@ impl BodyList {
         self.bodies.iter_mut().map(|(id, body)| (*id, body))
     }
 
+    // What the bodies own outside the list itself
+    pub fn heap_memory(&self) -> usize {
+        self.bodies
+            .iter()
+            .map(|(_, body)| meta_memory(&body.meta) + burns_memory(&body.burns))
+            .sum()
+    }
+
     pub fn iter_mut_pairs(&mut self, mut f: impl FnMut(BodyId, &mut Body, BodyId, &mut Body)) {
         for i in 0..self.bodies.len() {
             for j in i + 1..self.bodies.len() {
@@ -1,5 +1,10 @@
+use crate::rendering::GpuCamera;
 use cgmath::*;
 use serde::{Deserialize, Serialize};
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+// Radians per point dragged
+const ORBIT_SPEED: f64 = 0.01;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Camera {
@@ -8,6 +13,12 @@ pub struct Camera {
     pub view_height: f64,
     pub width: f64,
     pub height: f64,
+    // How far a 3D world is turned around the z axis and then tipped toward the viewer, both zero
+    // looks straight down on the plane
+    #[serde(default)]
+    pub yaw: f64,
+    #[serde(default)]
+    pub pitch: f64,
 }
 
 impl Camera {
@@ -18,6 +29,69 @@ impl Camera {
             view_height,
             width: 0.0,
             height: 0.0,
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+
+    pub fn is_turned(&self) -> bool {
+        self.yaw != 0.0 || self.pitch != 0.0
+    }
+
+    // Where a point lands on the plane everything is drawn in, the same point for anything in the
+    // plane until the view is turned
+    pub fn project(&self, pos: Vector3<f64>) -> Vector2<f64> {
+        if !self.is_turned() {
+            return pos.truncate();
+        }
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        let y = pos.x * yaw_sin + pos.y * yaw_cos;
+        Vector2::new(
+            pos.x * yaw_cos - pos.y * yaw_sin,
+            y * pitch_cos + pos.z * pitch_sin,
+        )
+    }
+
+    // The point in the plane that is drawn here, None while looking at the plane edge on
+    pub fn unproject(&self, pos: Vector2<f64>) -> Option<Vector2<f64>> {
+        if !self.is_turned() {
+            return Some(pos);
+        }
+        let pitch_cos = self.pitch.cos();
+        if pitch_cos.abs() < 1e-6 {
+            return None;
+        }
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (x, y) = (pos.x, pos.y / pitch_cos);
+        Some(Vector2::new(
+            x * yaw_cos + y * yaw_sin,
+            y * yaw_cos - x * yaw_sin,
+        ))
+    }
+
+    // Draw depth of a sphere, nearer ones cover farther ones while all of them stay between the
+    // trails and the markers
+    pub fn depth(&self, pos: Vector3<f64>) -> f64 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        let y = pos.x * yaw_sin + pos.y * yaw_cos;
+        let toward = pos.z * pitch_cos - y * pitch_sin;
+        0.1 + 0.04 * toward / (toward.abs() + self.view_height)
+    }
+
+    // Dragging sideways turns the world around, dragging up and down tips it over, at most edge on
+    pub fn orbit(&mut self, delta: Vector2<f64>) {
+        self.yaw = (self.yaw + delta.x * ORBIT_SPEED).rem_euclid(TAU);
+        self.pitch = (self.pitch + delta.y * ORBIT_SPEED).clamp(-FRAC_PI_2, FRAC_PI_2);
+    }
+
+    // For drawing with a different aspect than the view, like exported pictures
+    pub fn gpu(&self, aspect: f32) -> GpuCamera {
+        GpuCamera {
+            position: (self.pos - self.offset).cast().unwrap(),
+            vertical_height: self.view_height as f32,
+            aspect,
         }
     }
 
@@ -37,7 +111,7 @@ impl Camera {
             x: (pos.x - self.pos.x + self.offset.x)
                 * (self.width / (self.view_height * (self.width / self.height)))
                 + self.width * 0.5,
-            y: (pos.y - self.pos.y + self.offset.y) * (self.height / self.view_height)
+            y: -(pos.y - self.pos.y + self.offset.y) * (self.height / self.view_height)
                 + self.height * 0.5,
         }
     }
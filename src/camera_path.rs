@@ -0,0 +1,128 @@
+use crate::{
+    body::BodyId,
+    world::{Focus, World, format_time},
+};
+use cgmath::{Vector2, Vector3, Zero};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    pub state: usize,
+    pub pos: Vector2<f64>,
+    pub view_height: f64,
+    pub focus: Option<BodyId>,
+}
+
+fn catmull_rom(
+    p0: Vector3<f64>,
+    p1: Vector3<f64>,
+    p2: Vector3<f64>,
+    p3: Vector3<f64>,
+    t: f64,
+) -> Vector3<f64> {
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (t * t)
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (t * t * t))
+        * 0.5
+}
+
+// Keys on the timeline for the camera to move along while time passes
+pub fn ui(ui: &mut egui::Ui, world: &mut World) {
+    ui.label("Camera Path:");
+    world.modified_since_save_to_file |= ui
+        .checkbox(&mut world.follow_camera_path, "Follow")
+        .on_hover_text("Move the camera along the keys while time passes")
+        .changed();
+    if ui
+        .button("Add Key")
+        .on_hover_text("Remember the current view at this time")
+        .clicked()
+    {
+        let key = CameraKeyframe {
+            state: world.current_state,
+            // Keys only follow bodies, a barycenter view is kept where it is
+            pos: match world.focused {
+                Some(Focus::Barycenter) => world.camera.pos - world.camera.offset,
+                _ => world.camera.pos,
+            },
+            view_height: world.camera.view_height,
+            focus: world.focused_body(),
+        };
+        match world
+            .camera_path
+            .binary_search_by_key(&world.current_state, |key| key.state)
+        {
+            Ok(index) => world.camera_path[index] = key,
+            Err(index) => world.camera_path.insert(index, key),
+        }
+        world.modified_since_save_to_file = true;
+    }
+    let mut remove = None;
+    for (i, key) in world.camera_path.iter().enumerate() {
+        let label = ui
+            .selectable_label(
+                key.state == world.current_state,
+                format_time(key.state as f64 * world.step_size),
+            )
+            .on_hover_text("Click to jump, middle click to remove");
+        if label.clicked() {
+            world.current_state = key.state.min(world.states.len() - 1);
+            world.accumulated_time = 0.0;
+        }
+        if label.clicked_by(egui::PointerButton::Middle) {
+            remove = Some(i);
+        }
+    }
+    if let Some(remove) = remove {
+        world.camera_path.remove(remove);
+        world.modified_since_save_to_file = true;
+    }
+}
+
+pub fn apply(world: &mut World) {
+    if !world.follow_camera_path || world.camera_path.is_empty() {
+        return;
+    }
+    let time = world.current_state as f64 + world.accumulated_time / world.step_size;
+    let last = world.camera_path.len() - 1;
+    let segment = world
+        .camera_path
+        .partition_point(|key| key.state as f64 <= time)
+        .saturating_sub(1)
+        .min(last.saturating_sub(1));
+
+    // Keys are blended as view centers so keys focused on different bodies still line up
+    let focus_pos = |focus: Option<BodyId>| {
+        focus
+            .and_then(|focus| world.state().position(focus))
+            .unwrap_or(Vector2::zero())
+    };
+    let key = |index: usize| {
+        let key = world.camera_path[index.min(last)];
+        let center = key.pos + focus_pos(key.focus);
+        Vector3::new(center.x, center.y, key.view_height.ln())
+    };
+    let (a, b) = (
+        world.camera_path[segment],
+        world.camera_path[(segment + 1).min(last)],
+    );
+    let t = if b.state > a.state {
+        ((time - a.state as f64) / (b.state - a.state) as f64).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let view = catmull_rom(
+        key(segment.saturating_sub(1)),
+        key(segment),
+        key(segment + 1),
+        key(segment + 2),
+        t,
+    );
+
+    let focused = if t < 0.5 { a.focus } else { b.focus };
+    world.camera.pos = view.truncate() - focus_pos(focused);
+    world.camera.view_height = view.z.exp();
+    world.focused = focused.map(Focus::Body);
+}
@@ -0,0 +1,310 @@
+use crate::{
+    body::BodyId,
+    plot::{self, Series},
+    universe::Universe,
+    world::format_time,
+};
+use cgmath::{InnerSpace, Vector3, Zero};
+use eframe::egui;
+use std::sync::{Arc, Mutex};
+
+// Samples kept per run, the interval between them follows from the duration
+const SAMPLES: usize = 1000;
+// Once the separation is this fraction of the system's size the runs are just different and the
+// growth stops being exponential
+const SATURATION: f64 = 0.1;
+// Smaller moves relative to a body's distance from the origin are mostly lost to rounding, a few
+// thousand times the f64 precision
+const MIN_RELATIVE_OFFSET: f64 = 1e-12;
+
+pub struct DivergenceState {
+    // Time since the start and the separation then
+    pub samples: Vec<[f64; 2]>,
+    pub stop: bool,
+}
+
+// The state as it is and a copy with one body moved a tiny bit, stepped side by side on a thread of
+// their own so the main timeline is left alone
+pub struct Divergence {
+    pub start: usize,
+    pub body: BodyId,
+    // The offset asked for and the one the body actually moved by after rounding, in meters
+    pub requested: f64,
+    pub epsilon: f64,
+    // Largest distance from the center of mass, for telling when the runs stop diverging
+    pub size: f64,
+    pub shared: Arc<Mutex<DivergenceState>>,
+}
+
+// Root sum square of every body's position difference, bodies gone from either run are skipped
+fn separation(a: &Universe, b: &Universe) -> f64 {
+    a.bodies
+        .iter()
+        .filter_map(|(id, body)| Some((b.bodies.get(id)?.pos - body.pos).magnitude2()))
+        .sum::<f64>()
+        .sqrt()
+}
+
+impl Divergence {
+    pub fn spawn(
+        universe: &Universe,
+        start: usize,
+        body: BodyId,
+        relative_offset: f64,
+        step_size: f64,
+        duration: f64,
+    ) -> Option<Self> {
+        let mut a = universe.clone();
+        a.expand();
+        let total_mass = a.total_mass();
+        let center = a
+            .bodies
+            .iter()
+            .map(|(_, body)| body.pos * body.mass(a.three_d))
+            .fold(Vector3::zero(), |sum, pos| sum + pos)
+            / total_mass.max(f64::MIN_POSITIVE);
+        let size = a
+            .bodies
+            .iter()
+            .map(|(_, body)| (body.pos - center).magnitude())
+            .fold(0.0, f64::max);
+
+        // The offset is relative to the system so it means the same in any units
+        let mut b = a.clone();
+        let moved = &mut b.bodies.get_mut(body)?.pos.x;
+        let requested = relative_offset * size;
+        let original = *moved;
+        *moved += requested.max(original.abs() * MIN_RELATIVE_OFFSET);
+        let epsilon = *moved - original;
+
+        let shared = Arc::new(Mutex::new(DivergenceState {
+            samples: vec![[0.0, epsilon]],
+            stop: false,
+        }));
+        let steps = (duration / step_size).ceil() as usize;
+        let interval = (steps / SAMPLES).max(1);
+        let thread_shared = shared.clone();
+        std::thread::spawn(move || {
+            for step in 1..=steps {
+                if thread_shared.lock().unwrap().stop {
+                    return;
+                }
+                a.step(step_size);
+                b.step(step_size);
+                if step % interval == 0 || step == steps {
+                    let sample = [step as f64 * step_size, separation(&a, &b)];
+                    thread_shared.lock().unwrap().samples.push(sample);
+                }
+            }
+        });
+        Some(Self {
+            start,
+            body,
+            requested,
+            epsilon,
+            size,
+            shared,
+        })
+    }
+
+    // Least squares slope of the log separation before it saturates
+    pub fn lyapunov(&self, samples: &[[f64; 2]]) -> Option<f64> {
+        let growing: Vec<[f64; 2]> = samples
+            .iter()
+            .take_while(|[_, separation]| *separation < self.size * SATURATION)
+            .filter(|[_, separation]| *separation > 0.0)
+            .map(|&[time, separation]| [time, separation.ln()])
+            .collect();
+        if growing.len() < 3 {
+            return None;
+        }
+        let count = growing.len() as f64;
+        let mean_time = growing.iter().map(|[time, _]| time).sum::<f64>() / count;
+        let mean_log = growing.iter().map(|[_, log]| log).sum::<f64>() / count;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for [time, log] in &growing {
+            covariance += (time - mean_time) * (log - mean_log);
+            variance += (time - mean_time) * (time - mean_time);
+        }
+        (variance > 0.0).then(|| covariance / variance)
+    }
+}
+
+impl Drop for Divergence {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().stop = true;
+    }
+}
+
+pub struct Chaos {
+    pub body: Option<BodyId>,
+    // Fraction of the system's size the copy is moved by
+    pub relative_offset: f64,
+    pub duration: f64,
+    pub run: Option<Divergence>,
+}
+
+impl Default for Chaos {
+    fn default() -> Self {
+        Self {
+            body: None,
+            relative_offset: 1e-9,
+            duration: 1000.0,
+            run: None,
+        }
+    }
+}
+
+impl Chaos {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        universe: &Universe,
+        current_state: usize,
+        step_size: f64,
+        selected: Option<BodyId>,
+    ) {
+        let name = |id: Option<BodyId>| {
+            id.and_then(|id| universe.bodies.get(id))
+                .map_or("None".to_string(), |body| body.meta.name.clone())
+        };
+        ui.horizontal(|ui| {
+            let label = ui.label("Perturb:");
+            egui::ComboBox::from_id_salt("Chaos Body")
+                .selected_text(name(self.body))
+                .show_ui(ui, |ui| {
+                    for (id, other) in universe.bodies.iter() {
+                        ui.selectable_value(&mut self.body, Some(id), &other.meta.name);
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            if ui
+                .add_enabled(selected.is_some(), egui::Button::new("Use Selected"))
+                .clicked()
+            {
+                self.body = selected;
+            }
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Offset:");
+            ui.add(
+                egui::DragValue::new(&mut self.relative_offset)
+                    .range(1e-15..=1e-2)
+                    .speed(1e-10)
+                    .custom_formatter(|value, _| format!("{value:.0e}"))
+                    .suffix("x size"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text(
+                "How far the copy of the body is moved along x, as a fraction of the system's size",
+            );
+            let label = ui.label("Duration:");
+            ui.add(
+                egui::DragValue::new(&mut self.duration)
+                    .range(step_size..=f64::INFINITY)
+                    .speed(10.0)
+                    .suffix("s"),
+            )
+            .labelled_by(label.id);
+        });
+        ui.horizontal(|ui| {
+            let body = self.body.filter(|&id| universe.bodies.get(id).is_some());
+            if ui
+                .add_enabled(body.is_some(), egui::Button::new("Start Here"))
+                .on_hover_text("Run the current state and a perturbed copy of it side by side")
+                .clicked()
+            {
+                self.run = body.and_then(|body| {
+                    Divergence::spawn(
+                        universe,
+                        current_state,
+                        body,
+                        self.relative_offset,
+                        step_size,
+                        self.duration,
+                    )
+                });
+            }
+            if ui
+                .add_enabled(self.run.is_some(), egui::Button::new("Stop"))
+                .clicked()
+            {
+                self.run = None;
+            }
+        });
+        let Some(run) = &self.run else {
+            ui.label("Pick a body to perturb and start a run");
+            return;
+        };
+
+        if run.epsilon == 0.0 {
+            ui.colored_label(
+                egui::Color32::RED,
+                "The offset came out as zero, both runs are the same and nothing can diverge",
+            )
+            .on_hover_text(
+                "The system has no size to scale it by, move the body away from the others",
+            );
+            return;
+        }
+        if run.epsilon > run.requested {
+            ui.label(format!(
+                "Offset raised to {:.1e}m, less is lost to rounding this far from the origin",
+                run.epsilon
+            ));
+        }
+
+        let samples = run.shared.lock().unwrap().samples.clone();
+        let &[last_time, last_separation] = samples.last().unwrap();
+        ui.label(format!(
+            "Separation: {last_separation:.3e}m after {} ({:.1}x the offset)",
+            format_time(last_time),
+            last_separation / run.epsilon
+        ));
+        match run.lyapunov(&samples) {
+            Some(exponent) if exponent > 0.0 => {
+                ui.label(format!(
+                    "Lyapunov Exponent: {exponent:.4e}/s (e-folding every {})",
+                    format_time(1.0 / exponent)
+                ))
+                .on_hover_text(
+                    "Fitted to the separation before it grows past a tenth of the system's size",
+                );
+            }
+            Some(exponent) => {
+                ui.label(format!(
+                    "Lyapunov Exponent: {exponent:.4e}/s (not diverging)"
+                ));
+            }
+            None if samples.len() > 2
+                && samples[1..]
+                    .iter()
+                    .all(|[_, separation]| *separation == 0.0) =>
+            {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    "The runs have not separated at all, the offset may be lost to rounding",
+                );
+            }
+            None => {
+                ui.label("Lyapunov Exponent: waiting for samples");
+            }
+        }
+        let points: Vec<[f64; 2]> = samples
+            .iter()
+            .map(|&[time, separation]| [time, separation.log10()])
+            .collect();
+        plot::line_plot(
+            ui,
+            "separation",
+            200.0,
+            &[Series {
+                name: "log10 Separation (m)",
+                color: egui::Color32::LIGHT_RED,
+                points: &points,
+            }],
+            current_state.saturating_sub(run.start) as f64 * step_size,
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    body::{Body, BodyMeta},
+    universe::Units,
+    world::World,
+};
+use cgmath::{Vector2, Vector3};
+use eframe::egui;
+use std::{
+    f64::consts::{PI, TAU},
+    sync::Arc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choreography {
+    FigureEight,
+    LagrangeTriangle,
+    FourBodyChain,
+    FiveBodyChain,
+}
+
+impl Choreography {
+    pub const ALL: [Choreography; 4] = [
+        Choreography::FigureEight,
+        Choreography::LagrangeTriangle,
+        Choreography::FourBodyChain,
+        Choreography::FiveBodyChain,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Choreography::FigureEight => "Figure Eight",
+            Choreography::LagrangeTriangle => "Lagrange Triangle",
+            Choreography::FourBodyChain => "4 Body Chain",
+            Choreography::FiveBodyChain => "5 Body Chain",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Choreography::FigureEight => {
+                "Three equal bodies chasing each other around a figure eight"
+            }
+            Choreography::LagrangeTriangle => "Three equal bodies turning as a rigid triangle",
+            Choreography::FourBodyChain => {
+                "Four equal bodies following each other around one circle"
+            }
+            Choreography::FiveBodyChain => {
+                "Five equal bodies following each other around one circle"
+            }
+        }
+    }
+
+    // Positions and velocities for unit masses with a gravitational constant of 1, about one unit
+    // across
+    fn unit_solution(self) -> Vec<(Vector2<f64>, Vector2<f64>)> {
+        match self {
+            // Chenciner and Montgomery's initial conditions
+            Choreography::FigureEight => {
+                let pos = Vector2::new(0.97000436, -0.24308753);
+                let vel = Vector2::new(-0.93240737, -0.86473146);
+                vec![
+                    (pos, -vel / 2.0),
+                    (-pos, -vel / 2.0),
+                    (Vector2::new(0.0, 0.0), vel),
+                ]
+            }
+            Choreography::LagrangeTriangle => ring(3),
+            Choreography::FourBodyChain => ring(4),
+            Choreography::FiveBodyChain => ring(5),
+        }
+    }
+}
+
+// Equal masses on a regular polygon, each pulled towards the center just enough to circle it
+fn ring(count: usize) -> Vec<(Vector2<f64>, Vector2<f64>)> {
+    let pull: f64 = (1..count)
+        .map(|k| 1.0 / (4.0 * (PI * k as f64 / count as f64).sin()))
+        .sum();
+    let speed = pull.sqrt();
+    (0..count)
+        .map(|i| {
+            let angle = TAU * i as f64 / count as f64;
+            let (sin, cos) = angle.sin_cos();
+            (Vector2::new(cos, sin), Vector2::new(-sin, cos) * speed)
+        })
+        .collect()
+}
+
+const COLORS: [[f64; 3]; 5] = [
+    [1.0, 0.5, 0.3],
+    [0.3, 0.8, 1.0],
+    [0.6, 1.0, 0.4],
+    [1.0, 0.9, 0.3],
+    [0.9, 0.4, 1.0],
+];
+
+// Distances scale with size, so keeping the orbits the same shape means speeding every body up by
+// sqrt(G * mass / size)
+pub fn build(world: &mut World, choreography: Choreography, size: f64, mass: f64) {
+    let speed = (world.state().gravity * mass / size).sqrt();
+    let radius = 0.03 * size;
+    for (i, (pos, vel)) in choreography.unit_solution().into_iter().enumerate() {
+        world.states[0].bodies.push(Body {
+            meta: Arc::new(BodyMeta {
+                name: format!("Body {}", i + 1),
+                density: mass / (PI * radius * radius),
+                color: Vector3::from(COLORS[i % COLORS.len()]),
+                high_accuracy: false,
+                pinned: false,
+                parent: None,
+            }),
+            pos: (pos * size).extend(0.0),
+            vel: (vel * speed).extend(0.0),
+            radius,
+            thrust: None,
+            spin: 0.0,
+            angle: 0.0,
+            charge: 0.0,
+            landed: None,
+            burns: vec![],
+        });
+    }
+    world.camera.view_height = 3.0 * size;
+}
+
+pub struct ChoreographyWindow {
+    pub choreography: Choreography,
+    pub size: f64,
+    pub mass: f64,
+}
+
+impl Default for ChoreographyWindow {
+    fn default() -> Self {
+        Self {
+            choreography: Choreography::FigureEight,
+            size: 100.0,
+            mass: 1000.0,
+        }
+    }
+}
+
+impl ChoreographyWindow {
+    // Returns a new world when one was asked for
+    pub fn ui(&mut self, ui: &mut egui::Ui, step_size: f64) -> Option<World> {
+        let label = ui.label("Choreography:");
+        egui::ComboBox::from_id_salt("Choreography")
+            .selected_text(self.choreography.name())
+            .show_ui(ui, |ui| {
+                for choreography in Choreography::ALL {
+                    ui.selectable_value(&mut self.choreography, choreography, choreography.name());
+                }
+            })
+            .response
+            .labelled_by(label.id);
+        ui.label(self.choreography.description());
+        ui.horizontal(|ui| {
+            let label = ui.label("Size:");
+            ui.add(
+                egui::DragValue::new(&mut self.size)
+                    .range(0.001..=f64::INFINITY)
+                    .speed(1.0),
+            )
+            .labelled_by(label.id);
+            let label = ui.label("Mass:");
+            ui.add(
+                egui::DragValue::new(&mut self.mass)
+                    .range(0.001..=f64::INFINITY)
+                    .speed(10.0),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Mass of each body");
+        });
+        ui.button("Create World").clicked().then(|| {
+            let mut world = World::new(step_size, Units::Sandbox);
+            world.name = self.choreography.name().to_string();
+            build(&mut world, self.choreography, self.size, self.mass);
+            world.restart_generation();
+            world
+        })
+    }
+}
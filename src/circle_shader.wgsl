@@ -23,6 +23,7 @@ struct Circle {
     position: vec3<f32>,
     color: vec3<f32>,
     radius: f32,
+    shading: f32,
 };
 
 @group(1)
@@ -49,8 +50,14 @@ fn vertex(input: VertexInput) -> VertexOutput {
 
 @fragment
 fn fragment(input: VertexOutput) -> @location(0) vec4<f32> {
-    if dot(input.uv, input.uv) > 1.0 {
+    let distance2 = dot(input.uv, input.uv);
+    if distance2 > 1.0 {
         discard;
     }
-    return vec4<f32>(circles[input.circle_index].color, 1.0);
+    let circle = circles[input.circle_index];
+    // Lit from the upper left and a little in front, as if the circle were a ball
+    let normal = vec3<f32>(input.uv, sqrt(1.0 - distance2));
+    let light = max(dot(normal, normalize(vec3<f32>(-0.4, 0.5, 0.75))), 0.0);
+    let brightness = mix(1.0, 0.25 + 0.85 * light, circle.shading);
+    return vec4<f32>(circle.color * brightness, 1.0);
 }
@@ -0,0 +1,128 @@
+use crate::{
+    save::{self, Save},
+    universe::history_hash,
+    world,
+};
+use anyhow::{Context, bail};
+use std::io::{Read, Write};
+
+const USAGE: &str = "Usage: orbit_playground [OPTIONS]
+
+Without options the editor is opened, with any of them a save is processed without a window.
+
+Options:
+  --input <PATH>     Save to read, json, binary or gzipped, - for stdin (default: -)
+  --output <PATH>    Where to write the result, - for stdout (default: -)
+  --format <FORMAT>  Output format, json or binary (default: json)
+  --steps <COUNT>    Steps to simulate past the saved current time (default: 0)
+  --verify           Fail if the rebuilt history does not match the hash stored in the save
+  --help             Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Binary,
+}
+
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let mut input = "-".to_string();
+    let mut output = "-".to_string();
+    let mut format = Format::Json;
+    let mut steps = 0;
+    let mut verify = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--input" => input = value()?.clone(),
+            "--output" => output = value()?.clone(),
+            "--format" => {
+                format = match value()?.as_str() {
+                    "json" => Format::Json,
+                    "binary" => Format::Binary,
+                    other => bail!("Unknown format {other}, expected json or binary"),
+                }
+            }
+            "--steps" => steps = value()?.parse().context("--steps must be a whole number")?,
+            "--verify" => verify = true,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other => bail!("Unknown argument {other}\n\n{USAGE}"),
+        }
+    }
+
+    let mut bytes = vec![];
+    if input == "-" {
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("Failed to read save from stdin")?;
+    } else {
+        bytes = std::fs::read(&input).with_context(|| format!("Failed to read {input}"))?;
+    }
+    let mut save = read_save(&bytes)?;
+    if verify {
+        let Some(expected) = save.data.history_hash else {
+            bail!("The save has no history hash to verify against");
+        };
+        let rebuilt = history_hash(&save.states[..=save.data.current_state]);
+        if rebuilt != expected {
+            bail!("Rebuilt history hash {rebuilt:016x} does not match the saved {expected:016x}");
+        }
+        eprintln!("History verified: {rebuilt:016x}");
+    }
+
+    let target = save.data.current_state + steps;
+    let states = save.states.to_mut();
+    while states.len() <= target {
+        let mut next = states.last().unwrap().clone();
+        next.step(save.data.step_size);
+        states.push(next);
+        // Truncates back to the state a queued body or maneuver was added to, stepping goes on
+        // from there
+        world::apply_scheduled(
+            states,
+            &save.data.spawn_queue,
+            &save.data.maneuver_nodes,
+            save.data.step_size,
+            save.data.current_state,
+        );
+    }
+    save.data.current_state = target;
+    save.data.history_hash = Some(history_hash(&save.states[..=target]));
+    save.data.version = save::SAVE_VERSION;
+
+    let bytes = write_save(&save, format)?;
+    if output == "-" {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&bytes)?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(&output, bytes).with_context(|| format!("Failed to write {output}"))?;
+    }
+    Ok(())
+}
+
+pub fn read_save(bytes: &[u8]) -> anyhow::Result<Save<'static>> {
+    let (save, problems) = save::read(bytes)?;
+    for problem in problems {
+        eprintln!("Warning: {problem}");
+    }
+    Ok(save)
+}
+
+pub fn write_save(save: &Save, format: Format) -> anyhow::Result<Vec<u8>> {
+    Ok(match format {
+        Format::Json => serde_json::to_vec(save)?,
+        Format::Binary => {
+            let mut bytes = vec![];
+            ciborium::into_writer(save, &mut bytes)?;
+            bytes
+        }
+    })
+}
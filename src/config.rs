@@ -0,0 +1,108 @@
+use crate::settings::Settings;
+use anyhow::Context;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+// How often the file is checked for edits made outside the app
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const HEADER: &str =
+    "# Orbit Playground preferences, edits are picked up while the app is running\n";
+
+pub fn path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "Orbit Playground")
+        .map(|dirs| dirs.config_dir().join("config.toml"))
+}
+
+pub struct ConfigFile {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    // What the file holds, None until it has been written or read once
+    written: Option<Settings>,
+    last_poll: Instant,
+}
+
+impl ConfigFile {
+    // Also returns the settings in the file if there already is one
+    pub fn open() -> Option<(ConfigFile, Option<Settings>)> {
+        let mut file = ConfigFile {
+            path: path()?,
+            modified: None,
+            written: None,
+            last_poll: Instant::now(),
+        };
+        let settings = file.read().unwrap_or_else(|error| {
+            tracing::error!("{error:#}");
+            None
+        });
+        Some((file, settings))
+    }
+
+    fn read(&mut self) -> anyhow::Result<Option<Settings>> {
+        let Ok(metadata) = std::fs::metadata(&self.path) else {
+            return Ok(None);
+        };
+        self.modified = metadata.modified().ok();
+        let text = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let settings = from_toml(&text)
+            .with_context(|| format!("Invalid preferences in {}", self.path.display()))?;
+        self.written = Some(settings.clone());
+        Ok(Some(settings))
+    }
+
+    fn write(&mut self, settings: &Settings) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, to_toml(settings)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        self.written = Some(settings.clone());
+        Ok(())
+    }
+
+    // Picks up outside edits to the file and writes back changes made in the app
+    pub fn sync(&mut self, settings: &mut Settings) {
+        if self.last_poll.elapsed() >= POLL_INTERVAL {
+            self.last_poll = Instant::now();
+            let modified = std::fs::metadata(&self.path)
+                .and_then(|m| m.modified())
+                .ok();
+            if modified.is_some() && modified != self.modified {
+                match self.read() {
+                    Ok(Some(read)) => *settings = read,
+                    Ok(None) => {}
+                    // Keep what is running so a half typed edit does not reset everything
+                    Err(error) => tracing::warn!("{error:#}"),
+                }
+            }
+        }
+        if self.written.as_ref() != Some(settings)
+            && let Err(error) = self.write(settings)
+        {
+            tracing::error!("{error:#}");
+            // Stop retrying every frame, the next change will try again
+            self.written = Some(settings.clone());
+        }
+    }
+}
+
+// Profiles are the same format as the config file so one can also be copied over it by hand
+pub fn import(path: &Path) -> anyhow::Result<Settings> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    from_toml(&text).with_context(|| format!("Invalid settings profile in {}", path.display()))
+}
+
+pub fn to_toml(settings: &Settings) -> anyhow::Result<String> {
+    Ok(HEADER.to_string() + &toml_edit::ser::to_string_pretty(settings)?)
+}
+
+fn from_toml(text: &str) -> anyhow::Result<Settings> {
+    Ok(toml_edit::de::from_str(text)?)
+}
@@ -0,0 +1,370 @@
+use crate::{body::BodyId, universe::Universe};
+use cgmath::{InnerSpace, Vector2};
+use std::f64::consts::TAU;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Conic {
+    pub mu: f64,
+    pub pos: Vector2<f64>,
+    pub vel: Vector2<f64>,
+}
+
+impl Conic {
+    pub fn propagate(&self, dt: f64) -> (Vector2<f64>, Vector2<f64>) {
+        let r0 = self.pos.magnitude();
+        if self.mu <= 0.0 || r0 == 0.0 {
+            return (self.pos + self.vel * dt, self.vel);
+        }
+        let sqrt_mu = self.mu.sqrt();
+        let vr0 = self.pos.dot(self.vel) / r0;
+        let alpha = 2.0 / r0 - self.vel.magnitude2() / self.mu;
+
+        // Only whole periods can be skipped, hyperbolic and parabolic paths never repeat
+        let dt = if alpha > 0.0 {
+            dt % (TAU / (sqrt_mu * alpha.powi(3).sqrt()))
+        } else {
+            dt
+        };
+
+        let mut x = sqrt_mu * alpha.abs() * dt;
+        for _ in 0..64 {
+            let z = alpha * x * x;
+            let f = r0 * vr0 / sqrt_mu * x * x * stumpff_c(z)
+                + (1.0 - alpha * r0) * x.powi(3) * stumpff_s(z)
+                + r0 * x
+                - sqrt_mu * dt;
+            let df = r0 * vr0 / sqrt_mu * x * (1.0 - z * stumpff_s(z))
+                + (1.0 - alpha * r0) * x * x * stumpff_c(z)
+                + r0;
+            let ratio = f / df;
+            x -= ratio;
+            if ratio.abs() < 1e-10 {
+                break;
+            }
+        }
+
+        let z = alpha * x * x;
+        let f = 1.0 - x * x / r0 * stumpff_c(z);
+        let g = dt - x.powi(3) / sqrt_mu * stumpff_s(z);
+        let pos = self.pos * f + self.vel * g;
+        let r = pos.magnitude();
+        let f_dot = sqrt_mu / (r * r0) * (z * x * stumpff_s(z) - x);
+        let g_dot = 1.0 - x * x / r * stumpff_c(z);
+        (pos, self.pos * f_dot + self.vel * g_dot)
+    }
+}
+
+fn stumpff_c(z: f64) -> f64 {
+    if z > 0.0 {
+        (1.0 - z.sqrt().cos()) / z
+    } else if z < 0.0 {
+        ((-z).sqrt().cosh() - 1.0) / -z
+    } else {
+        0.5
+    }
+}
+
+fn stumpff_s(z: f64) -> f64 {
+    if z > 0.0 {
+        let sqrt_z = z.sqrt();
+        (sqrt_z - sqrt_z.sin()) / sqrt_z.powi(3)
+    } else if z < 0.0 {
+        let sqrt_z = (-z).sqrt();
+        (sqrt_z.sinh() - sqrt_z) / sqrt_z.powi(3)
+    } else {
+        1.0 / 6.0
+    }
+}
+
+struct Track {
+    id: BodyId,
+    mass: f64,
+    parent: Option<usize>,
+    conic: Conic,
+    epoch: f64,
+    pos: Vector2<f64>,
+    vel: Vector2<f64>,
+    soi: f64,
+}
+
+pub fn predict(
+    universe: &Universe,
+    interval: f64,
+    count: usize,
+) -> Vec<Vec<(BodyId, Vector2<f64>)>> {
+    let mut tracks: Vec<Track> = universe
+        .bodies
+        .iter()
+        .map(|(id, body)| Track {
+            id,
+            mass: body.mass(universe.three_d),
+            parent: None,
+            conic: Conic {
+                mu: 0.0,
+                pos: body.pos.truncate(),
+                vel: body.vel.truncate(),
+            },
+            epoch: 0.0,
+            pos: body.pos.truncate(),
+            vel: body.vel.truncate(),
+            soi: f64::INFINITY,
+        })
+        .collect();
+
+    // Heavier bodies are processed first so a parent is always up to date before its children
+    let mut order: Vec<usize> = (0..tracks.len()).collect();
+    order.sort_by(|&a, &b| tracks[b].mass.total_cmp(&tracks[a].mass));
+
+    let mut samples = Vec::with_capacity(count + 1);
+    for sample in 0..=count {
+        let time = sample as f64 * interval;
+        for (rank, &i) in order.iter().enumerate() {
+            let (rel_pos, rel_vel) = tracks[i].conic.propagate(time - tracks[i].epoch);
+            (tracks[i].pos, tracks[i].vel) = match tracks[i].parent {
+                Some(parent) => (tracks[parent].pos + rel_pos, tracks[parent].vel + rel_vel),
+                None => (rel_pos, rel_vel),
+            };
+
+            let parent = order[..rank]
+                .iter()
+                .copied()
+                .filter(|&candidate| {
+                    tracks[candidate].mass > 0.0
+                        && (tracks[i].pos - tracks[candidate].pos).magnitude()
+                            < tracks[candidate].soi
+                })
+                .min_by(|&a, &b| tracks[a].soi.total_cmp(&tracks[b].soi));
+
+            if parent != tracks[i].parent || sample == 0 {
+                tracks[i].parent = parent;
+                tracks[i].epoch = time;
+                tracks[i].conic = match parent {
+                    Some(parent) => Conic {
+                        mu: universe.active_gravity() * (tracks[parent].mass + tracks[i].mass),
+                        pos: tracks[i].pos - tracks[parent].pos,
+                        vel: tracks[i].vel - tracks[parent].vel,
+                    },
+                    None => Conic {
+                        mu: 0.0,
+                        pos: tracks[i].pos,
+                        vel: tracks[i].vel,
+                    },
+                };
+            }
+
+            tracks[i].soi = match parent {
+                Some(parent) => {
+                    (tracks[i].pos - tracks[parent].pos).magnitude()
+                        * (tracks[i].mass / tracks[parent].mass).powf(0.4)
+                }
+                None => f64::INFINITY,
+            };
+        }
+        samples.push(tracks.iter().map(|track| (track.id, track.pos)).collect());
+    }
+    samples
+}
+
+pub struct Influence {
+    pub id: BodyId,
+    pub parent: Option<BodyId>,
+    pub soi: f64,
+}
+
+// Which body dominates which in a single state, by the same rule the prediction above uses
+pub fn hierarchy(universe: &Universe) -> Vec<Influence> {
+    let mut bodies: Vec<(BodyId, f64, Vector2<f64>)> = universe
+        .bodies
+        .iter()
+        .map(|(id, body)| (id, body.mass(universe.three_d), body.pos.truncate()))
+        .collect();
+    bodies.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut influences: Vec<Influence> = Vec::with_capacity(bodies.len());
+    for (rank, &(id, mass, pos)) in bodies.iter().enumerate() {
+        let parent = (0..rank)
+            .filter(|&candidate| {
+                bodies[candidate].1 > 0.0
+                    && (pos - bodies[candidate].2).magnitude() < influences[candidate].soi
+            })
+            .min_by(|&a, &b| influences[a].soi.total_cmp(&influences[b].soi));
+        influences.push(Influence {
+            id,
+            parent: parent.map(|parent| bodies[parent].0),
+            soi: match parent {
+                Some(parent) => {
+                    (pos - bodies[parent].2).magnitude() * (mass / bodies[parent].1).powf(0.4)
+                }
+                None => f64::INFINITY,
+            },
+        });
+    }
+    influences
+}
+
+// Velocity relative to the parent that makes the current position the periapsis of an orbit with
+// the given eccentricity, zero is circular
+pub fn periapsis_velocity(
+    mu: f64,
+    offset: Vector2<f64>,
+    eccentricity: f64,
+    clockwise: bool,
+) -> Vector2<f64> {
+    let distance = offset.magnitude();
+    if mu <= 0.0 || distance == 0.0 {
+        return Vector2::new(0.0, 0.0);
+    }
+    let speed = (mu * (1.0 + eccentricity) / distance).sqrt();
+    let tangent = Vector2::new(-offset.y, offset.x) / distance;
+    if clockwise {
+        -tangent * speed
+    } else {
+        tangent * speed
+    }
+}
+
+pub struct Hohmann {
+    // Speed changes along the direction of travel, negative ones slow down
+    pub first_burn: f64,
+    pub second_burn: f64,
+    pub duration: f64,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+}
+
+// From one circular orbit to another around the same parent, half an ellipse touching both
+pub fn hohmann(mu: f64, from: f64, to: f64) -> Option<Hohmann> {
+    if mu <= 0.0 || from <= 0.0 || to <= 0.0 {
+        return None;
+    }
+    let semi_major_axis = (from + to) / 2.0;
+    let speed = |distance: f64| (mu * (2.0 / distance - 1.0 / semi_major_axis)).sqrt();
+    Some(Hohmann {
+        first_burn: speed(from) - (mu / from).sqrt(),
+        second_burn: (mu / to).sqrt() - speed(to),
+        duration: std::f64::consts::PI * (semi_major_axis.powi(3) / mu).sqrt(),
+        semi_major_axis,
+        eccentricity: (to - from).abs() / (to + from),
+    })
+}
+
+// Slow spiral out of a circular orbit under constant tangential thrust, each turn stays close to
+// circular so the speed drops by exactly the delta-v spent
+pub struct Spiral {
+    pub growth_per_orbit: f64,
+    // Delta-v and time until the orbital speed reaches zero, the usual escape estimate
+    pub escape_delta_v: f64,
+    pub escape_time: f64,
+    // Where the orbit ends up once the fuel runs out, None when the fuel is enough to escape
+    pub final_radius: Option<f64>,
+}
+
+pub fn spiral(mu: f64, radius: f64, acceleration: f64, fuel: Option<f64>) -> Option<Spiral> {
+    if mu <= 0.0 || radius <= 0.0 || acceleration <= 0.0 {
+        return None;
+    }
+    let speed = (mu / radius).sqrt();
+    Some(Spiral {
+        growth_per_orbit: 4.0 * std::f64::consts::PI * acceleration * radius.powi(3) / mu,
+        escape_delta_v: speed,
+        escape_time: speed / acceleration,
+        final_radius: fuel
+            .filter(|&fuel| fuel < speed)
+            .map(|fuel| mu / (speed - fuel).powi(2)),
+    })
+}
+
+// Semi-major axis of a bound orbit, or the current distance for unbound paths
+fn orbit_size(universe: &Universe, id: BodyId, parent: BodyId) -> Option<f64> {
+    let body = universe.bodies.get(id)?;
+    let parent = universe.bodies.get(parent)?;
+    let mu =
+        universe.active_gravity() * (body.mass(universe.three_d) + parent.mass(universe.three_d));
+    let distance = (body.pos - parent.pos).truncate().magnitude();
+    let energy = (body.vel - parent.vel).truncate().magnitude2() / 2.0 - mu / distance;
+    Some(if energy < 0.0 && mu > 0.0 {
+        -mu / (2.0 * energy)
+    } else {
+        distance
+    })
+}
+
+// Shared Hill radius of two bodies orbiting the same parent, cbrt((m1 + m2) / 3M) * (a1 + a2) / 2.
+// The parent is the one either body orbits, or else the most massive other body
+pub fn mutual_hill_radius(universe: &Universe, a: BodyId, b: BodyId) -> Option<(f64, BodyId)> {
+    let (body_a, body_b) = (universe.bodies.get(a)?, universe.bodies.get(b)?);
+    let influences = hierarchy(universe);
+    let parent_of = |id: BodyId| {
+        influences
+            .iter()
+            .find(|influence| influence.id == id)
+            .and_then(|influence| influence.parent)
+    };
+    let parent = parent_of(a)
+        .filter(|&parent| parent != b)
+        .or_else(|| parent_of(b).filter(|&parent| parent != a))
+        .or_else(|| {
+            universe
+                .bodies
+                .iter()
+                .filter(|&(other, _)| other != a && other != b)
+                .max_by(|a, b| {
+                    a.1.mass(universe.three_d)
+                        .total_cmp(&b.1.mass(universe.three_d))
+                })
+                .map(|(other, _)| other)
+        })?;
+    let parent_mass = universe.bodies.get(parent)?.mass(universe.three_d);
+    if parent_mass <= 0.0 {
+        return None;
+    }
+    let size = (orbit_size(universe, a, parent)? + orbit_size(universe, b, parent)?) / 2.0;
+    Some((
+        size * ((body_a.mass(universe.three_d) + body_b.mass(universe.three_d))
+            / (3.0 * parent_mass))
+            .cbrt(),
+        parent,
+    ))
+}
+
+// Radius within which the body's own gravity holds on to moons against the body it orbits,
+// a * (1 - e) * cbrt(m / 3M). Returns the radius and the body it was measured against
+pub fn hill_radius(universe: &Universe, id: BodyId) -> Option<(f64, BodyId)> {
+    let body = universe.bodies.get(id)?;
+    let parent = hierarchy(universe)
+        .into_iter()
+        .find(|influence| influence.id == id)
+        .and_then(|influence| influence.parent)
+        .or_else(|| {
+            universe
+                .bodies
+                .iter()
+                .filter(|&(other, _)| other != id)
+                .max_by(|a, b| {
+                    a.1.mass(universe.three_d)
+                        .total_cmp(&b.1.mass(universe.three_d))
+                })
+                .map(|(other, _)| other)
+        })?;
+    let parent_body = universe.bodies.get(parent)?;
+    if parent_body.mass(universe.three_d) <= 0.0 {
+        return None;
+    }
+    let mu = universe.active_gravity()
+        * (body.mass(universe.three_d) + parent_body.mass(universe.three_d));
+    let pos = (body.pos - parent_body.pos).truncate();
+    let vel = (body.vel - parent_body.vel).truncate();
+    let distance = pos.magnitude();
+    let energy = vel.magnitude2() / 2.0 - mu / distance;
+    // Unbound paths have no periapsis to speak of, so the current distance is used
+    let periapsis = if energy < 0.0 && mu > 0.0 {
+        let eccentricity = ((vel.magnitude2() - mu / distance) * pos - pos.dot(vel) * vel) / mu;
+        -mu / (2.0 * energy) * (1.0 - eccentricity.magnitude())
+    } else {
+        distance
+    };
+    Some((
+        periapsis
+            * (body.mass(universe.three_d) / (3.0 * parent_body.mass(universe.three_d))).cbrt(),
+        parent,
+    ))
+}
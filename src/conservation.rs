@@ -0,0 +1,158 @@
+use crate::{
+    plot::{self, Series},
+    universe::Universe,
+};
+use cgmath::InnerSpace;
+use eframe::egui;
+
+// Pair potentials summed per frame so long runs are scanned without stalling the ui
+const PAIR_BUDGET: usize = 2_000_000;
+
+#[derive(Clone, Copy)]
+pub struct Sample {
+    pub energy: f64,
+    pub momentum: f64,
+}
+
+// Total energy and momentum per state, kept between frames and only extended as the future grows
+pub struct Conservation {
+    // None for thinned states, they have nothing left to sum
+    pub samples: Vec<Option<Sample>>,
+    // A previous run kept to compare against, named after its integrator
+    pub pinned: Option<(String, Vec<Option<Sample>>)>,
+    pub relative: bool,
+}
+
+impl Default for Conservation {
+    fn default() -> Self {
+        Self {
+            samples: vec![],
+            pinned: None,
+            relative: true,
+        }
+    }
+}
+
+impl Conservation {
+    // States from index onward changed and have to be summed again
+    pub fn truncate(&mut self, index: usize) {
+        self.samples.truncate(index);
+    }
+
+    pub fn scan(&mut self, states: &[Universe]) {
+        let mut budget = PAIR_BUDGET;
+        while let Some(universe) = states.get(self.samples.len()) {
+            let count = universe.bodies.len();
+            let pairs = count * count.saturating_sub(1) / 2 + count;
+            if pairs > budget && budget < PAIR_BUDGET {
+                return;
+            }
+            budget = budget.saturating_sub(pairs);
+            self.samples.push(sample(universe));
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, step_size: f64, current_state: usize, name: &str) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.relative, "Relative Drift")
+                .on_hover_text("Plot the change from the first state as a fraction of it");
+            if ui
+                .button("Pin")
+                .on_hover_text("Keep this run to compare against after changing the integrator")
+                .clicked()
+            {
+                self.pinned = Some((name.to_string(), self.samples.clone()));
+            }
+            if self.pinned.is_some() && ui.button("Unpin").clicked() {
+                self.pinned = None;
+            }
+        });
+        let drift = |samples: &[Option<Sample>], value: fn(&Sample) -> f64| {
+            let first = samples.iter().flatten().next().map(value)?;
+            let last = samples.iter().flatten().next_back().map(value)?;
+            Some(((last - first) / first.abs()).abs())
+        };
+        if let Some(drift) = drift(&self.samples, |sample| sample.energy) {
+            ui.label(format!("Energy Drift: {drift:.4e}"));
+        }
+        if let Some(drift) = drift(&self.samples, |sample| sample.momentum) {
+            ui.label(format!("Momentum Drift: {drift:.4e}"));
+        }
+        ui.label(format!("Scanned: {} states", self.samples.len()));
+
+        let mut series = vec![(name.to_string(), &self.samples, egui::Color32::LIGHT_BLUE)];
+        if let Some((name, samples)) = &self.pinned {
+            series.push((format!("{name} (Pinned)"), samples, egui::Color32::ORANGE));
+        }
+        for (title, value) in [
+            (
+                "Total Energy",
+                (|sample: &Sample| sample.energy) as fn(&Sample) -> f64,
+            ),
+            ("Total Momentum", |sample: &Sample| sample.momentum),
+        ] {
+            ui.label(title);
+            let points: Vec<Vec<[f64; 2]>> = series
+                .iter()
+                .map(|(_, samples, _)| self.points(samples, value, step_size))
+                .collect();
+            let lines: Vec<Series> = series
+                .iter()
+                .zip(&points)
+                .map(|((name, _, color), points)| Series {
+                    name,
+                    color: *color,
+                    points,
+                })
+                .collect();
+            plot::line_plot(ui, title, 120.0, &lines, current_state as f64 * step_size);
+        }
+    }
+
+    fn points(
+        &self,
+        samples: &[Option<Sample>],
+        value: fn(&Sample) -> f64,
+        step_size: f64,
+    ) -> Vec<[f64; 2]> {
+        let first = samples.iter().flatten().next().map(value).unwrap_or(0.0);
+        let stride = plot::stride(samples.len());
+        samples
+            .iter()
+            .enumerate()
+            .step_by(stride)
+            .map(|(index, sample)| {
+                let y = sample.as_ref().map_or(f64::NAN, |sample| {
+                    let y = value(sample);
+                    if !self.relative {
+                        y
+                    } else if first == 0.0 {
+                        y - first
+                    } else {
+                        (y - first) / first.abs()
+                    }
+                });
+                [index as f64 * step_size, y]
+            })
+            .collect()
+    }
+}
+
+fn sample(universe: &Universe) -> Option<Sample> {
+    if universe.is_thinned() {
+        return None;
+    }
+    let expanded;
+    let universe = if universe.is_compact() {
+        let mut clone = universe.clone();
+        clone.expand();
+        expanded = clone;
+        &expanded
+    } else {
+        universe
+    };
+    Some(Sample {
+        energy: universe.total_energy(),
+        momentum: universe.momentum().magnitude(),
+    })
+}
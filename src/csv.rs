@@ -0,0 +1,80 @@
+use crate::world::World;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    // Every generated state, past and future
+    All,
+    // The states the trails are drawn over
+    Visible,
+}
+
+impl Range {
+    pub const ALL: [Range; 2] = [Range::All, Range::Visible];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Range::All => "All Generated States",
+            Range::Visible => "Visible Trail Range",
+        }
+    }
+}
+
+// Names are quoted when they would otherwise break the row apart
+fn field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// One row per body per state, thinned states have nothing left to write and are skipped
+pub fn table(world: &World, range: Range) -> String {
+    let last = world.states.len() - 1;
+    let (first, last) = match range {
+        Range::All => (0, last),
+        Range::Visible => (
+            world
+                .current_state
+                .saturating_sub((world.show_past / world.step_size) as usize),
+            (world.current_state + (world.show_future / world.step_size) as usize).min(last),
+        ),
+    };
+    // Worlds in the plane keep the columns they always had
+    let three_d = world.state().three_d;
+    let mut csv = String::from(if three_d {
+        "time,id,name,x,y,z,vx,vy,vz,mass\n"
+    } else {
+        "time,id,name,x,y,vx,vy,mass\n"
+    });
+    for index in first..=last {
+        let mut universe = world.states[index].clone();
+        if universe.is_thinned() {
+            continue;
+        }
+        universe.expand();
+        let time = index as f64 * world.step_size;
+        for (id, body) in universe.bodies.iter() {
+            let (pos, vel) = if three_d {
+                (
+                    format!("{},{},{}", body.pos.x, body.pos.y, body.pos.z),
+                    format!("{},{},{}", body.vel.x, body.vel.y, body.vel.z),
+                )
+            } else {
+                (
+                    format!("{},{}", body.pos.x, body.pos.y),
+                    format!("{},{}", body.vel.x, body.vel.y),
+                )
+            };
+            _ = writeln!(
+                csv,
+                "{time},{},{},{pos},{vel},{}",
+                id.get_id(),
+                field(&body.meta.name),
+                body.mass(universe.three_d)
+            );
+        }
+    }
+    csv
+}
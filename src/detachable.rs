@@ -0,0 +1,59 @@
+use eframe::egui;
+
+// Shows a window inside the main one, or in its own OS window while detached so it can be moved to
+// another monitor. Returns where the window is when it is shown inside the main one
+pub fn show(
+    ctx: &egui::Context,
+    title: &str,
+    id: &str,
+    open: &mut bool,
+    detached: &mut bool,
+    mut add_contents: impl FnMut(&mut egui::Ui),
+) -> Option<egui::Rect> {
+    if !*open {
+        return None;
+    }
+    if !*detached {
+        return egui::Window::new(title)
+            .id(egui::Id::new(id))
+            .open(open)
+            .show(ctx, |ui| {
+                if ui
+                    .small_button("Detach")
+                    .on_hover_text("Move this into its own window")
+                    .clicked()
+                {
+                    *detached = true;
+                }
+                add_contents(ui);
+            })
+            .map(|response| response.response.rect);
+    }
+    ctx.show_viewport_immediate(
+        egui::ViewportId::from_hash_of(id),
+        egui::ViewportBuilder::default()
+            .with_title(title)
+            .with_inner_size([360.0, 480.0]),
+        |ctx, class| {
+            // Backends without multiple windows draw it inside the main one anyway
+            if class == egui::ViewportClass::Embedded {
+                *detached = false;
+                return;
+            }
+            egui::CentralPanel::default().show(ctx, |ui| {
+                if ui
+                    .small_button("Attach")
+                    .on_hover_text("Move this back into the main window")
+                    .clicked()
+                {
+                    *detached = false;
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| add_contents(ui));
+            });
+            if ctx.input(|i| i.viewport().close_requested()) {
+                *open = false;
+            }
+        },
+    );
+    None
+}
@@ -22,6 +22,19 @@ impl DrawHandler {
             },
             color,
             radius,
+            shading: 0.0,
+        });
+    }
+    pub fn sphere(&mut self, pos: Vector2<f32>, radius: f32, color: Vector3<f32>, depth: f32) {
+        self.circles.push(GpuCircle {
+            position: Vector3 {
+                x: pos.x,
+                y: pos.y,
+                z: depth,
+            },
+            color,
+            radius,
+            shading: 1.0,
         });
     }
     pub fn rect(
@@ -0,0 +1,144 @@
+use crate::{
+    body::BodyId,
+    universe::Universe,
+    world::{Focus, World},
+};
+use cgmath::{InnerSpace, Vector3, Zero};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Center {
+    Focus,
+    Barycenter,
+}
+
+// Thinned states keep nothing so masses come from the current state
+fn mass(world: &World, universe: &Universe, id: BodyId) -> f64 {
+    universe
+        .mass(id)
+        .or_else(|| {
+            let state = world.state();
+            Some(state.bodies.get(id)?.mass(state.three_d))
+        })
+        .unwrap_or(0.0)
+}
+
+fn center_state(
+    world: &World,
+    universe: &Universe,
+    center: Center,
+) -> Option<(Vector3<f64>, Vector3<f64>)> {
+    match center {
+        Center::Focus => match world.focused {
+            Some(Focus::Body(focus)) => {
+                Some((universe.position3(focus)?, universe.velocity3(focus)?))
+            }
+            Some(Focus::Barycenter) => center_state(world, universe, Center::Barycenter),
+            None => Some((Vector3::zero(), Vector3::zero())),
+        },
+        Center::Barycenter => {
+            let mut total_mass = 0.0;
+            let mut pos = Vector3::zero();
+            let mut vel = Vector3::zero();
+            for id in universe.body_ids() {
+                let mass = mass(world, universe, id);
+                total_mass += mass;
+                pos += universe.position3(id)? * mass;
+                vel += universe.velocity3(id)? * mass;
+            }
+            (total_mass > 0.0).then(|| (pos / total_mass, vel / total_mass))
+        }
+    }
+}
+
+pub fn table(world: &World, target: BodyId, center: Center, interval: f64) -> String {
+    let name = |id: BodyId| {
+        world
+            .state()
+            .bodies
+            .get(id)
+            .map_or("Unnamed".to_string(), |body| body.meta.name.clone())
+    };
+    let center_name = match (center, world.focused) {
+        (Center::Focus, Some(Focus::Body(focus))) => name(focus),
+        (Center::Focus, Some(Focus::Barycenter)) | (Center::Barycenter, _) => {
+            "Barycenter".to_string()
+        }
+        (Center::Focus, None) => "Origin".to_string(),
+    };
+    let stride = ((interval / world.step_size).round() as usize).max(1);
+
+    let mut table = String::new();
+    let rule = "*".repeat(79);
+    _ = writeln!(table, "{rule}");
+    _ = writeln!(table, " Target body name: {}", name(target));
+    _ = writeln!(table, " Center body name: {center_name}");
+    _ = writeln!(
+        table,
+        " Step-size       : {} s",
+        stride as f64 * world.step_size
+    );
+    _ = writeln!(table, " Reference frame : world axes, units m and m/s");
+    _ = writeln!(table, "{rule}");
+    // Worlds in the plane keep the columns they always had
+    let three_d = world.state().three_d;
+    let columns: &[&str] = if three_d {
+        &["Time (s)", "X", "Y", "Z", "VX", "VY", "VZ", "RG", "RR"]
+    } else {
+        &["Time (s)", "X", "Y", "VX", "VY", "RG", "RR"]
+    };
+    let row: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{column:>24}"))
+        .collect();
+    _ = writeln!(table, "{},", row.join(", "));
+    _ = writeln!(table, "$$SOE");
+    for (index, universe) in world.states.iter().enumerate().step_by(stride) {
+        // Thinned states have nothing to report and are skipped rather than regenerated
+        let (Some(pos), Some(vel)) = (universe.position3(target), universe.velocity3(target))
+        else {
+            continue;
+        };
+        let Some((center_pos, center_vel)) = center_state(world, universe, center) else {
+            continue;
+        };
+        let pos = pos - center_pos;
+        let vel = vel - center_vel;
+        let range = pos.magnitude();
+        let range_rate = if range > 0.0 {
+            pos.dot(vel) / range
+        } else {
+            0.0
+        };
+        let values = if three_d {
+            vec![
+                index as f64 * world.step_size,
+                pos.x,
+                pos.y,
+                pos.z,
+                vel.x,
+                vel.y,
+                vel.z,
+                range,
+                range_rate,
+            ]
+        } else {
+            vec![
+                index as f64 * world.step_size,
+                pos.x,
+                pos.y,
+                vel.x,
+                vel.y,
+                range,
+                range_rate,
+            ]
+        };
+        let row: Vec<String> = values
+            .iter()
+            .map(|value| format!("{value:>24.15E}"))
+            .collect();
+        _ = writeln!(table, "{},", row.join(", "));
+    }
+    _ = writeln!(table, "$$EOE");
+    table
+}
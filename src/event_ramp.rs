@@ -0,0 +1,47 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// Slows playback down near bookmarks and a predicted impact so the moment isn't skipped past
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EventRamp {
+    pub enabled: bool,
+    pub speed: f64,
+    pub window: f64,
+}
+
+impl Default for EventRamp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: 1.0,
+            window: 5.0,
+        }
+    }
+}
+
+impl EventRamp {
+    // Returns whether any of it was changed
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Slow Near Events")
+            .on_hover_text("Slow down near bookmarks and predicted impacts")
+            .changed();
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut self.speed)
+                    .range(0.0..=f64::INFINITY)
+                    .speed(0.1)
+                    .suffix("x"),
+            )
+            .changed();
+        ui.label("within");
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut self.window)
+                    .range(0.0..=f64::INFINITY)
+                    .suffix("s"),
+            )
+            .changed();
+        changed
+    }
+}
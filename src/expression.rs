@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Constant {
+    pub name: String,
+    pub expression: String,
+}
+
+pub fn default_constants() -> Vec<Constant> {
+    [
+        ("AU", "1.495978707e11"),
+        ("M_sun", "1.98847e30"),
+        ("r_sun", "6.957e8"),
+        ("M_earth", "5.9722e24"),
+        ("r_earth", "6.371e6"),
+    ]
+    .into_iter()
+    .map(|(name, expression)| Constant {
+        name: name.to_string(),
+        expression: expression.to_string(),
+    })
+    .collect()
+}
+
+// Deeper nesting is rejected rather than risking the stack, no sensible expression gets close
+const MAX_DEPTH: usize = 100;
+
+pub fn evaluate(text: &str, variables: &BTreeMap<String, f64>) -> Option<f64> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+        variables,
+    };
+    let value = parser.expression()?;
+    parser.skip_whitespace();
+    (parser.pos == parser.text.len()).then_some(value)
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    // Every level of nesting, brackets, arguments, signs and exponents, passes through unary
+    depth: usize,
+    variables: &'a BTreeMap<String, f64>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        if self.text.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(b'+') {
+                value += self.term()?;
+            } else if self.eat(b'-') {
+                value -= self.term()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(b'*') {
+                value *= self.unary()?;
+            } else if self.eat(b'/') {
+                value /= self.unary()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = if self.eat(b'-') {
+            Some(-self.unary()?)
+        } else if self.eat(b'+') {
+            self.unary()
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            Some(base.powf(self.unary()?))
+        } else {
+            Some(base)
+        }
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        if self.eat(b'(') {
+            let value = self.expression()?;
+            return self.eat(b')').then_some(value);
+        }
+        self.skip_whitespace();
+        let start = self.pos;
+        match *self.text.get(self.pos)? {
+            b'0'..=b'9' | b'.' => {
+                while self
+                    .text
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == b'.')
+                {
+                    self.pos += 1;
+                }
+                if matches!(self.text.get(self.pos), Some(b'e' | b'E')) {
+                    let mantissa_end = self.pos;
+                    self.pos += 1;
+                    if matches!(self.text.get(self.pos), Some(b'+' | b'-')) {
+                        self.pos += 1;
+                    }
+                    if !self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                        self.pos = mantissa_end;
+                    }
+                    while self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                        self.pos += 1;
+                    }
+                }
+                std::str::from_utf8(&self.text[start..self.pos])
+                    .ok()?
+                    .parse()
+                    .ok()
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                while self
+                    .text
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_')
+                {
+                    self.pos += 1;
+                }
+                let name = std::str::from_utf8(&self.text[start..self.pos]).ok()?;
+                if self.eat(b'(') {
+                    let mut arguments = vec![self.expression()?];
+                    while self.eat(b',') {
+                        arguments.push(self.expression()?);
+                    }
+                    if !self.eat(b')') {
+                        return None;
+                    }
+                    call(name, &arguments)
+                } else {
+                    self.variables.get(name).copied()
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn call(name: &str, arguments: &[f64]) -> Option<f64> {
+    Some(match (name, arguments) {
+        ("sqrt", &[x]) => x.sqrt(),
+        ("cbrt", &[x]) => x.cbrt(),
+        ("abs", &[x]) => x.abs(),
+        ("exp", &[x]) => x.exp(),
+        ("ln", &[x]) => x.ln(),
+        ("log10", &[x]) => x.log10(),
+        ("sin", &[x]) => x.sin(),
+        ("cos", &[x]) => x.cos(),
+        ("tan", &[x]) => x.tan(),
+        ("asin", &[x]) => x.asin(),
+        ("acos", &[x]) => x.acos(),
+        ("atan", &[x]) => x.atan(),
+        ("atan2", &[y, x]) => y.atan2(x),
+        ("pow", &[x, y]) => x.powf(y),
+        ("min", &[x, y]) => x.min(y),
+        ("max", &[x, y]) => x.max(y),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> Option<f64> {
+        let variables = BTreeMap::from([("x".to_string(), 3.0), ("AU".to_string(), 1.5e11)]);
+        evaluate(text, &variables)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Some(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), Some(9.0));
+        assert_eq!(eval("1 - 2 - 3"), Some(-4.0));
+        assert_eq!(eval("8 / 2 / 2"), Some(2.0));
+        assert_eq!(eval("2 * 3 ^ 2"), Some(18.0));
+        assert_eq!(eval("2 ^ 3 ^ 2"), Some(512.0));
+        assert_eq!(eval("2 * x + 1"), Some(7.0));
+    }
+
+    #[test]
+    fn unary_minus_and_powers() {
+        assert_eq!(eval("-2 ^ 2"), Some(-4.0));
+        assert_eq!(eval("(-2) ^ 2"), Some(4.0));
+        assert_eq!(eval("2 ^ -1"), Some(0.5));
+        assert_eq!(eval("--2"), Some(2.0));
+        assert_eq!(eval("-+-2"), Some(2.0));
+        assert_eq!(eval("3 - -x"), Some(6.0));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(eval("2e-3"), Some(2e-3));
+        assert_eq!(eval("1.5E+2"), Some(150.0));
+        assert_eq!(eval(".5"), Some(0.5));
+        assert_eq!(eval("2e3 * AU"), Some(3e14));
+        // An exponent without digits is not part of the number, which leaves trailing input
+        assert_eq!(eval("1e"), None);
+        assert_eq!(eval("1e-"), None);
+        assert_eq!(eval("1.2.3"), None);
+    }
+
+    #[test]
+    fn function_arity() {
+        assert_eq!(eval("sqrt(16)"), Some(4.0));
+        assert_eq!(eval("max(1, x)"), Some(3.0));
+        assert_eq!(eval("pow(2, 10)"), Some(1024.0));
+        assert_eq!(eval("sqrt(1, 2)"), None);
+        assert_eq!(eval("atan2(1)"), None);
+        assert_eq!(eval("sqrt()"), None);
+        assert_eq!(eval("nope(1)"), None);
+        assert_eq!(eval("y"), None);
+    }
+
+    #[test]
+    fn trailing_and_missing_input() {
+        assert_eq!(eval("  4  "), Some(4.0));
+        assert_eq!(eval(""), None);
+        assert_eq!(eval("1 2"), None);
+        assert_eq!(eval("(1"), None);
+        assert_eq!(eval("1)"), None);
+        assert_eq!(eval("1 +"), None);
+        assert_eq!(eval("x x"), None);
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_DEPTH - 1)), Some(1.0));
+        assert_eq!(eval(&nested(MAX_DEPTH)), None);
+        assert_eq!(eval(&nested(100_000)), None);
+        assert_eq!(eval(&format!("{}1", "-".repeat(100_000))), None);
+        assert_eq!(
+            eval(&format!(
+                "{}1{}",
+                "sqrt(".repeat(100_000),
+                ")".repeat(100_000)
+            )),
+            None
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    body::BodyId,
+    plot::{self, Series},
+    universe::Universe,
+};
+use cgmath::InnerSpace;
+use eframe::egui;
+
+// States read per frame so long runs are filled in without stalling the ui
+const STATE_BUDGET: usize = 200_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Speed,
+    Distance,
+    X,
+    Y,
+    Acceleration,
+}
+
+impl Quantity {
+    pub const ALL: [Quantity; 5] = [
+        Quantity::Speed,
+        Quantity::Distance,
+        Quantity::X,
+        Quantity::Y,
+        Quantity::Acceleration,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::Speed => "Speed",
+            Quantity::Distance => "Distance to Focus",
+            Quantity::X => "X Position",
+            Quantity::Y => "Y Position",
+            Quantity::Acceleration => "Acceleration",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Quantity::Speed => "m/s",
+            Quantity::Distance | Quantity::X | Quantity::Y => "m",
+            Quantity::Acceleration => "m/s^2",
+        }
+    }
+}
+
+// One value per state for a body, kept between frames and only extended as the future grows
+pub struct Graph {
+    pub body: Option<BodyId>,
+    pub quantity: Quantity,
+    // The focused body distances were measured from, a new focus measures them again
+    pub reference: Option<BodyId>,
+    // NaN where the body did not exist or the state was thinned
+    pub values: Vec<f64>,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self {
+            body: None,
+            quantity: Quantity::Speed,
+            reference: None,
+            values: vec![],
+        }
+    }
+}
+
+impl Graph {
+    // States from index onward changed and have to be read again
+    pub fn truncate(&mut self, index: usize) {
+        self.values.truncate(index);
+    }
+
+    pub fn scan(&mut self, states: &[Universe], step_size: f64) {
+        let Some(id) = self.body else {
+            return;
+        };
+        let end = states.len().min(self.values.len() + STATE_BUDGET);
+        for index in self.values.len()..end {
+            let universe = &states[index];
+            let value = match self.quantity {
+                Quantity::Speed => universe.velocity3(id).map(|vel| vel.magnitude()),
+                Quantity::Distance => self.reference.and_then(|reference| {
+                    Some((universe.position3(id)? - universe.position3(reference)?).magnitude())
+                }),
+                Quantity::X => universe.position(id).map(|pos| pos.x),
+                Quantity::Y => universe.position(id).map(|pos| pos.y),
+                // From the change in velocity since the state before
+                Quantity::Acceleration => index.checked_sub(1).and_then(|previous| {
+                    let change = universe.velocity3(id)? - states[previous].velocity3(id)?;
+                    Some(change.magnitude() / step_size)
+                }),
+            };
+            self.values.push(value.unwrap_or(f64::NAN));
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        states: &[Universe],
+        step_size: f64,
+        current_state: usize,
+        selected: Option<BodyId>,
+        focused: Option<BodyId>,
+    ) {
+        let universe = &states[current_state];
+        let name = |id: Option<BodyId>| {
+            id.and_then(|id| universe.bodies.get(id))
+                .map_or("None".to_string(), |body| body.meta.name.clone())
+        };
+        let (mut body, mut quantity) = (self.body, self.quantity);
+        ui.horizontal(|ui| {
+            let label = ui.label("Body:");
+            egui::ComboBox::from_id_salt("Graph Body")
+                .selected_text(name(body))
+                .show_ui(ui, |ui| {
+                    for (id, other) in universe.bodies.iter() {
+                        ui.selectable_value(&mut body, Some(id), &other.meta.name);
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            if ui
+                .add_enabled(selected.is_some(), egui::Button::new("Use Selected"))
+                .clicked()
+            {
+                body = selected;
+            }
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Quantity:");
+            egui::ComboBox::from_id_salt("Graph Quantity")
+                .selected_text(quantity.name())
+                .show_ui(ui, |ui| {
+                    for option in Quantity::ALL {
+                        ui.selectable_value(&mut quantity, option, option.name());
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+        });
+        let reference = (quantity == Quantity::Distance)
+            .then_some(focused)
+            .flatten();
+        if body != self.body || quantity != self.quantity || reference != self.reference {
+            *self = Self {
+                body,
+                quantity,
+                reference,
+                values: vec![],
+            };
+        }
+        if body.is_none() {
+            ui.label("Pick a body to plot");
+            return;
+        }
+        if quantity == Quantity::Distance && reference.is_none() {
+            ui.label("Focus a body to measure the distance from");
+            return;
+        }
+        self.scan(states, step_size);
+
+        if let Some(value) = self
+            .values
+            .get(current_state)
+            .filter(|value| value.is_finite())
+        {
+            ui.label(format!("Now: {value:.6}{}", quantity.unit()));
+        }
+        let stride = plot::stride(self.values.len());
+        let points: Vec<[f64; 2]> = self
+            .values
+            .iter()
+            .enumerate()
+            .step_by(stride)
+            .map(|(index, &value)| [index as f64 * step_size, value])
+            .collect();
+        let series_name = format!("{} ({})", quantity.name(), quantity.unit());
+        plot::line_plot(
+            ui,
+            "graph",
+            200.0,
+            &[Series {
+                name: &series_name,
+                color: egui::Color32::LIGHT_BLUE,
+                points: &points,
+            }],
+            current_state as f64 * step_size,
+        );
+        if self.values.len() < states.len() {
+            ui.label(format!(
+                "Reading states: {} / {}",
+                self.values.len(),
+                states.len()
+            ));
+        }
+    }
+}
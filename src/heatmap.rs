@@ -0,0 +1,90 @@
+use crate::{drawing::DrawHandler, universe::Universe};
+use cgmath::{InnerSpace, Vector2, Vector3};
+use std::collections::HashMap;
+
+// Pair distance checks allowed per frame so long runs are scanned without stalling the ui
+const PAIR_CHECK_BUDGET: usize = 2_000_000;
+
+// Counts of close approaches per grid cell, in world space rather than relative to any body
+pub struct HeatMap {
+    pub cell_size: f64,
+    pub approach_factor: f64,
+    pub cells: HashMap<(i64, i64), f64>,
+    pub max: f64,
+    pub scanned: usize,
+}
+
+impl HeatMap {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            approach_factor: 3.0,
+            cells: HashMap::new(),
+            max: 0.0,
+            scanned: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.cells.clear();
+        self.max = 0.0;
+        self.scanned = 0;
+    }
+
+    // Keeps going from where the last frame stopped
+    pub fn scan(&mut self, states: &[Universe]) {
+        let mut budget = PAIR_CHECK_BUDGET;
+        while self.scanned < states.len() {
+            let universe = &states[self.scanned];
+            let count = universe.bodies.len();
+            let checks = count * count.saturating_sub(1) / 2;
+            if checks > budget && budget < PAIR_CHECK_BUDGET {
+                return;
+            }
+            budget = budget.saturating_sub(checks);
+            self.accumulate(universe);
+            self.scanned += 1;
+        }
+    }
+
+    fn accumulate(&mut self, universe: &Universe) {
+        // Compact and thinned states have no radii to compare against
+        let bodies: Vec<_> = universe.bodies.iter().map(|(_, body)| body).collect();
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                let dist = (b.pos - a.pos).magnitude();
+                if dist < (a.radius + b.radius) * self.approach_factor {
+                    let middle = (a.pos + b.pos) * 0.5;
+                    let cell = (
+                        (middle.x / self.cell_size).floor() as i64,
+                        (middle.y / self.cell_size).floor() as i64,
+                    );
+                    let heat = self.cells.entry(cell).or_default();
+                    *heat += 1.0;
+                    self.max = self.max.max(*heat);
+                }
+            }
+        }
+    }
+
+    pub fn draw(&self, d: &mut DrawHandler) {
+        if self.max <= 0.0 {
+            return;
+        }
+        let cold = Vector3::new(0.25, 0.1, 0.35);
+        let hot = Vector3::new(1.0, 0.85, 0.2);
+        let size = Vector2::new(self.cell_size, self.cell_size).cast().unwrap();
+        for (&(x, y), &heat) in &self.cells {
+            // Log scale so a single hot spot does not wash out the rest
+            let t = ((1.0 + heat).ln() / (1.0 + self.max).ln()) as f32;
+            let center = Vector2::new(x as f64 + 0.5, y as f64 + 0.5) * self.cell_size;
+            d.rect(
+                center.cast().unwrap(),
+                size,
+                0.0,
+                cold * (1.0 - t) + hot * t,
+                0.0,
+            );
+        }
+    }
+}
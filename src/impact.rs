@@ -0,0 +1,95 @@
+use crate::{
+    body::{Body, BodyId},
+    universe::Universe,
+};
+use cgmath::{InnerSpace, Vector3};
+use eframe::egui;
+
+// Where and when the selected body hits the focused body
+pub struct Impact {
+    pub state: usize,
+    pub pos: Vector3<f64>,
+    pub relative_vel: Vector3<f64>,
+}
+
+impl Impact {
+    // Seconds from the current state until the bodies touch
+    pub fn time(&self, current_state: usize, step_size: f64) -> f64 {
+        (self.state - current_state) as f64 * step_size
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.relative_vel.magnitude()
+    }
+
+    // None when the bodies just touch with no speed between them, there is no direction to measure
+    pub fn angle(&self) -> Option<f64> {
+        let scale = self.speed() * self.pos.magnitude();
+        (scale > 0.0).then(|| {
+            (self.relative_vel.dot(self.pos).abs() / scale)
+                .min(1.0)
+                .asin()
+                .to_degrees()
+        })
+    }
+}
+
+// The first state from the current one on where the selected body touches the surface of the
+// focused body
+pub fn predict(
+    states: &[Universe],
+    current_state: usize,
+    selected: BodyId,
+    focused: BodyId,
+) -> Option<Impact> {
+    if selected == focused {
+        return None;
+    }
+    states
+        .iter()
+        .enumerate()
+        .skip(current_state)
+        .find_map(|(index, universe)| {
+            let body = universe.bodies.get(selected)?;
+            let focus = universe.bodies.get(focused)?;
+            let focus_to_body = body.pos - focus.pos;
+            (focus_to_body.magnitude() < focus.radius + body.radius).then_some(Impact {
+                state: index,
+                pos: focus_to_body,
+                relative_vel: body.vel - focus.vel,
+            })
+        })
+}
+
+// Above the surface of the focused body rather than its center
+pub fn altitude_ui(ui: &mut egui::Ui, body: &Body, focus: &Body) {
+    ui.horizontal(|ui| {
+        let label = ui.label("Altitude:");
+        ui.add_enabled(
+            false,
+            egui::DragValue::new(&mut ((body.pos - focus.pos).magnitude() - focus.radius))
+                .suffix("m"),
+        )
+        .labelled_by(label.id);
+    });
+}
+
+pub fn warning_ui(ui: &mut egui::Ui, time: f64) {
+    ui.colored_label(egui::Color32::RED, format!("Warning: Impact in {time:.2}s"));
+}
+
+pub fn window(ctx: &egui::Context, impact: &Impact, current_state: usize, step_size: f64) {
+    egui::Window::new("Impact")
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Time To Impact: {:.2}s",
+                impact.time(current_state, step_size)
+            ));
+            ui.label(format!("Impact Speed: {:.3}m/s", impact.speed()));
+            ui.label(match impact.angle() {
+                Some(angle) => format!("Impact Angle: {angle:.1}°"),
+                None => "Impact Angle: None".to_string(),
+            });
+        });
+}
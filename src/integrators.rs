@@ -0,0 +1,234 @@
+use crate::body::{Body, BodyList};
+use cgmath::{InnerSpace, Vector2, Vector3};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::ops::{AddAssign, SubAssign};
+
+// Below this many bodies splitting the force loop across threads costs more than it saves
+const PARALLEL_THRESHOLD: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    Euler,
+    Rk4,
+    Verlet,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 3] = [Integrator::Euler, Integrator::Rk4, Integrator::Verlet];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Euler => "Euler",
+            Integrator::Rk4 => "Runge-Kutta 4",
+            Integrator::Verlet => "Velocity Verlet",
+        }
+    }
+}
+
+// Positions the force loops and integrators work on, in the plane or in 3D
+pub trait Space: InnerSpace<Scalar = f64> + AddAssign + SubAssign + Send + Sync {
+    // Only 3D worlds step in 3D, and their bodies are balls
+    const THREE_D: bool;
+    fn from_plane(v: Vector2<f64>) -> Self;
+    // Position and velocity
+    fn phase(body: &Body) -> (Self, Self);
+    fn set_phase(body: &mut Body, pos: Self, vel: Self);
+}
+
+impl Space for Vector2<f64> {
+    const THREE_D: bool = false;
+
+    fn from_plane(v: Vector2<f64>) -> Self {
+        v
+    }
+
+    fn phase(body: &Body) -> (Self, Self) {
+        (body.pos.truncate(), body.vel.truncate())
+    }
+
+    fn set_phase(body: &mut Body, pos: Self, vel: Self) {
+        (body.pos, body.vel) = (pos.extend(body.pos.z), vel.extend(body.vel.z));
+    }
+}
+
+impl Space for Vector3<f64> {
+    const THREE_D: bool = true;
+
+    fn from_plane(v: Vector2<f64>) -> Self {
+        v.extend(0.0)
+    }
+
+    fn phase(body: &Body) -> (Self, Self) {
+        (body.pos, body.vel)
+    }
+
+    fn set_phase(body: &mut Body, pos: Self, vel: Self) {
+        (body.pos, body.vel) = (pos, vel);
+    }
+}
+
+// The force law divides by distance to this power, 2 for real gravity. Takes the squared distance
+// since that is what the loops have at hand
+pub fn falloff(dist2: f64, exponent: f64) -> f64 {
+    if exponent == 2.0 {
+        dist2
+    } else {
+        dist2.powf(exponent * 0.5)
+    }
+}
+
+pub fn accelerations<V: Space>(masses: &[f64], pos: &[V], gravity: f64, exponent: f64) -> Vec<V> {
+    pair_accelerations(masses, pos, gravity, exponent, |_, _| true)
+}
+
+// Only pairs that include returns true for pull on each other
+pub fn pair_accelerations<V: Space>(
+    masses: &[f64],
+    pos: &[V],
+    gravity: f64,
+    exponent: f64,
+    include: impl Fn(usize, usize) -> bool + Sync,
+) -> Vec<V> {
+    if pos.len() < PARALLEL_THRESHOLD {
+        let mut acc = vec![V::zero(); pos.len()];
+        for i in 0..pos.len() {
+            for j in i + 1..pos.len() {
+                if !include(i, j) {
+                    continue;
+                }
+                let a_to_b = pos[j] - pos[i];
+                let falloff = falloff(a_to_b.magnitude2(), exponent);
+                acc[i] += a_to_b.normalize() * (gravity * masses[j] / falloff);
+                acc[j] -= a_to_b.normalize() * (gravity * masses[i] / falloff);
+            }
+        }
+        return acc;
+    }
+    // Every body sums its own row so threads never write to the same buffer entry
+    (0..pos.len())
+        .into_par_iter()
+        .map(|i| {
+            let mut acc = V::zero();
+            for j in 0..pos.len() {
+                if j == i || !include(i.min(j), i.max(j)) {
+                    continue;
+                }
+                let to_body = pos[j] - pos[i];
+                acc += to_body.normalize()
+                    * (gravity * masses[j] / falloff(to_body.magnitude2(), exponent));
+            }
+            acc
+        })
+        .collect()
+}
+
+// Like charges push apart and opposite ones pull together, massless bodies are left alone
+pub fn coulomb_accelerations<V: Space>(
+    masses: &[f64],
+    charges: &[f64],
+    pos: &[V],
+    coulomb: f64,
+) -> Vec<V> {
+    let mut acc = vec![V::zero(); pos.len()];
+    for i in 0..pos.len() {
+        if charges[i] == 0.0 {
+            continue;
+        }
+        for j in i + 1..pos.len() {
+            if charges[j] == 0.0 {
+                continue;
+            }
+            let a_to_b = pos[j] - pos[i];
+            let dist2 = a_to_b.magnitude2();
+            let force = a_to_b.normalize() * (coulomb * charges[i] * charges[j] / dist2);
+            if masses[i] > 0.0 {
+                acc[i] -= force / masses[i];
+            }
+            if masses[j] > 0.0 {
+                acc[j] += force / masses[j];
+            }
+        }
+    }
+    acc
+}
+
+// Kick then drift, the same semi-implicit step the plane's own Euler path takes
+pub fn euler<V: Space>(
+    bodies: &mut BodyList,
+    dt: f64,
+    accelerations: impl Fn(&[f64], &[V]) -> Vec<V>,
+) {
+    let masses: Vec<f64> = bodies
+        .iter()
+        .map(|(_, body)| body.mass(V::THREE_D))
+        .collect();
+    let (pos, vel): (Vec<V>, Vec<V>) = bodies.iter().map(|(_, body)| V::phase(body)).unzip();
+    let acc = accelerations(&masses, &pos);
+    for (i, (_, body)) in bodies.iter_mut().enumerate() {
+        let vel = vel[i] + acc[i] * dt;
+        V::set_phase(body, pos[i] + vel * dt, vel);
+    }
+}
+
+pub fn rk4<V: Space>(
+    bodies: &mut BodyList,
+    dt: f64,
+    accelerations: impl Fn(&[f64], &[V]) -> Vec<V>,
+) {
+    let masses: Vec<f64> = bodies
+        .iter()
+        .map(|(_, body)| body.mass(V::THREE_D))
+        .collect();
+    let (pos, vel): (Vec<V>, Vec<V>) = bodies.iter().map(|(_, body)| V::phase(body)).unzip();
+
+    let offset = |base: &[V], delta: &[V], h: f64| -> Vec<V> {
+        base.iter().zip(delta).map(|(&b, &d)| b + d * h).collect()
+    };
+
+    let k1_vel = vel.clone();
+    let k1_acc = accelerations(&masses, &pos);
+    let k2_vel = offset(&vel, &k1_acc, dt * 0.5);
+    let k2_acc = accelerations(&masses, &offset(&pos, &k1_vel, dt * 0.5));
+    let k3_vel = offset(&vel, &k2_acc, dt * 0.5);
+    let k3_acc = accelerations(&masses, &offset(&pos, &k2_vel, dt * 0.5));
+    let k4_vel = offset(&vel, &k3_acc, dt);
+    let k4_acc = accelerations(&masses, &offset(&pos, &k3_vel, dt));
+
+    for (i, (_, body)) in bodies.iter_mut().enumerate() {
+        V::set_phase(
+            body,
+            pos[i] + (k1_vel[i] + k2_vel[i] * 2.0 + k3_vel[i] * 2.0 + k4_vel[i]) * (dt / 6.0),
+            vel[i] + (k1_acc[i] + k2_acc[i] * 2.0 + k3_acc[i] * 2.0 + k4_acc[i]) * (dt / 6.0),
+        );
+    }
+}
+
+// Kick drift kick, symplectic so the energy error stays bounded instead of growing every orbit
+pub fn verlet<V: Space>(
+    bodies: &mut BodyList,
+    dt: f64,
+    accelerations: impl Fn(&[f64], &[V]) -> Vec<V>,
+) {
+    let masses: Vec<f64> = bodies
+        .iter()
+        .map(|(_, body)| body.mass(V::THREE_D))
+        .collect();
+    let (mut pos, mut vel): (Vec<V>, Vec<V>) =
+        bodies.iter().map(|(_, body)| V::phase(body)).unzip();
+
+    let acc = accelerations(&masses, &pos);
+    for i in 0..pos.len() {
+        vel[i] += acc[i] * (dt * 0.5);
+        pos[i] += vel[i] * dt;
+    }
+    let acc = accelerations(&masses, &pos);
+    for i in 0..pos.len() {
+        vel[i] += acc[i] * (dt * 0.5);
+    }
+
+    for (i, (_, body)) in bodies.iter_mut().enumerate() {
+        V::set_phase(body, pos[i], vel[i]);
+    }
+}
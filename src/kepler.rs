@@ -0,0 +1,219 @@
+use crate::world::{World, format_time};
+use cgmath::{InnerSpace, Matrix3, SquareMatrix, Vector2, Vector3};
+use eframe::egui;
+use std::f64::consts::TAU;
+
+// Samples fitted at most, long spans are strided down to this
+const MAX_SAMPLES: usize = 2000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Elements {
+    // Semi-latus rectum, unlike the semi-major axis it exists for every conic
+    pub p: f64,
+    pub eccentricity: f64,
+    // Radians counterclockwise from the x axis
+    pub periapsis_angle: f64,
+}
+
+impl Elements {
+    // From a single position and velocity relative to the parent
+    pub fn osculating(mu: f64, pos: Vector2<f64>, vel: Vector2<f64>) -> Option<Elements> {
+        if mu <= 0.0 || pos.magnitude() == 0.0 {
+            return None;
+        }
+        let eccentricity =
+            ((vel.magnitude2() - mu / pos.magnitude()) * pos - pos.dot(vel) * vel) / mu;
+        Some(Elements {
+            p: pos.perp_dot(vel).powi(2) / mu,
+            eccentricity: eccentricity.magnitude(),
+            periapsis_angle: eccentricity.y.atan2(eccentricity.x),
+        })
+    }
+
+    pub fn semi_major_axis(&self) -> Option<f64> {
+        (self.eccentricity < 1.0).then(|| self.p / (1.0 - self.eccentricity.powi(2)))
+    }
+
+    pub fn period(&self, mu: f64) -> Option<f64> {
+        let a = self.semi_major_axis()?;
+        (mu > 0.0).then(|| TAU * (a.powi(3) / mu).sqrt())
+    }
+
+    pub fn distance(&self, angle: f64) -> f64 {
+        self.p / (1.0 + self.eccentricity * (angle - self.periapsis_angle).cos())
+    }
+}
+
+pub struct Fit {
+    pub elements: Elements,
+    pub samples: usize,
+    // Root mean square of how far the samples are from the fitted conic, along the radius
+    pub residual: f64,
+}
+
+// With the parent at a focus, 1/r = 1/p + (e/p) cos(w) cos(t) + (e/p) sin(w) sin(t), which is linear
+// in three unknowns and can be solved by least squares directly
+pub fn fit(samples: &[Vector2<f64>]) -> Option<Fit> {
+    let samples: Vec<Vector2<f64>> = samples
+        .iter()
+        .copied()
+        .filter(|pos| pos.magnitude() > 0.0)
+        .collect();
+    if samples.len() < 3 {
+        return None;
+    }
+    let mut normal = Matrix3::from_value(0.0);
+    let mut rhs = Vector3::new(0.0, 0.0, 0.0);
+    for pos in &samples {
+        let r = pos.magnitude();
+        let basis = Vector3::new(1.0, pos.x / r, pos.y / r);
+        for row in 0..3 {
+            for column in 0..3 {
+                normal[column][row] += basis[row] * basis[column];
+            }
+        }
+        rhs += basis / r;
+    }
+    let [c0, c1, c2]: [f64; 3] = (normal.invert()? * rhs).into();
+    if c0 <= 0.0 {
+        return None;
+    }
+    let elements = Elements {
+        p: 1.0 / c0,
+        eccentricity: (c1 * c1 + c2 * c2).sqrt() / c0,
+        periapsis_angle: c2.atan2(c1),
+    };
+    let residual = (samples
+        .iter()
+        .map(|pos| (elements.distance(pos.y.atan2(pos.x)) - pos.magnitude()).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64)
+        .sqrt();
+    Some(Fit {
+        elements,
+        samples: samples.len(),
+        residual,
+    })
+}
+
+pub struct KeplerWindow {
+    // Seconds of past to fit
+    pub span: f64,
+}
+
+impl Default for KeplerWindow {
+    fn default() -> Self {
+        Self { span: 100.0 }
+    }
+}
+
+impl KeplerWindow {
+    pub fn ui(&mut self, ui: &mut egui::Ui, world: &World) {
+        if world.unavailable_in_3d(ui) {
+            return;
+        }
+        ui.horizontal(|ui| {
+            let label = ui.label("Observed:");
+            ui.add(
+                egui::DragValue::new(&mut self.span)
+                    .range(0.0..=f64::INFINITY)
+                    .speed(1.0)
+                    .suffix("s"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("How far back the recorded trail is fitted");
+        });
+        let (Some(id), Some(parent)) = (world.selected, world.focused_body()) else {
+            ui.label("Select a body and focus the body it orbits");
+            return;
+        };
+        let universe = world.state();
+        let (Some(body), Some(parent_body)) =
+            (universe.bodies.get(id), universe.bodies.get(parent))
+        else {
+            return;
+        };
+        if id == parent {
+            ui.label("Select a body other than the focused one");
+            return;
+        }
+        let mu =
+            universe.gravity * (body.mass(universe.three_d) + parent_body.mass(universe.three_d));
+        let first = world
+            .current_state
+            .saturating_sub((self.span / world.step_size) as usize);
+        let stride = (world.current_state - first) / MAX_SAMPLES + 1;
+        let observed: Vec<Vector2<f64>> = (first..=world.current_state)
+            .rev()
+            .step_by(stride)
+            .filter_map(|index| {
+                let universe = &world.states[index];
+                Some(universe.position(id)? - universe.position(parent)?)
+            })
+            .collect();
+        let fitted = fit(&observed);
+        let osculating = Elements::osculating(
+            mu,
+            (body.pos - parent_body.pos).truncate(),
+            (body.vel - parent_body.vel).truncate(),
+        );
+
+        let distance =
+            |value: Option<f64>| value.map_or("-".into(), |value| format!("{value:.3}m"));
+        egui::Grid::new("Kepler Fit")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.strong("Fitted");
+                ui.strong("Osculating");
+                ui.end_row();
+                let mut row = |label: &str, value: &dyn Fn(&Elements) -> String| {
+                    ui.label(label);
+                    for elements in [
+                        fitted.as_ref().map(|fit| &fit.elements),
+                        osculating.as_ref(),
+                    ] {
+                        ui.label(elements.map_or("-".into(), value));
+                    }
+                    ui.end_row();
+                };
+                row("Semi-Major Axis", &|elements| {
+                    distance(elements.semi_major_axis())
+                });
+                row("Eccentricity", &|elements| {
+                    format!("{:.5}", elements.eccentricity)
+                });
+                row("Periapsis", &|elements| {
+                    format!("{:.3}m", elements.p / (1.0 + elements.eccentricity))
+                });
+                row("Apoapsis", &|elements| {
+                    distance(
+                        (elements.eccentricity < 1.0)
+                            .then(|| elements.p / (1.0 - elements.eccentricity)),
+                    )
+                });
+                row("Argument of Periapsis", &|elements| {
+                    format!("{:.2}deg", elements.periapsis_angle.to_degrees())
+                });
+                row("Period", &|elements| {
+                    elements.period(mu).map_or("-".into(), format_time)
+                });
+            });
+        match &fitted {
+            Some(fit) => {
+                ui.label(format!(
+                    "{} samples, {:.4}m RMS residual",
+                    fit.samples, fit.residual
+                ));
+            }
+            None => {
+                ui.label("Not enough recorded trail to fit");
+            }
+        }
+        // Perturbations from other bodies make the osculating orbit wander around the average one
+        ui.label(
+            "The fit averages over the whole trail, the osculating orbit is only this instant",
+        );
+    }
+}
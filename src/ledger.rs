@@ -0,0 +1,47 @@
+use crate::{body::Body, world::format_time};
+use cgmath::InnerSpace;
+use eframe::egui;
+
+// Every burn the body has made and what its engine spent, which is what a mission would have to
+// carry in propellant. Returns whether the ledger was cleared
+pub fn ui(ui: &mut egui::Ui, body: &mut Body, can_edit: bool) -> bool {
+    let mut changed = false;
+    ui.collapsing("Delta-v Ledger", |ui| {
+        let spent = body.thrust.map_or(0.0, |thrust| thrust.spent);
+        if body.burns.is_empty() && spent == 0.0 {
+            ui.label("No burns yet");
+            return;
+        }
+        egui::Grid::new("Delta-v Ledger")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for burn in &body.burns {
+                    ui.label(format_time(burn.time));
+                    ui.label(burn.kind.name());
+                    ui.label(format!("{:.3}m/s", burn.delta_v.magnitude()));
+                    ui.end_row();
+                }
+                if spent > 0.0 {
+                    ui.label("");
+                    ui.label("Engine");
+                    ui.label(format!("{spent:.3}m/s"));
+                    ui.end_row();
+                }
+            });
+        ui.label(format!("Total: {:.3}m/s", body.total_delta_v()))
+            .on_hover_text("Sum of every burn's size, the cost in propellant");
+        if ui
+            .add_enabled(can_edit, egui::Button::new("Clear"))
+            .on_hover_text("Start counting again from this state")
+            .clicked()
+        {
+            changed = true;
+            body.burns.clear();
+            if let Some(thrust) = &mut body.thrust {
+                thrust.spent = 0.0;
+            }
+        }
+    });
+    changed
+}
@@ -0,0 +1,173 @@
+use eframe::egui;
+use std::{collections::VecDeque, fmt::Write, sync::Mutex, time::Instant};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_log::{LogTracer, NormalizeEvent};
+use tracing_subscriber::{
+    Layer,
+    filter::Targets,
+    fmt,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+};
+
+// Older entries are dropped so a noisy worker cannot grow the log forever
+const LOG_LIMIT: usize = 2000;
+
+static LOG: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    // Seconds since the app started
+    pub time: f64,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+// Keeps events for the log window, the console gets its own layer
+struct Collector {
+    start: Instant,
+}
+
+// The message comes first and every other field is appended as key=value
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else if !field.name().starts_with("log.") {
+            _ = write!(self.rest, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else if !field.name().starts_with("log.") {
+            _ = write!(self.rest, " {}={value:?}", field.name());
+        }
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Collector {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        // Records from the log crate carry their real target in fields
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        let mut log = LOG.lock().unwrap();
+        if log.len() >= LOG_LIMIT {
+            log.pop_front();
+        }
+        log.push_back(Entry {
+            time: self.start.elapsed().as_secs_f64(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: fields.message + &fields.rest,
+        });
+    }
+}
+
+pub fn init() {
+    // Debug output is only kept from this crate, dependencies are limited to warnings
+    let filter = Targets::new()
+        .with_default(Level::WARN)
+        .with_target(env!("CARGO_CRATE_NAME"), Level::DEBUG);
+    let subscriber = tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(Collector {
+            start: Instant::now(),
+        })
+        .with(filter);
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        return;
+    }
+    // Dependencies like wgpu report through the log crate, only their warnings are kept
+    _ = LogTracer::builder()
+        .with_max_level(log::LevelFilter::Warn)
+        .init();
+}
+
+pub struct LogWindow {
+    pub level: Level,
+    pub module: String,
+    pub follow: bool,
+}
+
+impl Default for LogWindow {
+    fn default() -> Self {
+        Self {
+            level: Level::INFO,
+            module: String::new(),
+            follow: true,
+        }
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::ERROR => egui::Color32::from_rgb(240, 90, 90),
+        Level::WARN => egui::Color32::from_rgb(240, 200, 80),
+        Level::INFO => egui::Color32::from_rgb(120, 200, 240),
+        _ => egui::Color32::GRAY,
+    }
+}
+
+impl LogWindow {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let label = ui.label("Level:");
+            egui::ComboBox::from_id_salt("Log Level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG] {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            let label = ui.label("Module:");
+            ui.text_edit_singleline(&mut self.module)
+                .labelled_by(label.id)
+                .on_hover_text("Only show entries whose module contains this");
+            ui.checkbox(&mut self.follow, "Follow");
+            if ui.button("Clear").clicked() {
+                LOG.lock().unwrap().clear();
+            }
+        });
+        ui.separator();
+        let entries: Vec<Entry> = LOG
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.level <= self.level && entry.target.contains(&self.module))
+            .cloned()
+            .collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(self.follow)
+            .show_rows(ui, row_height, entries.len(), |ui, rows| {
+                for entry in &entries[rows] {
+                    ui.horizontal(|ui| {
+                        ui.monospace(format!("{:9.3}", entry.time));
+                        ui.colored_label(
+                            level_color(entry.level),
+                            egui::RichText::new(format!("{:>5}", entry.level.as_str())).monospace(),
+                        );
+                        ui.monospace(&entry.target);
+                        ui.monospace(&entry.message);
+                    });
+                }
+            });
+    }
+}
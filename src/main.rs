@@ -1,22 +1,78 @@
 use crate::{
+    choreography::ChoreographyWindow,
+    config::ConfigFile,
     drawing::DrawHandler,
+    kepler::KeplerWindow,
+    logging::LogWindow,
+    moons::MoonWindow,
+    presets::PRESETS,
+    profiler::{Phase, Profiler},
+    recovery::{CrashReport, Snapshotter},
     rendering::{GpuCamera, RenderData, RenderState},
+    rescale::RescaleWindow,
     save::Save,
-    world::World,
+    settings::{Settings, apply_font_size},
+    tools::Tool,
+    world::{Focus, World},
 };
+use anyhow::Context;
 use eframe::{
     egui::{self},
     wgpu,
 };
 use egui_file_dialog::FileDialog;
 use peak_alloc::PeakAlloc;
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+const RECENT_FILES_LIMIT: usize = 8;
 
+pub mod alarms;
+pub mod auto_orbit;
 pub mod body;
 pub mod camera;
+pub mod camera_path;
+pub mod chaos;
+pub mod choreography;
+pub mod cli;
+pub mod config;
+pub mod conics;
+pub mod conservation;
+pub mod csv;
+pub mod detachable;
 pub mod drawing;
+pub mod ephemeris;
+pub mod event_ramp;
+pub mod expression;
+pub mod graphs;
+pub mod heatmap;
+pub mod impact;
+pub mod integrators;
+pub mod kepler;
+pub mod ledger;
+pub mod logging;
+pub mod moons;
+pub mod observer;
+pub mod orbit_info;
+pub mod overlays;
+pub mod picking;
+pub mod plot;
+pub mod presets;
+pub mod profiler;
+pub mod recovery;
+pub mod reference;
 pub mod rendering;
+pub mod replay;
+pub mod report;
+pub mod rescale;
 pub mod save;
+pub mod settings;
+pub mod svg;
+pub mod thrust;
+pub mod tools;
+pub mod tutorial;
 pub mod universe;
 pub mod world;
 
@@ -27,18 +83,73 @@ struct App {
     last_time: Option<std::time::Instant>,
     lagging: bool,
     stats_open: bool,
+    profiler: Profiler,
+    log_open: bool,
+    log_window: LogWindow,
+    choreography_open: bool,
+    choreography_window: ChoreographyWindow,
     file_dialog: FileDialog,
     file_interaction: FileInteraction,
-    help_open: bool,
+    constants_open: bool,
+    statistics_open: bool,
+    statistics_detached: bool,
+    world_info_detached: bool,
+    conservation_detached: bool,
+    graph_detached: bool,
+    ephemeris_open: bool,
+    cross_check_open: bool,
+    trash_open: bool,
+    experiment_open: bool,
+    approach_open: bool,
+    comparison_open: bool,
+    moons_open: bool,
+    rescale_open: bool,
+    transfer_open: bool,
+    kepler_open: bool,
+    replay_open: bool,
+    kepler_window: KeplerWindow,
+    moon_window: MoonWindow,
+    rescale_window: RescaleWindow,
+    stopwatch_open: bool,
+    alarms_open: bool,
+    overlays_open: bool,
+    observer_open: bool,
+    spawn_queue_open: bool,
+    heat_map_open: bool,
+    conservation_open: bool,
+    graph_open: bool,
+    chaos_open: bool,
+    clock_open: bool,
+    pip_open: bool,
+    settings_open: bool,
+    settings: Settings,
+    config: Option<ConfigFile>,
+    last_zoom: f32,
+    applied_font_size: Option<f32>,
     worlds: Vec<World>,
     selected_world: usize,
-    new_world_time_step: usize,
+    recent_files: Vec<String>,
+    snapshotter: Snapshotter,
+    crash_report: Option<CrashReport>,
+    // Path of the last opened file and whatever could not be loaded from it
+    load_report: Option<(String, Vec<String>)>,
+    // Size of the world view last frame, exported pictures match it
+    view_size: egui::Vec2,
+    screenshot_open: bool,
+    screenshot_size: [u32; 2],
 }
 
 enum FileInteraction {
     None,
     Save,
     Load,
+    ExportEphemeris,
+    ExportProfile,
+    ImportProfile,
+    ExportReport,
+    ExportCsv(csv::Range),
+    ExportSvg,
+    Screenshot,
 }
 
 impl App {
@@ -47,59 +158,361 @@ impl App {
         let state = RenderState::new(renderer.target_format, &renderer.device, &renderer.queue)?;
         renderer.renderer.write().callback_resources.insert(state);
 
-        let mut new_world_time_step = 512;
-        let mut worlds = vec![World::new(1.0 / new_world_time_step as f64)];
-        let mut help_open = true;
+        let mut worlds = vec![];
+        let mut recent_files = vec![];
+        let mut clock_open = false;
+        let mut pip_open = false;
+        let (config, config_settings) = ConfigFile::open().unzip();
+        let mut settings = config_settings.flatten().unwrap_or_default();
 
         if let Some(storage) = cc.storage {
             let saves: Result<Vec<Save>, serde_json::Error> =
                 serde_json::from_str(storage.get_string("Worlds").unwrap_or_default().as_str());
 
-            if let Ok(saves) = saves {
-                worlds = saves
-                    .into_iter()
-                    .map(|save| World::from_save(save))
-                    .collect();
-                println!("Loaded Successfully");
-            } else {
-                println!("Failed To Load What Was Previously opened")
+            match saves {
+                Ok(saves) => {
+                    worlds = saves
+                        .into_iter()
+                        .map(|save| World::from_save(save))
+                        .collect();
+                    tracing::info!("Loaded {} previously open worlds", worlds.len());
+                }
+                Err(error) => tracing::error!("Failed to load what was previously open: {error}"),
             }
-            if let Some(string) = storage.get_string("HelpOpen") {
-                help_open = serde_json::from_str(string.as_str()).unwrap();
+            if let Some(string) = storage.get_string("ClockOpen") {
+                clock_open = serde_json::from_str(string.as_str()).unwrap();
+            };
+            if let Some(string) = storage.get_string("PipOpen") {
+                pip_open = serde_json::from_str(string.as_str()).unwrap();
             };
-            if let Some(string) = storage.get_string("NewWorldTimeStep") {
-                new_world_time_step = serde_json::from_str(string.as_str()).unwrap();
+            if let Some(string) = storage.get_string("RecentFiles") {
+                recent_files = serde_json::from_str(string.as_str()).unwrap_or_default();
             };
+            // Preferences used to be kept here, they are moved over to the config file once
+            if config.as_ref().is_none_or(|config| !config.path.exists()) {
+                if let Some(string) = storage.get_string("Settings") {
+                    settings = serde_json::from_str(string.as_str()).unwrap_or_default();
+                };
+                if let Some(string) = storage.get_string("NewWorldTimeStep") {
+                    settings.new_world_time_step =
+                        serde_json::from_str(string.as_str()).unwrap_or(512);
+                };
+            }
         }
 
         Ok(Self {
             last_time: None,
             lagging: false,
             stats_open: true,
+            profiler: Profiler::default(),
+            log_open: false,
+            log_window: LogWindow::default(),
+            choreography_open: false,
+            choreography_window: ChoreographyWindow::default(),
             file_dialog: FileDialog::new()
                 .add_file_filter_extensions("Orbit Save", vec!["orbit"])
+                .add_file_filter_extensions("Settings Profile", vec!["toml"])
                 .default_file_filter("Orbit Save")
                 .add_save_extension("Orbit Save", "orbit")
+                .add_save_extension("Settings Profile", "toml")
                 .default_save_extension("Orbit Save"),
             file_interaction: FileInteraction::None,
-            help_open,
+            constants_open: false,
+            statistics_open: false,
+            statistics_detached: false,
+            world_info_detached: false,
+            conservation_detached: false,
+            graph_detached: false,
+            ephemeris_open: false,
+            cross_check_open: false,
+            trash_open: false,
+            experiment_open: false,
+            approach_open: false,
+            comparison_open: false,
+            moons_open: false,
+            rescale_open: false,
+            transfer_open: false,
+            kepler_open: false,
+            replay_open: false,
+            kepler_window: KeplerWindow::default(),
+            moon_window: MoonWindow::default(),
+            rescale_window: RescaleWindow::default(),
+            stopwatch_open: false,
+            alarms_open: false,
+            overlays_open: false,
+            observer_open: false,
+            spawn_queue_open: false,
+            heat_map_open: false,
+            conservation_open: false,
+            graph_open: false,
+            chaos_open: false,
+            clock_open,
+            pip_open,
+            settings_open: false,
+            settings,
+            config,
+            last_zoom: cc.egui_ctx.zoom_factor(),
+            applied_font_size: None,
             worlds,
             selected_world: 0,
-            new_world_time_step,
+            recent_files,
+            snapshotter: Snapshotter::new(),
+            crash_report: recovery::take_report(),
+            load_report: None,
+            view_size: egui::vec2(1280.0, 720.0),
+            screenshot_open: false,
+            screenshot_size: [3840, 2160],
         })
     }
     fn world(&mut self) -> &mut World {
         self.selected_world = self.selected_world.min(self.worlds.len() - 1);
         &mut self.worlds[self.selected_world]
     }
+
+    // The world view drawn offscreen at the given size, as rows of RGBA pixels
+    fn render_offscreen(
+        &mut self,
+        frame: &eframe::Frame,
+        [width, height]: [u32; 2],
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut d = DrawHandler::new();
+        let view = self.world().view();
+        self.world().draw_states(&view, &mut d);
+        let camera = self.world().camera.gpu(width as f32 / height as f32);
+        let render_state = frame.wgpu_render_state().context("No wgpu renderer")?;
+        render_state
+            .renderer
+            .write()
+            .callback_resources
+            .get_mut::<RenderState>()
+            .context("Renderer is not set up")?
+            .screenshot(
+                &render_state.device,
+                &render_state.queue,
+                &camera,
+                &d.quads,
+                &d.circles,
+                [width, height],
+            )
+    }
+
+    fn remember_file(&mut self, path: &Path) {
+        let path = path.to_str().unwrap().to_string();
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(RECENT_FILES_LIMIT);
+    }
+
+    fn open_file(&mut self, path: &Path) {
+        let loaded = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .and_then(|bytes| save::read(&bytes));
+        let problems = match loaded {
+            Ok((save, problems)) => {
+                self.worlds.push(World::from_save(save));
+                self.selected_world = self.worlds.len();
+                self.remember_file(path);
+                problems
+            }
+            Err(error) => vec![format!("{error:#}")],
+        };
+        for problem in &problems {
+            tracing::warn!("{}: {problem}", path.display());
+        }
+        if !problems.is_empty() {
+            self.load_report = Some((path.display().to_string(), problems));
+        }
+    }
+
+    fn load_report_ui(&mut self, ctx: &egui::Context) {
+        let Some((path, problems)) = &self.load_report else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Loading Problems")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Not everything in {path} could be loaded:"));
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for problem in problems {
+                            ui.label(format!("• {problem}"));
+                        }
+                    });
+            });
+        if !open {
+            self.load_report = None;
+        }
+    }
+
+    fn crash_report_ui(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.crash_report else {
+            return;
+        };
+        let mut close = false;
+        let mut recover = false;
+        egui::Window::new("Orbit Playground Crashed")
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Sorry, the last session ended in a crash.");
+                if report.worlds.is_empty() {
+                    ui.label("No worlds were open to recover.");
+                } else {
+                    ui.label(format!(
+                        "{} world(s) open at the time were saved and can be reopened.",
+                        report.worlds.len()
+                    ));
+                }
+                ui.collapsing("Details", |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| ui.monospace(&report.message));
+                    if ui.button("Copy to Clipboard").clicked() {
+                        ui.ctx().copy_text(report.message.clone());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if !report.worlds.is_empty() {
+                        recover = ui.button("Reopen Worlds").clicked();
+                    }
+                    close = ui.button("Dismiss").clicked();
+                });
+            });
+        if recover {
+            for path in &report.worlds {
+                if let Ok(bytes) = std::fs::read(path)
+                    && let Ok((save, _)) = save::read(&bytes)
+                {
+                    self.worlds.push(World::from_save(save));
+                }
+            }
+            self.selected_world = self.worlds.len();
+        }
+        if recover || close {
+            recovery::clear(report);
+            self.crash_report = None;
+        }
+    }
+
+    // Shown instead of a world when none are open
+    fn start_screen(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.heading("Orbit Playground");
+            ui.add_space(20.0);
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.group(|ui| {
+                ui.vertical(|ui| {
+                    ui.strong("Blank World");
+                    ui.label("An empty space to build in");
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Time Step:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings.new_world_time_step)
+                                .prefix("1/"),
+                        )
+                        .labelled_by(label.id);
+                    });
+                    if ui.button("Create").clicked() {
+                        self.worlds.push(World::new(
+                            1.0 / self.settings.new_world_time_step as f64,
+                            self.settings.default_units,
+                        ));
+                    }
+                    if ui
+                        .button("Create 3D")
+                        .on_hover_text(World::THREE_D_HINT)
+                        .clicked()
+                    {
+                        self.worlds.push(World::new_3d(
+                            1.0 / self.settings.new_world_time_step as f64,
+                            self.settings.default_units,
+                        ));
+                    }
+                });
+            });
+            ui.group(|ui| {
+                ui.vertical(|ui| {
+                    ui.strong("Tutorial");
+                    ui.label("Learn the controls step by step");
+                    if ui.button("Start").clicked() {
+                        self.worlds.push(tutorial::world(
+                            1.0 / self.settings.new_world_time_step as f64,
+                        ));
+                    }
+                });
+            });
+            for preset in &PRESETS {
+                ui.group(|ui| {
+                    ui.vertical(|ui| {
+                        ui.strong(preset.name);
+                        ui.label(preset.description);
+                        if ui.button("Open").clicked() {
+                            self.worlds
+                                .push(preset.world(1.0 / self.settings.new_world_time_step as f64));
+                        }
+                    });
+                });
+            }
+        });
+        ui.add_space(20.0);
+        ui.strong("Recent Files");
+        if self.recent_files.is_empty() {
+            ui.label("Saved and opened worlds show up here");
+        }
+        let mut open = None;
+        for path in &self.recent_files {
+            let exists = Path::new(path).exists();
+            if ui
+                .add_enabled(exists, egui::Button::new(path.as_str()).frame(false))
+                .on_disabled_hover_text("This file no longer exists")
+                .clicked()
+            {
+                open = Some(PathBuf::from(path));
+            }
+        }
+        if ui.button("Open Other").clicked() {
+            self.file_interaction = FileInteraction::Load;
+            self.file_dialog.pick_file();
+        }
+        if let Some(path) = open {
+            self.open_file(&path);
+        }
+    }
 }
 
 impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         let time = std::time::Instant::now();
         let dt = time - self.last_time.unwrap_or(time);
         self.last_time = Some(time);
 
+        if let Some(config) = &mut self.config {
+            config.sync(&mut self.settings);
+        }
+        self.snapshotter.update(&self.worlds);
+        if ctx.style().visuals != self.settings.theme.visuals() {
+            ctx.set_visuals(self.settings.theme.visuals());
+        }
+        // Zooming with the keyboard is kept in the settings instead of being undone, a new zoom
+        // only shows up in zoom_factor the frame after it is set
+        let zoom = ctx.zoom_factor();
+        if zoom != self.last_zoom && zoom != self.settings.ui_scale {
+            self.settings.ui_scale = zoom;
+        }
+        if zoom != self.settings.ui_scale {
+            ctx.set_zoom_factor(self.settings.ui_scale);
+        }
+        self.last_zoom = zoom;
+        if self.applied_font_size != Some(self.settings.font_size) {
+            apply_font_size(ctx, self.settings.font_size);
+            self.applied_font_size = Some(self.settings.font_size);
+        }
+
         let dt = dt.as_secs_f64();
 
         egui::TopBottomPanel::top("Menu").show(ctx, |ui| {
@@ -107,18 +520,38 @@ impl eframe::App for App {
                 ui.menu_button("File", |ui| {
                     ui.horizontal(|ui| {
                         if ui.button("New").clicked() {
-                            self.worlds
-                                .push(World::new(1.0 / self.new_world_time_step as f64));
+                            self.worlds.push(World::new(
+                                1.0 / self.settings.new_world_time_step as f64,
+                                self.settings.default_units,
+                            ));
+                        }
+                        if ui
+                            .button("New 3D")
+                            .on_hover_text(World::THREE_D_HINT)
+                            .clicked()
+                        {
+                            self.worlds.push(World::new_3d(
+                                1.0 / self.settings.new_world_time_step as f64,
+                                self.settings.default_units,
+                            ));
                         }
-                        ui.label("Time Step:");
-                        ui.add(egui::DragValue::new(&mut self.new_world_time_step).prefix("1/"))
+                        let label = ui.label("Time Step:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings.new_world_time_step)
+                                .prefix("1/"),
+                        )
+                        .labelled_by(label.id)
                     });
-                    if ui.button("Save").clicked() {
+                    let has_world = !self.worlds.is_empty();
+                    if ui
+                        .add_enabled(has_world, egui::Button::new("Save"))
+                        .clicked()
+                    {
                         match &self.world().save_path {
                             Some(path) => {
                                 let path = PathBuf::from(path);
-                                _ = std::fs::write(
-                                    path,
+                                write_file(
+                                    &path,
                                     serde_json::to_string(&self.world().to_save()).unwrap(),
                                 );
                                 self.world().modified_since_save_to_file = false;
@@ -129,7 +562,10 @@ impl eframe::App for App {
                             }
                         }
                     };
-                    if ui.button("Save As").clicked() {
+                    if ui
+                        .add_enabled(has_world, egui::Button::new("Save As"))
+                        .clicked()
+                    {
                         self.file_interaction = FileInteraction::Save;
                         self.file_dialog.save_file();
                     }
@@ -137,10 +573,7 @@ impl eframe::App for App {
                         for world in &mut self.worlds {
                             if let Some(path) = &world.save_path {
                                 let path = PathBuf::from(path);
-                                _ = std::fs::write(
-                                    path,
-                                    serde_json::to_string(&world.to_save()).unwrap(),
-                                );
+                                write_file(&path, serde_json::to_string(&world.to_save()).unwrap());
                                 world.modified_since_save_to_file = false;
                             }
                         }
@@ -149,11 +582,157 @@ impl eframe::App for App {
                         self.file_interaction = FileInteraction::Load;
                         self.file_dialog.pick_file();
                     }
+                    if ui
+                        .button("Generate Report")
+                        .on_hover_text(
+                            "An HTML page with a summary, plots and events of this world",
+                        )
+                        .clicked()
+                    {
+                        self.file_interaction = FileInteraction::ExportReport;
+                        self.file_dialog.save_file();
+                    }
+                    if ui
+                        .button("Screenshot")
+                        .on_hover_text("Render the view to a PNG at any resolution")
+                        .clicked()
+                    {
+                        self.screenshot_open = true;
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Export View as SVG")
+                        .on_hover_text("The bodies, trails and markers in view as a vector picture")
+                        .clicked()
+                    {
+                        self.file_interaction = FileInteraction::ExportSvg;
+                        self.file_dialog.save_file();
+                    }
+                    ui.menu_button("Export CSV", |ui| {
+                        for range in csv::Range::ALL {
+                            if ui
+                                .button(range.name())
+                                .on_hover_text(
+                                    "Time, id, name, position, velocity and mass of every body in every state",
+                                )
+                                .clicked()
+                            {
+                                self.file_interaction = FileInteraction::ExportCsv(range);
+                                self.file_dialog.save_file();
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                ui.menu_button("View", |ui| {
+                    let Some(world) = self.worlds.get_mut(self.selected_world) else {
+                        ui.label("No world open");
+                        return;
+                    };
+                    // Markers are placed in the plane, which bodies in a 3D world leave
+                    ui.add_enabled_ui(!world.state().three_d, |ui| {
+                        ui.checkbox(&mut world.barycenter_marker, "Barycenter Marker");
+                        ui.checkbox(
+                            &mut world.group_barycenter_marker,
+                            "Group Barycenter Marker",
+                        )
+                        .on_hover_text("The selected body and everything orbiting it");
+                        ui.checkbox(&mut world.apsis_markers, "Apsis Markers");
+                        ui.checkbox(&mut world.soi_rings, "Spheres of Influence")
+                            .on_hover_text(
+                                "Where each body dominates the pull of the body it orbits",
+                            );
+                        ui.checkbox(&mut world.hill_sphere, "Hill Sphere")
+                            .on_hover_text("Where moons of the focused body can stay bound to it");
+                        ui.checkbox(&mut world.spin_gauges, "Spin Gauges").on_hover_text(
+                            "How fast each body spins next to the rate it would be tidally locked at",
+                        );
+                    });
+                    if world.state().three_d
+                        && ui
+                            .add_enabled(
+                                world.camera.is_turned(),
+                                egui::Button::new("Look From Above"),
+                            )
+                            .on_hover_text("Drag with the right mouse button to turn the view")
+                            .clicked()
+                    {
+                        (world.camera.yaw, world.camera.pitch) = (0.0, 0.0);
+                        ui.close_menu();
+                    }
+                    if ui.button("Focus Barycenter").clicked() {
+                        world.set_focus(Some(Focus::Barycenter));
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(world.focused.is_some(), egui::Button::new("Unfocus"))
+                        .clicked()
+                    {
+                        world.set_focus(None);
+                        ui.close_menu();
+                    }
                 });
                 ui.menu_button("Windows", |ui| {
                     self.stats_open |= ui.button("Stats").clicked();
-                    self.help_open |= ui.button("Help").clicked();
+                    self.log_open |= ui.button("Log").clicked();
+                    if ui.button("Tutorial").clicked() {
+                        self.worlds.push(tutorial::world(
+                            1.0 / self.settings.new_world_time_step as f64,
+                        ));
+                        self.selected_world = self.worlds.len();
+                    }
+                    self.settings_open |= ui.button("Settings").clicked();
+                    self.choreography_open |= ui.button("Choreographies").clicked();
+                    self.constants_open |= ui.button("Constants").clicked();
+                    self.statistics_open |= ui.button("Statistics").clicked();
+                    self.ephemeris_open |= ui.button("Ephemeris").clicked();
+                    self.cross_check_open |= ui.button("Cross Check").clicked();
+                    self.trash_open |= ui.button("Trash").clicked();
+                    self.experiment_open |= ui.button("Experiment").clicked();
+                    self.stopwatch_open |= ui.button("Stopwatch").clicked();
+                    self.alarms_open |= ui.button("Alarms").clicked();
+                    self.overlays_open |= ui.button("Overlays").clicked();
+                    self.observer_open |= ui.button("Observer").clicked();
+                    self.approach_open |= ui.button("Closest Approach").clicked();
+                    self.comparison_open |= ui.button("Compare Bodies").clicked();
+                    self.moons_open |= ui.button("Moon Generator").clicked();
+                    self.rescale_open |= ui.button("Rescale World").clicked();
+                    self.transfer_open |= ui.button("Transfer Planner").clicked();
+                    self.kepler_open |= ui.button("Kepler Fit").clicked();
+                    self.replay_open |= ui.button("Action Replay").clicked();
+                    self.spawn_queue_open |= ui.button("Spawn Queue").clicked();
+                    self.heat_map_open |= ui.button("Heat Map").clicked();
+                    self.conservation_open |= ui.button("Conservation").clicked();
+                    self.graph_open |= ui.button("Graphs").clicked();
+                    self.chaos_open |= ui.button("Chaos").clicked();
+                    ui.checkbox(&mut self.clock_open, "Clock");
+                    ui.checkbox(&mut self.pip_open, "Picture in Picture");
                 });
+                if let Some(world) = self.worlds.get_mut(self.selected_world) {
+                    ui.separator();
+                    let back = world.focus_history.last().copied();
+                    if ui
+                        .add_enabled(back.is_some(), egui::Button::new("Back"))
+                        .on_hover_text(format!(
+                            "Focus {}, also the back mouse button",
+                            world.focus_name(back.flatten())
+                        ))
+                        .clicked()
+                    {
+                        world.focus_back();
+                    }
+                    let forward = world.focus_future.last().copied();
+                    if ui
+                        .add_enabled(forward.is_some(), egui::Button::new("Forward"))
+                        .on_hover_text(format!(
+                            "Focus {}, also the forward mouse button",
+                            world.focus_name(forward.flatten())
+                        ))
+                        .clicked()
+                    {
+                        world.focus_forward();
+                    }
+                }
             });
             ui.horizontal(|ui| {
                 ui.label("Open Worlds: ");
@@ -186,46 +765,164 @@ impl eframe::App for App {
                     self.worlds.remove(remove);
                 }
                 if ui.button("+").clicked() {
-                    self.worlds
-                        .push(World::new(1.0 / self.new_world_time_step as f64));
+                    self.worlds.push(World::new(
+                        1.0 / self.settings.new_world_time_step as f64,
+                        self.settings.default_units,
+                    ));
                 }
             })
         });
 
         self.file_dialog.update(ctx);
-        'file_loading: {
-            if let Some(path) = self.file_dialog.take_picked() {
-                match core::mem::replace(&mut self.file_interaction, FileInteraction::None) {
-                    FileInteraction::None => {}
-                    FileInteraction::Save => {
-                        let save_string = serde_json::to_string(&self.world().to_save()).unwrap();
+        if let Some(path) = self.file_dialog.take_picked() {
+            match core::mem::replace(&mut self.file_interaction, FileInteraction::None) {
+                FileInteraction::None => {}
+                FileInteraction::Save => {
+                    let save_string = serde_json::to_string(&self.world().to_save()).unwrap();
+                    let mut path = path;
+                    if path.extension().is_none() {
+                        path.set_extension("orbit");
+                    }
+                    write_file(&path, save_string);
+                    self.world().save_path = Some(path.to_str().unwrap().to_string());
+                    self.world().modified_since_save_to_file = false;
+                    self.world().name = path.file_name().unwrap().to_str().unwrap().to_string();
+                    self.remember_file(&path);
+                }
+                FileInteraction::Load => self.open_file(&path),
+                FileInteraction::ExportEphemeris => {
+                    let world = self.world();
+                    if let Some(target) = world.selected {
+                        let table = ephemeris::table(
+                            world,
+                            target,
+                            world.ephemeris_center,
+                            world.ephemeris_interval,
+                        );
                         let mut path = path;
                         if path.extension().is_none() {
-                            path.set_extension("orbit");
+                            path.set_extension("txt");
                         }
-                        _ = std::fs::write(&path, save_string);
-                        self.world().save_path = Some(path.to_str().unwrap().to_string());
-                        self.world().modified_since_save_to_file = false;
-                        self.world().name = path.file_name().unwrap().to_str().unwrap().to_string();
-                    }
-                    FileInteraction::Load => {
-                        let Ok(string) = std::fs::read_to_string(path) else {
-                            break 'file_loading;
-                        };
-                        let new_world = World::from_save(serde_json::from_str(&string).unwrap());
-                        self.worlds.push(new_world);
-                        self.selected_world = self.worlds.len();
+                        write_file(&path, table);
                     }
                 }
+                FileInteraction::ExportReport => {
+                    let mut path = path;
+                    if path.extension().is_none() {
+                        path.set_extension("html");
+                    }
+                    let [width, height] = report::SCREENSHOT_SIZE;
+                    let screenshot = match self
+                        .render_offscreen(frame, [width, height])
+                        .and_then(|pixels| encode_png(width, height, &pixels))
+                    {
+                        Ok(png) => Some(png),
+                        Err(error) => {
+                            tracing::warn!("Exporting the report without a screenshot: {error:#}");
+                            None
+                        }
+                    };
+                    write_file(&path, report::html(self.world(), screenshot.as_deref()));
+                }
+                FileInteraction::ExportCsv(range) => {
+                    let mut path = path;
+                    if path.extension().is_none() {
+                        path.set_extension("csv");
+                    }
+                    write_file(&path, csv::table(self.world(), range));
+                }
+                FileInteraction::ExportSvg => {
+                    let mut path = path;
+                    if path.extension().is_none() {
+                        path.set_extension("svg");
+                    }
+                    let mut d = DrawHandler::new();
+                    let view = self.world().view();
+                    self.world().draw_states(&view, &mut d);
+                    let size = self.view_size;
+                    let camera = self.world().camera.gpu(size.x / size.y);
+                    write_file(&path, svg::document(&d, &camera, size.x, size.y));
+                }
+                FileInteraction::Screenshot => {
+                    let mut path = path;
+                    if path.extension().is_none() {
+                        path.set_extension("png");
+                    }
+                    let [width, height] = self.screenshot_size;
+                    let result = self
+                        .render_offscreen(frame, [width, height])
+                        .and_then(|pixels| encode_png(width, height, &pixels))
+                        .and_then(|png| Ok(std::fs::write(&path, png)?));
+                    match result {
+                        Ok(()) => tracing::info!("Wrote {}", path.display()),
+                        Err(error) => tracing::error!("Failed to take screenshot: {error:#}"),
+                    }
+                }
+                FileInteraction::ExportProfile => {
+                    let mut path = path;
+                    if path.extension().is_none() {
+                        path.set_extension("toml");
+                    }
+                    match config::to_toml(&self.settings) {
+                        Ok(profile) => write_file(&path, profile),
+                        Err(error) => tracing::error!("Failed to export settings: {error:#}"),
+                    }
+                }
+                FileInteraction::ImportProfile => match config::import(&path) {
+                    Ok(settings) => {
+                        tracing::info!("Imported settings from {}", path.display());
+                        self.settings = settings;
+                    }
+                    Err(error) => {
+                        tracing::warn!("{error:#}");
+                        self.load_report =
+                            Some((path.display().to_string(), vec![format!("{error:#}")]));
+                    }
+                },
             }
         }
 
+        egui::Window::new("Screenshot")
+            .open(&mut self.screenshot_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let [width, height] = &mut self.screenshot_size;
+                ui.horizontal(|ui| {
+                    let label = ui.label("Size:");
+                    ui.add(egui::DragValue::new(width).range(1..=16384).suffix("px"))
+                        .labelled_by(label.id);
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(height).range(1..=16384).suffix("px"))
+                        .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Window").clicked() {
+                        self.screenshot_size = [
+                            (self.view_size.x * ctx.pixels_per_point()) as u32,
+                            (self.view_size.y * ctx.pixels_per_point()) as u32,
+                        ];
+                    }
+                    for (name, size) in [("1080p", [1920, 1080]), ("4K", [3840, 2160]), ("8K", [7680, 4320])] {
+                        if ui.button(name).clicked() {
+                            self.screenshot_size = size;
+                        }
+                    }
+                });
+                ui.label("Taller pictures show the same height of the world in more detail, wider ones show more to the sides");
+                if ui.button("Save PNG").clicked() {
+                    self.file_interaction = FileInteraction::Screenshot;
+                    self.file_dialog.save_file();
+                }
+            });
+
         egui::Window::new("Stats")
             .open(&mut self.stats_open)
             .resizable(false)
             .show(ctx, |ui| {
                 ui.label(format!("Frame Time: {:.3}ms", 1000.0 * dt));
                 ui.label(format!("FPS: {:.3}", 1.0 / dt));
+                ui.checkbox(&mut self.profiler.enabled, "Profiler")
+                    .on_hover_text("Show where each frame's time goes");
                 if self.lagging {
                     ui.label("The game is lagging!");
                 }
@@ -236,56 +933,310 @@ impl eframe::App for App {
                 ))
             });
 
-        egui::Window::new("Guide")
-            .open(&mut self.help_open)
+        egui::Window::new("Settings")
+            .open(&mut self.settings_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.settings.ui(ui);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Export Profile")
+                        .on_hover_text("Save every setting and keybinding to a file to set up another machine the same way")
+                        .clicked()
+                    {
+                        self.file_interaction = FileInteraction::ExportProfile;
+                        self.file_dialog.save_file();
+                    }
+                    if ui
+                        .button("Import Profile")
+                        .on_hover_text("Replace every setting and keybinding with ones from an exported profile")
+                        .clicked()
+                    {
+                        self.file_interaction = FileInteraction::ImportProfile;
+                        self.file_dialog.pick_file();
+                    }
+                });
+            });
+
+        egui::Window::new("Log")
+            .open(&mut self.log_open)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| self.log_window.ui(ui));
+
+        let step_size = 1.0 / self.settings.new_world_time_step as f64;
+        egui::Window::new("Choreographies")
+            .open(&mut self.choreography_open)
             .resizable(false)
             .show(ctx, |ui| {
-                ui.heading("How to use:");
-                ui.label(
-                    "- Time (Bottom Bar)\n\
-                        The First slider controls where you are in the simulation\n\n\
-                        Gen Future is in seconds and controls how many seconds into the future it is allowed to simulate from the current time(controlled from the slider above)\n\n\
-                        Show Future is the amount of seconds bodies paths are displayed into the future\n\n\
-                        Path Quality controls how often a new line is drawn, eg:128 every 128t a line is drawn to show the path(This is only visual)\n\n\
-                        Speed Controls how fast the simulation is played back, The simulation starts Paused\n\n\
-                        Delete Past and Delete Future removes the past or future\n\n\n\
-                        - Controls\n\
-                        WASD to move around\n\n\
-                        Right Click on a body to focus on it, making all orbit paths and bodys relative to it. Right Click again not on a body to unfocus\n\n\
-                        Left Click on a body to select it, when a body is selected a window will appear with the body's components, When paused you can edit these components (NOTE: When editing components, from that point the simulation has to recompute. Do not have Gen Future too high to avoid lag)\n\
-                        ",
-                );
+                if let Some(world) = self.choreography_window.ui(ui, step_size) {
+                    self.worlds.push(world);
+                    self.selected_world = self.worlds.len() - 1;
+                }
             });
 
-        egui::Window::new("World Info").show(ctx, |ui| {
-            ui.horizontal(|ui| ui.label(format!("Time Step: 1/{}", 1.0 / self.world().step_size)));
-        });
+        self.profiler.overlay(ctx);
+        self.crash_report_ui(ctx);
+        self.load_report_ui(ctx);
 
         if self.worlds.is_empty() {
-            self.worlds.push(World::new(1.0 / 512.0));
+            egui::CentralPanel::default().show(ctx, |ui| self.start_screen(ui));
+            self.profiler.end_frame(time.elapsed());
+            return;
         }
 
-        self.world().ui(ctx, dt);
+        let mut constants_open = self.constants_open;
+        egui::Window::new("Constants")
+            .open(&mut constants_open)
+            .show(ctx, |ui| self.world().constants_ui(ui));
+        self.constants_open = constants_open;
+
+        let (mut statistics_open, mut statistics_detached) =
+            (self.statistics_open, self.statistics_detached);
+        detachable::show(
+            ctx,
+            "Statistics",
+            "Statistics",
+            &mut statistics_open,
+            &mut statistics_detached,
+            |ui| self.world().statistics_ui(ui),
+        );
+        (self.statistics_open, self.statistics_detached) = (statistics_open, statistics_detached);
+
+        let mut ephemeris_open = self.ephemeris_open;
+        egui::Window::new("Ephemeris")
+            .open(&mut ephemeris_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.world().ephemeris_ui(ui) {
+                    self.file_interaction = FileInteraction::ExportEphemeris;
+                    self.file_dialog.save_file();
+                }
+            });
+        self.ephemeris_open = ephemeris_open;
+
+        let mut cross_check_open = self.cross_check_open;
+        egui::Window::new("Cross Check")
+            .open(&mut cross_check_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().cross_check_ui(ui));
+        self.cross_check_open = cross_check_open;
+
+        let mut stopwatch_open = self.stopwatch_open;
+        egui::Window::new("Stopwatch")
+            .open(&mut stopwatch_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().stopwatch_ui(ui));
+        // Closing the window also stops clicks from picking trail points
+        if self.stopwatch_open && !stopwatch_open && self.world().tool == Tool::Measure {
+            self.world().set_tool(Tool::Select);
+        }
+        self.stopwatch_open = stopwatch_open;
+
+        let mut alarms_open = self.alarms_open;
+        egui::Window::new("Alarms")
+            .open(&mut alarms_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().alarms_ui(ui));
+        self.alarms_open = alarms_open;
+
+        let mut overlays_open = self.overlays_open;
+        egui::Window::new("Overlays")
+            .open(&mut overlays_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().overlays_ui(ui));
+        self.overlays_open = overlays_open;
+
+        let mut observer_open = self.observer_open;
+        egui::Window::new("Observer")
+            .open(&mut observer_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().observer_ui(ui));
+        self.observer_open = observer_open;
+
+        let mut approach_open = self.approach_open;
+        egui::Window::new("Closest Approach")
+            .open(&mut approach_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().approach_ui(ui));
+        self.approach_open = approach_open;
+
+        let mut comparison_open = self.comparison_open;
+        egui::Window::new("Compare Bodies")
+            .open(&mut comparison_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().comparison_ui(ui));
+        self.comparison_open = comparison_open;
+
+        let mut spawn_queue_open = self.spawn_queue_open;
+        egui::Window::new("Spawn Queue")
+            .open(&mut spawn_queue_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().spawn_queue_ui(ui));
+        self.spawn_queue_open = spawn_queue_open;
+
+        let mut heat_map_open = self.heat_map_open;
+        egui::Window::new("Heat Map")
+            .open(&mut heat_map_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().heat_map_ui(ui));
+        self.heat_map_open = heat_map_open;
+
+        let (mut conservation_open, mut conservation_detached) =
+            (self.conservation_open, self.conservation_detached);
+        detachable::show(
+            ctx,
+            "Conservation",
+            "Conservation",
+            &mut conservation_open,
+            &mut conservation_detached,
+            |ui| self.world().conservation_ui(ui),
+        );
+        (self.conservation_open, self.conservation_detached) =
+            (conservation_open, conservation_detached);
+
+        let (mut graph_open, mut graph_detached) = (self.graph_open, self.graph_detached);
+        detachable::show(
+            ctx,
+            "Graphs",
+            "Graphs",
+            &mut graph_open,
+            &mut graph_detached,
+            |ui| self.world().graph_ui(ui),
+        );
+        (self.graph_open, self.graph_detached) = (graph_open, graph_detached);
+
+        let mut chaos_open = self.chaos_open;
+        egui::Window::new("Chaos")
+            .open(&mut chaos_open)
+            .default_width(400.0)
+            .show(ctx, |ui| self.world().chaos_ui(ui));
+        self.chaos_open = chaos_open;
+
+        let settings = self.settings.clone();
+        self.world().ui(ctx, dt, &settings);
+
+        // World Info is always shown, closing its own window only attaches it again
+        let (mut world_info_open, mut world_info_detached) = (true, self.world_info_detached);
+        detachable::show(
+            ctx,
+            "World Info",
+            "World Info",
+            &mut world_info_open,
+            &mut world_info_detached,
+            |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Time Step: 1/{}", 1.0 / self.world().step_size))
+                });
+                if self.world().generation_paused {
+                    ui.label("Generation: Paused");
+                } else {
+                    ui.label(format!(
+                        "Generation: {:.0} steps/s",
+                        self.world().steps_per_second()
+                    ));
+                }
+                if let Some(eta) = self.world().regen_eta() {
+                    ui.label(format!("Rebuilding Future: {eta:.1}s left"));
+                }
+                match self.world().history_verified {
+                    Some(true) => {
+                        ui.label("History: Matches Save");
+                    }
+                    Some(false) => {
+                        ui.colored_label(egui::Color32::RED, "History: Differs From Save")
+                        .on_hover_text(
+                            "Rebuilding the saved history gave different results than when it was saved, the simulation is not deterministic on this machine",
+                        );
+                    }
+                    None => {}
+                }
+                self.world().universe_ui(ui);
+            },
+        );
+        self.world_info_detached = world_info_detached && world_info_open;
+
+        // Restoring a body edits the current state so this has to come after World::ui
+        let mut trash_open = self.trash_open;
+        egui::Window::new("Trash")
+            .open(&mut trash_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().trash_ui(ui));
+        self.trash_open = trash_open;
+
+        // Moving the slider edits the current state too
+        let mut experiment_open = self.experiment_open;
+        egui::Window::new("Experiment")
+            .open(&mut experiment_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().experiment_ui(ui));
+        self.experiment_open = experiment_open;
+
+        let mut replay_open = self.replay_open;
+        egui::Window::new("Action Replay")
+            .open(&mut replay_open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(world) = replay::ui(ui, self.world()) {
+                    self.worlds.push(world);
+                    self.selected_world = self.worlds.len() - 1;
+                }
+            });
+        self.replay_open = replay_open;
+
+        let mut transfer_open = self.transfer_open;
+        egui::Window::new("Transfer Planner")
+            .open(&mut transfer_open)
+            .resizable(false)
+            .show(ctx, |ui| self.world().transfer_ui(ui));
+        self.transfer_open = transfer_open;
+
+        self.world();
+        let world = &mut self.worlds[self.selected_world];
+        egui::Window::new("Moon Generator")
+            .open(&mut self.moons_open)
+            .resizable(false)
+            .show(ctx, |ui| self.moon_window.ui(ui, world));
+        egui::Window::new("Rescale World")
+            .open(&mut self.rescale_open)
+            .resizable(false)
+            .show(ctx, |ui| self.rescale_window.ui(ui, world));
+        egui::Window::new("Kepler Fit")
+            .open(&mut self.kepler_open)
+            .resizable(false)
+            .show(ctx, |ui| self.kepler_window.ui(ui, world));
+
+        tutorial::ui(self.world(), ctx);
 
         egui::CentralPanel::default()
             .frame(egui::Frame::NONE.fill(egui::Color32::from_rgb(50, 50, 50)))
             .show(ctx, |ui| {
+                let (tool, three_d) = (self.world().tool, self.world().state().three_d);
+                if let Some(tool) = tools::toolbar(ui, tool, three_d) {
+                    self.world().set_tool(tool);
+                }
                 let (rect, response) =
                     ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
                 let aspect = rect.width() / rect.height();
+                self.view_size = rect.size();
 
                 self.world().world_input(&response, rect, ui);
                 self.world().move_time(dt);
-                self.world().gen_future();
+                let start = std::time::Instant::now();
+                self.world().gen_future(&settings);
+                self.profiler.add(Phase::SimAppend, start.elapsed());
 
+                let start = std::time::Instant::now();
                 let mut d = DrawHandler::new();
-
-                self.world().draw_states(&mut d);
+                let view = self.world().view();
+                self.world().draw_states(&view, &mut d);
+                self.profiler.add(Phase::PathBuilding, start.elapsed());
 
                 ui.painter()
                     .add(eframe::egui_wgpu::Callback::new_paint_callback(
                         rect,
                         RenderData {
+                            viewport: 0,
                             camera: GpuCamera {
                                 position: (self.world().camera.pos - self.world().camera.offset)
                                     .cast()
@@ -295,22 +1246,98 @@ impl eframe::App for App {
                             },
                             quads: d.quads,
                             circles: d.circles,
+                            upload_time: self.profiler.upload_timer(),
                         },
                     ));
+
+                self.world().draw_labels(ui.painter(), rect);
+                if self.clock_open {
+                    self.world().draw_clock(ui.painter(), rect);
+                }
             });
 
+        let mut pip_open = self.pip_open;
+        egui::Window::new("Picture in Picture")
+            .open(&mut pip_open)
+            .default_size([320.0, 240.0])
+            .show(ctx, |ui| {
+                let (rect, response) =
+                    ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+                let aspect = rect.width() / rect.height();
+
+                self.world().pip_input(&response, rect, &settings);
+
+                let start = std::time::Instant::now();
+                let mut d = DrawHandler::new();
+                let view = self.world().pip_view();
+                self.world().draw_states(&view, &mut d);
+                self.profiler.add(Phase::PathBuilding, start.elapsed());
+
+                ui.painter()
+                    .add(eframe::egui_wgpu::Callback::new_paint_callback(
+                        rect,
+                        RenderData {
+                            viewport: 1,
+                            camera: GpuCamera {
+                                position: (view.camera.pos - view.camera.offset).cast().unwrap(),
+                                vertical_height: view.camera.view_height as f32,
+                                aspect,
+                            },
+                            quads: d.quads,
+                            circles: d.circles,
+                            upload_time: self.profiler.upload_timer(),
+                        },
+                    ));
+            });
+        self.pip_open = pip_open;
+
+        self.profiler.end_frame(time.elapsed());
         ctx.request_repaint();
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         let saves: Vec<Save> = self.worlds.iter().map(|world| world.to_save()).collect();
         storage.set_string("Worlds", serde_json::to_string(&saves).unwrap());
-        storage.set_string("NewWorldTimeStep", self.new_world_time_step.to_string());
-        storage.set_string("HelpOpen", self.help_open.to_string());
+        storage.set_string("ClockOpen", self.clock_open.to_string());
+        storage.set_string("PipOpen", self.pip_open.to_string());
+        storage.set_string(
+            "RecentFiles",
+            serde_json::to_string(&self.recent_files).unwrap(),
+        );
+    }
+
+    fn auto_save_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(self.settings.autosave_interval.max(1.0))
+    }
+}
+
+fn encode_png(width: u32, height: u32, pixels: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut png = vec![];
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(png)
+}
+
+fn write_file(path: &Path, contents: String) {
+    match std::fs::write(path, contents) {
+        Ok(()) => tracing::info!("Wrote {}", path.display()),
+        Err(error) => tracing::error!("Failed to write {}: {error}", path.display()),
     }
 }
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(error) = cli::run(&args) {
+            eprintln!("{error:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    logging::init();
+    recovery::install_hook();
     eframe::run_native(
         "Orbit Playground",
         eframe::NativeOptions {
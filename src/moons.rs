@@ -0,0 +1,217 @@
+use crate::{
+    body::{Body, BodyId, BodyMeta, radius_from_volume},
+    conics,
+    world::World,
+};
+use cgmath::{Vector2, Vector3};
+use eframe::egui;
+use std::{f64::consts::TAU, sync::Arc};
+
+// Period ratios close enough to these get pushed apart, they pump each other's eccentricity
+const RESONANCES: [(f64, f64); 7] = [
+    (2.0, 1.0),
+    (3.0, 2.0),
+    (4.0, 3.0),
+    (5.0, 3.0),
+    (5.0, 4.0),
+    (3.0, 1.0),
+    (5.0, 2.0),
+];
+const RESONANCE_TOLERANCE: f64 = 0.02;
+
+// Splitmix64, the same seed always gives the same system
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut x = self.0;
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+        x ^= x >> 31;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next()
+    }
+}
+
+fn near_resonance(period_ratio: f64) -> bool {
+    RESONANCES
+        .iter()
+        .any(|(p, q)| (period_ratio / (p / q) - 1.0).abs() < RESONANCE_TOLERANCE)
+}
+
+pub struct MoonWindow {
+    pub count: usize,
+    // Fractions of the planet's mass, masses are spread evenly in log space between them
+    pub min_mass: f64,
+    pub max_mass: f64,
+    // Gap between neighbouring orbits in mutual Hill radii, about 10 keeps them apart for a long time
+    pub spacing: f64,
+    pub seed: u64,
+    pub placed: Option<usize>,
+}
+
+impl Default for MoonWindow {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            min_mass: 1e-5,
+            max_mass: 1e-3,
+            spacing: 12.0,
+            seed: 1,
+            placed: None,
+        }
+    }
+}
+
+// Moons start at three planet radii and stop at half the planet's Hill sphere. Returns how many fit
+pub fn generate(world: &mut World, planet: BodyId, window: &MoonWindow) -> usize {
+    let universe = world.state();
+    let Some(planet_body) = universe.bodies.get(planet).cloned() else {
+        return 0;
+    };
+    let three_d = universe.three_d;
+    let planet_mass = planet_body.mass(three_d);
+    if planet_mass <= 0.0 {
+        return 0;
+    }
+    let gravity = universe.gravity;
+    let limit =
+        conics::hill_radius(universe, planet).map_or(f64::INFINITY, |(radius, _)| radius / 2.0);
+    let mut rng = Rng(window.seed);
+    let (min_mass, max_mass) = (
+        window.min_mass.min(window.max_mass).ln(),
+        window.min_mass.max(window.max_mass).ln(),
+    );
+    let mut previous: Option<(f64, f64)> = None;
+    let mut placed = 0;
+    for i in 0..window.count {
+        let mass = planet_mass * rng.range(min_mass, max_mass).exp();
+        let distance = match previous {
+            None => planet_body.radius * 3.0,
+            Some((previous_distance, previous_mass)) => {
+                // The gap is a number of mutual Hill radii, which themselves grow with the orbits
+                let hill = ((mass + previous_mass) / (3.0 * planet_mass)).cbrt();
+                let half_gap = window.spacing * rng.range(1.0, 1.3) * hill / 2.0;
+                if half_gap >= 1.0 {
+                    break;
+                }
+                let mut distance = previous_distance * (1.0 + half_gap) / (1.0 - half_gap);
+                while near_resonance((distance / previous_distance).powf(1.5)) {
+                    distance *= 1.01;
+                }
+                distance
+            }
+        };
+        if distance > limit {
+            break;
+        }
+        let angle = rng.range(0.0, TAU);
+        let offset = Vector2::new(angle.cos(), angle.sin()) * distance;
+        let vel = conics::periapsis_velocity(
+            gravity * (planet_mass + mass),
+            offset,
+            0.0,
+            planet_body.spin < 0.0,
+        );
+        let density = planet_body.meta.density;
+        let shade = rng.range(0.5, 0.9);
+        world.states[world.current_state].bodies.push(Body {
+            meta: Arc::new(BodyMeta {
+                name: format!("{} Moon {}", planet_body.meta.name, i + 1),
+                density,
+                color: Vector3::new(
+                    shade,
+                    shade * rng.range(0.9, 1.0),
+                    shade * rng.range(0.85, 1.0),
+                ),
+                high_accuracy: false,
+                pinned: false,
+                parent: Some(planet),
+            }),
+            pos: planet_body.pos + offset.extend(0.0),
+            vel: planet_body.vel + vel.extend(0.0),
+            radius: radius_from_volume(mass / density, three_d),
+            thrust: None,
+            spin: 0.0,
+            angle: 0.0,
+            charge: 0.0,
+            landed: None,
+            burns: vec![],
+        });
+        previous = Some((distance, mass));
+        placed += 1;
+    }
+    if placed > 0 {
+        world.current_state_modified = true;
+        world.modified_since_save_to_file = true;
+    }
+    placed
+}
+
+impl MoonWindow {
+    pub fn ui(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        let planet = world
+            .selected
+            .and_then(|id| Some((id, world.state().bodies.get(id)?.meta.name.clone())));
+        let Some((planet, name)) = planet else {
+            ui.label("Select a planet to give it moons");
+            return;
+        };
+        ui.label(format!("Planet: {name}"));
+        ui.horizontal(|ui| {
+            let label = ui.label("Moons:");
+            ui.add(egui::DragValue::new(&mut self.count).range(1..=50))
+                .labelled_by(label.id);
+            let label = ui.label("Seed:");
+            ui.add(egui::DragValue::new(&mut self.seed))
+                .labelled_by(label.id);
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Mass:");
+            ui.add(
+                egui::DragValue::new(&mut self.min_mass)
+                    .range(1e-9..=1.0)
+                    .speed(1e-5),
+            )
+            .labelled_by(label.id);
+            ui.label("to");
+            ui.add(
+                egui::DragValue::new(&mut self.max_mass)
+                    .range(1e-9..=1.0)
+                    .speed(1e-5),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Fraction of the planet's mass");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Spacing:");
+            ui.add(
+                egui::DragValue::new(&mut self.spacing)
+                    .range(1.0..=50.0)
+                    .speed(0.1),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Gap between neighbouring orbits in mutual Hill radii");
+        });
+        if ui
+            .add_enabled(world.can_edit(), egui::Button::new("Generate"))
+            .on_disabled_hover_text("Press Edit Here to add moons")
+            .clicked()
+        {
+            self.placed = Some(generate(world, planet, self));
+            // Clicking again gives a different system
+            self.seed = self.seed.wrapping_add(1);
+        }
+        if let Some(placed) = self.placed {
+            ui.label(if placed < self.count {
+                format!("Placed {placed} moons, the rest did not fit in the Hill sphere")
+            } else {
+                format!("Placed {placed} moons")
+            });
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{body::BodyId, universe::Universe};
+use cgmath::{InnerSpace, Vector2};
+use serde::{Deserialize, Serialize};
+
+// Someone watching the system through signals of a finite speed, so everything is seen where it
+// was when the signal left it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Observer {
+    pub pos: Vector2<f64>,
+    // Rides along with this body instead of staying at pos
+    pub on: Option<BodyId>,
+    pub signal_speed: f64,
+}
+
+impl Observer {
+    pub fn position(&self, universe: &Universe) -> Vector2<f64> {
+        self.on
+            .and_then(|on| universe.position(on))
+            .unwrap_or(self.pos)
+    }
+
+    // Where the body appears now and how old that sight is, found from the past states. None when
+    // its signal left before the start of the history or from a thinned state
+    pub fn apparent(
+        &self,
+        states: &[Universe],
+        current_state: usize,
+        step_size: f64,
+        id: BodyId,
+    ) -> Option<(Vector2<f64>, f64)> {
+        let observer = self.position(&states[current_state]);
+        let position = |back: usize| states[current_state - back].position(id);
+        // How far the signal sent back then has come past the body, it only grows going back as
+        // long as nothing moves faster than the signal
+        let lead = |back: usize| {
+            position(back).map(|pos| {
+                self.signal_speed * back as f64 * step_size - (pos - observer).magnitude()
+            })
+        };
+        if lead(current_state)? < 0.0 {
+            return None;
+        }
+        let (mut low, mut high) = (0, current_state);
+        while high - low > 1 {
+            let middle = (low + high) / 2;
+            if lead(middle)? < 0.0 {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        if high == 0 {
+            return Some((position(0)?, 0.0));
+        }
+        let (before, after) = (lead(low)?, lead(high)?);
+        let fraction = before / (before - after);
+        let pos = position(low)? + (position(high)? - position(low)?) * fraction;
+        Some((pos, (low as f64 + fraction) * step_size))
+    }
+}
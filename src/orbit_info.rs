@@ -0,0 +1,46 @@
+use crate::{body::Body, conics};
+use cgmath::InnerSpace;
+use eframe::egui;
+
+// How the selected body moves relative to the focused body and what it would take to circularize
+pub fn ui(ui: &mut egui::Ui, body: &Body, focus: &Body, gravity: f64, three_d: bool) {
+    ui.collapsing("Orbit Info", |ui| {
+        let offset = body.pos - focus.pos;
+        let relative_vel = body.vel - focus.vel;
+        let mu = gravity * (body.mass(three_d) + focus.mass(three_d));
+        let energy = relative_vel.magnitude2() / 2.0 - mu / offset.magnitude();
+        // Keeps turning the way it already does
+        let circular = conics::periapsis_velocity(
+            mu,
+            offset.truncate(),
+            0.0,
+            offset.cross(relative_vel).z < 0.0,
+        );
+        egui::Grid::new("Orbit Info").num_columns(2).show(ui, |ui| {
+            ui.label("Orbital Speed:");
+            ui.label(format!("{:.3}m/s", relative_vel.magnitude()));
+            ui.end_row();
+            ui.label("Specific Energy:");
+            ui.label(format!(
+                "{energy:.3}J/kg {}",
+                if energy < 0.0 {
+                    "(Bound)"
+                } else {
+                    "(Escaping)"
+                }
+            ))
+            .on_hover_text("Kinetic plus potential energy per kilogram");
+            ui.end_row();
+            // Circular orbits are only worked out in the plane
+            if !three_d {
+                ui.label("Circularize:");
+                ui.label(format!(
+                    "{:.3}m/s",
+                    (circular - relative_vel.truncate()).magnitude()
+                ))
+                .on_hover_text("Delta-v for a circular orbit at this distance");
+                ui.end_row();
+            }
+        });
+    });
+}
@@ -0,0 +1,248 @@
+use crate::{
+    body::{Body, BodyId},
+    universe::Universe,
+};
+use cgmath::{InnerSpace, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverlayKind {
+    Lagrange,
+    ZeroVelocity,
+}
+
+impl OverlayKind {
+    pub const ALL: [OverlayKind; 2] = [OverlayKind::Lagrange, OverlayKind::ZeroVelocity];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OverlayKind::Lagrange => "Lagrange Points",
+            OverlayKind::ZeroVelocity => "Zero-Velocity Curves",
+        }
+    }
+}
+
+// Analysis drawn on top of the world for a pair of bodies, the heavier one is the primary
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Overlay {
+    pub kind: OverlayKind,
+    pub a: BodyId,
+    pub b: BodyId,
+    pub visible: bool,
+    // Whose Jacobi constant bounds the regions, None follows the selected body
+    #[serde(default)]
+    pub test: Option<BodyId>,
+}
+
+// Reaching this would take negative kinetic energy in the rotating frame
+pub struct ForbiddenCell {
+    pub pos: Vector2<f64>,
+    pub size: f64,
+    // Degrees, as rects are drawn
+    pub angle: f64,
+}
+
+// Cells across the rotating frame, in separations from the barycenter
+const FORBIDDEN_EXTENT: f64 = 2.0;
+const FORBIDDEN_RESOLUTION: usize = 96;
+
+pub struct Marker {
+    pub pos: Vector2<f64>,
+    pub label: &'static str,
+    pub color: Vector3<f64>,
+}
+
+struct Pair {
+    barycenter: Vector2<f64>,
+    barycenter_vel: Vector2<f64>,
+    // From the primary to the secondary, and a quarter turn ahead of it in the direction it orbits
+    along: Vector2<f64>,
+    ahead: Vector2<f64>,
+    separation: f64,
+    // Secondary mass over total mass
+    mu: f64,
+    // Angular rate of a circular orbit at this separation, the rotating frame turns this fast
+    rate: f64,
+}
+
+impl Pair {
+    fn new(universe: &Universe, a: BodyId, b: BodyId) -> Option<Pair> {
+        let gravity = universe.active_gravity();
+        let (mut primary, mut secondary) = (universe.bodies.get(a)?, universe.bodies.get(b)?);
+        let mass = |body: &Body| body.mass(universe.three_d);
+        if mass(secondary) > mass(primary) {
+            std::mem::swap(&mut primary, &mut secondary);
+        }
+        let (primary_mass, secondary_mass) = (mass(primary), mass(secondary));
+        let total = primary_mass + secondary_mass;
+        let offset = (secondary.pos - primary.pos).truncate();
+        let separation = offset.magnitude();
+        if total <= 0.0 || separation == 0.0 {
+            return None;
+        }
+        let along = offset / separation;
+        let relative_vel = (secondary.vel - primary.vel).truncate();
+        let turn = if along.perp_dot(relative_vel) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        Some(Pair {
+            barycenter: (primary.pos * primary_mass + secondary.pos * secondary_mass).truncate()
+                / total,
+            barycenter_vel: (primary.vel * primary_mass + secondary.vel * secondary_mass)
+                .truncate()
+                / total,
+            along,
+            ahead: Vector2::new(-along.y, along.x) * turn,
+            separation,
+            mu: secondary_mass / total,
+            rate: (gravity * total / separation.powi(3)).sqrt(),
+        })
+    }
+
+    // Position given in the rotating frame, in units of the separation from the barycenter
+    fn point(&self, x: f64, y: f64) -> Vector2<f64> {
+        self.barycenter + (self.along * x + self.ahead * y) * self.separation
+    }
+
+    // The inverse of point
+    fn rotating(&self, pos: Vector2<f64>) -> Vector2<f64> {
+        let offset = (pos - self.barycenter) / self.separation;
+        Vector2::new(offset.dot(self.along), offset.dot(self.ahead))
+    }
+
+    // Velocity seen from the rotating frame, in separations per 1/rate
+    fn rotating_velocity(&self, pos: Vector2<f64>, vel: Vector2<f64>) -> Vector2<f64> {
+        let rotating = self.rotating(pos);
+        let relative = (vel - self.barycenter_vel) / (self.separation * self.rate);
+        Vector2::new(
+            relative.dot(self.along) + rotating.y,
+            relative.dot(self.ahead) - rotating.x,
+        )
+    }
+}
+
+// Twice the effective potential of the restricted three-body problem, in the rotating frame units
+fn effective_potential(mu: f64, pos: Vector2<f64>) -> f64 {
+    let to_primary = (pos - Vector2::new(-mu, 0.0)).magnitude();
+    let to_secondary = (pos - Vector2::new(1.0 - mu, 0.0)).magnitude();
+    pos.magnitude2() + 2.0 * (1.0 - mu) / to_primary + 2.0 * mu / to_secondary
+}
+
+// Net pull along the line through both bodies in the rotating frame, zero at L1, L2 and L3
+fn collinear_force(mu: f64, x: f64) -> f64 {
+    let (to_primary, to_secondary) = (x + mu, x - 1.0 + mu);
+    x - (1.0 - mu) * to_primary / to_primary.abs().powi(3)
+        - mu * to_secondary / to_secondary.abs().powi(3)
+}
+
+// The force changes sign exactly once between the bounds
+fn bisect(mu: f64, mut low: f64, mut high: f64) -> f64 {
+    let low_sign = collinear_force(mu, low).signum();
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if collinear_force(mu, middle).signum() == low_sign {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+pub fn lagrange_points(universe: &Universe, a: BodyId, b: BodyId) -> Option<[Vector2<f64>; 5]> {
+    let pair = Pair::new(universe, a, b)?;
+    let mu = pair.mu;
+    let margin = 1e-9;
+    let l1 = bisect(mu, -mu + margin, 1.0 - mu - margin);
+    let l2 = bisect(mu, 1.0 - mu + margin, 3.0);
+    let l3 = bisect(mu, -3.0, -mu - margin);
+    // L4 and L5 make equilateral triangles with both bodies, L4 leading
+    let height = 3.0_f64.sqrt() / 2.0;
+    let x = 0.5 - mu;
+    Some([
+        pair.point(l1, 0.0),
+        pair.point(l2, 0.0),
+        pair.point(l3, 0.0),
+        pair.point(x, height),
+        pair.point(x, -height),
+    ])
+}
+
+pub struct JacobiReadout {
+    pub constant: f64,
+    // At L1 to L5, a body with a larger constant can never cross the neck at that point
+    pub lagrange: [f64; 5],
+}
+
+// Only meaningful for a body light enough not to move the pair, on a pair in a near circular orbit
+pub fn jacobi(universe: &Universe, a: BodyId, b: BodyId, body: BodyId) -> Option<JacobiReadout> {
+    let pair = Pair::new(universe, a, b)?;
+    if pair.rate <= 0.0 {
+        return None;
+    }
+    let body = universe.bodies.get(body)?;
+    let pos = pair.rotating(body.pos.truncate());
+    let vel = pair.rotating_velocity(body.pos.truncate(), body.vel.truncate());
+    let points = lagrange_points(universe, a, b)?;
+    Some(JacobiReadout {
+        constant: effective_potential(pair.mu, pos) - vel.magnitude2(),
+        lagrange: points.map(|point| effective_potential(pair.mu, pair.rotating(point))),
+    })
+}
+
+pub fn forbidden_cells(
+    universe: &Universe,
+    a: BodyId,
+    b: BodyId,
+    test: BodyId,
+) -> Vec<ForbiddenCell> {
+    let Some(readout) = jacobi(universe, a, b, test) else {
+        return vec![];
+    };
+    let Some(pair) = Pair::new(universe, a, b) else {
+        return vec![];
+    };
+    let step = 2.0 * FORBIDDEN_EXTENT / FORBIDDEN_RESOLUTION as f64;
+    // Rects turn their local x axis to (sin, cos), which should point along the pair
+    let angle = pair.along.x.atan2(pair.along.y).to_degrees();
+    let mut cells = vec![];
+    for i in 0..FORBIDDEN_RESOLUTION {
+        for j in 0..FORBIDDEN_RESOLUTION {
+            let x = -FORBIDDEN_EXTENT + (i as f64 + 0.5) * step;
+            let y = -FORBIDDEN_EXTENT + (j as f64 + 0.5) * step;
+            if effective_potential(pair.mu, Vector2::new(x, y)) < readout.constant {
+                cells.push(ForbiddenCell {
+                    pos: pair.point(x, y),
+                    // A little overlap hides seams between neighbours
+                    size: step * pair.separation * 1.02,
+                    angle,
+                });
+            }
+        }
+    }
+    cells
+}
+
+impl Overlay {
+    pub fn markers(&self, universe: &Universe) -> Vec<Marker> {
+        match self.kind {
+            OverlayKind::Lagrange => {
+                let Some(points) = lagrange_points(universe, self.a, self.b) else {
+                    return vec![];
+                };
+                points
+                    .into_iter()
+                    .zip(["L1", "L2", "L3", "L4", "L5"])
+                    .map(|(pos, label)| Marker {
+                        pos,
+                        label,
+                        color: Vector3::new(0.4, 1.0, 0.6),
+                    })
+                    .collect()
+            }
+            OverlayKind::ZeroVelocity => vec![],
+        }
+    }
+}
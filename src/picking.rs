@@ -0,0 +1,53 @@
+use cgmath::{InnerSpace, Vector2};
+
+pub fn point_in_circle(point: Vector2<f64>, center: Vector2<f64>, radius: f64) -> bool {
+    (point - center).magnitude2() < radius * radius
+}
+
+pub fn distance_to_segment(point: Vector2<f64>, start: Vector2<f64>, end: Vector2<f64>) -> f64 {
+    let start_to_end = end - start;
+    let length2 = start_to_end.magnitude2();
+    let t = if length2 > 0.0 {
+        ((point - start).dot(start_to_end) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (start + start_to_end * t - point).magnitude()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_edge_is_outside() {
+        let center = Vector2::new(1.0, 1.0);
+        assert!(point_in_circle(Vector2::new(1.5, 1.5), center, 1.0));
+        assert!(!point_in_circle(Vector2::new(2.0, 1.0), center, 1.0));
+        assert!(!point_in_circle(center, center, 0.0));
+    }
+
+    #[test]
+    fn segment_distance_clamps_to_the_ends() {
+        let start = Vector2::new(0.0, 0.0);
+        let end = Vector2::new(4.0, 0.0);
+        assert_eq!(distance_to_segment(Vector2::new(2.0, 3.0), start, end), 3.0);
+        assert_eq!(
+            distance_to_segment(Vector2::new(-3.0, 4.0), start, end),
+            5.0
+        );
+        assert_eq!(
+            distance_to_segment(Vector2::new(7.0, -4.0), start, end),
+            5.0
+        );
+    }
+
+    #[test]
+    fn zero_length_segment_is_a_point() {
+        let point = Vector2::new(3.0, 4.0);
+        assert_eq!(
+            distance_to_segment(Vector2::new(0.0, 0.0), point, point),
+            5.0
+        );
+    }
+}
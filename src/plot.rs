@@ -0,0 +1,45 @@
+use eframe::egui;
+use egui_plot::{Legend, Line, Plot, PlotPoints, VLine};
+
+// Points per plotted line, long timelines are strided down to this
+const MAX_POINTS: usize = 1000;
+
+pub struct Series<'a> {
+    pub name: &'a str,
+    pub color: egui::Color32,
+    // x and y, gaps are left where y is not finite
+    pub points: &'a [[f64; 2]],
+}
+
+// Step between the samples kept when a timeline of len states is plotted
+pub fn stride(len: usize) -> usize {
+    len / MAX_POINTS + 1
+}
+
+// A line plot with a legend and a line at now, fitted to everything given until the user pans or
+// zooms, double clicking fits it again
+pub fn line_plot(ui: &mut egui::Ui, id: &str, height: f32, series: &[Series], now: f64) {
+    Plot::new(id)
+        .height(height)
+        .legend(Legend::default())
+        .x_axis_label("Time (s)")
+        .show(ui, |plot_ui| {
+            plot_ui.vline(VLine::new(now).color(egui::Color32::GRAY).width(1.0));
+            for series in series {
+                // Each run of finite points is its own line, lines sharing a name share a legend
+                // entry
+                for segment in series
+                    .points
+                    .split(|[_, y]| !y.is_finite())
+                    .filter(|segment| !segment.is_empty())
+                {
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(segment.to_vec()))
+                            .name(series.name)
+                            .color(series.color)
+                            .width(1.5),
+                    );
+                }
+            }
+        });
+}
@@ -0,0 +1,183 @@
+use crate::{
+    body::{Body, BodyMeta},
+    choreography::{self, Choreography},
+    universe::Units,
+    world::World,
+};
+use cgmath::{Vector2, Vector3, Zero};
+use std::sync::Arc;
+
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub build: fn(&mut World),
+}
+
+pub const PRESETS: [Preset; 7] = [
+    Preset {
+        name: "Sun and Planet",
+        description: "A single planet on a circular orbit",
+        build: sun_and_planet,
+    },
+    Preset {
+        name: "Planet and Moon",
+        description: "A moon orbiting a planet that orbits a sun",
+        build: planet_and_moon,
+    },
+    Preset {
+        name: "Binary Star",
+        description: "Two equal stars circling their barycenter",
+        build: binary_star,
+    },
+    Preset {
+        name: "Figure Eight",
+        description: "Three bodies chasing each other around a figure eight",
+        build: figure_eight,
+    },
+    Preset {
+        name: "Lagrange Triangle",
+        description: "Three bodies turning as a rigid triangle",
+        build: lagrange_triangle,
+    },
+    Preset {
+        name: "4 Body Chain",
+        description: "Four bodies following each other around one circle",
+        build: four_body_chain,
+    },
+    Preset {
+        name: "5 Body Chain",
+        description: "Five bodies following each other around one circle",
+        build: five_body_chain,
+    },
+];
+
+impl Preset {
+    pub fn world(&self, step_size: f64) -> World {
+        let mut world = World::new(step_size, Units::Sandbox);
+        world.name = self.name.to_string();
+        (self.build)(&mut world);
+        world.restart_generation();
+        world
+    }
+}
+
+// Every preset lies in the plane, so masses are those of discs
+fn body(name: &str, color: Vector3<f64>, radius: f64, pos: Vector2<f64>) -> Body {
+    Body {
+        meta: Arc::new(BodyMeta {
+            name: name.into(),
+            density: 1.0,
+            color,
+            high_accuracy: false,
+            pinned: false,
+            parent: None,
+        }),
+        pos: pos.extend(0.0),
+        vel: Vector3::zero(),
+        radius,
+        thrust: None,
+        spin: 0.0,
+        angle: 0.0,
+        charge: 0.0,
+        landed: None,
+        burns: vec![],
+    }
+}
+
+// Speed of a circular orbit at distance around a body of the given mass
+fn circular_speed(world: &World, mass: f64, distance: f64) -> f64 {
+    (world.state().gravity * mass / distance).sqrt()
+}
+
+fn sun_and_planet(world: &mut World) {
+    let mut sun = body(
+        "Sun",
+        Vector3::new(1.0, 0.8, 0.2),
+        10.0,
+        Vector2::new(0.0, 0.0),
+    );
+    let mut planet = body(
+        "Planet",
+        Vector3::new(0.3, 0.6, 1.0),
+        2.0,
+        Vector2::new(100.0, 0.0),
+    );
+    planet.vel.y = circular_speed(world, sun.mass(false), 100.0);
+    // The sun is given the opposite momentum so the system does not drift
+    sun.vel.y = -planet.vel.y * planet.mass(false) / sun.mass(false);
+
+    let bodies = &mut world.states[0].bodies;
+    bodies.push(sun);
+    bodies.push(planet);
+    world.camera.view_height = 300.0;
+}
+
+fn planet_and_moon(world: &mut World) {
+    let sun = body(
+        "Sun",
+        Vector3::new(1.0, 0.8, 0.2),
+        20.0,
+        Vector2::new(0.0, 0.0),
+    );
+    let mut planet = body(
+        "Planet",
+        Vector3::new(0.3, 0.6, 1.0),
+        4.0,
+        Vector2::new(400.0, 0.0),
+    );
+    let mut moon = body(
+        "Moon",
+        Vector3::new(0.7, 0.7, 0.7),
+        0.5,
+        Vector2::new(415.0, 0.0),
+    );
+    planet.vel.y = circular_speed(world, sun.mass(false), 400.0);
+    moon.vel.y = planet.vel.y + circular_speed(world, planet.mass(false), 15.0);
+
+    let bodies = &mut world.states[0].bodies;
+    bodies.push(sun);
+    let planet = bodies.push(planet);
+    moon.meta_mut().parent = Some(planet);
+    bodies.push(moon);
+    world.camera.view_height = 1000.0;
+}
+
+fn binary_star(world: &mut World) {
+    let mut a = body(
+        "Star A",
+        Vector3::new(1.0, 0.6, 0.3),
+        5.0,
+        Vector2::new(-50.0, 0.0),
+    );
+    let mut b = body(
+        "Star B",
+        Vector3::new(0.6, 0.7, 1.0),
+        5.0,
+        Vector2::new(50.0, 0.0),
+    );
+    // Each star circles the barycenter halfway between them
+    let speed = (world.state().gravity * a.mass(false) * 50.0).sqrt() / 100.0;
+    a.vel.y = -speed;
+    b.vel.y = speed;
+
+    let bodies = &mut world.states[0].bodies;
+    bodies.push(a);
+    bodies.push(b);
+    world.camera.view_height = 250.0;
+}
+
+fn figure_eight(world: &mut World) {
+    choreography::build(world, Choreography::FigureEight, 100.0, 1000.0);
+}
+
+fn lagrange_triangle(world: &mut World) {
+    choreography::build(world, Choreography::LagrangeTriangle, 100.0, 1000.0);
+}
+
+fn four_body_chain(world: &mut World) {
+    choreography::build(world, Choreography::FourBodyChain, 100.0, 1000.0);
+}
+
+fn five_body_chain(world: &mut World) {
+    choreography::build(world, Choreography::FiveBodyChain, 100.0, 1000.0);
+}
@@ -0,0 +1,118 @@
+use eframe::egui;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    SimAppend,
+    PathBuilding,
+    BufferUpload,
+    Ui,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::SimAppend,
+        Phase::PathBuilding,
+        Phase::BufferUpload,
+        Phase::Ui,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::SimAppend => "Sim Append",
+            Phase::PathBuilding => "Path Building",
+            Phase::BufferUpload => "Buffer Upload",
+            Phase::Ui => "UI",
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            Phase::SimAppend => egui::Color32::from_rgb(90, 170, 250),
+            Phase::PathBuilding => egui::Color32::from_rgb(250, 170, 60),
+            Phase::BufferUpload => egui::Color32::from_rgb(200, 90, 220),
+            Phase::Ui => egui::Color32::from_rgb(110, 200, 110),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    pub enabled: bool,
+    frame: [Duration; 4],
+    // Seconds, smoothed over the last few frames so the numbers can be read
+    average: [f64; 4],
+    // Buffers are written in the render callback after the frame's update has returned, so this
+    // holds the previous frame's upload time
+    upload: Arc<Mutex<Duration>>,
+}
+
+impl Profiler {
+    pub fn add(&mut self, phase: Phase, time: Duration) {
+        self.frame[phase as usize] += time;
+    }
+
+    pub fn upload_timer(&self) -> Arc<Mutex<Duration>> {
+        self.upload.clone()
+    }
+
+    // Whatever part of the update was not measured counts as UI time
+    pub fn end_frame(&mut self, update_time: Duration) {
+        self.frame[Phase::BufferUpload as usize] =
+            std::mem::take(&mut *self.upload.lock().unwrap());
+        let measured =
+            self.frame[Phase::SimAppend as usize] + self.frame[Phase::PathBuilding as usize];
+        self.frame[Phase::Ui as usize] = update_time.saturating_sub(measured);
+        for (average, time) in self.average.iter_mut().zip(std::mem::take(&mut self.frame)) {
+            *average = *average * 0.9 + time.as_secs_f64() * 0.1;
+        }
+    }
+
+    pub fn overlay(&self, ctx: &egui::Context) {
+        if !self.enabled {
+            return;
+        }
+        egui::Area::new(egui::Id::new("Profiler"))
+            .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let total: f64 = self.average.iter().sum();
+                    let width = 200.0;
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(width, 10.0), egui::Sense::hover());
+                    let mut left = rect.left();
+                    for phase in Phase::ALL {
+                        let share = if total > 0.0 {
+                            self.average[phase as usize] / total
+                        } else {
+                            0.0
+                        };
+                        let right = left + share as f32 * width;
+                        ui.painter().rect_filled(
+                            egui::Rect::from_x_y_ranges(left..=right, rect.y_range()),
+                            0.0,
+                            phase.color(),
+                        );
+                        left = right;
+                    }
+                    for phase in Phase::ALL {
+                        ui.horizontal(|ui| {
+                            let (swatch, _) = ui
+                                .allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                            ui.painter().rect_filled(swatch, 2.0, phase.color());
+                            ui.label(format!(
+                                "{}: {:.3}ms",
+                                phase.name(),
+                                1000.0 * self.average[phase as usize]
+                            ));
+                        });
+                    }
+                    ui.label(format!("Total: {:.3}ms", 1000.0 * total));
+                });
+            });
+    }
+}
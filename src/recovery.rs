@@ -0,0 +1,117 @@
+use crate::world::World;
+use std::{
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// How stale the worlds written out after a crash can be
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+
+const REPORT_FILE: &str = "crash.txt";
+
+// The panic hook cannot reach the app while it is unwinding, so it writes out whatever was last
+// handed over here
+static SNAPSHOT: Mutex<Vec<(String, String)>> = Mutex::new(vec![]);
+
+pub struct CrashReport {
+    pub message: String,
+    pub worlds: Vec<PathBuf>,
+}
+
+pub fn dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "Orbit Playground")
+        .map(|dirs| dirs.data_dir().join("recovery"))
+}
+
+pub struct Snapshotter {
+    last: Option<Instant>,
+}
+
+impl Snapshotter {
+    pub fn new() -> Self {
+        Self { last: None }
+    }
+
+    pub fn update(&mut self, worlds: &[World]) {
+        if self
+            .last
+            .is_some_and(|last| last.elapsed() < SNAPSHOT_INTERVAL)
+        {
+            return;
+        }
+        self.last = Some(Instant::now());
+        let saves = worlds
+            .iter()
+            .filter_map(|world| {
+                let save = serde_json::to_string(&world.to_save()).ok()?;
+                Some((world.name.clone(), save))
+            })
+            .collect();
+        *SNAPSHOT.lock().unwrap() = saves;
+    }
+}
+
+impl Default for Snapshotter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Keeps the default hook so the panic still shows up on the console
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        if let Err(error) = write_report(&format!("{info}\n\n{backtrace}")) {
+            tracing::error!("Failed to write crash report: {error:#}");
+        }
+    }));
+}
+
+fn write_report(report: &str) -> anyhow::Result<()> {
+    let dir = dir().ok_or_else(|| anyhow::anyhow!("No data directory"))?;
+    std::fs::create_dir_all(&dir)?;
+    // The panic may have happened while the snapshot was being replaced
+    let snapshot = match SNAPSHOT.try_lock() {
+        Ok(snapshot) => snapshot.clone(),
+        Err(std::sync::TryLockError::Poisoned(snapshot)) => snapshot.into_inner().clone(),
+        Err(std::sync::TryLockError::WouldBlock) => vec![],
+    };
+    for (index, (name, save)) in snapshot.iter().enumerate() {
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        std::fs::write(dir.join(format!("{index}_{name}.orbit")), save)?;
+    }
+    std::fs::write(dir.join(REPORT_FILE), report)?;
+    Ok(())
+}
+
+// Reads the report left by the last crash, if there is one, and clears it so it is only shown once
+pub fn take_report() -> Option<CrashReport> {
+    let dir = dir()?;
+    let message = std::fs::read_to_string(dir.join(REPORT_FILE)).ok()?;
+    _ = std::fs::remove_file(dir.join(REPORT_FILE));
+    let mut worlds: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "orbit")
+        })
+        .collect();
+    worlds.sort();
+    Some(CrashReport { message, worlds })
+}
+
+// Recovered worlds are deleted once they have been opened or dismissed
+pub fn clear(report: &CrashReport) {
+    for path in &report.worlds {
+        _ = std::fs::remove_file(path);
+    }
+}
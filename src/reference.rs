@@ -0,0 +1,304 @@
+use crate::{
+    body::BodyId,
+    integrators::{self, falloff},
+    universe::{Background, Universe},
+};
+use cgmath::{InnerSpace, Vector2};
+use std::sync::{Arc, Mutex};
+
+// Dormand-Prince 5(4) tableau, the nodes are not needed as gravity does not depend on time
+const A: [[f64; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+        0.0,
+        0.0,
+    ],
+    [
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+        0.0,
+    ],
+    [
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+const B: [f64; 7] = [
+    35.0 / 384.0,
+    0.0,
+    500.0 / 1113.0,
+    125.0 / 192.0,
+    -2187.0 / 6784.0,
+    11.0 / 84.0,
+    0.0,
+];
+const B_LOW: [f64; 7] = [
+    5179.0 / 57600.0,
+    0.0,
+    7571.0 / 16695.0,
+    393.0 / 640.0,
+    -92097.0 / 339200.0,
+    187.0 / 2100.0,
+    1.0 / 40.0,
+];
+
+// Positions and velocities of every body packed as [x, y, vx, vy, ...]
+pub struct ReferenceIntegrator {
+    pub ids: Vec<BodyId>,
+    pub masses: Vec<f64>,
+    // Empty when nothing is charged
+    pub charges: Vec<f64>,
+    pub coulomb: f64,
+    // Pinned bodies stay put and feel nothing
+    pub pinned: Vec<bool>,
+    pub gravity: f64,
+    pub exponent: f64,
+    pub uniform_field: Vector2<f64>,
+    pub background: Option<Background>,
+    pub state: Vec<f64>,
+    pub time: f64,
+    pub dt: f64,
+    pub tolerance: f64,
+}
+
+impl ReferenceIntegrator {
+    pub fn new(universe: &Universe, initial_dt: f64, tolerance: f64) -> Self {
+        let mut ids = vec![];
+        let mut masses = vec![];
+        let mut pinned = vec![];
+        let mut state = vec![];
+        for (id, body) in universe.bodies.iter() {
+            ids.push(id);
+            masses.push(body.mass(universe.three_d));
+            pinned.push(body.meta.pinned);
+            let vel = if body.meta.pinned {
+                Vector2::new(0.0, 0.0)
+            } else {
+                body.vel.truncate()
+            };
+            state.extend([body.pos.x, body.pos.y, vel.x, vel.y]);
+        }
+        Self {
+            ids,
+            masses,
+            charges: if universe.is_charged() {
+                universe
+                    .bodies
+                    .iter()
+                    .map(|(_, body)| body.charge)
+                    .collect()
+            } else {
+                vec![]
+            },
+            coulomb: universe.coulomb,
+            pinned,
+            gravity: universe.active_gravity(),
+            exponent: universe.force_exponent,
+            uniform_field: universe.uniform_field,
+            background: universe.background,
+            state,
+            time: 0.0,
+            dt: initial_dt,
+            tolerance,
+        }
+    }
+
+    fn derivative(&self, state: &[f64], out: &mut [f64]) {
+        for i in 0..self.masses.len() {
+            out[i * 4] = state[i * 4 + 2];
+            out[i * 4 + 1] = state[i * 4 + 3];
+            out[i * 4 + 2] = 0.0;
+            out[i * 4 + 3] = 0.0;
+        }
+        for i in 0..self.masses.len() {
+            for j in i + 1..self.masses.len() {
+                let a_to_b = Vector2::new(
+                    state[j * 4] - state[i * 4],
+                    state[j * 4 + 1] - state[i * 4 + 1],
+                );
+                let dist2 = a_to_b.magnitude2();
+                let direction = a_to_b / dist2.sqrt();
+                let falloff = falloff(dist2, self.exponent);
+                let a_acc = direction * (self.gravity * self.masses[j] / falloff);
+                let b_acc = direction * (self.gravity * self.masses[i] / falloff);
+                out[i * 4 + 2] += a_acc.x;
+                out[i * 4 + 3] += a_acc.y;
+                out[j * 4 + 2] -= b_acc.x;
+                out[j * 4 + 3] -= b_acc.y;
+            }
+        }
+        if !self.charges.is_empty() {
+            let pos: Vec<Vector2<f64>> = state
+                .chunks_exact(4)
+                .map(|body| Vector2::new(body[0], body[1]))
+                .collect();
+            let electric =
+                integrators::coulomb_accelerations(&self.masses, &self.charges, &pos, self.coulomb);
+            for (i, acc) in electric.into_iter().enumerate() {
+                out[i * 4 + 2] += acc.x;
+                out[i * 4 + 3] += acc.y;
+            }
+        }
+        for i in 0..self.masses.len() {
+            if self.pinned[i] {
+                out[i * 4 + 2] = 0.0;
+                out[i * 4 + 3] = 0.0;
+                continue;
+            }
+            let mut acc = self.uniform_field;
+            if let Some(background) = self.background {
+                acc += background
+                    .acceleration(self.gravity, Vector2::new(state[i * 4], state[i * 4 + 1]));
+            }
+            out[i * 4 + 2] += acc.x;
+            out[i * 4 + 3] += acc.y;
+        }
+    }
+
+    // Returns whether the step was accepted and the step size to try next
+    fn try_step(&mut self, dt: f64) -> (bool, f64) {
+        let n = self.state.len();
+        let mut k = vec![vec![0.0; n]; 7];
+        let mut stage = vec![0.0; n];
+        for s in 0..7 {
+            for (i, value) in stage.iter_mut().enumerate() {
+                *value = self.state[i] + dt * (0..s).map(|j| A[s][j] * k[j][i]).sum::<f64>();
+            }
+            self.derivative(&stage, &mut k[s]);
+        }
+
+        let mut error: f64 = 0.0;
+        let mut next = vec![0.0; n];
+        for i in 0..n {
+            let high = (0..7).map(|s| B[s] * k[s][i]).sum::<f64>();
+            let low = (0..7).map(|s| B_LOW[s] * k[s][i]).sum::<f64>();
+            next[i] = self.state[i] + dt * high;
+            let scale = self.tolerance * (1.0 + self.state[i].abs().max(next[i].abs()));
+            error = error.max((dt * (high - low)).abs() / scale);
+        }
+
+        let factor = if error == 0.0 {
+            5.0
+        } else {
+            (0.9 * error.powf(-0.2)).clamp(0.2, 5.0)
+        };
+        if error <= 1.0 && next.iter().all(|value| value.is_finite()) {
+            self.state = next;
+            self.time += dt;
+            (true, dt * factor)
+        } else {
+            (false, dt * factor.min(0.5))
+        }
+    }
+
+    // False when the step size collapsed before reaching the time, the run can not go on
+    pub fn advance_to(&mut self, time: f64) -> bool {
+        while self.time < time {
+            let remaining = time - self.time;
+            let dt = self.dt.min(remaining);
+            let (accepted, next_dt) = self.try_step(dt);
+            // Shortening the step to land on the sample time should not shrink the next one
+            if !accepted || dt == self.dt {
+                self.dt = next_dt;
+            }
+            if self.dt < 1e-12 * time.abs().max(1.0) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn positions(&self) -> Vec<(BodyId, Vector2<f64>)> {
+        self.ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, Vector2::new(self.state[i * 4], self.state[i * 4 + 1])))
+            .collect()
+    }
+}
+
+pub struct Sample {
+    pub index: usize,
+    pub positions: Vec<(BodyId, Vector2<f64>)>,
+}
+
+pub struct CrossCheckState {
+    pub samples: Vec<Sample>,
+    pub horizon: usize,
+    pub stop: bool,
+    // The state the reference run gave up at
+    pub failed: Option<usize>,
+}
+
+pub struct CrossCheck {
+    pub start: usize,
+    pub shared: Arc<Mutex<CrossCheckState>>,
+}
+
+impl CrossCheck {
+    pub fn spawn(
+        universe: &Universe,
+        start: usize,
+        step_size: f64,
+        interval: usize,
+        tolerance: f64,
+    ) -> Self {
+        let shared = Arc::new(Mutex::new(CrossCheckState {
+            samples: vec![],
+            horizon: start,
+            stop: false,
+            failed: None,
+        }));
+        let mut integrator = ReferenceIntegrator::new(universe, step_size, tolerance);
+        let thread_shared = shared.clone();
+        std::thread::spawn(move || {
+            let mut index = start;
+            loop {
+                {
+                    let lock = thread_shared.lock().unwrap();
+                    if lock.stop {
+                        return;
+                    }
+                    // Staying behind the main run keeps this from hogging a core forever
+                    if index > lock.horizon {
+                        drop(lock);
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                        continue;
+                    }
+                }
+                if !integrator.advance_to((index - start) as f64 * step_size) {
+                    thread_shared.lock().unwrap().failed = Some(index);
+                    return;
+                }
+                let positions = integrator.positions();
+                thread_shared
+                    .lock()
+                    .unwrap()
+                    .samples
+                    .push(Sample { index, positions });
+                index += interval;
+            }
+        });
+        Self { start, shared }
+    }
+}
+
+impl Drop for CrossCheck {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().stop = true;
+    }
+}
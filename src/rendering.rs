@@ -1,5 +1,9 @@
 use eframe::{egui, wgpu};
 use encase::{ShaderSize, ShaderType};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 #[derive(ShaderType)]
 pub struct GpuCamera {
@@ -21,22 +25,31 @@ pub struct GpuCircle {
     pub position: cgmath::Vector3<f32>,
     pub color: cgmath::Vector3<f32>,
     pub radius: f32,
+    // 1 lights the circle like a ball, 0 keeps it flat
+    pub shading: f32,
 }
 
-pub struct RenderState {
+// Every viewport drawn in a frame needs its own buffers as all of them are prepared before painting
+struct ViewportBuffers {
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
 
     quads_buffer: wgpu::Buffer,
-    quads_bind_group_layout: wgpu::BindGroupLayout,
     quads_bind_group: wgpu::BindGroup,
 
-    quad_render_pipeline: wgpu::RenderPipeline,
-
     circles_buffer: wgpu::Buffer,
-    circles_bind_group_layout: wgpu::BindGroupLayout,
     circles_bind_group: wgpu::BindGroup,
+}
 
+pub struct RenderState {
+    viewports: Vec<ViewportBuffers>,
+    target_format: wgpu::TextureFormat,
+
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    quads_bind_group_layout: wgpu::BindGroupLayout,
+    quad_render_pipeline: wgpu::RenderPipeline,
+
+    circles_bind_group_layout: wgpu::BindGroupLayout,
     circle_render_pipeline: wgpu::RenderPipeline,
 }
 
@@ -46,12 +59,6 @@ impl RenderState {
         device: &wgpu::Device,
         _queue: &wgpu::Queue,
     ) -> anyhow::Result<Self> {
-        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Camera Buffer"),
-            size: GpuCamera::SHADER_SIZE.get(),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
         let camera_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Camera Bind Group Layout"),
@@ -66,21 +73,7 @@ impl RenderState {
                     count: None,
                 }],
             });
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Camera Bind Group"),
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-        });
 
-        let quads_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Quads Buffer"),
-            size: GpuQuad::SHADER_SIZE.get(),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
         let quads_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Quads Bind Group Layout"),
@@ -95,14 +88,6 @@ impl RenderState {
                     count: None,
                 }],
             });
-        let quads_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Quads Bind Group"),
-            layout: &quads_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: quads_buffer.as_entire_binding(),
-            }],
-        });
 
         let quad_shader = device.create_shader_module(wgpu::include_wgsl!("./quad_shader.wgsl"));
 
@@ -156,12 +141,6 @@ impl RenderState {
             cache: None,
         });
 
-        let circles_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Circles Buffer"),
-            size: GpuCircle::SHADER_SIZE.get(),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
         let circles_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Circles Bind Group Layout"),
@@ -176,14 +155,6 @@ impl RenderState {
                     count: None,
                 }],
             });
-        let circles_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Circles Bind Group"),
-            layout: &circles_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: circles_buffer.as_entire_binding(),
-            }],
-        });
 
         let circle_shader =
             device.create_shader_module(wgpu::include_wgsl!("./circle_shader.wgsl"));
@@ -240,52 +211,103 @@ impl RenderState {
             });
 
         Ok(Self {
-            camera_buffer,
-            camera_bind_group,
+            viewports: vec![],
+            target_format,
 
-            quads_buffer,
+            camera_bind_group_layout,
             quads_bind_group_layout,
-            quads_bind_group,
-
             quad_render_pipeline,
 
-            circles_buffer,
             circles_bind_group_layout,
-            circles_bind_group,
-
             circle_render_pipeline,
         })
     }
-}
 
-pub struct RenderData {
-    pub camera: GpuCamera,
-    pub quads: Vec<GpuQuad>,
-    pub circles: Vec<GpuCircle>,
-}
+    fn viewport(&mut self, device: &wgpu::Device, index: usize) -> &mut ViewportBuffers {
+        while self.viewports.len() <= index {
+            let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Camera Buffer"),
+                size: GpuCamera::SHADER_SIZE.get(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Camera Bind Group"),
+                layout: &self.camera_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                }],
+            });
 
-impl eframe::egui_wgpu::CallbackTrait for RenderData {
-    fn prepare(
-        &self,
+            let quads_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Quads Buffer"),
+                size: GpuQuad::SHADER_SIZE.get(),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let quads_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Quads Bind Group"),
+                layout: &self.quads_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: quads_buffer.as_entire_binding(),
+                }],
+            });
+
+            let circles_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Circles Buffer"),
+                size: GpuCircle::SHADER_SIZE.get(),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let circles_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Circles Bind Group"),
+                layout: &self.circles_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: circles_buffer.as_entire_binding(),
+                }],
+            });
+
+            self.viewports.push(ViewportBuffers {
+                camera_buffer,
+                camera_bind_group,
+
+                quads_buffer,
+                quads_bind_group,
+
+                circles_buffer,
+                circles_bind_group,
+            });
+        }
+        &mut self.viewports[index]
+    }
+
+    fn upload(
+        &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        _screen_descriptor: &eframe::egui_wgpu::ScreenDescriptor,
-        _egui_encoder: &mut wgpu::CommandEncoder,
-        callback_resources: &mut eframe::egui_wgpu::CallbackResources,
-    ) -> Vec<wgpu::CommandBuffer> {
-        let state: &mut RenderState = callback_resources.get_mut().unwrap();
+        viewport: usize,
+        camera: &GpuCamera,
+        quads: &Vec<GpuQuad>,
+        circles: &Vec<GpuCircle>,
+    ) {
+        let quads_bind_group_layout = self.quads_bind_group_layout.clone();
+        let circles_bind_group_layout = self.circles_bind_group_layout.clone();
+        let state = self.viewport(device, viewport);
 
         {
             let mut camera_buffer = queue
                 .write_buffer_with(&state.camera_buffer, 0, GpuCamera::SHADER_SIZE)
                 .unwrap();
             encase::UniformBuffer::new(&mut *camera_buffer)
-                .write(&self.camera)
+                .write(camera)
                 .unwrap();
         }
 
         {
-            let size = self.quads.size();
+            let size = quads.size();
             if size.get() > state.quads_buffer.size() {
                 state.quads_buffer = device.create_buffer(&wgpu::BufferDescriptor {
                     label: Some("Quads Buffer"),
@@ -295,7 +317,7 @@ impl eframe::egui_wgpu::CallbackTrait for RenderData {
                 });
                 state.quads_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
                     label: Some("Quads Bind Group"),
-                    layout: &state.quads_bind_group_layout,
+                    layout: &quads_bind_group_layout,
                     entries: &[wgpu::BindGroupEntry {
                         binding: 0,
                         resource: state.quads_buffer.as_entire_binding(),
@@ -307,12 +329,12 @@ impl eframe::egui_wgpu::CallbackTrait for RenderData {
                 .write_buffer_with(&state.quads_buffer, 0, size)
                 .unwrap();
             encase::StorageBuffer::new(&mut *quads_buffer)
-                .write(&self.quads)
+                .write(quads)
                 .unwrap();
         }
 
         {
-            let size = self.circles.size();
+            let size = circles.size();
             if size.get() > state.circles_buffer.size() {
                 state.circles_buffer = device.create_buffer(&wgpu::BufferDescriptor {
                     label: Some("Circles Buffer"),
@@ -322,7 +344,7 @@ impl eframe::egui_wgpu::CallbackTrait for RenderData {
                 });
                 state.circles_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
                     label: Some("Circles Bind Group"),
-                    layout: &state.circles_bind_group_layout,
+                    layout: &circles_bind_group_layout,
                     entries: &[wgpu::BindGroupEntry {
                         binding: 0,
                         resource: state.circles_buffer.as_entire_binding(),
@@ -334,10 +356,189 @@ impl eframe::egui_wgpu::CallbackTrait for RenderData {
                 .write_buffer_with(&state.circles_buffer, 0, size)
                 .unwrap();
             encase::StorageBuffer::new(&mut *circles_buffer)
-                .write(&self.circles)
+                .write(circles)
                 .unwrap();
         }
+    }
+}
+
+// Past the two on screen so a screenshot never overwrites buffers waiting to be painted
+const SCREENSHOT_VIEWPORT: usize = 2;
+
+impl RenderState {
+    // Draws into a texture of its own instead of the window so the picture can be larger than the
+    // screen, returns rows of RGBA pixels
+    pub fn screenshot(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &GpuCamera,
+        quads: &Vec<GpuQuad>,
+        circles: &Vec<GpuCircle>,
+        [width, height]: [u32; 2],
+    ) -> anyhow::Result<Vec<u8>> {
+        let limit = device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > limit || height > limit {
+            anyhow::bail!("Screenshots can be at most {limit} pixels on each side");
+        }
+        self.upload(device, queue, SCREENSHOT_VIEWPORT, camera, quads, circles);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let color = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.target_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot Depth Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth24Plus,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        // Rows of a texture copy have to start on aligned offsets
+        let row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Buffer"),
+            size: row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        // Same gray as the world view's panel, which egui gives in gamma space
+        let background = egui::Color32::from_rgb(50, 50, 50);
+        let [r, g, b, _] = if self.target_format.is_srgb() {
+            egui::Rgba::from(background).to_array()
+        } else {
+            background.to_normalized_gamma_f32()
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Screenshot Encoder"),
+        });
+        {
+            let color_view = color.create_view(&wgpu::TextureViewDescriptor::default());
+            let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screenshot Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: r as f64,
+                            g: g as f64,
+                            b: b as f64,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let viewport = &self.viewports[SCREENSHOT_VIEWPORT];
+            render_pass.set_pipeline(&self.quad_render_pipeline);
+            render_pass.set_bind_group(0, &viewport.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &viewport.quads_bind_group, &[]);
+            render_pass.draw(0..4, 0..quads.len() as _);
+
+            render_pass.set_pipeline(&self.circle_render_pipeline);
+            render_pass.set_bind_group(0, &viewport.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &viewport.circles_bind_group, &[]);
+            render_pass.draw(0..4, 0..circles.len() as _);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &color,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+        queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+        let bgra = matches!(
+            self.target_format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for line in slice.get_mapped_range().chunks(row as usize) {
+            for pixel in line[..(width * 4) as usize].chunks(4) {
+                if bgra {
+                    pixels.extend([pixel[2], pixel[1], pixel[0], 255]);
+                } else {
+                    pixels.extend([pixel[0], pixel[1], pixel[2], 255]);
+                }
+            }
+        }
+        readback.unmap();
+        Ok(pixels)
+    }
+}
+
+pub struct RenderData {
+    pub viewport: usize,
+    pub camera: GpuCamera,
+    pub quads: Vec<GpuQuad>,
+    pub circles: Vec<GpuCircle>,
+    pub upload_time: Arc<Mutex<Duration>>,
+}
 
+impl eframe::egui_wgpu::CallbackTrait for RenderData {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &eframe::egui_wgpu::ScreenDescriptor,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut eframe::egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let start = Instant::now();
+        let state: &mut RenderState = callback_resources.get_mut().unwrap();
+        state.upload(
+            device,
+            queue,
+            self.viewport,
+            &self.camera,
+            &self.quads,
+            &self.circles,
+        );
+        *self.upload_time.lock().unwrap() += start.elapsed();
         vec![]
     }
 
@@ -348,15 +549,16 @@ impl eframe::egui_wgpu::CallbackTrait for RenderData {
         callback_resources: &eframe::egui_wgpu::CallbackResources,
     ) {
         let state: &RenderState = callback_resources.get().unwrap();
+        let viewport = &state.viewports[self.viewport];
 
         render_pass.set_pipeline(&state.quad_render_pipeline);
-        render_pass.set_bind_group(0, &state.camera_bind_group, &[]);
-        render_pass.set_bind_group(1, &state.quads_bind_group, &[]);
+        render_pass.set_bind_group(0, &viewport.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &viewport.quads_bind_group, &[]);
         render_pass.draw(0..4, 0..self.quads.len() as _);
 
         render_pass.set_pipeline(&state.circle_render_pipeline);
-        render_pass.set_bind_group(0, &state.camera_bind_group, &[]);
-        render_pass.set_bind_group(1, &state.circles_bind_group, &[]);
+        render_pass.set_bind_group(0, &viewport.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &viewport.circles_bind_group, &[]);
         render_pass.draw(0..4, 0..self.circles.len() as _);
     }
 }
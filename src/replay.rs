@@ -0,0 +1,162 @@
+use crate::{
+    universe::{Units, Universe},
+    world::World,
+};
+use eframe::egui;
+
+#[derive(Clone)]
+pub enum Action {
+    Play,
+    Pause,
+    Jump,
+    Speed(f64),
+    // The edited state as it was committed, which covers new bodies and deletions too
+    Edit(Box<Universe>),
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Play => "Play",
+            Action::Pause => "Pause",
+            Action::Jump => "Jump",
+            Action::Speed(_) => "Speed",
+            Action::Edit(_) => "Edit",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Recorded {
+    // Real seconds since recording started
+    pub time: f64,
+    // Counted from the state recording started at, which is the first state of a replay
+    pub state: usize,
+    pub action: Action,
+}
+
+pub struct Recording {
+    pub start: Universe,
+    pub start_state: usize,
+    pub step_size: f64,
+    pub speed: f64,
+    pub actions: Vec<Recorded>,
+    pub elapsed: f64,
+    pub last_playing: bool,
+    pub last_state: usize,
+    pub last_speed: f64,
+}
+
+impl Recording {
+    pub fn new(world: &World) -> Self {
+        let mut start = world.state().clone();
+        start.changed = true;
+        Self {
+            start,
+            start_state: world.current_state,
+            step_size: world.step_size,
+            speed: world.speed,
+            actions: vec![],
+            elapsed: 0.0,
+            last_playing: false,
+            last_state: world.current_state,
+            last_speed: world.speed,
+        }
+    }
+
+    pub fn push(&mut self, state: usize, action: Action) {
+        self.actions.push(Recorded {
+            time: self.elapsed,
+            state: state.saturating_sub(self.start_state),
+            action,
+        });
+    }
+
+    // A fresh world starting from where recording started, playing the actions back in real time
+    pub fn replay(&self) -> World {
+        let mut world = World::new(self.step_size, Units::Sandbox);
+        world.name = "Replay".to_string();
+        world.states[0] = self.start.clone();
+        world.speed = self.speed;
+        world.replay = Some(Replay {
+            actions: self.actions.clone(),
+            next: 0,
+            clock: 0.0,
+        });
+        world.restart_generation();
+        world
+    }
+}
+
+pub struct Replay {
+    pub actions: Vec<Recorded>,
+    pub next: usize,
+    pub clock: f64,
+}
+
+// Returns a new world when a replay was asked for
+pub fn ui(ui: &mut egui::Ui, world: &mut World) -> Option<World> {
+    let mut replay = None;
+    match &world.recording {
+        Some(recording) => {
+            ui.label(format!(
+                "Recording, {} actions in {:.1}s",
+                recording.actions.len(),
+                recording.elapsed
+            ));
+            if ui.button("Stop Recording").clicked() {
+                world.finished_recording = world.recording.take();
+            }
+        }
+        None => {
+            if ui
+                .button("Start Recording")
+                .on_hover_text("Record playback, jumps and edits from the current state")
+                .clicked()
+            {
+                world.recording = Some(Recording::new(world));
+            }
+        }
+    }
+    if let Some(recording) = &world.finished_recording {
+        ui.separator();
+        ui.label(format!(
+            "Last recording: {} actions over {:.1}s",
+            recording.actions.len(),
+            recording.elapsed
+        ));
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("Recorded Actions")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for recorded in &recording.actions {
+                            ui.label(format!("{:.2}s", recorded.time));
+                            ui.label(recorded.action.name());
+                            ui.label(format!("State {}", recorded.state));
+                            ui.end_row();
+                        }
+                    });
+            });
+        if ui
+            .button("Replay in New World")
+            .on_hover_text("Start from the recorded state and repeat every action at the same time")
+            .clicked()
+        {
+            replay = Some(recording.replay());
+        }
+    }
+    if let Some(replay) = &world.replay {
+        ui.separator();
+        ui.label(format!(
+            "Replaying, {} of {} actions done",
+            replay.next,
+            replay.actions.len()
+        ));
+        if ui.button("Stop Replay").clicked() {
+            world.replay = None;
+        }
+    }
+    replay
+}
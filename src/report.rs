@@ -0,0 +1,333 @@
+use crate::{
+    alarms,
+    body::BodyId,
+    universe::Universe,
+    world::{World, format_time},
+};
+use cgmath::{InnerSpace, Vector3};
+use std::fmt::Write;
+
+// States sampled per report plot, every point is written into the file
+const SAMPLES: usize = 500;
+// Separation lines past this many bodies make the plot unreadable
+const MAX_SEPARATIONS: usize = 8;
+const PLOT_WIDTH: f64 = 720.0;
+// Twice the page's width so the picture stays sharp on high density screens
+pub const SCREENSHOT_SIZE: [u32; 2] = [1440, 810];
+const PLOT_HEIGHT: f64 = 240.0;
+const COLORS: [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#808000",
+];
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            text.push(if i <= chunk.len() {
+                ALPHABET[((group >> (18 - 6 * i)) & 63) as usize] as char
+            } else {
+                '='
+            });
+        }
+    }
+    text
+}
+
+struct Series {
+    name: String,
+    points: Vec<(f64, f64)>,
+}
+
+// A line plot as inline SVG, time along the bottom and a dashed line at the current time
+fn plot(title: &str, unit: &str, series: &[Series], now: f64) -> String {
+    let points = series.iter().flat_map(|series| series.points.iter());
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for &(x, y) in points.filter(|(_, y)| y.is_finite()) {
+        (min_x, max_x) = (min_x.min(x), max_x.max(x));
+        (min_y, max_y) = (min_y.min(y), max_y.max(y));
+    }
+    let mut svg = String::new();
+    _ = writeln!(svg, "<h3>{}</h3>", escape(title));
+    if min_x > max_x {
+        _ = writeln!(svg, "<p>Nothing to plot</p>");
+        return svg;
+    }
+    if max_x == min_x {
+        max_x = min_x + 1.0;
+    }
+    if max_y == min_y {
+        (min_y, max_y) = (min_y - 1.0, max_y + 1.0);
+    }
+    let (left, bottom) = (80.0, PLOT_HEIGHT - 24.0);
+    let x = |value: f64| left + (value - min_x) / (max_x - min_x) * (PLOT_WIDTH - left - 8.0);
+    let y = |value: f64| bottom - (value - min_y) / (max_y - min_y) * (bottom - 8.0);
+    _ = writeln!(
+        svg,
+        r#"<svg width="{PLOT_WIDTH}" height="{PLOT_HEIGHT}" xmlns="http://www.w3.org/2000/svg" font-size="11">"#
+    );
+    _ = writeln!(
+        svg,
+        r##"<rect x="{left}" y="8" width="{}" height="{}" fill="none" stroke="#888"/>"##,
+        PLOT_WIDTH - left - 8.0,
+        bottom - 8.0
+    );
+    _ = writeln!(
+        svg,
+        r#"<text x="{}" y="14" text-anchor="end">{max_y:.4e}{unit}</text>"#,
+        left - 4.0
+    );
+    _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{bottom}" text-anchor="end">{min_y:.4e}{unit}</text>"#,
+        left - 4.0
+    );
+    _ = writeln!(
+        svg,
+        r#"<text x="{left}" y="{}">{}</text>"#,
+        PLOT_HEIGHT - 8.0,
+        format_time(min_x)
+    );
+    _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+        PLOT_WIDTH - 8.0,
+        PLOT_HEIGHT - 8.0,
+        format_time(max_x)
+    );
+    if (min_x..=max_x).contains(&now) {
+        _ = writeln!(
+            svg,
+            r##"<line x1="{0}" y1="8" x2="{0}" y2="{bottom}" stroke="#888" stroke-dasharray="4"/>"##,
+            x(now)
+        );
+    }
+    for (i, series) in series.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let path: Vec<String> = series
+            .points
+            .iter()
+            .filter(|(_, value)| value.is_finite())
+            .map(|&(time, value)| format!("{:.1},{:.1}", x(time), y(value)))
+            .collect();
+        _ = writeln!(
+            svg,
+            r#"<polyline fill="none" stroke="{color}" points="{}"/>"#,
+            path.join(" ")
+        );
+        _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{color}">{}</text>"#,
+            left + 8.0,
+            24.0 + 14.0 * i as f64,
+            escape(&series.name)
+        );
+    }
+    _ = writeln!(svg, "</svg>");
+    svg
+}
+
+// States to sample with thinned ones skipped, compact ones are expanded so energies can be summed
+fn samples(world: &World) -> Vec<(f64, Universe)> {
+    let stride = world.states.len() / SAMPLES + 1;
+    world
+        .states
+        .iter()
+        .enumerate()
+        .step_by(stride)
+        .filter(|(_, universe)| !universe.is_thinned())
+        .map(|(index, universe)| {
+            let mut universe = universe.clone();
+            universe.expand();
+            (index as f64 * world.step_size, universe)
+        })
+        .collect()
+}
+
+// A self contained HTML page, plots are inline SVG and the screenshot a PNG data URI so it can be
+// opened or printed anywhere
+pub fn html(world: &World, screenshot: Option<&[u8]>) -> String {
+    let universe = world.state();
+    let now = world.current_state as f64 * world.step_size;
+    let name = |id: BodyId| {
+        universe
+            .bodies
+            .get(id)
+            .map_or("Unnamed".to_string(), |body| body.meta.name.clone())
+    };
+    let mut html = String::new();
+    _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    );
+    _ = writeln!(html, "<title>{}</title>", escape(&world.name));
+    _ = writeln!(
+        html,
+        "<style>body {{ font-family: sans-serif; max-width: 760px; margin: auto; }} \
+         table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #ccc; padding: 2px 6px; }}</style>"
+    );
+    _ = writeln!(html, "</head>\n<body>");
+    _ = writeln!(html, "<h1>{}</h1>", escape(&world.name));
+    if let Some(png) = screenshot {
+        _ = writeln!(
+            html,
+            "<img src=\"data:image/png;base64,{}\" alt=\"The world at {}\" style=\"width: 100%\">",
+            base64(png),
+            format_time(now)
+        );
+    }
+
+    _ = writeln!(html, "<h2>Summary</h2>\n<table>");
+    let rows = [
+        ("Time", format_time(now)),
+        (
+            "Generated Until",
+            format_time((world.states.len() - 1) as f64 * world.step_size),
+        ),
+        ("Time Step", format!("{}s", world.step_size)),
+        ("Integrator", world.state().integrator.name().to_string()),
+        ("Gravity", format!("{:e}", universe.gravity)),
+        ("Bodies", universe.bodies.len().to_string()),
+        ("Total Mass", format!("{:.4e}kg", universe.total_mass())),
+        ("Total Energy", format!("{:.4e}J", universe.total_energy())),
+        (
+            "Angular Momentum",
+            format!("{:.4e}kg m^2/s", universe.angular_momentum()),
+        ),
+    ];
+    for (label, value) in rows {
+        _ = writeln!(html, "<tr><th>{label}</th><td>{value}</td></tr>");
+    }
+    _ = writeln!(html, "</table>");
+
+    _ = writeln!(html, "<h2>Bodies</h2>\n<table>");
+    _ = writeln!(
+        html,
+        "<tr><th>Name</th><th>Mass (kg)</th><th>Radius (m)</th><th>Position (m)</th><th>Velocity (m/s)</th></tr>"
+    );
+    let vector = |v: Vector3<f64>| {
+        if universe.three_d {
+            format!("{:.3}, {:.3}, {:.3}", v.x, v.y, v.z)
+        } else {
+            format!("{:.3}, {:.3}", v.x, v.y)
+        }
+    };
+    for (_, body) in universe.bodies.iter() {
+        _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{:.4e}</td><td>{:.4}</td><td>{}</td><td>{}</td></tr>",
+            escape(&body.meta.name),
+            body.mass(universe.three_d),
+            body.radius,
+            vector(body.pos),
+            vector(body.vel)
+        );
+    }
+    _ = writeln!(html, "</table>");
+
+    let samples = samples(world);
+    _ = writeln!(html, "<h2>Plots</h2>");
+    let energy = |name: &str, value: fn(&Universe) -> f64| Series {
+        name: name.to_string(),
+        points: samples
+            .iter()
+            .map(|(time, universe)| (*time, value(universe)))
+            .collect(),
+    };
+    html += &plot(
+        "Energy",
+        "J",
+        &[
+            energy("Kinetic", Universe::kinetic_energy),
+            energy("Potential", Universe::potential_energy),
+            energy("Total", Universe::total_energy),
+        ],
+        now,
+    );
+    // From the focused body, or from the barycenter when nothing is focused
+    let center = world.focused_body();
+    let separations: Vec<Series> = universe
+        .body_ids()
+        .into_iter()
+        .filter(|&id| Some(id) != center)
+        .take(MAX_SEPARATIONS)
+        .map(|id| Series {
+            name: name(id),
+            points: samples
+                .iter()
+                .filter_map(|(time, universe)| {
+                    let origin = match center {
+                        Some(center) => universe.position3(center)?,
+                        None => universe.barycenter3()?,
+                    };
+                    Some((*time, (universe.position3(id)? - origin).magnitude()))
+                })
+                .collect(),
+        })
+        .collect();
+    html += &plot(
+        &format!(
+            "Distance from {}",
+            center.map_or("Barycenter".to_string(), name)
+        ),
+        "m",
+        &separations,
+        now,
+    );
+
+    _ = writeln!(html, "<h2>Events</h2>\n<ul>");
+    let mut events: Vec<(f64, String)> = world
+        .bookmarks
+        .iter()
+        .map(|&bookmark| (bookmark as f64 * world.step_size, "Bookmark".to_string()))
+        .collect();
+    let mut alarms = world.alarms.clone();
+    let mut previous_count = None;
+    for (time, universe) in &samples {
+        let count = universe.bodies.len();
+        if let Some(previous) = previous_count
+            && previous != count
+        {
+            events.push((*time, format!("Body count went from {previous} to {count}")));
+        }
+        previous_count = Some(count);
+        for index in alarms::check(&mut alarms, universe) {
+            let alarm = &alarms[index];
+            events.push((
+                *time,
+                format!(
+                    "{} and {} came within {}m",
+                    name(alarm.a),
+                    name(alarm.b),
+                    alarm.distance
+                ),
+            ));
+        }
+    }
+    if let Some(impact) = world.predict_impact(&world.view()) {
+        events.push((
+            impact.state as f64 * world.step_size,
+            "Predicted impact of the selected body".to_string(),
+        ));
+    }
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if events.is_empty() {
+        _ = writeln!(html, "<li>None</li>");
+    }
+    for (time, event) in events {
+        _ = writeln!(html, "<li>{} {}</li>", format_time(time), escape(&event));
+    }
+    _ = writeln!(html, "</ul>");
+    _ = writeln!(html, "</body>\n</html>");
+    html
+}
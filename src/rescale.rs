@@ -0,0 +1,182 @@
+use crate::{body::Body, universe::Background, world::World};
+use eframe::egui;
+use std::sync::Arc;
+
+pub struct RescaleWindow {
+    pub distance: f64,
+    pub mass: f64,
+    pub velocity: f64,
+    // Velocities follow from the other two so every orbit keeps its shape with G unchanged
+    pub keep_orbits: bool,
+}
+
+impl Default for RescaleWindow {
+    fn default() -> Self {
+        Self {
+            distance: 1.0,
+            mass: 1.0,
+            velocity: 1.0,
+            keep_orbits: true,
+        }
+    }
+}
+
+impl RescaleWindow {
+    // Orbits only keep their shape this way under an inverse square force
+    fn velocity(&self, inverse_square: bool) -> f64 {
+        if self.keep_orbits && inverse_square {
+            (self.mass / self.distance).sqrt()
+        } else {
+            self.velocity
+        }
+    }
+}
+
+fn scale_body(body: &mut Body, distance: f64, mass: f64, velocity: f64, three_d: bool) {
+    let time = distance / velocity;
+    body.pos *= distance;
+    body.vel *= velocity;
+    body.radius *= distance;
+    body.spin /= time;
+    // Keeps the electric acceleration scaling like every other acceleration, which is just the
+    // mass factor when orbits are kept
+    body.charge *= velocity * (mass * distance).sqrt();
+    // Mass goes with the area or volume, so the density takes up whatever the radius did not
+    let meta = Arc::make_mut(&mut body.meta);
+    meta.density *= mass / distance.powi(if three_d { 3 } else { 2 });
+    for burn in &mut body.burns {
+        burn.delta_v *= velocity;
+    }
+    if let Some(thrust) = &mut body.thrust {
+        thrust.acceleration *= velocity / time;
+        thrust.spent *= velocity;
+        if let Some(fuel) = &mut thrust.fuel {
+            *fuel *= velocity;
+        }
+    }
+}
+
+// Everything else measured in meters, kilograms or seconds is scaled along so the world behaves the
+// same, only slower or faster by the time factor. Times on the timeline stay where they are
+pub fn rescale(world: &mut World, distance: f64, mass: f64, velocity: f64) {
+    let time = distance / velocity;
+    let universe = &mut world.states[world.current_state];
+    let three_d = universe.three_d;
+    for (_, body) in universe.bodies.iter_mut() {
+        scale_body(body, distance, mass, velocity, three_d);
+    }
+    universe.uniform_field *= velocity / time;
+    universe.landing_speed *= velocity;
+    universe.tidal_lag *= time;
+    universe.background = universe.background.map(|background| match background {
+        Background::Harmonic { strength } => Background::Harmonic {
+            strength: strength / (time * time),
+        },
+        Background::Halo {
+            mass: halo_mass,
+            scale,
+        } => Background::Halo {
+            mass: halo_mass * mass,
+            scale: scale * distance,
+        },
+    });
+    universe.fragmentation = universe.fragmentation.map(|mut fragmentation| {
+        fragmentation.energy *= mass * velocity * velocity;
+        fragmentation.min_radius *= distance;
+        fragmentation
+    });
+    for spawn in &mut world.spawn_queue {
+        scale_body(&mut spawn.body, distance, mass, velocity, three_d);
+    }
+    for alarm in &mut world.alarms {
+        alarm.distance *= distance;
+    }
+    for annotation in &mut world.annotations {
+        annotation.pos *= distance;
+    }
+    for keyframe in &mut world.camera_path {
+        keyframe.pos *= distance;
+        keyframe.view_height *= distance;
+    }
+    if let Some(observer) = &mut world.observer {
+        observer.pos *= distance;
+        observer.signal_speed *= velocity;
+    }
+    world.camera.pos *= distance;
+    world.camera.offset *= distance;
+    world.camera.view_height *= distance;
+    world.undoable_edit.get_or_insert("Rescale World");
+    world.current_state_modified = true;
+    world.modified_since_save_to_file = true;
+}
+
+impl RescaleWindow {
+    pub fn ui(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        let factor = |ui: &mut egui::Ui, label: &str, value: &mut f64, hover: &str| {
+            ui.horizontal(|ui| {
+                let label = ui.label(label);
+                let speed = *value * 0.01;
+                ui.add(
+                    egui::DragValue::new(value)
+                        .range(1e-12..=1e12)
+                        .speed(speed)
+                        .prefix("x"),
+                )
+                .labelled_by(label.id)
+                .on_hover_text(hover);
+                for (text, multiply) in
+                    [("/1000", 1e-3), ("/10", 0.1), ("x10", 10.0), ("x1000", 1e3)]
+                {
+                    if ui.button(text).clicked() {
+                        *value *= multiply;
+                    }
+                }
+            });
+        };
+        factor(
+            ui,
+            "Distances:",
+            &mut self.distance,
+            "Positions, radii and the camera",
+        );
+        factor(ui, "Masses:", &mut self.mass, "Densities change to match");
+        let inverse_square = world.states[world.current_state].force_exponent == 2.0;
+        ui.add_enabled(
+            inverse_square,
+            egui::Checkbox::new(&mut self.keep_orbits, "Keep Orbits"),
+        )
+        .on_hover_text("Scale velocities by the square root of masses over distances so every orbit keeps its shape")
+        .on_disabled_hover_text("Only works with an inverse square force");
+        if self.keep_orbits && inverse_square {
+            ui.label(format!("Velocities: x{:.6}", self.velocity(inverse_square)));
+        } else {
+            factor(
+                ui,
+                "Velocities:",
+                &mut self.velocity,
+                "Orbits change shape unless this is the square root of masses over distances",
+            );
+        }
+        let time = self.distance / self.velocity(inverse_square);
+        ui.label(format!(
+            "Everything will take {time:.6} times as long, time step stays the same"
+        ));
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(world.can_edit(), egui::Button::new("Apply"))
+                .on_disabled_hover_text("Press Edit Here to rescale the world")
+                .clicked()
+            {
+                rescale(
+                    world,
+                    self.distance,
+                    self.mass,
+                    self.velocity(inverse_square),
+                );
+            }
+            if ui.button("Reset").clicked() {
+                *self = Self::default();
+            }
+        });
+    }
+}
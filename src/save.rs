@@ -1,10 +1,25 @@
 use crate::{
-    body::{Body, BodyId, BodyList},
+    alarms::Alarm,
+    body::{Body, BodyId, BodyList, Landing, ManeuverId, ThrustDirection},
     camera::Camera,
-    universe::Universe,
+    camera_path::CameraKeyframe,
+    event_ramp::EventRamp,
+    expression::{Constant, default_constants},
+    integrators::Integrator,
+    observer::Observer,
+    overlays::Overlay,
+    universe::{
+        Background, CollisionMode, Fragmentation, Units, Universe, default_barnes_hut_theta,
+        default_coulomb, default_force_exponent,
+    },
+    world::{Annotation, ManeuverNode, QueuedSpawn},
 };
+use anyhow::{Context, bail};
+use cgmath::{Vector2, Zero};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize, ser::SerializeStruct};
-use std::{borrow::Cow, collections::BTreeMap};
+use serde_json::{Map, Value};
+use std::{borrow::Cow, collections::BTreeMap, io::Read};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Data {
@@ -17,9 +32,104 @@ pub struct Data {
     pub current_state: usize,
     pub step_size: f64,
     pub speed: f64,
-    pub save_path: Option<String>
+    pub save_path: Option<String>,
+    #[serde(default)]
+    pub quick_prediction: bool,
+    #[serde(default)]
+    pub bookmarks: Vec<usize>,
+    #[serde(default)]
+    pub event_ramp: EventRamp,
+    #[serde(default = "default_constants")]
+    pub constants: Vec<Constant>,
+    #[serde(default)]
+    pub camera_path: Vec<CameraKeyframe>,
+    #[serde(default)]
+    pub alarms: Vec<Alarm>,
+    #[serde(default)]
+    pub overlays: Vec<Overlay>,
+    #[serde(default)]
+    pub observer: Option<Observer>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    pub spawn_queue: Vec<QueuedSpawn>,
+    #[serde(default)]
+    pub maneuver_nodes: Vec<ManeuverNode>,
+    #[serde(default)]
+    pub follow_camera_path: bool,
+    // Every state keeps its own since version 3, this is the current one for older builds and what
+    // states from older saves fall back to
+    #[serde(default)]
+    pub integrator: Integrator,
+    #[serde(default)]
+    pub barycenter_trail: bool,
+    #[serde(default)]
+    pub barycenter_marker: bool,
+    #[serde(default)]
+    pub group_barycenter_marker: bool,
+    #[serde(default)]
+    pub apsis_markers: bool,
+    #[serde(default)]
+    pub soi_rings: bool,
+    #[serde(default)]
+    pub hill_sphere: bool,
+    #[serde(default)]
+    pub spin_gauges: bool,
+    #[serde(default)]
+    pub units: Units,
+    // Hash of every state up to the current one, checked against the rebuilt history on load
+    #[serde(default)]
+    pub history_hash: Option<u64>,
+    // Saves from before the format was versioned read as 0
+    #[serde(default)]
+    pub version: u32,
 }
 
+// Only used to fill in whatever could not be read from a damaged save
+impl Default for Data {
+    fn default() -> Self {
+        Self {
+            name: "Unnamed".to_string(),
+            camera: Camera::new(Vector2::zero(), Vector2::zero(), 10.0),
+            gen_future: 20000,
+            show_future: 100.0,
+            show_past: 100.0,
+            path_quality: 128,
+            current_state: 0,
+            step_size: 1.0 / 512.0,
+            speed: 1.0,
+            save_path: None,
+            quick_prediction: false,
+            bookmarks: vec![],
+            event_ramp: EventRamp::default(),
+            constants: default_constants(),
+            camera_path: vec![],
+            alarms: vec![],
+            overlays: vec![],
+            observer: None,
+            annotations: vec![],
+            spawn_queue: vec![],
+            maneuver_nodes: vec![],
+            follow_camera_path: false,
+            integrator: Integrator::default(),
+            barycenter_trail: false,
+            barycenter_marker: false,
+            group_barycenter_marker: false,
+            apsis_markers: false,
+            soi_rings: false,
+            hill_sphere: false,
+            spin_gauges: false,
+            units: Units::default(),
+            history_hash: None,
+            version: SAVE_VERSION,
+        }
+    }
+}
+
+// Bumped whenever a change to the format would be misread by older builds. Version 2 records
+// which maneuver nodes each state had applied, version 3 the integrator of each state
+pub const SAVE_VERSION: u32 = 3;
+
 #[derive(Debug)]
 pub struct Save<'a> {
     pub data: Data,
@@ -56,6 +166,21 @@ impl Serialize for Save<'_> {
         struct UniverseSerializer<'a> {
             index: usize,
             gravity: f64,
+            gravity_off: bool,
+            force_exponent: f64,
+            integrator: Integrator,
+            kepler_drift: bool,
+            collision_mode: CollisionMode,
+            barnes_hut_theta: f64,
+            adaptive_substeps: bool,
+            tidal_lag: f64,
+            fragmentation: Option<Fragmentation>,
+            landing_speed: f64,
+            coulomb: f64,
+            uniform_field: Vector2<f64>,
+            background: Option<Background>,
+            three_d: bool,
+            maneuvers: Vec<usize>,
             bodies: BodyListSerialiser<'a>,
         }
 
@@ -73,6 +198,21 @@ impl Serialize for Save<'_> {
                         universe.changed.then_some(UniverseSerializer {
                             index,
                             gravity: universe.gravity,
+                            gravity_off: universe.gravity_off,
+                            force_exponent: universe.force_exponent,
+                            integrator: universe.integrator,
+                            kepler_drift: universe.kepler_drift,
+                            collision_mode: universe.collision_mode,
+                            barnes_hut_theta: universe.barnes_hut_theta,
+                            adaptive_substeps: universe.adaptive_substeps,
+                            tidal_lag: universe.tidal_lag,
+                            fragmentation: universe.fragmentation,
+                            landing_speed: universe.landing_speed,
+                            coulomb: universe.coulomb,
+                            uniform_field: universe.uniform_field,
+                            background: universe.background,
+                            three_d: universe.three_d,
+                            maneuvers: universe.maneuvers.iter().map(|id| id.get()).collect(),
                             bodies: BodyListSerialiser {
                                 body_list: &universe.bodies,
                             },
@@ -81,7 +221,7 @@ impl Serialize for Save<'_> {
                 ))
             }
         }
-        
+
         assert!(self.states[0].changed);
         s.serialize_field(
             "states",
@@ -94,24 +234,55 @@ impl Serialize for Save<'_> {
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename = "Universe")]
+struct SavedUniverse {
+    index: usize,
+    gravity: f64,
+    #[serde(default)]
+    gravity_off: bool,
+    #[serde(default = "default_force_exponent")]
+    force_exponent: f64,
+    #[serde(default)]
+    integrator: Option<Integrator>,
+    // Saved as jacobi before it was named for what it does
+    #[serde(default, alias = "jacobi")]
+    kepler_drift: bool,
+    #[serde(default)]
+    collision_mode: CollisionMode,
+    #[serde(default = "default_barnes_hut_theta")]
+    barnes_hut_theta: f64,
+    #[serde(default)]
+    adaptive_substeps: bool,
+    #[serde(default)]
+    tidal_lag: f64,
+    #[serde(default)]
+    fragmentation: Option<Fragmentation>,
+    #[serde(default)]
+    landing_speed: f64,
+    #[serde(default = "default_coulomb")]
+    coulomb: f64,
+    #[serde(default = "Vector2::zero")]
+    uniform_field: Vector2<f64>,
+    #[serde(default)]
+    background: Option<Background>,
+    #[serde(default)]
+    three_d: bool,
+    #[serde(default)]
+    maneuvers: Vec<usize>,
+    bodies: Vec<(usize, Body)>,
+}
+
 impl<'de> Deserialize<'de> for Save<'_> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        #[serde(rename = "Universe")]
-        struct UniverseImpl {
-            index: usize,
-            gravity: f64,
-            bodies: Vec<(usize, Body)>,
-        }
-
         #[derive(Deserialize)]
         #[serde(rename = "Save")]
         struct SaveImpl {
             data: Data,
-            states: Vec<UniverseImpl>,
+            states: Vec<SavedUniverse>,
         }
 
         let SaveImpl {
@@ -119,21 +290,53 @@ impl<'de> Deserialize<'de> for Save<'_> {
                 data @ Data {
                     current_state,
                     step_size,
+                    integrator,
+                    version,
                     ..
                 },
             states,
         } = SaveImpl::deserialize(deserializer)?;
-        assert_eq!(states[0].index, 0);
-
-        let mut result_states = vec![];
+        if states.first().is_none_or(|universe| universe.index != 0) {
+            return Err(serde::de::Error::custom(
+                "The first state must be at index 0",
+            ));
+        }
 
         let mut id_to_body_id = BTreeMap::<usize, BodyId>::new();
+        let mut id_to_maneuver_id = BTreeMap::<usize, ManeuverId>::new();
+        let mut keyframes = vec![];
         let mut universes = states.into_iter().peekable();
         while let Some(universe) = universes.next() {
             let mut new_universe = Universe {
                 bodies: BodyList::new(),
                 gravity: universe.gravity,
+                gravity_off: universe.gravity_off,
+                force_exponent: universe.force_exponent,
+                integrator: universe.integrator.unwrap_or(integrator),
                 changed: true,
+                maneuvers: universe
+                    .maneuvers
+                    .iter()
+                    .map(|&id| {
+                        *id_to_maneuver_id
+                            .entry(id)
+                            .or_insert_with(ManeuverId::next_id)
+                    })
+                    .collect(),
+                compact: None,
+                thinned: false,
+                kepler_drift: universe.kepler_drift,
+                collision_mode: universe.collision_mode,
+                barnes_hut_theta: universe.barnes_hut_theta,
+                adaptive_substeps: universe.adaptive_substeps,
+                tidal_lag: universe.tidal_lag,
+                fragmentation: universe.fragmentation,
+                landing_speed: universe.landing_speed,
+                coulomb: universe.coulomb,
+                uniform_field: universe.uniform_field,
+                background: universe.background,
+                three_d: universe.three_d,
+                hash: 0,
             };
             for (id, body) in universe.bodies {
                 new_universe.bodies.insert(
@@ -141,23 +344,466 @@ impl<'de> Deserialize<'de> for Save<'_> {
                     body,
                 );
             }
-            result_states.push(new_universe);
 
-            let step_count = universes
-                .peek()
-                .map_or(current_state, |universe| universe.index)
-                .saturating_sub(universe.index);
+            // Stepped up to the state before the next keyframe, the last one up to the current state
+            let step_count = match universes.peek() {
+                Some(next) => next.index.saturating_sub(universe.index + 1),
+                None => current_state.saturating_sub(universe.index),
+            };
+            keyframes.push((universe.index, new_universe, step_count));
+        }
 
-            for _ in 0..step_count {
-                let mut stepped_universe = result_states.last().unwrap().clone();
-                stepped_universe.step(step_size);
-                result_states.push(stepped_universe);
+        // Saved body ids only mean something within the save file
+        let remap = |id: BodyId| id_to_body_id.get(&id.get_id().get()).copied();
+        for (_, universe, _) in &mut keyframes {
+            for (_, body) in universe.bodies.iter_mut() {
+                if let Some(parent) = body.meta.parent {
+                    body.meta_mut().parent = remap(parent);
+                }
+                if let Some(thrust) = &mut body.thrust
+                    && let ThrustDirection::Tangential { around } = thrust.direction
+                {
+                    thrust.direction = match remap(around) {
+                        Some(around) => ThrustDirection::Tangential { around },
+                        None => ThrustDirection::Prograde,
+                    };
+                }
+                body.landed = body.landed.and_then(|landing| {
+                    Some(Landing {
+                        on: remap(landing.on)?,
+                        ..landing
+                    })
+                });
+            }
+        }
+        let mut data = data;
+        for key in &mut data.camera_path {
+            key.focus = key.focus.and_then(remap);
+        }
+        // Pairs that lost a body are dropped
+        let remap_pair = |a: &mut BodyId, b: &mut BodyId| {
+            let (Some(new_a), Some(new_b)) = (remap(*a), remap(*b)) else {
+                return false;
+            };
+            (*a, *b) = (new_a, new_b);
+            true
+        };
+        data.alarms
+            .retain_mut(|alarm| remap_pair(&mut alarm.a, &mut alarm.b));
+        data.overlays
+            .retain_mut(|overlay| remap_pair(&mut overlay.a, &mut overlay.b));
+        for overlay in &mut data.overlays {
+            overlay.test = overlay.test.and_then(remap);
+        }
+        if let Some(observer) = &mut data.observer {
+            observer.on = observer.on.and_then(remap);
+        }
+        // Spawns that have not happened yet are in no state, they only need a fresh id
+        for spawn in &mut data.spawn_queue {
+            spawn.id = remap(spawn.id).unwrap_or_else(BodyId::next_id);
+        }
+        data.maneuver_nodes.retain_mut(|node| {
+            let Some(body) = remap(node.body) else {
+                return false;
+            };
+            node.body = body;
+            node.reference = node.reference.and_then(remap);
+            true
+        });
+        for node in &mut data.maneuver_nodes {
+            if version < 2 {
+                // Older saves had every node whose state was kept as a keyframe applied already
+                let index = (node.time / step_size).round() as usize;
+                if let Some((_, universe, _)) = keyframes
+                    .iter_mut()
+                    .find(|(keyframe, _, _)| *keyframe == index)
+                {
+                    universe.maneuvers.push(node.id);
+                }
+            } else {
+                node.id = *id_to_maneuver_id
+                    .entry(node.id.get())
+                    .or_insert_with(ManeuverId::next_id);
             }
         }
 
+        // Each keyframe only depends on itself so the segments can be stepped in parallel
+        let segments: Vec<Vec<Universe>> = keyframes
+            .into_par_iter()
+            .map(|(_, universe, step_count)| {
+                let mut segment = Vec::with_capacity(step_count + 1);
+                segment.push(universe);
+                for _ in 0..step_count {
+                    let mut stepped_universe = segment.last().unwrap().clone();
+                    stepped_universe.step(step_size);
+                    segment.push(stepped_universe);
+                }
+                segment
+            })
+            .collect();
+        let result_states: Vec<Universe> = segments.into_iter().flatten().collect();
+
         Ok(Save {
             data,
             states: result_states.into(),
         })
     }
 }
+
+// Gzip is unwrapped first, then json saves always start with an object and anything else is
+// treated as binary
+fn parse(bytes: &[u8]) -> anyhow::Result<Value> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = vec![];
+        flate2::read::GzDecoder::new(bytes)
+            .read_to_end(&mut decompressed)
+            .context("Failed to decompress save")?;
+        return parse(&decompressed);
+    }
+    if bytes.trim_ascii_start().starts_with(b"{") {
+        serde_json::from_slice(bytes).context("Failed to parse json save")
+    } else {
+        ciborium::from_reader(bytes).context("Failed to parse binary save")
+    }
+}
+
+// Loads as much of the save as can be read, returning what had to be left out or reset
+pub fn read(bytes: &[u8]) -> anyhow::Result<(Save<'static>, Vec<String>)> {
+    let value = parse(bytes)?;
+    let Value::Object(root) = &value else {
+        bail!("The save does not contain a world");
+    };
+    let mut problems = vec![];
+    let version = value["data"]["version"].as_u64().unwrap_or(0);
+    if version > SAVE_VERSION as u64 {
+        problems.push(format!(
+            "Saved in a newer format (version {version}, this build reads up to {SAVE_VERSION}), anything newer is left out"
+        ));
+    }
+    if let Ok(save) = Save::deserialize(&value) {
+        return Ok((save, problems));
+    }
+
+    let mut recovered = Map::new();
+    recovered.insert("data".into(), recover_data(root.get("data"), &mut problems));
+    recovered.insert(
+        "states".into(),
+        recover_states(root.get("states"), &mut problems),
+    );
+    let save =
+        Save::deserialize(&Value::Object(recovered)).context("Failed to rebuild the world")?;
+    Ok((save, problems))
+}
+
+// Each field is tried on its own so one bad value only resets itself
+fn recover_data(saved: Option<&Value>, problems: &mut Vec<String>) -> Value {
+    let mut data = serde_json::to_value(Data::default()).unwrap();
+    let Some(Value::Object(saved)) = saved else {
+        problems.push("The world's settings could not be read and were reset".into());
+        return data;
+    };
+    for (key, field) in saved {
+        let mut candidate = data.clone();
+        candidate[key] = field.clone();
+        if Data::deserialize(&candidate).is_ok() {
+            data = candidate;
+        } else {
+            problems.push(format!("{key} could not be read and was reset"));
+        }
+    }
+    data
+}
+
+// Every state builds on the ones before it, so reading stops at the first bad one
+fn recover_states(saved: Option<&Value>, problems: &mut Vec<String>) -> Value {
+    let mut states = vec![];
+    for state in saved.and_then(Value::as_array).into_iter().flatten() {
+        match SavedUniverse::deserialize(state) {
+            Ok(universe) if states.is_empty() && universe.index != 0 => break,
+            Ok(_) => states.push(state.clone()),
+            Err(error) => {
+                problems.push(format!(
+                    "Saved state {} could not be read ({error}), it and every later one were left out",
+                    states.len() + 1
+                ));
+                break;
+            }
+        }
+    }
+    if states.is_empty() {
+        problems.push("No bodies could be read, the world starts out empty".into());
+        let mut empty = Map::new();
+        empty.insert("index".into(), 0.into());
+        empty.insert("gravity".into(), 1.0.into());
+        empty.insert("bodies".into(), Value::Array(vec![]));
+        states.push(Value::Object(empty));
+    }
+    Value::Array(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::BodyMeta;
+    use crate::universe::history_hash;
+    use cgmath::Vector3;
+    use std::{io::Write, sync::Arc};
+
+    fn body(name: &str, x: f64) -> Body {
+        Body {
+            meta: Arc::new(BodyMeta {
+                name: name.to_string(),
+                density: 1.0,
+                color: Vector3::new(1.0, 1.0, 1.0),
+                high_accuracy: false,
+                pinned: false,
+                parent: None,
+            }),
+            pos: Vector3::new(x, 0.0, 0.0),
+            vel: Vector3::zero(),
+            radius: 1.0,
+            thrust: None,
+            spin: 0.0,
+            angle: 0.0,
+            charge: 0.0,
+            landed: None,
+            burns: vec![],
+        }
+    }
+
+    // Five states with keyframes at 0, 2 and 4, a third body shows up at 4
+    fn saved() -> Value {
+        let mut first = Universe::new(1.0);
+        first.bodies.push(body("A", -10.0));
+        first.bodies.push(body("B", 10.0));
+        let mut states = vec![first];
+        for index in 1..5 {
+            let mut universe = states.last().unwrap().clone();
+            universe.changed = index % 2 == 0;
+            if index == 4 {
+                universe.bodies.push(body("C", 30.0));
+            }
+            states.push(universe);
+        }
+        let data = Data {
+            name: "Test".to_string(),
+            current_state: 4,
+            ..Data::default()
+        };
+        serde_json::to_value(Save {
+            data,
+            states: states.into(),
+        })
+        .unwrap()
+    }
+
+    fn body_counts(save: &Save) -> Vec<usize> {
+        save.states
+            .iter()
+            .map(|universe| universe.bodies.len())
+            .collect()
+    }
+
+    #[test]
+    fn reads_json_binary_and_gzip() {
+        let json = serde_json::to_vec(&saved()).unwrap();
+        let mut binary = vec![];
+        ciborium::into_writer(&saved(), &mut binary).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&binary).unwrap();
+        let gzip = encoder.finish().unwrap();
+
+        for bytes in [json, binary, gzip] {
+            let (save, problems) = read(&bytes).unwrap();
+            assert!(problems.is_empty(), "{problems:?}");
+            assert_eq!(save.data.name, "Test");
+            assert_eq!(body_counts(&save), [2, 2, 2, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn bad_data_field_only_resets_itself() {
+        let mut value = saved();
+        value["data"]["speed"] = "fast".into();
+        let (save, problems) = read(&serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(problems, ["speed could not be read and was reset"]);
+        assert_eq!(save.data.speed, Data::default().speed);
+        assert_eq!(save.data.name, "Test");
+        assert_eq!(body_counts(&save), [2, 2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn corrupt_state_drops_it_and_every_later_one() {
+        let mut value = saved();
+        value["states"][1]["gravity"] = "strong".into();
+        let (save, problems) = read(&serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("Saved state 2 could not be read"),
+            "{problems:?}"
+        );
+        // The first keyframe is stepped on up to the current state without the third body
+        assert_eq!(body_counts(&save), [2, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn missing_first_state_starts_empty() {
+        let mut value = saved();
+        value["states"].as_array_mut().unwrap().remove(0);
+        let (save, problems) = read(&serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(
+            problems,
+            ["No bodies could be read, the world starts out empty"]
+        );
+        assert_eq!(save.data.name, "Test");
+        assert!(body_counts(&save).iter().all(|&count| count == 0));
+    }
+
+    // Positions and velocities from before 3D worlds have no z
+    fn drop_z(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for key in ["pos", "vel"] {
+                    if let Some(Value::Object(vector)) = map.get_mut(key) {
+                        vector.remove("z");
+                    }
+                }
+                map.values_mut().for_each(drop_z);
+            }
+            Value::Array(values) => values.iter_mut().for_each(drop_z),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn reads_bodies_saved_without_z() {
+        let mut value = saved();
+        drop_z(&mut value);
+        assert!(value["states"][0]["bodies"][0][1]["pos"].get("z").is_none());
+        let (save, problems) = read(&serde_json::to_vec(&value).unwrap()).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        let positions: Vec<Vector3<f64>> = save.states[4]
+            .bodies
+            .iter()
+            .map(|(_, body)| body.pos)
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                Vector3::new(-10.0, 0.0, 0.0),
+                Vector3::new(10.0, 0.0, 0.0),
+                Vector3::new(30.0, 0.0, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_integrator_of_each_state() {
+        let mut first = Universe::new(1.0);
+        first.bodies.push(body("A", -10.0));
+        first.bodies.push(Body {
+            vel: Vector3::new(0.0, 0.5, 0.0),
+            ..body("B", 10.0)
+        });
+        let mut states = vec![first];
+        for index in 1..6 {
+            let mut universe = states.last().unwrap().clone();
+            if index == 3 {
+                // Switched partway, like an edit made from the panel
+                universe.integrator = Integrator::Rk4;
+                universe.changed = true;
+            } else {
+                universe.step(0.1);
+            }
+            states.push(universe);
+        }
+        let data = Data {
+            current_state: 5,
+            step_size: 0.1,
+            ..Data::default()
+        };
+        // Binary keeps every bit of the keyframes so the rebuilt states can be compared exactly
+        let mut bytes = vec![];
+        ciborium::into_writer(
+            &Save {
+                data,
+                states: states.as_slice().into(),
+            },
+            &mut bytes,
+        )
+        .unwrap();
+
+        let (save, problems) = read(&bytes).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        let integrators: Vec<Integrator> = save
+            .states
+            .iter()
+            .map(|universe| universe.integrator)
+            .collect();
+        assert_eq!(
+            integrators,
+            [
+                Integrator::Euler,
+                Integrator::Euler,
+                Integrator::Euler,
+                Integrator::Rk4,
+                Integrator::Rk4,
+                Integrator::Rk4
+            ]
+        );
+        for (saved, loaded) in states.iter().zip(save.states.iter()) {
+            let positions = |universe: &Universe| -> Vec<Vector3<f64>> {
+                universe.bodies.iter().map(|(_, body)| body.pos).collect()
+            };
+            assert_eq!(positions(saved), positions(loaded));
+        }
+    }
+
+    #[test]
+    fn history_hash_survives_a_save_and_load() {
+        let mut first = Universe::new(1.0);
+        first.bodies.push(body("A", -10.0));
+        first.bodies.push(Body {
+            vel: Vector3::new(0.0, 0.5, 0.0),
+            ..body("B", 10.0)
+        });
+        let mut states = vec![first];
+        for index in 1..8 {
+            let mut universe = states.last().unwrap().clone();
+            if index == 4 {
+                universe.bodies.push(body("C", 30.0));
+                universe.changed = true;
+            } else {
+                universe.step(0.1);
+            }
+            states.push(universe);
+        }
+        let hash = history_hash(&states);
+        let data = Data {
+            current_state: 7,
+            step_size: 0.1,
+            history_hash: Some(hash),
+            ..Data::default()
+        };
+        let mut bytes = vec![];
+        ciborium::into_writer(
+            &Save {
+                data,
+                states: states.as_slice().into(),
+            },
+            &mut bytes,
+        )
+        .unwrap();
+
+        let (save, problems) = read(&bytes).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(save.data.history_hash, Some(hash));
+        assert_eq!(history_hash(&save.states), hash);
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_save() {
+        assert!(read(b"not a save").is_err());
+        assert!(read(b"[1, 2, 3]").is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::{config, universe::Units};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        }
+    }
+}
+
+// Everything that can be done from the keyboard, looked up through Keybindings so every shortcut
+// can be rebound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    NudgeUp,
+    NudgeDown,
+    NudgeLeft,
+    NudgeRight,
+    NewBody,
+    DeleteBody,
+    SelectNext,
+    SelectPrevious,
+    FocusSelected,
+    PlayPause,
+    StepForward,
+    StepBack,
+    Undo,
+    Redo,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::NudgeUp,
+        Action::NudgeDown,
+        Action::NudgeLeft,
+        Action::NudgeRight,
+        Action::NewBody,
+        Action::DeleteBody,
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::FocusSelected,
+        Action::PlayPause,
+        Action::StepForward,
+        Action::StepBack,
+        Action::Undo,
+        Action::Redo,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::PanUp => "Pan Up",
+            Action::PanDown => "Pan Down",
+            Action::PanLeft => "Pan Left",
+            Action::PanRight => "Pan Right",
+            Action::NudgeUp => "Nudge Up (Shift for Velocity)",
+            Action::NudgeDown => "Nudge Down (Shift for Velocity)",
+            Action::NudgeLeft => "Nudge Left (Shift for Velocity)",
+            Action::NudgeRight => "Nudge Right (Shift for Velocity)",
+            Action::NewBody => "New Body",
+            Action::DeleteBody => "Delete Body",
+            Action::SelectNext => "Select Next Body",
+            Action::SelectPrevious => "Select Previous Body",
+            Action::FocusSelected => "Focus Selected Body",
+            Action::PlayPause => "Play or Pause",
+            Action::StepForward => "Step Forward",
+            Action::StepBack => "Step Back",
+            Action::Undo => "Undo (Ctrl+)",
+            Action::Redo => "Redo (Ctrl+)",
+        }
+    }
+
+    fn default_key(self) -> egui::Key {
+        match self {
+            Action::PanUp => egui::Key::W,
+            Action::PanDown => egui::Key::S,
+            Action::PanLeft => egui::Key::A,
+            Action::PanRight => egui::Key::D,
+            Action::NudgeUp => egui::Key::ArrowUp,
+            Action::NudgeDown => egui::Key::ArrowDown,
+            Action::NudgeLeft => egui::Key::ArrowLeft,
+            Action::NudgeRight => egui::Key::ArrowRight,
+            Action::NewBody => egui::Key::N,
+            Action::DeleteBody => egui::Key::Delete,
+            Action::SelectNext => egui::Key::CloseBracket,
+            Action::SelectPrevious => egui::Key::OpenBracket,
+            Action::FocusSelected => egui::Key::F,
+            Action::PlayPause => egui::Key::Space,
+            Action::StepForward => egui::Key::Period,
+            Action::StepBack => egui::Key::Comma,
+            Action::Undo => egui::Key::Z,
+            Action::Redo => egui::Key::Y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keybindings(BTreeMap<Action, egui::Key>);
+
+impl Keybindings {
+    pub fn key(&self, action: Action) -> egui::Key {
+        self.0
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_key())
+    }
+
+    pub fn pressed(&self, input: &egui::InputState, action: Action) -> bool {
+        input.key_pressed(self.key(action))
+    }
+
+    pub fn down(&self, input: &egui::InputState, action: Action) -> bool {
+        input.key_down(self.key(action))
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("Keybindings").show(ui, |ui| {
+            for action in Action::ALL {
+                let mut key = self.key(action);
+                let label = ui.label(action.name());
+                egui::ComboBox::from_id_salt(action)
+                    .selected_text(key.name())
+                    .show_ui(ui, |ui| {
+                        for &option in egui::Key::ALL {
+                            ui.selectable_value(&mut key, option, option.name());
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                self.0.insert(action, key);
+                ui.end_row();
+            }
+        });
+        if ui.button("Reset Keybindings").clicked() {
+            *self = Keybindings::default();
+        }
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_key()))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: Theme,
+    pub ui_scale: f32,
+    pub font_size: f32,
+    pub new_world_time_step: usize,
+    pub default_units: Units,
+    pub autosave_interval: f64,
+    pub auto_gen_future: bool,
+    pub cpu_budget: f64,
+    pub edit_debounce: f64,
+    pub edit_transactions: bool,
+    pub memory_budget: f64,
+    pub compact_history: bool,
+    pub multi_rate_history: bool,
+    pub history_max_stride: usize,
+    pub simulation_threads: usize,
+    pub camera_move_speed: f64,
+    pub camera_zoom_speed: f64,
+    pub keybindings: Keybindings,
+}
+
+// Holding Shift or Ctrl while panning or zooming
+const FAST_CAMERA_FACTOR: f64 = 4.0;
+const SLOW_CAMERA_FACTOR: f64 = 0.25;
+// Fraction of the view height one line of scrolling zooms by
+const ZOOM_STEP: f64 = 0.005;
+
+impl Settings {
+    pub fn camera_modifier(&self, modifiers: egui::Modifiers) -> f64 {
+        let mut factor = 1.0;
+        if modifiers.shift {
+            factor *= FAST_CAMERA_FACTOR;
+        }
+        if modifiers.command {
+            factor *= SLOW_CAMERA_FACTOR;
+        }
+        factor
+    }
+
+    // Every step scales the view by the same ratio so zooming feels the same at any scale
+    pub fn zoom_factor(&self, input: &egui::InputState) -> f64 {
+        (-input.raw_scroll_delta.y as f64
+            * ZOOM_STEP
+            * self.camera_zoom_speed
+            * self.camera_modifier(input.modifiers))
+        .exp()
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Preferences");
+        if let Some(path) = config::path() {
+            ui.label(format!("Stored in {}", path.display()));
+        }
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            ui.radio_value(&mut self.theme, Theme::Dark, "Dark");
+            ui.radio_value(&mut self.theme, Theme::Light, "Light");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("UI Scale:");
+            ui.add(
+                egui::DragValue::new(&mut self.ui_scale)
+                    .range(0.5..=3.0)
+                    .speed(0.01),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Zoom the whole interface, also changed with Ctrl + and Ctrl -");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Font Size:");
+            ui.add(
+                egui::DragValue::new(&mut self.font_size)
+                    .range(6.0..=32.0)
+                    .speed(0.1)
+                    .suffix("pt"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Size of body text, headings and small text are scaled along with it");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("New World Time Step:");
+            ui.add(
+                egui::DragValue::new(&mut self.new_world_time_step)
+                    .range(1..=usize::MAX)
+                    .prefix("1/"),
+            )
+            .labelled_by(label.id);
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("New World Units:");
+            egui::ComboBox::from_id_salt("Default Units")
+                .selected_text(self.default_units.name())
+                .show_ui(ui, |ui| {
+                    // Custom has no gravitational constant of its own to start from
+                    for option in Units::ALL
+                        .into_iter()
+                        .filter(|units| units.gravity().is_some())
+                    {
+                        ui.selectable_value(&mut self.default_units, option, option.name());
+                    }
+                })
+                .response
+                .labelled_by(label.id)
+                .on_hover_text("Gravitational constant new empty worlds start with");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Autosave Interval:");
+            ui.add(
+                egui::DragValue::new(&mut self.autosave_interval)
+                    .range(1.0..=3600.0)
+                    .speed(1.0)
+                    .suffix("s"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("How often open worlds are saved so they come back after a restart");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Camera Move Speed:");
+            ui.add(
+                egui::DragValue::new(&mut self.camera_move_speed)
+                    .range(0.01..=100.0)
+                    .speed(0.01)
+                    .suffix("x"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Screen heights per second panned with the keyboard");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Camera Zoom Speed:");
+            ui.add(
+                egui::DragValue::new(&mut self.camera_zoom_speed)
+                    .range(0.01..=100.0)
+                    .speed(0.01)
+                    .suffix("x"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Hold Shift to pan and zoom faster, Ctrl to go slower");
+        });
+        ui.collapsing("Keybindings", |ui| self.keybindings.ui(ui));
+
+        ui.heading("Generation");
+        ui.checkbox(&mut self.auto_gen_future, "Auto Gen Future")
+            .on_hover_text("Pick Gen Future from how long each step takes to simulate");
+        ui.horizontal(|ui| {
+            let label = ui.label("CPU Budget:");
+            ui.add(
+                egui::DragValue::new(&mut self.cpu_budget)
+                    .range(0.01..=1.0)
+                    .speed(0.01)
+                    .suffix("s/s"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Seconds of CPU time the generation thread may use per second");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Simulation Threads:");
+            let max_threads = std::thread::available_parallelism().map_or(1, |count| count.get());
+            ui.add(
+                egui::DragValue::new(&mut self.simulation_threads)
+                    .range(0..=max_threads)
+                    .speed(0.1)
+                    .custom_formatter(|value, _| match value as usize {
+                        0 => "Auto".to_string(),
+                        threads => threads.to_string(),
+                    }),
+            )
+            .labelled_by(label.id)
+            .on_hover_text(
+                "Threads each step of a large world is split across, Auto uses every core",
+            );
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Edit Delay:");
+            ui.add(
+                egui::DragValue::new(&mut self.edit_debounce)
+                    .range(0.0..=10.0)
+                    .speed(0.01)
+                    .suffix("s"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("How long to wait after the last edit before regenerating the future");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Memory Budget:");
+            ui.add(
+                egui::DragValue::new(&mut self.memory_budget)
+                    .range(1.0..=f64::INFINITY)
+                    .speed(10.0)
+                    .suffix("mb"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text(
+                "Warn when the past, future and undo history of a world would use more than this, undo keeps at most a quarter of it",
+            );
+        });
+        ui.checkbox(&mut self.compact_history, "Compact History")
+            .on_hover_text(
+                "Store the past at single precision to save memory, replaying it will not be exact",
+            );
+        ui.checkbox(&mut self.multi_rate_history, "Multi-Rate History")
+            .on_hover_text(
+                "Only keep every Nth state far behind the current time, the rest are regenerated when you go back there",
+            );
+        ui.add_enabled_ui(self.multi_rate_history, |ui| {
+            ui.horizontal(|ui| {
+                let label = ui.label("Max Stride:");
+                ui.add(
+                    egui::DragValue::new(&mut self.history_max_stride)
+                        .range(1..=4096)
+                        .speed(1.0),
+                )
+                .labelled_by(label.id)
+                .on_hover_text("Keep at least every Nth state no matter how far away it is");
+            });
+        });
+        ui.checkbox(&mut self.edit_transactions, "Edit Transactions")
+            .on_hover_text(
+                "Preview edits until Apply Edits is pressed instead of replacing the future",
+            );
+    }
+}
+
+// egui's own body text size
+const DEFAULT_FONT_SIZE: f32 = 12.5;
+
+pub fn apply_font_size(ctx: &egui::Context, font_size: f32) {
+    let defaults = egui::Style::default().text_styles;
+    ctx.all_styles_mut(|style| {
+        for (text_style, font) in &mut style.text_styles {
+            if let Some(default) = defaults.get(text_style) {
+                font.size = default.size * font_size / DEFAULT_FONT_SIZE;
+            }
+        }
+    });
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Dark,
+            ui_scale: 1.0,
+            font_size: DEFAULT_FONT_SIZE,
+            new_world_time_step: 512,
+            default_units: Units::Sandbox,
+            autosave_interval: 30.0,
+            auto_gen_future: false,
+            cpu_budget: 1.0,
+            edit_debounce: 0.25,
+            edit_transactions: false,
+            memory_budget: 2000.0,
+            compact_history: false,
+            multi_rate_history: false,
+            history_max_stride: 64,
+            simulation_threads: 0,
+            camera_move_speed: 1.0,
+            camera_zoom_speed: 1.0,
+            keybindings: Keybindings::default(),
+        }
+    }
+}
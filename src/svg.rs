@@ -0,0 +1,94 @@
+use crate::{
+    drawing::DrawHandler,
+    rendering::{GpuCamera, GpuCircle, GpuQuad},
+};
+use cgmath::Vector2;
+use std::fmt::Write;
+
+// Same as the world view's panel
+const BACKGROUND: &str = "#323232";
+
+fn color(color: cgmath::Vector3<f32>) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.x),
+        channel(color.y),
+        channel(color.z)
+    )
+}
+
+enum Shape<'a> {
+    Quad(&'a GpuQuad),
+    Circle(&'a GpuCircle),
+}
+
+// The frame as the gpu would draw it, shapes are ordered by depth like the depth buffer does and
+// quads come first at the same depth since their pipeline runs first
+pub fn document(d: &DrawHandler, camera: &GpuCamera, width: f32, height: f32) -> String {
+    let scale = height / camera.vertical_height;
+    let to_screen = |pos: Vector2<f32>| {
+        (
+            (pos.x - camera.position.x) * scale + width * 0.5,
+            height * 0.5 - (pos.y - camera.position.y) * scale,
+        )
+    };
+    let mut shapes: Vec<(f32, Shape)> = d
+        .quads
+        .iter()
+        .map(|quad| (quad.position.z, Shape::Quad(quad)))
+        .chain(
+            d.circles
+                .iter()
+                .map(|circle| (circle.position.z, Shape::Circle(circle))),
+        )
+        .collect();
+    shapes.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut svg = String::new();
+    _ = writeln!(
+        svg,
+        r#"<svg width="{width}" height="{height}" viewBox="0 0 {width} {height}" xmlns="http://www.w3.org/2000/svg">"#
+    );
+    _ = writeln!(
+        svg,
+        r#"<rect width="{width}" height="{height}" fill="{BACKGROUND}"/>"#
+    );
+    for (_, shape) in shapes {
+        match shape {
+            Shape::Quad(quad) => {
+                // Rotations follow the quad shader, the local x axis points along (sin, cos)
+                let (sin, cos) = quad.rotation.sin_cos();
+                let corners: Vec<String> = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)]
+                    .into_iter()
+                    .map(|(u, v)| {
+                        let (x, y) = (u * quad.size.x, v * quad.size.y);
+                        let pos = Vector2::new(
+                            x * sin - y * cos + quad.position.x,
+                            x * cos + y * sin + quad.position.y,
+                        );
+                        let (x, y) = to_screen(pos);
+                        format!("{x:.2},{y:.2}")
+                    })
+                    .collect();
+                _ = writeln!(
+                    svg,
+                    r#"<polygon points="{}" fill="{}"/>"#,
+                    corners.join(" "),
+                    color(quad.color)
+                );
+            }
+            Shape::Circle(circle) => {
+                let (x, y) = to_screen(Vector2::new(circle.position.x, circle.position.y));
+                _ = writeln!(
+                    svg,
+                    r#"<circle cx="{x:.2}" cy="{y:.2}" r="{:.2}" fill="{}"/>"#,
+                    circle.radius * scale,
+                    color(circle.color)
+                );
+            }
+        }
+    }
+    _ = writeln!(svg, "</svg>");
+    svg
+}
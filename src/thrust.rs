@@ -0,0 +1,153 @@
+use crate::{
+    body::{Body, BodyId, Thrust, ThrustDirection},
+    conics::Spiral,
+    world::format_time,
+};
+use eframe::egui;
+
+// Engine settings of the selected body, with where a tangential burn spirals out to. Returns
+// whether anything was changed
+pub fn ui(
+    ui: &mut egui::Ui,
+    selected: BodyId,
+    body: &mut Body,
+    focused: Option<BodyId>,
+    body_names: &[(BodyId, String)],
+    parser: impl Fn(&str) -> Option<f64> + Copy,
+    spiral: Option<&Spiral>,
+) -> bool {
+    let mut changed = false;
+    let mut thrusting = body.thrust.is_some();
+    if ui
+        .checkbox(&mut thrusting, "Thrust")
+        .on_hover_text("Push this body with an engine every step")
+        .changed()
+    {
+        changed = true;
+        body.thrust = thrusting.then(Thrust::default);
+    }
+    // Spirals go around the focused body, or the parent when nothing else is focused
+    let around = focused.filter(|&id| id != selected).or(body.meta.parent);
+    if let Some(thrust) = &mut body.thrust {
+        ui.indent("Thrust", |ui| {
+            ui.horizontal(|ui| {
+                let label = ui.label("Direction:");
+                egui::ComboBox::from_id_salt("Thrust Direction")
+                    .selected_text(thrust.direction.name())
+                    .show_ui(ui, |ui| {
+                        for direction in [
+                            ThrustDirection::Prograde,
+                            ThrustDirection::Retrograde,
+                            ThrustDirection::Fixed { angle: 0.0 },
+                        ]
+                        .into_iter()
+                        .chain(around.map(|around| ThrustDirection::Tangential { around }))
+                        {
+                            if ui
+                                .selectable_label(
+                                    thrust.direction.name() == direction.name(),
+                                    direction.name(),
+                                )
+                                .clicked()
+                                && thrust.direction.name() != direction.name()
+                            {
+                                changed = true;
+                                thrust.direction = direction;
+                            }
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                if let ThrustDirection::Fixed { angle } = &mut thrust.direction {
+                    let mut degrees = angle.to_degrees();
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut degrees)
+                                .speed(1.0)
+                                .custom_parser(parser)
+                                .suffix("°"),
+                        )
+                        .labelled_by(label.id)
+                        .changed()
+                    {
+                        changed = true;
+                        *angle = degrees.to_radians();
+                    }
+                }
+                if let ThrustDirection::Tangential { around } = &mut thrust.direction {
+                    let name = body_names
+                        .iter()
+                        .find(|(id, _)| id == around)
+                        .map_or("None", |(_, name)| name.as_str());
+                    egui::ComboBox::from_id_salt("Thrust Around")
+                        .selected_text(format!("around {name}"))
+                        .show_ui(ui, |ui| {
+                            for (id, name) in body_names {
+                                if *id != selected
+                                    && ui.selectable_value(around, *id, name).changed()
+                                {
+                                    changed = true;
+                                }
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+                }
+            });
+            if let Some(spiral) = spiral {
+                ui.label(format!(
+                    "Spiral: +{:.3}m per orbit",
+                    spiral.growth_per_orbit
+                ))
+                .on_hover_text(
+                    "How much the radius grows each turn while the orbit is close to circular",
+                );
+                match spiral.final_radius {
+                    Some(radius) => ui.label(format!("Fuel runs out at a radius of {radius:.3}m")),
+                    None => ui.label(format!(
+                        "Escapes after {} ({:.3}m/s)",
+                        format_time(spiral.escape_time),
+                        spiral.escape_delta_v
+                    )),
+                }
+                .on_hover_text("The trail ahead shows the actual spiral");
+            }
+            ui.horizontal(|ui| {
+                let label = ui.label("Acceleration:");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut thrust.acceleration)
+                            .speed(0.001)
+                            .range(0.0..=f64::INFINITY)
+                            .custom_parser(parser)
+                            .suffix("m/s^2"),
+                    )
+                    .labelled_by(label.id)
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                let mut limited = thrust.fuel.is_some();
+                if ui
+                    .checkbox(&mut limited, "Fuel:")
+                    .on_hover_text("Delta-v left to spend, the engine stops once it runs out")
+                    .changed()
+                {
+                    changed = true;
+                    thrust.fuel = limited.then_some(10.0);
+                }
+                if let Some(fuel) = &mut thrust.fuel {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(fuel)
+                                .speed(0.1)
+                                .range(0.0..=f64::INFINITY)
+                                .custom_parser(parser)
+                                .suffix("m/s"),
+                        )
+                        .changed();
+                }
+            });
+        });
+    }
+    changed
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+
+// What the primary mouse button does in the viewport. Right click always focuses and middle click
+// always adds a body, whatever the tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tool {
+    #[default]
+    Select,
+    Pan,
+    Add,
+    Measure,
+    Annotate,
+    Maneuver,
+    Observe,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 7] = [
+        Tool::Select,
+        Tool::Pan,
+        Tool::Add,
+        Tool::Measure,
+        Tool::Annotate,
+        Tool::Maneuver,
+        Tool::Observe,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Select => "Select",
+            Tool::Pan => "Pan",
+            Tool::Add => "Add",
+            Tool::Measure => "Measure",
+            Tool::Annotate => "Annotate",
+            Tool::Maneuver => "Maneuver",
+            Tool::Observe => "Observe",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            Tool::Select => "Click a body to select it",
+            Tool::Pan => "Drag to move the view",
+            Tool::Add => "Click to add a body, only while editing",
+            Tool::Measure => "Click two points on one trail to time the path between them",
+            Tool::Annotate => "Click to place a note, or click a note to edit it",
+            Tool::Maneuver => {
+                "Click the selected body's predicted path to place a burn, or click a burn to edit it"
+            }
+            Tool::Observe => "Click to place the observer, clicking a body puts it on that body",
+        }
+    }
+
+    // Trail timing, burns and light delay all work out positions in the plane
+    pub fn works_in_3d(self) -> bool {
+        matches!(self, Tool::Select | Tool::Pan | Tool::Add | Tool::Annotate)
+    }
+}
+
+// Returns the tool that was picked, if it changed
+pub fn toolbar(ui: &mut egui::Ui, current: Tool, three_d: bool) -> Option<Tool> {
+    let mut picked = None;
+    ui.horizontal(|ui| {
+        for tool in Tool::ALL {
+            if ui
+                .add_enabled(
+                    !three_d || tool.works_in_3d(),
+                    egui::SelectableLabel::new(current == tool, tool.name()),
+                )
+                .on_hover_text(tool.hint())
+                .on_disabled_hover_text("Not available in 3D worlds")
+                .clicked()
+                && current != tool
+            {
+                picked = Some(tool);
+            }
+        }
+    });
+    picked
+}
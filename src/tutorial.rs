@@ -0,0 +1,223 @@
+use crate::{
+    body::{Body, BodyId, BodyMeta},
+    universe::Units,
+    world::World,
+};
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
+use eframe::egui;
+use std::sync::Arc;
+
+// Seconds of simulation the player has to move through to finish the scrubbing step
+const SCRUB_SECONDS: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Pan,
+    PlaceBody,
+    GiveVelocity,
+    Focus,
+    Scrub,
+    Done,
+}
+
+pub struct Tutorial {
+    pub step: Step,
+    pub sun: BodyId,
+    pub body: Option<BodyId>,
+    pub camera_start: Vector2<f64>,
+    pub time_start: usize,
+}
+
+pub fn world(step_size: f64) -> World {
+    let mut world = World::new(step_size, Units::Sandbox);
+    world.name = "Tutorial".to_string();
+    world.camera.view_height = 300.0;
+    let sun = world.states[0].bodies.push(Body {
+        meta: Arc::new(BodyMeta {
+            name: "Sun".into(),
+            density: 1.0,
+            color: Vector3::new(1.0, 0.8, 0.2),
+            high_accuracy: false,
+            pinned: false,
+            parent: None,
+        }),
+        pos: Vector3::zero(),
+        vel: Vector3::zero(),
+        radius: 10.0,
+        thrust: None,
+        spin: 0.0,
+        angle: 0.0,
+        charge: 0.0,
+        landed: None,
+        burns: vec![],
+    });
+    world.restart_generation();
+    world.tutorial = Some(Tutorial {
+        step: Step::Pan,
+        sun,
+        body: None,
+        camera_start: world.camera.pos,
+        time_start: 0,
+    });
+    world
+}
+
+fn advance(world: &mut World) {
+    let universe = world.state();
+    let Some(tutorial) = &world.tutorial else {
+        return;
+    };
+    let next = match tutorial.step {
+        Step::Pan => {
+            let moved = (world.camera.pos - tutorial.camera_start).magnitude();
+            (moved > world.camera.view_height * 0.25).then_some(Step::PlaceBody)
+        }
+        Step::PlaceBody => universe
+            .bodies
+            .iter()
+            .any(|(id, _)| id != tutorial.sun)
+            .then_some(Step::GiveVelocity),
+        Step::GiveVelocity => tutorial
+            .body
+            .and_then(|id| universe.bodies.get(id))
+            .is_some_and(|body| body.vel.magnitude2() > 0.0)
+            .then_some(Step::Focus),
+        Step::Focus => (world.focused_body() == Some(tutorial.sun)).then_some(Step::Scrub),
+        Step::Scrub => {
+            let moved = world.current_state.abs_diff(tutorial.time_start) as f64 * world.step_size;
+            (moved >= SCRUB_SECONDS).then_some(Step::Done)
+        }
+        Step::Done => None,
+    };
+    // The placed body is whichever one is not the sun, it may have been replaced since
+    let body = universe
+        .bodies
+        .iter()
+        .map(|(id, _)| id)
+        .find(|&id| id != tutorial.sun);
+    let current_state = world.current_state;
+    let tutorial = world.tutorial.as_mut().unwrap();
+    tutorial.body = body;
+    if let Some(next) = next {
+        tutorial.step = next;
+        tutorial.time_start = current_state;
+    }
+}
+
+fn screen_pos(world: &World, view: egui::Rect, id: BodyId) -> Option<egui::Pos2> {
+    let body = world.state().bodies.get(id)?;
+    let pos = world.camera.world_to_screen(body.pos.truncate());
+    Some(view.min + egui::vec2(pos.x as f32, pos.y as f32))
+}
+
+// Prompts are pinned next to the part of the screen they talk about
+pub fn ui(world: &mut World, ctx: &egui::Context) {
+    advance(world);
+    let Some(tutorial) = &world.tutorial else {
+        return;
+    };
+    let view = ctx.available_rect();
+    let above_time_bar = (
+        egui::Align2::CENTER_BOTTOM,
+        world.time_bar_rect.center_top() - egui::vec2(0.0, 8.0),
+    );
+
+    let (title, pivot, pos) = match tutorial.step {
+        Step::Pan => (
+            "1/5 Look Around",
+            egui::Align2::CENTER_TOP,
+            view.center_top() + egui::vec2(0.0, 16.0),
+        ),
+        Step::PlaceBody => ("2/5 Place A Body", above_time_bar.0, above_time_bar.1),
+        Step::GiveVelocity => match (world.selected_window_rect, world.selected) {
+            (Some(rect), Some(selected)) if Some(selected) == tutorial.body => (
+                "3/5 Give It Velocity",
+                egui::Align2::RIGHT_TOP,
+                rect.left_top() - egui::vec2(8.0, 0.0),
+            ),
+            _ => (
+                "3/5 Give It Velocity",
+                egui::Align2::LEFT_BOTTOM,
+                tutorial
+                    .body
+                    .and_then(|id| screen_pos(world, view, id))
+                    .unwrap_or(view.center())
+                    + egui::vec2(12.0, -12.0),
+            ),
+        },
+        Step::Focus => (
+            "4/5 Focus The Sun",
+            egui::Align2::LEFT_BOTTOM,
+            screen_pos(world, view, tutorial.sun).unwrap_or(view.center())
+                + egui::vec2(24.0, -24.0),
+        ),
+        Step::Scrub => ("5/5 Move Through Time", above_time_bar.0, above_time_bar.1),
+        Step::Done => ("Done", egui::Align2::CENTER_CENTER, view.center()),
+    };
+
+    let circular_speed = tutorial.body.and_then(|id| {
+        let universe = world.state();
+        let body = universe.bodies.get(id)?;
+        let sun = universe.bodies.get(tutorial.sun)?;
+        Some(
+            (universe.gravity * sun.mass(universe.three_d) / (body.pos - sun.pos).magnitude())
+                .sqrt(),
+        )
+    });
+    let step = tutorial.step;
+    let mut finish = false;
+    egui::Window::new(title)
+        .id("Tutorial".into())
+        .collapsible(false)
+        .resizable(false)
+        .pivot(pivot)
+        .fixed_pos(pos)
+        .show(ctx, |ui| {
+            ui.set_max_width(280.0);
+            match step {
+                Step::Pan => {
+                    ui.label("Hold W, A, S or D to move the camera and scroll to zoom.");
+                }
+                Step::PlaceBody => {
+                    ui.label(
+                        "Press Edit Here in the time bar so this moment can be changed, then press N to place a body in the middle of the screen.",
+                    );
+                }
+                Step::GiveVelocity => {
+                    ui.label(
+                        "Left click your new body to select it, then drag its Vel fields or hold Shift and press the arrow keys to push it.",
+                    );
+                    if let Some(speed) = circular_speed {
+                        ui.label(format!(
+                            "About {speed:.2}m/s sideways to the sun gives a circular orbit."
+                        ));
+                    }
+                }
+                Step::Focus => {
+                    ui.label(
+                        "Right click the sun to focus it, paths are then drawn relative to it. Right click empty space to unfocus.",
+                    );
+                }
+                Step::Scrub => {
+                    ui.label(
+                        "Press Edit Here again to stop editing, then drag the time slider or press play to watch the orbit.",
+                    );
+                }
+                Step::Done => {
+                    ui.label(
+                        "That is everything you need to build a system. A few more things to try:",
+                    );
+                    ui.label("- Number fields accept expressions like 3*r_earth or sqrt(G*M/r).");
+                    ui.label("- Delete Past and Delete Future trim the timeline, Ctrl+Z undoes that.");
+                    ui.label("- Gen Future sets how far ahead is simulated, keep it low if it lags.");
+                    finish = ui.button("Finish").clicked();
+                }
+            }
+            if step != Step::Done && ui.small_button("Skip Tutorial").clicked() {
+                finish = true;
+            }
+        });
+    if finish {
+        world.tutorial = None;
+    }
+}
@@ -1,11 +1,519 @@
-use crate::{body::BodyList, drawing::DrawHandler};
-use cgmath::InnerSpace;
+use crate::{
+    body::{
+        Body, BodyId, BodyList, BodyMeta, Burn, Landing, ManeuverId, Thrust, ThrustDirection,
+        burns_memory, meta_memory, radius_from_volume, volume,
+    },
+    camera::Camera,
+    conics::Conic,
+    drawing::DrawHandler,
+    integrators::{self, Integrator, Space, falloff},
+};
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    f64::consts::{PI, TAU},
+    ops::Range,
+    sync::Arc,
+};
+
+const HIGH_ACCURACY_SUBSTEPS: usize = 16;
+// Adaptive substeps aim for this many steps across the shortest encounter timescale
+const STEPS_PER_ENCOUNTER: f64 = 16.0;
+const MAX_ADAPTIVE_SUBSTEPS: usize = 1024;
+// Above this many bodies gravity is approximated with a Barnes-Hut quadtree
+const BARNES_HUT_THRESHOLD: usize = 256;
+// Bodies sharing a position would otherwise be split forever
+const QUADTREE_MAX_DEPTH: usize = 48;
+
+pub const SI_GRAVITY: f64 = 6.674_30e-11;
+
+// Which gravitational constant a world was set up with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Units {
+    #[default]
+    Sandbox,
+    Si,
+    Custom,
+}
+
+impl Units {
+    pub const ALL: [Units; 3] = [Units::Sandbox, Units::Si, Units::Custom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Units::Sandbox => "Sandbox",
+            Units::Si => "SI",
+            Units::Custom => "Custom",
+        }
+    }
+
+    pub fn gravity(self) -> Option<f64> {
+        match self {
+            Units::Sandbox => Some(1.0),
+            Units::Si => Some(SI_GRAVITY),
+            Units::Custom => None,
+        }
+    }
+}
+
+pub fn default_coulomb() -> f64 {
+    1.0
+}
+
+pub fn default_force_exponent() -> f64 {
+    2.0
+}
+
+pub fn default_barnes_hut_theta() -> f64 {
+    0.5
+}
+
+struct QuadNode {
+    mass: f64,
+    center_of_mass: Vector2<f64>,
+    size: f64,
+    children: Vec<usize>,
+    bodies: Range<usize>,
+}
+
+struct QuadTree {
+    nodes: Vec<QuadNode>,
+    order: Vec<usize>,
+    slot: Vec<usize>,
+}
+
+impl QuadTree {
+    fn new(masses: &[f64], pos: &[Vector2<f64>]) -> Self {
+        let (min, max) = pos.iter().fold(
+            (
+                Vector2::new(f64::INFINITY, f64::INFINITY),
+                Vector2::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(min, max), p| {
+                (
+                    Vector2::new(min.x.min(p.x), min.y.min(p.y)),
+                    Vector2::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        );
+        let center = (min + max) * 0.5;
+        let half = ((max.x - min.x).max(max.y - min.y) * 0.5).max(f64::MIN_POSITIVE);
+
+        let mut tree = Self {
+            nodes: vec![],
+            order: (0..pos.len()).collect(),
+            slot: vec![0; pos.len()],
+        };
+        tree.build(masses, pos, 0..pos.len(), center, half, 0);
+        for (i, &body) in tree.order.iter().enumerate() {
+            tree.slot[body] = i;
+        }
+        tree
+    }
+
+    fn build(
+        &mut self,
+        masses: &[f64],
+        pos: &[Vector2<f64>],
+        bodies: Range<usize>,
+        center: Vector2<f64>,
+        half: f64,
+        depth: usize,
+    ) -> usize {
+        let index = self.nodes.len();
+        let order = &mut self.order[bodies.clone()];
+        let mass: f64 = order.iter().map(|&i| masses[i]).sum();
+        let center_of_mass = if mass > 0.0 {
+            order
+                .iter()
+                .map(|&i| pos[i] * masses[i])
+                .sum::<Vector2<f64>>()
+                / mass
+        } else {
+            center
+        };
+
+        let quadrant =
+            |p: Vector2<f64>| (p.x >= center.x) as usize + 2 * (p.y >= center.y) as usize;
+        let split = order.len() > 1 && depth < QUADTREE_MAX_DEPTH;
+        if split {
+            order.sort_unstable_by_key(|&i| quadrant(pos[i]));
+        }
+        self.nodes.push(QuadNode {
+            mass,
+            center_of_mass,
+            size: half * 2.0,
+            children: vec![],
+            bodies: bodies.clone(),
+        });
+
+        if split {
+            let mut start = bodies.start;
+            for q in 0..4 {
+                let count = self.order[start..bodies.end]
+                    .iter()
+                    .take_while(|&&i| quadrant(pos[i]) == q)
+                    .count();
+                if count > 0 {
+                    let quarter = half * 0.5;
+                    let child_center = center
+                        + Vector2::new(
+                            if q & 1 == 1 { quarter } else { -quarter },
+                            if q & 2 == 2 { quarter } else { -quarter },
+                        );
+                    let child = self.build(
+                        masses,
+                        pos,
+                        start..start + count,
+                        child_center,
+                        quarter,
+                        depth + 1,
+                    );
+                    self.nodes[index].children.push(child);
+                }
+                start += count;
+            }
+        }
+        index
+    }
+
+    fn acceleration(
+        &self,
+        i: usize,
+        masses: &[f64],
+        pos: &[Vector2<f64>],
+        gravity: f64,
+        exponent: f64,
+        theta: f64,
+    ) -> Vector2<f64> {
+        let mut acc = Vector2::zero();
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.children.is_empty() {
+                for &j in &self.order[node.bodies.clone()] {
+                    if j != i {
+                        let to_body = pos[j] - pos[i];
+                        acc += to_body.normalize()
+                            * (gravity * masses[j] / falloff(to_body.magnitude2(), exponent));
+                    }
+                }
+                continue;
+            }
+            let to_node = node.center_of_mass - pos[i];
+            let dist = to_node.magnitude();
+            // A node containing the body itself always has to be opened
+            if !node.bodies.contains(&self.slot[i]) && node.size < theta * dist {
+                acc += to_node / dist * (gravity * node.mass / falloff(dist * dist, exponent));
+            } else {
+                stack.extend_from_slice(&node.children);
+            }
+        }
+        acc
+    }
+}
+
+// Uses the direct sum for small systems, a theta of zero disables the approximation
+pub fn accelerations(
+    masses: &[f64],
+    pos: &[Vector2<f64>],
+    gravity: f64,
+    exponent: f64,
+    theta: f64,
+) -> Vec<Vector2<f64>> {
+    if theta <= 0.0 || pos.len() <= BARNES_HUT_THRESHOLD {
+        return integrators::accelerations(masses, pos, gravity, exponent);
+    }
+    let tree = QuadTree::new(masses, pos);
+    (0..pos.len())
+        .into_par_iter()
+        .map(|i| tree.acceleration(i, masses, pos, gravity, exponent, theta))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CollisionMode {
+    #[default]
+    PassThrough,
+    Merge,
+    Bounce {
+        restitution: f64,
+        #[serde(default)]
+        friction: f64,
+    },
+}
+
+// How fast the other body goes around this one, the same seen from either of them
+fn orbit_rate(body: &Body, other: &Body) -> f64 {
+    let to_other = (other.pos - body.pos).truncate();
+    to_other.perp_dot((other.vel - body.vel).truncate()) / to_other.magnitude2()
+}
+
+// Torque per unit of spin difference from the orbital rate that the other body raises on this one,
+// tides are only raised in the plane
+fn tidal_strength(gravity: f64, lag: f64, body: &Body, other: &Body) -> f64 {
+    let dist2 = (other.pos - body.pos).magnitude2();
+    let mass = other.mass(false);
+    lag * gravity * mass * mass * body.radius.powi(5) / dist2.powi(3)
+}
+
+fn bounce(a: &mut Body, b: &mut Body, restitution: f64, friction: f64, three_d: bool) {
+    let a_to_b = b.pos - a.pos;
+    let dist = a_to_b.magnitude();
+    let overlap = a.radius + b.radius - dist;
+    if overlap <= 0.0 || dist == 0.0 {
+        return;
+    }
+    let normal = a_to_b / dist;
+    // Massless bodies take the whole response
+    let (a_mass, b_mass) = (a.mass(three_d), b.mass(three_d));
+    let (a_inverse, b_inverse) = match (a_mass > 0.0, b_mass > 0.0) {
+        (true, true) => (1.0 / a_mass, 1.0 / b_mass),
+        (false, true) => (1.0, 0.0),
+        (true, false) => (0.0, 1.0),
+        (false, false) => (1.0, 1.0),
+    };
+    let total_inverse = a_inverse + b_inverse;
+
+    let closing = (b.vel - a.vel).dot(normal);
+    if closing < 0.0 {
+        let impulse = -(1.0 + restitution) * closing / total_inverse;
+        a.vel -= normal * impulse * a_inverse;
+        b.vel += normal * impulse * b_inverse;
+
+        // Friction at the contact point trades sliding for spin, at most friction times the
+        // push between them. Spin only turns the surface within the plane, so contacts above
+        // or below it have less of it to work with
+        let lever = if normal.z == 0.0 {
+            1.0
+        } else {
+            normal.truncate().magnitude()
+        };
+        if friction > 0.0 && a.radius > 0.0 && b.radius > 0.0 && lever > 0.0 {
+            let tangent = Vector3::new(-normal.y, normal.x, 0.0) / lever;
+            let sliding =
+                (b.vel - a.vel).dot(tangent) - (b.spin * b.radius + a.spin * a.radius) * lever;
+            // A disc's moment of inertia makes turning it twice as hard as moving it
+            let limit = friction * impulse;
+            let tangential = (-sliding / (3.0 * total_inverse)).clamp(-limit, limit);
+            a.vel -= tangent * tangential * a_inverse;
+            b.vel += tangent * tangential * b_inverse;
+            a.spin -= 2.0 * tangential * a_inverse / a.radius * lever;
+            b.spin -= 2.0 * tangential * b_inverse / b.radius * lever;
+        }
+    }
+    a.pos -= normal * overlap * a_inverse / total_inverse;
+    b.pos += normal * overlap * b_inverse / total_inverse;
+}
+
+// Touching the surface at the landed spot and moving with it
+fn resting_place(body: &Body, surface: &Body, landing: Landing) -> (Vector3<f64>, Vector3<f64>) {
+    let (sin, cos) = (surface.angle + landing.angle).sin_cos();
+    let offset = Vector3::new(cos, sin, 0.0) * (body.radius + surface.radius);
+    (
+        surface.pos + offset,
+        surface.vel + Vector3::new(-offset.y, offset.x, 0.0) * surface.spin,
+    )
+}
+
+// A landed body and what it rests on are not colliding
+fn resting(a_id: BodyId, a: &Body, b_id: BodyId, b: &Body) -> bool {
+    a.landed.is_some_and(|landing| landing.on == b_id)
+        || b.landed.is_some_and(|landing| landing.on == a_id)
+}
+
+// An analytic potential every body moves in, centered on the origin, standing in for matter that
+// is not simulated as bodies
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Background {
+    // Pulls toward the center in proportion to the distance, every orbit takes the same time
+    Harmonic { strength: f64 },
+    // Navarro-Frenk-White profile, the enclosed mass keeps growing so rotation curves flatten out
+    Halo { mass: f64, scale: f64 },
+}
+
+impl Background {
+    pub const ALL: [Background; 2] = [
+        Background::Harmonic { strength: 0.01 },
+        Background::Halo {
+            mass: 1000.0,
+            scale: 10.0,
+        },
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Background::Harmonic { .. } => "Harmonic Trap",
+            Background::Halo { .. } => "Halo",
+        }
+    }
+
+    pub fn acceleration<V: Space>(self, gravity: f64, pos: V) -> V {
+        match self {
+            Background::Harmonic { strength } => pos * -strength,
+            Background::Halo { mass, scale } => {
+                let r = pos.magnitude();
+                if r == 0.0 || scale <= 0.0 {
+                    return V::zero();
+                }
+                let x = r / scale;
+                let enclosed = mass * ((1.0 + x).ln() - x / (1.0 + x));
+                pos / r * -(gravity * enclosed / (r * r))
+            }
+        }
+    }
+
+    // Per unit mass
+    pub fn potential<V: Space>(self, gravity: f64, pos: V) -> f64 {
+        match self {
+            Background::Harmonic { strength } => 0.5 * strength * pos.magnitude2(),
+            Background::Halo { mass, scale } => {
+                let r = pos.magnitude();
+                if scale <= 0.0 {
+                    0.0
+                } else if r == 0.0 {
+                    -gravity * mass / scale
+                } else {
+                    -gravity * mass * (1.0 + r / scale).ln() / r
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fragmentation {
+    // Kinetic energy of the approach, measured from the pair's center of mass
+    pub energy: f64,
+    pub pieces: usize,
+    // Share of the impact energy the debris keeps as it flies apart
+    pub dispersion: f64,
+    // Pairs that would break into smaller pieces than this merge or bounce instead
+    pub min_radius: f64,
+}
+
+impl Default for Fragmentation {
+    fn default() -> Self {
+        Self {
+            energy: 5.0,
+            pieces: 6,
+            dispersion: 0.3,
+            min_radius: 0.1,
+        }
+    }
+}
+
+fn impact_energy(a: &Body, b: &Body, three_d: bool) -> f64 {
+    let (a_mass, b_mass) = (a.mass(three_d), b.mass(three_d));
+    let total_mass = a_mass + b_mass;
+    if total_mass <= 0.0 {
+        return 0.0;
+    }
+    0.5 * a_mass * b_mass / total_mass * (b.vel - a.vel).magnitude2()
+}
+
+// Only the plane's own Euler step pulls bodies pair by pair
+fn attract(gravity: f64, exponent: f64, a: &mut Body, b: &mut Body, dt: f64) {
+    let a_to_b = b.pos - a.pos;
+    let falloff = falloff(a_to_b.magnitude2(), exponent);
+
+    a.vel += a_to_b.normalize() * (gravity * b.mass(false) / falloff) * dt;
+    b.vel -= a_to_b.normalize() * (gravity * a.mass(false) / falloff) * dt;
+}
+
+// Everything that pulls on the bodies besides their gravity, gathered before a step so the
+// integrators can borrow the bodies
+struct Field {
+    gravity: f64,
+    coulomb: f64,
+    uniform_field: Vector2<f64>,
+    background: Option<Background>,
+    // Charges do not change during the step so they are gathered once
+    charges: Option<Vec<f64>>,
+    pinned: Vec<bool>,
+}
+
+impl Field {
+    fn add<V: Space>(&self, masses: &[f64], pos: &[V], mut acc: Vec<V>) -> Vec<V> {
+        if self.uniform_field != Vector2::zero() {
+            let uniform_field = V::from_plane(self.uniform_field);
+            for acc in &mut acc {
+                *acc += uniform_field;
+            }
+        }
+        if let Some(background) = self.background {
+            for (acc, &pos) in acc.iter_mut().zip(pos) {
+                *acc += background.acceleration(self.gravity, pos);
+            }
+        }
+        if let Some(charges) = &self.charges {
+            let electric = integrators::coulomb_accelerations(masses, charges, pos, self.coulomb);
+            for (acc, electric) in acc.iter_mut().zip(electric) {
+                *acc += electric;
+            }
+        }
+        for (acc, &pinned) in acc.iter_mut().zip(&self.pinned) {
+            if pinned {
+                *acc = V::zero();
+            }
+        }
+        acc
+    }
+}
 
 #[derive(Debug)]
 pub struct Universe {
     pub bodies: BodyList,
     pub gravity: f64,
+    // Bodies coast in straight lines, G is kept for when it is turned back on
+    pub gravity_off: bool,
+    // Gravity falls off with distance to this power, 2 is the real inverse square law
+    pub force_exponent: f64,
+    // Kept with the rest of the settings so switching it partway only changes the states after
+    pub integrator: Integrator,
     pub changed: bool,
+    // Maneuver nodes applied to this state, kept by clones so an edit reverted to a copy remembers
+    // them. Stepping clears them since the next state has not been given any
+    pub maneuvers: Vec<ManeuverId>,
+    pub compact: Option<Vec<CompactBody>>,
+    pub thinned: bool,
+    pub kepler_drift: bool,
+    pub collision_mode: CollisionMode,
+    pub barnes_hut_theta: f64,
+    pub adaptive_substeps: bool,
+    // Constant time lag of tidal bulges, 0 turns tides off
+    pub tidal_lag: f64,
+    // Shattering of hard impacts, None lets every collision merge or bounce
+    pub fragmentation: Option<Fragmentation>,
+    // Arrivals slower than this come to rest on the surface they touch, 0 turns landing off
+    pub landing_speed: f64,
+    // Coulomb constant for charged bodies, separate from gravity so the two can be balanced
+    pub coulomb: f64,
+    // Same acceleration for every body, like wind or the pull near a planet's surface
+    pub uniform_field: Vector2<f64>,
+    pub background: Option<Background>,
+    // Bodies move out of the plane too and are balls rather than discs. Kepler parents,
+    // Barnes-Hut, tides and landings all assume the plane and are left out
+    pub three_d: bool,
+    // Hash of the full precision state as it was generated, kept through compacting and thinning
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompactBody {
+    pub id: BodyId,
+    // Debris appears mid generation so compact bodies keep their own metadata
+    pub meta: Arc<BodyMeta>,
+    pub pos: Vector3<f32>,
+    pub vel: Vector3<f32>,
+    pub radius: f32,
+    pub thrust: Option<Thrust>,
+    pub spin: f32,
+    pub angle: f32,
+    pub charge: f32,
+    pub landed: Option<Landing>,
+    pub burns: Vec<Burn>,
 }
 
 impl Clone for Universe {
@@ -13,42 +521,1342 @@ impl Clone for Universe {
         Self {
             bodies: self.bodies.clone(),
             gravity: self.gravity,
+            gravity_off: self.gravity_off,
+            force_exponent: self.force_exponent,
+            integrator: self.integrator,
             changed: false,
+            maneuvers: self.maneuvers.clone(),
+            compact: self.compact.clone(),
+            thinned: self.thinned,
+            kepler_drift: self.kepler_drift,
+            collision_mode: self.collision_mode,
+            barnes_hut_theta: self.barnes_hut_theta,
+            adaptive_substeps: self.adaptive_substeps,
+            tidal_lag: self.tidal_lag,
+            fragmentation: self.fragmentation,
+            landing_speed: self.landing_speed,
+            coulomb: self.coulomb,
+            uniform_field: self.uniform_field,
+            background: self.background,
+            three_d: self.three_d,
+            hash: self.hash,
         }
     }
 }
 
+// FNV-1a, written out so the result does not depend on the standard library version
+fn fnv1a(hash: u64, value: u64) -> u64 {
+    value.to_le_bytes().iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+// Chains every state hash in order so a single differing bit anywhere changes the result
+pub fn history_hash(states: &[Universe]) -> u64 {
+    states.iter().fold(FNV_OFFSET, |hash, universe| {
+        // Edited states are changed after being generated so their stored hash is stale
+        let state_hash = if universe.changed && !universe.is_compact() && !universe.is_thinned() {
+            universe.content_hash()
+        } else {
+            universe.hash
+        };
+        fnv1a(hash, state_hash)
+    })
+}
+
 impl Universe {
     pub fn new(gravity: f64) -> Self {
         Self {
             bodies: BodyList::new(),
             gravity,
+            gravity_off: false,
+            force_exponent: default_force_exponent(),
+            integrator: Integrator::default(),
             changed: true,
+            maneuvers: vec![],
+            compact: None,
+            thinned: false,
+            kepler_drift: false,
+            collision_mode: CollisionMode::PassThrough,
+            barnes_hut_theta: default_barnes_hut_theta(),
+            adaptive_substeps: false,
+            tidal_lag: 0.0,
+            fragmentation: None,
+            landing_speed: 0.0,
+            coulomb: default_coulomb(),
+            uniform_field: Vector2::zero(),
+            background: None,
+            three_d: false,
+            hash: 0,
+        }
+    }
+
+    pub fn active_gravity(&self) -> f64 {
+        if self.gravity_off { 0.0 } else { self.gravity }
+    }
+
+    // Body ids are not included since they are handed out again when a save is loaded
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        hash = fnv1a(hash, self.gravity.to_bits());
+        hash = fnv1a(hash, self.barnes_hut_theta.to_bits());
+        hash = fnv1a(
+            hash,
+            self.kepler_drift as u64
+                | (self.adaptive_substeps as u64) << 1
+                | (self.gravity_off as u64) << 2
+                | (self.three_d as u64) << 3
+                | (self.integrator as u64) << 4,
+        );
+        if self.is_charged() {
+            hash = fnv1a(hash, self.coulomb.to_bits());
+        }
+        if self.force_exponent != 2.0 {
+            hash = fnv1a(hash, self.force_exponent.to_bits());
+        }
+        if self.uniform_field != Vector2::zero() {
+            hash = fnv1a(hash, self.uniform_field.x.to_bits());
+            hash = fnv1a(hash, self.uniform_field.y.to_bits());
+        }
+        match self.background {
+            Some(Background::Harmonic { strength }) => {
+                hash = fnv1a(hash, 1);
+                hash = fnv1a(hash, strength.to_bits());
+            }
+            Some(Background::Halo { mass, scale }) => {
+                hash = fnv1a(hash, 2);
+                hash = fnv1a(hash, mass.to_bits());
+                hash = fnv1a(hash, scale.to_bits());
+            }
+            None => {}
+        }
+        if let Some(fragmentation) = self.fragmentation {
+            hash = fnv1a(hash, fragmentation.energy.to_bits());
+            hash = fnv1a(hash, fragmentation.pieces as u64);
+            hash = fnv1a(hash, fragmentation.dispersion.to_bits());
+            hash = fnv1a(hash, fragmentation.min_radius.to_bits());
+        }
+        if self.landing_speed > 0.0 {
+            hash = fnv1a(hash, self.landing_speed.to_bits());
         }
+        hash = fnv1a(hash, self.bodies.len() as u64);
+        for (_, body) in self.bodies.iter() {
+            for value in [
+                body.pos.x,
+                body.pos.y,
+                body.vel.x,
+                body.vel.y,
+                body.radius,
+                body.meta.density,
+            ] {
+                hash = fnv1a(hash, value.to_bits());
+            }
+            if let Some(thrust) = &body.thrust {
+                hash = fnv1a(hash, thrust.fuel.unwrap_or(f64::INFINITY).to_bits());
+            }
+            if body.spin != 0.0 || body.angle != 0.0 {
+                hash = fnv1a(hash, body.spin.to_bits());
+                hash = fnv1a(hash, body.angle.to_bits());
+            }
+            if body.charge != 0.0 {
+                hash = fnv1a(hash, body.charge.to_bits());
+            }
+            if let Some(landing) = body.landed {
+                hash = fnv1a(hash, landing.angle.to_bits());
+            }
+            if body.pos.z != 0.0 || body.vel.z != 0.0 {
+                hash = fnv1a(hash, body.pos.z.to_bits());
+                hash = fnv1a(hash, body.vel.z.to_bits());
+            }
+        }
+        hash
+    }
+
+    pub fn is_charged(&self) -> bool {
+        self.bodies.iter().any(|(_, body)| body.charge != 0.0)
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.compact.is_some()
+    }
+
+    pub fn is_thinned(&self) -> bool {
+        self.thinned
+    }
+
+    // Thinned states keep nothing and have to be regenerated from an earlier state
+    pub fn thin(&mut self) {
+        self.bodies = BodyList::new();
+        self.compact = None;
+        self.thinned = true;
+    }
+
+    pub fn compact(&mut self) {
+        if self.is_compact() || self.is_thinned() {
+            return;
+        }
+        self.compact = Some(
+            self.bodies
+                .iter()
+                .map(|(id, body)| CompactBody {
+                    id,
+                    meta: body.meta.clone(),
+                    pos: body.pos.cast().unwrap(),
+                    vel: body.vel.cast().unwrap(),
+                    radius: body.radius as f32,
+                    thrust: body.thrust,
+                    spin: body.spin as f32,
+                    angle: body.angle as f32,
+                    charge: body.charge as f32,
+                    landed: body.landed,
+                    burns: body.burns.clone(),
+                })
+                .collect(),
+        );
+        self.bodies = BodyList::new();
+    }
+
+    pub fn expand(&mut self) {
+        let Some(compact) = self.compact.take() else {
+            return;
+        };
+        self.bodies.reserve(compact.len());
+        for compact_body in compact {
+            self.bodies.insert(
+                compact_body.id,
+                Body {
+                    meta: compact_body.meta,
+                    pos: compact_body.pos.cast().unwrap(),
+                    vel: compact_body.vel.cast().unwrap(),
+                    radius: compact_body.radius as f64,
+                    thrust: compact_body.thrust,
+                    spin: compact_body.spin as f64,
+                    angle: compact_body.angle as f64,
+                    charge: compact_body.charge as f64,
+                    landed: compact_body.landed,
+                    burns: compact_body.burns,
+                },
+            );
+        }
+    }
+
+    // Where the body is in the plane, what everything drawn flat works with
+    pub fn position(&self, id: BodyId) -> Option<Vector2<f64>> {
+        self.position3(id).map(|pos| pos.truncate())
     }
 
+    pub fn position3(&self, id: BodyId) -> Option<Vector3<f64>> {
+        match &self.compact {
+            Some(compact) => compact
+                .binary_search_by_key(&id, |body| body.id)
+                .ok()
+                .map(|index| compact[index].pos.cast().unwrap()),
+            None => self.bodies.get(id).map(|body| body.pos),
+        }
+    }
+
+    pub fn velocity(&self, id: BodyId) -> Option<Vector2<f64>> {
+        self.velocity3(id).map(|vel| vel.truncate())
+    }
+
+    pub fn velocity3(&self, id: BodyId) -> Option<Vector3<f64>> {
+        match &self.compact {
+            Some(compact) => compact
+                .binary_search_by_key(&id, |body| body.id)
+                .ok()
+                .map(|index| compact[index].vel.cast().unwrap()),
+            None => self.bodies.get(id).map(|body| body.vel),
+        }
+    }
+
+    pub fn mass(&self, id: BodyId) -> Option<f64> {
+        match &self.compact {
+            Some(compact) => compact
+                .binary_search_by_key(&id, |body| body.id)
+                .ok()
+                .map(|index| {
+                    let body = &compact[index];
+                    body.meta.density * volume(body.radius as f64, self.three_d)
+                }),
+            None => self.bodies.get(id).map(|body| body.mass(self.three_d)),
+        }
+    }
+
+    pub fn body_ids(&self) -> Vec<BodyId> {
+        match &self.compact {
+            Some(compact) => compact.iter().map(|body| body.id).collect(),
+            None => self.bodies.iter().map(|(id, _)| id).collect(),
+        }
+    }
+
+    // The outer step always covers dt so the timeline keeps one state per step
     pub fn step(&mut self, dt: f64) {
+        self.maneuvers.clear();
+        let substeps = if self.adaptive_substeps {
+            let timescale = self.encounter_timescale();
+            ((dt * STEPS_PER_ENCOUNTER / timescale).ceil() as usize).clamp(1, MAX_ADAPTIVE_SUBSTEPS)
+        } else {
+            1
+        };
+        let sub_dt = dt / substeps as f64;
+        for _ in 0..substeps {
+            self.substep(sub_dt);
+        }
+        self.hash = self.content_hash();
+    }
+
+    // Shortest time in which any pair could change a lot, either by closing the gap between them
+    // or by falling into each other
+    fn encounter_timescale(&self) -> f64 {
+        let bodies: Vec<_> = self.bodies.iter().map(|(_, body)| body).collect();
+        let mut timescale = f64::INFINITY;
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                let dist = (b.pos - a.pos).magnitude();
+                let speed = (b.vel - a.vel).magnitude();
+                let mu = self.active_gravity() * (a.mass(self.three_d) + b.mass(self.three_d));
+                if speed > 0.0 {
+                    timescale = timescale.min(dist / speed);
+                }
+                if mu > 0.0 {
+                    timescale = timescale.min((dist * dist * dist / mu).sqrt());
+                }
+            }
+        }
+        timescale
+    }
+
+    fn substep(&mut self, dt: f64) {
+        // Pinned bodies still pull on everything else but are put back where they were afterwards
+        let pinned: Vec<(BodyId, Vector3<f64>)> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| body.meta.pinned)
+            .map(|(id, body)| (id, body.pos))
+            .collect();
+        for &(id, _) in &pinned {
+            let body = self.bodies.get_mut(id).unwrap();
+            body.vel = Vector3::zero();
+        }
+
+        let field = Field {
+            gravity: self.active_gravity(),
+            coulomb: self.coulomb,
+            uniform_field: self.uniform_field,
+            background: self.background,
+            charges: self
+                .is_charged()
+                .then(|| self.bodies.iter().map(|(_, body)| body.charge).collect()),
+            pinned: self
+                .bodies
+                .iter()
+                .map(|(_, body)| body.meta.pinned)
+                .collect(),
+        };
+        let (exponent, theta) = (self.force_exponent, self.barnes_hut_theta);
+
+        if self.three_d {
+            let pull = |masses: &[f64], pos: &[Vector3<f64>]| {
+                let gravity = integrators::accelerations(masses, pos, field.gravity, exponent);
+                field.add(masses, pos, gravity)
+            };
+            match self.integrator {
+                Integrator::Euler => integrators::euler(&mut self.bodies, dt, pull),
+                Integrator::Rk4 => integrators::rk4(&mut self.bodies, dt, pull),
+                Integrator::Verlet => integrators::verlet(&mut self.bodies, dt, pull),
+            }
+        // Kepler orbits only exist for the inverse square law
+        } else if self.kepler_drift && self.force_exponent == 2.0 {
+            self.step_hierarchical(dt);
+            if field.charges.is_some() {
+                self.apply_coulomb(dt);
+            }
+            self.apply_uniform_field(dt);
+            self.apply_background(dt);
+        } else {
+            let pull = |masses: &[f64], pos: &[Vector2<f64>]| {
+                let gravity = accelerations(masses, pos, field.gravity, exponent, theta);
+                field.add(masses, pos, gravity)
+            };
+            match self.integrator {
+                Integrator::Euler => {
+                    self.step_direct(dt);
+                    if field.charges.is_some() {
+                        self.apply_coulomb(dt);
+                    }
+                    self.apply_uniform_field(dt);
+                    self.apply_background(dt);
+                }
+                // High accuracy substeps are only layered on top of Euler
+                Integrator::Rk4 => integrators::rk4(&mut self.bodies, dt, pull),
+                Integrator::Verlet => integrators::verlet(&mut self.bodies, dt, pull),
+            }
+        }
+        self.apply_thrust(dt);
+        if self.tidal_lag > 0.0 && !self.three_d {
+            self.apply_tides(dt);
+        }
+        self.rotate(dt);
+        if !self.three_d {
+            self.apply_landings();
+        }
+        self.resolve_collisions();
+
+        for (id, pos) in pinned {
+            // Merging may have removed the pinned body
+            if let Some(body) = self.bodies.get_mut(id) {
+                body.pos = pos;
+                body.vel = Vector3::zero();
+            }
+        }
+    }
+
+    // The Euler and hierarchical steps sum gravity on their own so charges are a kick afterwards
+    fn apply_coulomb(&mut self, dt: f64) {
+        let masses: Vec<f64> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.mass(self.three_d))
+            .collect();
+        let charges: Vec<f64> = self.bodies.iter().map(|(_, body)| body.charge).collect();
+        let pos: Vec<Vector2<f64>> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.pos.truncate())
+            .collect();
+        let acc = integrators::coulomb_accelerations(&masses, &charges, &pos, self.coulomb);
+        for ((_, body), acc) in self.bodies.iter_mut().zip(acc) {
+            if !body.meta.pinned {
+                body.vel += acc.extend(0.0) * dt;
+            }
+        }
+    }
+
+    fn apply_uniform_field(&mut self, dt: f64) {
+        if self.uniform_field == Vector2::zero() {
+            return;
+        }
+        for (_, body) in self.bodies.iter_mut() {
+            if !body.meta.pinned {
+                body.vel += self.uniform_field.extend(0.0) * dt;
+            }
+        }
+    }
+
+    fn apply_background(&mut self, dt: f64) {
+        let Some(background) = self.background else {
+            return;
+        };
+        let gravity = self.active_gravity();
+        for (_, body) in self.bodies.iter_mut() {
+            if !body.meta.pinned {
+                body.vel += background.acceleration(gravity, body.pos) * dt;
+            }
+        }
+    }
+
+    // Applied as a kick after the gravity step, which is plenty for gentle engines
+    fn apply_thrust(&mut self, dt: f64) {
+        let thrusting: Vec<BodyId> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| body.thrust.is_some())
+            .map(|(id, _)| id)
+            .collect();
+        for id in thrusting {
+            let around = self.bodies.get(id).and_then(|body| {
+                let ThrustDirection::Tangential { around } = body.thrust?.direction else {
+                    return None;
+                };
+                let center = self.bodies.get(around)?;
+                Some((
+                    (body.pos - center.pos).truncate(),
+                    (body.vel - center.vel).truncate(),
+                ))
+            });
+            let body = self.bodies.get_mut(id).unwrap();
+            let thrust = body.thrust.as_mut().unwrap();
+            let mut delta_v = thrust.acceleration * dt;
+            if let Some(fuel) = &mut thrust.fuel {
+                delta_v = delta_v.min(*fuel).max(0.0);
+                *fuel -= delta_v;
+            }
+            thrust.spent += delta_v;
+            let direction = thrust.direction3(body.vel, around);
+            body.vel += direction * delta_v;
+        }
+    }
+
+    // The body the strongest tides on this one come from, with the rate it goes around this one,
+    // which is the spin the tides pull toward
+    pub fn tidal_partner(&self, id: BodyId) -> Option<(BodyId, f64)> {
+        let body = self.bodies.get(id)?;
+        // Everything else in the strength is the same for every partner
+        let pull = |other: &Body| {
+            other.mass(self.three_d).powi(2) / (other.pos - body.pos).magnitude2().powi(3)
+        };
+        self.bodies
+            .iter()
+            .filter(|&(other_id, other)| other_id != id && other.pos != body.pos)
+            .max_by(|(_, a), (_, b)| pull(a).total_cmp(&pull(b)))
+            .map(|(other_id, other)| (other_id, orbit_rate(body, other)))
+    }
+
+    // Sum of the tidal torques from every other body, zero while tides are off
+    pub fn tidal_torque(&self, id: BodyId) -> f64 {
+        let Some(body) = self.bodies.get(id) else {
+            return 0.0;
+        };
+        if self.tidal_lag <= 0.0 || body.moment_of_inertia(self.three_d) <= 0.0 {
+            return 0.0;
+        }
+        let (gravity, lag) = (self.active_gravity(), self.tidal_lag);
+        self.bodies
+            .iter()
+            .filter(|&(other_id, other)| other_id != id && other.pos != body.pos)
+            .map(|(_, other)| {
+                tidal_strength(gravity, lag, body, other) * (orbit_rate(body, other) - body.spin)
+            })
+            .sum()
+    }
+
+    // Each body's bulge is dragged toward the other body, speeding up or slowing its spin toward
+    // the orbital rate, and the orbit gets the opposite change so angular momentum is conserved
+    fn apply_tides(&mut self, dt: f64) {
+        let (gravity, lag) = (self.active_gravity(), self.tidal_lag);
         self.bodies.iter_mut_pairs(|_, a, _, b| {
             let a_to_b = b.pos - a.pos;
             let dist2 = a_to_b.magnitude2();
-            let _dist = a_to_b.magnitude();
+            if dist2 == 0.0 {
+                return;
+            }
+            let orbit_rate = orbit_rate(a, b);
+            let torque = |body: &Body, other: &Body| {
+                let inertia = body.moment_of_inertia(self.three_d);
+                if inertia <= 0.0 {
+                    return 0.0;
+                }
+                let strength = tidal_strength(gravity, lag, body, other);
+                // Never spin past the orbital rate in a single step
+                let max = (orbit_rate - body.spin).abs() * inertia / dt;
+                (strength * (orbit_rate - body.spin)).clamp(-max, max)
+            };
+            let (a_torque, b_torque) = (torque(a, b), torque(b, a));
+            a.spin += a_torque * dt / a.moment_of_inertia(self.three_d).max(f64::MIN_POSITIVE);
+            b.spin += b_torque * dt / b.moment_of_inertia(self.three_d).max(f64::MIN_POSITIVE);
 
-            a.vel += a_to_b.normalize() * (self.gravity * b.mass() / dist2) * dt;
-            b.vel -= a_to_b.normalize() * (self.gravity * a.mass() / dist2) * dt;
+            let dist = dist2.sqrt();
+            let tangent = Vector3::new(-a_to_b.y, a_to_b.x, 0.0) / dist;
+            let force = tangent * -(a_torque + b_torque) / dist;
+            if b.mass(self.three_d) > 0.0 {
+                b.vel += force * dt / b.mass(self.three_d);
+            }
+            if a.mass(self.three_d) > 0.0 {
+                a.vel -= force * dt / a.mass(self.three_d);
+            }
         });
+    }
+
+    fn rotate(&mut self, dt: f64) {
+        for (_, body) in self.bodies.iter_mut() {
+            if body.spin != 0.0 {
+                body.angle = (body.angle + body.spin * dt).rem_euclid(TAU);
+            }
+        }
+    }
+
+    fn step_direct(&mut self, dt: f64) {
+        let masses: Vec<f64> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.mass(self.three_d))
+            .collect();
+        let pos: Vec<Vector2<f64>> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.pos.truncate())
+            .collect();
+        // The tree has no notion of pairs so high accuracy bodies are not separated out of it
+        if self.barnes_hut_theta > 0.0 && self.bodies.len() > BARNES_HUT_THRESHOLD {
+            let acc = accelerations(
+                &masses,
+                &pos,
+                self.active_gravity(),
+                self.force_exponent,
+                self.barnes_hut_theta,
+            );
+            for (i, (_, body)) in self.bodies.iter_mut().enumerate() {
+                body.vel += acc[i].extend(0.0) * dt;
+                body.pos += body.vel * dt;
+            }
+            return;
+        }
+
+        let flagged: Vec<bool> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.meta.high_accuracy)
+            .collect();
+        let acc = integrators::pair_accelerations(
+            &masses,
+            &pos,
+            self.active_gravity(),
+            self.force_exponent,
+            |i, j| !(flagged[i] && flagged[j]),
+        );
+        for (i, (_, body)) in self.bodies.iter_mut().enumerate() {
+            body.vel += acc[i].extend(0.0) * dt;
+        }
+
+        // Interactions between high accuracy bodies are substepped on their own
+        let high_accuracy: Vec<BodyId> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| body.meta.high_accuracy)
+            .map(|(id, _)| id)
+            .collect();
+        if !high_accuracy.is_empty() {
+            let sub_dt = dt / HIGH_ACCURACY_SUBSTEPS as f64;
+            let (gravity, exponent) = (self.active_gravity(), self.force_exponent);
+            for _ in 0..HIGH_ACCURACY_SUBSTEPS {
+                for (i, &a) in high_accuracy.iter().enumerate() {
+                    for &b in &high_accuracy[i + 1..] {
+                        if let [Some(a), Some(b)] = self.bodies.get_disjoint_mut([a, b]) {
+                            attract(gravity, exponent, a, b, sub_dt);
+                        }
+                    }
+                }
+                for &id in &high_accuracy {
+                    let body = self.bodies.get_mut(id).unwrap();
+                    body.pos += body.vel * sub_dt;
+                }
+            }
+        }
+
         self.bodies.iter_mut().for_each(|(_, body)| {
-            body.pos += body.vel * dt;
+            if !body.meta.high_accuracy {
+                body.pos += body.vel * dt;
+            }
         });
     }
 
-    pub fn draw(&self, d: &mut DrawHandler) {
+    // Children move on exact Kepler orbits around their parent and only the rest of gravity is
+    // integrated, which keeps moons from drifting away over long runs
+    fn step_hierarchical(&mut self, dt: f64) {
+        let ids: Vec<BodyId> = self.bodies.iter().map(|(id, _)| id).collect();
+        let index_of = |id: BodyId| ids.binary_search(&id).ok();
+        let mass: Vec<f64> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.mass(self.three_d))
+            .collect();
+        let (mut pos, mut vel): (Vec<Vector2<f64>>, Vec<Vector2<f64>>) = self
+            .bodies
+            .iter()
+            .map(|(_, body)| (body.pos.truncate(), body.vel.truncate()))
+            .unzip();
+
+        let mut parent: Vec<Option<usize>> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| body.meta.parent.and_then(index_of))
+            .collect();
+        // Parent chains that loop back on themselves are treated as roots
+        let mut depth = vec![0; ids.len()];
+        for i in 0..ids.len() {
+            let mut current = i;
+            while let Some(next) = parent[current] {
+                depth[i] += 1;
+                current = next;
+                if depth[i] > ids.len() {
+                    parent[i] = None;
+                    depth[i] = 0;
+                    break;
+                }
+            }
+        }
+        let mut order: Vec<usize> = (0..ids.len()).collect();
+        order.sort_by_key(|&i| depth[i]);
+
+        let (gravity, theta) = (self.active_gravity(), self.barnes_hut_theta);
+        let kepler_mu = |i: usize, p: usize| gravity * (mass[p] + mass[i]);
+        let kick = |pos: &[Vector2<f64>], vel: &mut [Vector2<f64>], h: f64| {
+            let acc = accelerations(&mass, pos, gravity, 2.0, theta);
+            let mut rel_vel: Vec<Vector2<f64>> = (0..pos.len())
+                .map(|i| match parent[i] {
+                    Some(p) => vel[i] - vel[p],
+                    None => vel[i],
+                })
+                .collect();
+            for i in 0..pos.len() {
+                rel_vel[i] += match parent[i] {
+                    Some(p) => {
+                        let r = pos[i] - pos[p];
+                        let kepler = -r * (kepler_mu(i, p) / r.magnitude().powi(3));
+                        (acc[i] - acc[p] - kepler) * h
+                    }
+                    None => acc[i] * h,
+                };
+            }
+            for &i in &order {
+                vel[i] = match parent[i] {
+                    Some(p) => vel[p] + rel_vel[i],
+                    None => rel_vel[i],
+                };
+            }
+        };
+        let drift = |pos: &mut [Vector2<f64>], vel: &mut [Vector2<f64>], h: f64| {
+            let relative: Vec<(Vector2<f64>, Vector2<f64>)> = (0..pos.len())
+                .map(|i| match parent[i] {
+                    Some(p) => Conic {
+                        mu: kepler_mu(i, p),
+                        pos: pos[i] - pos[p],
+                        vel: vel[i] - vel[p],
+                    }
+                    .propagate(h),
+                    None => (pos[i] + vel[i] * h, vel[i]),
+                })
+                .collect();
+            for &i in &order {
+                (pos[i], vel[i]) = match parent[i] {
+                    Some(p) => (pos[p] + relative[i].0, vel[p] + relative[i].1),
+                    None => relative[i],
+                };
+            }
+        };
+
+        kick(&pos, &mut vel, dt * 0.5);
+        drift(&mut pos, &mut vel, dt);
+        kick(&pos, &mut vel, dt * 0.5);
+
+        for (i, (_, body)) in self.bodies.iter_mut().enumerate() {
+            body.pos = pos[i].extend(0.0);
+            body.vel = vel[i].extend(0.0);
+        }
+    }
+
+    // Landed bodies are put back on their spot of the surface unless something pushed them off it,
+    // then slow arrivals land
+    fn apply_landings(&mut self) {
+        let landed: Vec<(BodyId, Landing)> = self
+            .bodies
+            .iter()
+            .filter_map(|(id, body)| Some((id, body.landed?)))
+            .collect();
+        if landed.is_empty() && self.landing_speed <= 0.0 {
+            return;
+        }
+        for (id, landing) in landed {
+            match self.bodies.get_disjoint_mut([id, landing.on]) {
+                [Some(body), Some(surface)] if self.landing_speed > 0.0 => {
+                    let (pos, vel) = resting_place(body, surface, landing);
+                    // Thrust or spinning faster than orbital speed at the surface lifts off
+                    if (body.vel - vel).dot(pos - surface.pos) > 0.0 {
+                        body.landed = None;
+                    } else {
+                        (body.pos, body.vel, body.spin) = (pos, vel, surface.spin);
+                    }
+                }
+                [Some(body), _] => body.landed = None,
+                _ => {}
+            }
+        }
+
+        let bodies: Vec<_> = self.bodies.iter().collect();
+        let mut landings = vec![];
+        for (i, &(a_id, a)) in bodies.iter().enumerate() {
+            for &(b_id, b) in &bodies[i + 1..] {
+                // The lighter body lands on the heavier one
+                let ((id, body), (surface_id, surface)) =
+                    if a.mass(self.three_d) < b.mass(self.three_d) {
+                        ((a_id, a), (b_id, b))
+                    } else {
+                        ((b_id, b), (a_id, a))
+                    };
+                let offset = body.pos - surface.pos;
+                if body.landed.is_some()
+                    || body.meta.pinned
+                    || offset.magnitude() > body.radius + surface.radius
+                    || offset == Vector3::zero()
+                    || landings.iter().any(|&(other, _)| other == id)
+                {
+                    continue;
+                }
+                let landing = Landing {
+                    on: surface_id,
+                    angle: offset.y.atan2(offset.x) - surface.angle,
+                };
+                let (_, vel) = resting_place(body, surface, landing);
+                if (body.vel - vel).magnitude() < self.landing_speed {
+                    landings.push((id, landing));
+                }
+            }
+        }
+        for (id, landing) in landings {
+            if let [Some(body), Some(surface)] = self.bodies.get_disjoint_mut([id, landing.on]) {
+                let (pos, vel) = resting_place(body, surface, landing);
+                (body.pos, body.vel, body.spin) = (pos, vel, surface.spin);
+                body.landed = Some(landing);
+            }
+        }
+    }
+
+    fn resolve_collisions(&mut self) {
+        if let Some(fragmentation) = self.fragmentation
+            && self.collision_mode != CollisionMode::PassThrough
+        {
+            // Debris can shatter again, the cap keeps one crowded step from cascading forever
+            for _ in 0..self.bodies.len() {
+                let Some((a, b)) = self.find_impact(fragmentation) else {
+                    break;
+                };
+                self.shatter(a, b, fragmentation);
+            }
+        }
+        match self.collision_mode {
+            CollisionMode::PassThrough => {}
+            CollisionMode::Merge => {
+                while let Some((a, b)) = self.find_overlap() {
+                    self.merge(a, b);
+                }
+            }
+            CollisionMode::Bounce {
+                restitution,
+                friction,
+            } => {
+                let three_d = self.three_d;
+                self.bodies.iter_mut_pairs(|a_id, a, b_id, b| {
+                    if !resting(a_id, a, b_id, b) {
+                        bounce(a, b, restitution, friction, three_d)
+                    }
+                });
+            }
+        }
+    }
+
+    fn find_overlap(&self) -> Option<(BodyId, BodyId)> {
+        let bodies: Vec<_> = self.bodies.iter().collect();
+        for (i, &(a_id, a)) in bodies.iter().enumerate() {
+            for &(b_id, b) in &bodies[i + 1..] {
+                if (b.pos - a.pos).magnitude() < a.radius + b.radius && !resting(a_id, a, b_id, b) {
+                    return Some((a_id, b_id));
+                }
+            }
+        }
+        None
+    }
+
+    fn find_impact(&self, fragmentation: Fragmentation) -> Option<(BodyId, BodyId)> {
+        if fragmentation.pieces < 2 {
+            return None;
+        }
+        let bodies: Vec<_> = self.bodies.iter().collect();
+        for (i, &(a_id, a)) in bodies.iter().enumerate() {
+            for &(b_id, b) in &bodies[i + 1..] {
+                let a_to_b = b.pos - a.pos;
+                if a_to_b.magnitude() >= a.radius + b.radius
+                    || (b.vel - a.vel).dot(a_to_b) >= 0.0
+                    || a.meta.pinned
+                    || b.meta.pinned
+                    || resting(a_id, a, b_id, b)
+                    || impact_energy(a, b, self.three_d) < fragmentation.energy
+                {
+                    continue;
+                }
+                let density = if a.mass(self.three_d) >= b.mass(self.three_d) {
+                    a.meta.density
+                } else {
+                    b.meta.density
+                };
+                let piece_mass =
+                    (a.mass(self.three_d) + b.mass(self.three_d)) / fragmentation.pieces as f64;
+                if density > 0.0
+                    && radius_from_volume(piece_mass / density, self.three_d)
+                        >= fragmentation.min_radius
+                {
+                    return Some((a_id, b_id));
+                }
+            }
+        }
+        None
+    }
+
+    // Both bodies become a ring of equal pieces around their center of mass, flying outward
+    // while keeping the pair's momentum and angular momentum
+    fn shatter(&mut self, a: BodyId, b: BodyId, fragmentation: Fragmentation) {
+        let a = self.bodies.remove(a).unwrap();
+        let b = self.bodies.remove(b).unwrap();
+        let three_d = self.three_d;
+        let energy = impact_energy(&a, &b, three_d);
+        let (a_mass, b_mass) = (a.mass(three_d), b.mass(three_d));
+        let heavier = if a_mass >= b_mass { &a } else { &b };
+        let meta = Arc::new(BodyMeta {
+            name: format!("{} Debris", heavier.meta.name),
+            density: heavier.meta.density,
+            color: heavier.meta.color,
+            high_accuracy: false,
+            pinned: false,
+            parent: None,
+        });
+        let total_mass = a_mass + b_mass;
+        let pos = (a.pos * a_mass + b.pos * b_mass) / total_mass;
+        let vel = (a.vel * a_mass + b.vel * b_mass) / total_mass;
+        let mut angular_momentum =
+            a.moment_of_inertia(three_d) * a.spin + b.moment_of_inertia(three_d) * b.spin;
+        for body in [&a, &b] {
+            angular_momentum += body.mass(three_d)
+                * (body.pos - pos)
+                    .truncate()
+                    .perp_dot((body.vel - vel).truncate());
+        }
+
+        let pieces = fragmentation.pieces;
+        let radius = radius_from_volume(total_mass / pieces as f64 / meta.density, three_d);
+        // Far enough out that neighbouring pieces do not touch
+        let ring = radius / (PI / pieces as f64).sin() * 1.1;
+        let outward = (2.0 * fragmentation.dispersion * energy / total_mass).sqrt();
+        let around = angular_momentum / (total_mass * ring);
+        // Turned by the impact itself so the debris does not always line up with the x axis
+        let turn = fnv1a(fnv1a(FNV_OFFSET, pos.x.to_bits()), pos.y.to_bits()) as f64
+            / u64::MAX as f64
+            * TAU;
+        for i in 0..pieces {
+            // The ring lies in the plane through the center of mass
+            let angle = turn + TAU * i as f64 / pieces as f64;
+            let direction = Vector3::new(angle.cos(), angle.sin(), 0.0);
+            let tangent = Vector3::new(-direction.y, direction.x, 0.0);
+            self.bodies.push(Body {
+                meta: meta.clone(),
+                pos: pos + direction * ring,
+                vel: vel + direction * outward + tangent * around,
+                radius,
+                thrust: None,
+                spin: 0.0,
+                angle: 0.0,
+                charge: (a.charge + b.charge) / pieces as f64,
+                landed: None,
+                burns: vec![],
+            });
+        }
+    }
+
+    // The heavier body survives, keeping its metadata and density
+    fn merge(&mut self, a: BodyId, b: BodyId) {
+        let three_d = self.three_d;
+        let (keep, gone) = if self.bodies.get(a).unwrap().mass(three_d)
+            >= self.bodies.get(b).unwrap().mass(three_d)
+        {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let gone = self.bodies.remove(gone).unwrap();
+        let keep = self.bodies.get_mut(keep).unwrap();
+        let (keep_mass, gone_mass) = (keep.mass(three_d), gone.mass(three_d));
+        let total_mass = keep_mass + gone_mass;
+        // Spin and the orbit of the two around each other both end up as the new body's spin
+        let mut angular_momentum = keep.moment_of_inertia(three_d) * keep.spin
+            + gone.moment_of_inertia(three_d) * gone.spin;
+        if total_mass > 0.0 {
+            let pos = (keep.pos * keep_mass + gone.pos * gone_mass) / total_mass;
+            let vel = (keep.vel * keep_mass + gone.vel * gone_mass) / total_mass;
+            for body in [&*keep, &gone] {
+                angular_momentum += body.mass(three_d)
+                    * (body.pos - pos)
+                        .truncate()
+                        .perp_dot((body.vel - vel).truncate());
+            }
+            keep.pos = pos;
+            keep.vel = vel;
+        }
+        keep.radius = if keep.meta.density > 0.0 {
+            radius_from_volume(total_mass / keep.meta.density, three_d)
+        } else {
+            radius_from_volume(
+                volume(keep.radius, three_d) + volume(gone.radius, three_d),
+                three_d,
+            )
+        };
+        let inertia = keep.moment_of_inertia(three_d);
+        if inertia > 0.0 {
+            keep.spin = angular_momentum / inertia;
+        }
+        keep.charge += gone.charge;
+    }
+
+    pub fn memory_estimate(&self) -> usize {
+        if self.is_thinned() {
+            return size_of::<Self>();
+        }
+        if self.compact.is_some() {
+            return self.compact_memory_estimate();
+        }
+        size_of::<Self>()
+            + self.bodies.len() * size_of::<(BodyId, Body)>()
+            + self.bodies.heap_memory()
+    }
+
+    // What memory_estimate gives once this state is compacted, without compacting it
+    pub fn compact_memory_estimate(&self) -> usize {
+        let (count, heap) = match &self.compact {
+            Some(compact) => (
+                compact.len(),
+                compact
+                    .iter()
+                    .map(|body| meta_memory(&body.meta) + burns_memory(&body.burns))
+                    .sum(),
+            ),
+            None => (self.bodies.len(), self.bodies.heap_memory()),
+        };
+        size_of::<Self>() + count * size_of::<CompactBody>() + heap
+    }
+
+    pub fn total_mass(&self) -> f64 {
+        self.bodies
+            .iter()
+            .map(|(_, body)| body.mass(self.three_d))
+            .sum()
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        self.bodies
+            .iter()
+            .map(|(_, body)| 0.5 * body.mass(self.three_d) * body.vel.magnitude2())
+            .sum()
+    }
+
+    pub fn potential_energy(&self) -> f64 {
+        let bodies: Vec<_> = self.bodies.iter().map(|(_, body)| body).collect();
+        let mut energy = 0.0;
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                let dist = (b.pos - a.pos).magnitude();
+                // The integral of the force out to infinity, which diverges at 1 and below
+                let potential = if self.force_exponent == 1.0 {
+                    -dist.ln()
+                } else {
+                    dist.powf(1.0 - self.force_exponent) / (self.force_exponent - 1.0)
+                };
+                energy -=
+                    self.active_gravity() * a.mass(self.three_d) * b.mass(self.three_d) * potential;
+            }
+        }
+        if let Some(background) = self.background {
+            for body in bodies {
+                energy +=
+                    body.mass(self.three_d) * background.potential(self.active_gravity(), body.pos);
+            }
+        }
+        energy
+    }
+
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    pub fn momentum(&self) -> Vector3<f64> {
+        self.bodies
+            .iter()
+            .fold(Vector3::zero(), |momentum, (_, body)| {
+                momentum + body.vel * body.mass(self.three_d)
+            })
+    }
+
+    pub fn barycenter(&self) -> Option<Vector2<f64>> {
+        self.barycenter3().map(|barycenter| barycenter.truncate())
+    }
+
+    pub fn barycenter3(&self) -> Option<Vector3<f64>> {
+        let (mass, weighted) = match &self.compact {
+            Some(compact) => {
+                compact
+                    .iter()
+                    .fold((0.0, Vector3::zero()), |(mass, weighted), body| {
+                        let body_mass =
+                            body.meta.density * volume(body.radius as f64, self.three_d);
+                        (
+                            mass + body_mass,
+                            weighted + body.pos.cast().unwrap() * body_mass,
+                        )
+                    })
+            }
+            None => {
+                self.bodies
+                    .iter()
+                    .fold((0.0, Vector3::zero()), |(mass, weighted), (_, body)| {
+                        (
+                            mass + body.mass(self.three_d),
+                            weighted + body.pos * body.mass(self.three_d),
+                        )
+                    })
+            }
+        };
+        (mass > 0.0).then(|| weighted / mass)
+    }
+
+    // Around the z axis, the only one bodies in the plane can turn around
+    pub fn angular_momentum(&self) -> f64 {
+        self.bodies
+            .iter()
+            .map(|(_, body)| body.mass(self.three_d) * body.pos.cross(body.vel).z)
+            .sum()
+    }
+
+    pub fn pairwise_distance_range(&self) -> Option<(f64, f64)> {
+        let bodies: Vec<_> = self.bodies.iter().map(|(_, body)| body).collect();
+        let mut range: Option<(f64, f64)> = None;
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                let distance = (b.pos - a.pos).magnitude();
+                range = Some(match range {
+                    Some((min, max)) => (min.min(distance), max.max(distance)),
+                    None => (distance, distance),
+                });
+            }
+        }
+        range
+    }
+
+    // Sorted by left edge so each body is only compared with the ones whose extent it reaches
+    pub fn overlapping_pairs(&self) -> Vec<(BodyId, BodyId)> {
+        let mut bodies: Vec<_> = self.bodies.iter().collect();
+        bodies.sort_by(|a, b| (a.1.pos.x - a.1.radius).total_cmp(&(b.1.pos.x - b.1.radius)));
+        let mut pairs = vec![];
+        for (i, (a_id, a)) in bodies.iter().enumerate() {
+            for (b_id, b) in &bodies[i + 1..] {
+                if b.pos.x - b.radius > a.pos.x + a.radius {
+                    break;
+                }
+                if (b.pos - a.pos).magnitude() < a.radius + b.radius {
+                    pairs.push((*a_id, *b_id));
+                }
+            }
+        }
+        pairs
+    }
+
+    pub fn overlaps(&self, a: BodyId, b: BodyId) -> bool {
+        let (Some(a), Some(b)) = (self.bodies.get(a), self.bodies.get(b)) else {
+            return false;
+        };
+        (b.pos - a.pos).magnitude() < a.radius + b.radius
+    }
+
+    pub fn dominant_body(&self) -> Option<BodyId> {
+        self.bodies
+            .iter()
+            .max_by(|(_, a), (_, b)| a.mass(self.three_d).total_cmp(&b.mass(self.three_d)))
+            .map(|(id, _)| id)
+    }
+
+    pub fn draw(&self, camera: &Camera, d: &mut DrawHandler) {
         self.bodies.iter().for_each(|(_, body)| {
-            d.circle(
-                body.pos.cast().unwrap(),
-                body.radius as f32,
-                body.color.cast().unwrap(),
-                0.1,
-            );
+            let pos = camera.project(body.pos);
+            if self.three_d {
+                d.sphere(
+                    pos.cast().unwrap(),
+                    body.radius as f32,
+                    body.meta.color.cast().unwrap(),
+                    camera.depth(body.pos) as f32,
+                );
+            } else {
+                d.circle(
+                    pos.cast().unwrap(),
+                    body.radius as f32,
+                    body.meta.color.cast().unwrap(),
+                    0.1,
+                );
+            }
+            // The spin axis points out of the plane, so the marker only reads from above
+            if (body.spin != 0.0 || body.angle != 0.0) && !camera.is_turned() {
+                let tip = pos + Vector2::new(body.angle.cos(), body.angle.sin()) * body.radius;
+                d.line(
+                    pos.cast().unwrap(),
+                    tip.cast().unwrap(),
+                    body.radius as f32 * 0.15,
+                    (body.meta.color * 0.5).cast().unwrap(),
+                    0.15,
+                );
+            }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::BurnKind;
+
+    // Plenty random for scattering bodies, there is no rand crate
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    fn scatter(count: usize) -> (Vec<f64>, Vec<Vector2<f64>>) {
+        let mut random = Lcg(12345);
+        (0..count)
+            .map(|_| {
+                let mass = 1.0 + random.next() * 99.0;
+                let pos = Vector2::new(random.next(), random.next()) * 1000.0
+                    - Vector2::new(500.0, 500.0);
+                (mass, pos)
+            })
+            .unzip()
+    }
+
+    // Relative to the typical acceleration, single bodies can feel almost no net pull
+    fn errors(exact: &[Vector2<f64>], approximate: &[Vector2<f64>]) -> Vec<f64> {
+        let rms =
+            (exact.iter().map(|acc| acc.magnitude2()).sum::<f64>() / exact.len() as f64).sqrt();
+        exact
+            .iter()
+            .zip(approximate)
+            .map(|(exact, approximate)| (approximate - exact).magnitude() / rms)
+            .collect()
+    }
+
+    #[test]
+    fn barnes_hut_is_close_to_direct_sum() {
+        let (masses, pos) = scatter(1000);
+        assert!(pos.len() > BARNES_HUT_THRESHOLD);
+        let exact = integrators::accelerations(&masses, &pos, 1.0, 2.0);
+        let approximate = accelerations(&masses, &pos, 1.0, 2.0, 0.5);
+        let errors = errors(&exact, &approximate);
+        let worst = errors.iter().copied().fold(0.0, f64::max);
+        let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+        assert!(worst < 1e-2, "worst error {worst}");
+        assert!(mean < 1e-3, "mean error {mean}");
+    }
+
+    #[test]
+    fn zero_theta_is_exact() {
+        let (masses, pos) = scatter(1000);
+        let exact = integrators::accelerations(&masses, &pos, 1.0, 2.0);
+        assert_eq!(accelerations(&masses, &pos, 1.0, 2.0, 0.0), exact);
+        // The tree itself opens every node, only the order of the sum differs
+        let tree = QuadTree::new(&masses, &pos);
+        let opened: Vec<Vector2<f64>> = (0..pos.len())
+            .map(|i| tree.acceleration(i, &masses, &pos, 1.0, 2.0, 0.0))
+            .collect();
+        let worst = errors(&exact, &opened).into_iter().fold(0.0, f64::max);
+        assert!(worst < 1e-12, "worst error {worst}");
+    }
+
+    fn ball(radius: f64, density: f64, x: f64) -> Body {
+        Body {
+            meta: Arc::new(BodyMeta {
+                name: "Ball".into(),
+                density,
+                color: Vector3::new(1.0, 1.0, 1.0),
+                high_accuracy: false,
+                pinned: false,
+                parent: None,
+            }),
+            pos: Vector3::new(x, 0.0, 0.0),
+            vel: Vector3::zero(),
+            radius,
+            thrust: None,
+            spin: 0.0,
+            angle: 0.0,
+            charge: 0.0,
+            landed: None,
+            burns: vec![],
+        }
+    }
+
+    #[test]
+    fn merged_balls_keep_mass_and_volume() {
+        let mut universe = Universe::new(1.0);
+        universe.three_d = true;
+        let a = universe.bodies.push(ball(1.0, 2.0, 0.0));
+        let b = universe.bodies.push(ball(2.0, 2.0, 1.0));
+        let mass = universe.mass(a).unwrap() + universe.mass(b).unwrap();
+        assert!((mass - 2.0 * 4.0 / 3.0 * PI * 9.0).abs() < 1e-9);
+        universe.merge(a, b);
+        let (_, merged) = universe.bodies.iter().next().unwrap();
+        assert!((merged.mass(true) - mass).abs() < 1e-9);
+        assert!((merged.radius - 9.0f64.cbrt()).abs() < 1e-12);
+
+        // Without a density the radius still adds up by volume, with equal masses the first stays
+        let a = universe.bodies.push(ball(1.0, 0.0, 0.0));
+        let b = universe.bodies.push(ball(1.0, 0.0, 1.0));
+        universe.merge(a, b);
+        assert!((universe.bodies.get(a).unwrap().radius - 2.0f64.cbrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn memory_estimate_counts_names_and_burns() {
+        let mut universe = Universe::new(1.0);
+        let id = universe.bodies.push(ball(1.0, 1.0, 0.0));
+        let plain = universe.memory_estimate();
+        let plain_compact = universe.compact_memory_estimate();
+
+        let body = universe.bodies.get_mut(id).unwrap();
+        body.meta_mut().name = "A".repeat(10_000);
+        for i in 0..1000 {
+            body.record_burn(i as f64, BurnKind::Nudge, Vector3::new(1.0, 0.0, 0.0));
+        }
+        let owned = 10_000 - "Ball".len() + 1000 * size_of::<Burn>();
+        assert!(universe.memory_estimate() >= plain + owned);
+        assert!(universe.compact_memory_estimate() >= plain_compact + owned);
+        universe.compact();
+        assert!(universe.memory_estimate() >= plain_compact + owned);
+
+        // A state cloned from it shares the name but has its own copy of the burns
+        let clone = universe.clone();
+        assert!(clone.memory_estimate() < plain_compact + owned);
+    }
+
+    // A star, a planet and a moon close around the planet, on circular orbits
+    fn hierarchical_triple() -> Universe {
+        let mut universe = Universe::new(1.0);
+        let star = universe.bodies.push(ball(10.0, 1.0, 0.0));
+        let planet = universe.bodies.push(ball(2.0, 1.0, 200.0));
+        let moon = universe.bodies.push(ball(0.3, 1.0, 210.0));
+        let mass = |id| universe.bodies.get(id).unwrap().mass(false);
+        let planet_speed = ((mass(star) + mass(planet)) / 200.0).sqrt();
+        let moon_speed = ((mass(planet) + mass(moon)) / 10.0).sqrt();
+        let planet_body = universe.bodies.get_mut(planet).unwrap();
+        planet_body.vel = Vector3::new(0.0, planet_speed, 0.0);
+        planet_body.meta_mut().parent = Some(star);
+        let moon_body = universe.bodies.get_mut(moon).unwrap();
+        moon_body.vel = Vector3::new(0.0, planet_speed + moon_speed, 0.0);
+        moon_body.meta_mut().parent = Some(planet);
+        universe
+    }
+
+    fn energy_drift(mut universe: Universe, dt: f64, steps: usize) -> f64 {
+        let start = universe.total_energy();
+        for _ in 0..steps {
+            universe.step(dt);
+        }
+        ((universe.total_energy() - start) / start).abs()
+    }
+
+    #[test]
+    fn kepler_drift_holds_energy_better_than_rk4() {
+        let mut direct = hierarchical_triple();
+        direct.integrator = Integrator::Rk4;
+        let mut hierarchical = hierarchical_triple();
+        hierarchical.kepler_drift = true;
+        // About 22 steps per orbit of the moon, for over a hundred of them
+        let (dt, steps) = (2.5, 2400);
+        let direct = energy_drift(direct, dt, steps);
+        let hierarchical = energy_drift(hierarchical, dt, steps);
+        assert!(hierarchical < 1e-4, "hierarchical drift {hierarchical}");
+        assert!(
+            hierarchical * 10.0 < direct,
+            "hierarchical {hierarchical}, direct {direct}"
+        );
+    }
+}
@@ -1,13 +1,106 @@
 use crate::{
-    body::{Body, BodyId},
+    alarms::{self, Alarm, Toast},
+    auto_orbit::AutoOrbit,
+    body::{Body, BodyId, BodyMeta, BurnKind, ManeuverId, ThrustDirection, density_unit},
     camera::Camera,
+    camera_path::{self, CameraKeyframe},
+    chaos::Chaos,
+    conics,
+    conservation::Conservation,
+    detachable,
     drawing::DrawHandler,
-    save::{Data, Save},
-    universe::Universe,
+    ephemeris,
+    event_ramp::EventRamp,
+    expression::{self, Constant, default_constants},
+    graphs::Graph,
+    heatmap::HeatMap,
+    impact::{self, Impact},
+    integrators::Integrator,
+    ledger,
+    observer::Observer,
+    orbit_info,
+    overlays::{self, ForbiddenCell, Marker, Overlay, OverlayKind},
+    picking,
+    reference::CrossCheck,
+    replay::{self, Recording, Replay},
+    save::{Data, SAVE_VERSION, Save},
+    settings::{Action, Settings},
+    thrust,
+    tools::Tool,
+    tutorial::Tutorial,
+    universe::{Background, CollisionMode, Fragmentation, Units, Universe, history_hash},
 };
 use cgmath::{InnerSpace, Vector2, Vector3, Zero};
 use eframe::egui;
-use std::sync::{Arc, Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    f64::consts::{FRAC_PI_2, TAU},
+    sync::{Arc, Condvar, Mutex},
+};
+
+// Seconds of budgeted generation work Auto Gen Future keeps ahead of the current state
+const AUTO_GEN_FUTURE_WINDOW: f64 = 10.0;
+// States this close behind the current state are kept at full precision by Compact History
+const COMPACT_HISTORY_MARGIN: usize = 1024;
+// States this close behind the current state are always kept by Multi-Rate History, the future is
+// never thinned since predictions and scheduled changes read it
+const MULTI_RATE_HISTORY_MARGIN: usize = 1024;
+// Far more than fits in memory, only there so a typed in Gen Future can not overflow state indices
+const MAX_GEN_FUTURE: usize = 1 << 40;
+// Timeline operations that can be undone, older ones are forgotten
+const TIMELINE_UNDO_LIMIT: usize = 8;
+// Share of the memory budget undone states may hold on to, older operations are forgotten past it
+// so Delete Past still frees memory
+const TIMELINE_UNDO_MEMORY_SHARE: f64 = 0.25;
+// Focuses Back can return to
+const FOCUS_HISTORY_LIMIT: usize = 64;
+// Screen pixels around a note that pick it with the Annotate tool
+const ANNOTATION_PICK_RADIUS: f64 = 12.0;
+// Bodies checked for collisions per frame, the scan picks up where it stopped next frame
+const COLLISION_SCAN_BUDGET: usize = 200_000;
+// Fast next to how things usually move, so delays show without everything looking far behind
+const DEFAULT_SIGNAL_SPEED: f64 = 50.0;
+
+fn history_stride(distance: usize, max_stride: usize) -> usize {
+    (distance / MULTI_RATE_HISTORY_MARGIN + 1)
+        .next_power_of_two()
+        .min(max_stride.max(1))
+}
+
+// How many of the states before end Multi-Rate History thins, counted a band of equal stride at a
+// time. end has to be at least the margin behind the current state
+fn thinned_states(current_state: usize, max_stride: usize, end: usize) -> usize {
+    let mut thinned = 0;
+    let mut stride = 1;
+    // Distances from the current state in low..high share a stride
+    let mut low = 0;
+    loop {
+        let last = stride >= max_stride;
+        let high = if last {
+            usize::MAX
+        } else {
+            stride * MULTI_RATE_HISTORY_MARGIN
+        };
+        let band_stride = stride.min(max_stride.max(1));
+        let start = (current_state + 1).saturating_sub(high);
+        let stop = (current_state + 1).saturating_sub(low).min(end);
+        if start < stop {
+            let kept = stop.div_ceil(band_stride) - start.div_ceil(band_stride);
+            thinned += stop - start - kept;
+        }
+        if last || start == 0 {
+            return thinned;
+        }
+        low = high;
+        stride *= 2;
+    }
+}
+
+// Just outside the body, with some room so small bodies still get a readable dial
+fn spin_gauge_radius(view: &View, body: &Body) -> f64 {
+    body.radius * 1.3 + 0.01 * view.camera.view_height
+}
 
 pub struct ThreadState {
     pub generation_state: Mutex<GenerationState>,
@@ -19,6 +112,358 @@ pub struct GenerationState {
     pub new_states: Vec<Universe>,
     pub states_buffer_size: usize,
     pub step_size: f64,
+    pub cpu_budget: f64,
+    pub threads: usize,
+    pub step_cost: f64,
+    pub step_time: f64,
+    pub paused: bool,
+}
+
+pub fn format_time(seconds: f64) -> String {
+    let hours = (seconds / 3600.0).floor();
+    let minutes = ((seconds - hours * 3600.0) / 60.0).floor();
+    format!(
+        "{hours:02}:{minutes:02}:{:05.2}",
+        seconds - hours * 3600.0 - minutes * 60.0
+    )
+}
+
+// What the camera follows, the barycenter is recomputed every frame like a body's position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Body(BodyId),
+    Barycenter,
+}
+
+impl Focus {
+    pub fn position(self, universe: &Universe) -> Option<Vector2<f64>> {
+        match self {
+            Focus::Body(id) => universe.position(id),
+            Focus::Barycenter => universe.barycenter(),
+        }
+    }
+
+    pub fn position3(self, universe: &Universe) -> Option<Vector3<f64>> {
+        match self {
+            Focus::Body(id) => universe.position3(id),
+            Focus::Barycenter => universe.barycenter3(),
+        }
+    }
+}
+
+// The camera and focus a viewport is drawn with, the main view and the picture in picture each have
+// their own
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub camera: Camera,
+    pub focused: Option<Focus>,
+}
+
+impl View {
+    pub fn focused_body(&self) -> Option<BodyId> {
+        match self.focused? {
+            Focus::Body(id) => Some(id),
+            Focus::Barycenter => None,
+        }
+    }
+
+    // Where the focus is drawn, which only differs from where it is once a 3D view is turned
+    pub fn focus_position(&self, universe: &Universe) -> Option<Vector2<f64>> {
+        Some(self.camera.project(self.focused?.position3(universe)?))
+    }
+
+    // Trails are drawn relative to the focus, which sits where it is in the current state
+    pub fn path_offset(&self, universe: &Universe) -> Vector2<f64> {
+        if let Some(pos) = self.focus_position(universe) {
+            pos + self.camera.offset
+        } else {
+            self.camera.offset
+        }
+    }
+
+    pub fn trail_position(&self, universe: &Universe, id: BodyId) -> Option<Vector2<f64>> {
+        Some(self.camera.project(universe.position3(id)?) - self.path_offset(universe))
+    }
+
+    // Keeps the view where it is while switching which body the camera follows
+    pub fn move_focus(&mut self, universe: &Universe, focus: Option<Focus>) {
+        if self.focused.is_some() {
+            self.camera.pos -= self.camera.offset;
+            self.camera.offset = Vector2::zero();
+        }
+        self.focused = None;
+        if let Some(focus) = focus
+            && let Some(focus_pos) = focus.position3(universe)
+        {
+            let focus_pos = self.camera.project(focus_pos);
+            self.focused = Some(focus);
+            self.camera.pos -= focus_pos;
+            self.camera.offset = -focus_pos;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrailPoint {
+    pub body: BodyId,
+    pub state: usize,
+}
+
+// Two points on one trail, only kept while the Measure tool is in use
+#[derive(Debug, Default)]
+pub struct Stopwatch {
+    pub start: Option<TrailPoint>,
+    pub end: Option<TrailPoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExperimentKind {
+    MassRatio,
+    SemiMajorAxis,
+}
+
+// A slider bound to a relation between two bodies, moving it edits the current state so the
+// prediction regenerates like any other edit
+#[derive(Debug)]
+pub struct Experiment {
+    pub kind: ExperimentKind,
+    pub a: Option<BodyId>,
+    pub b: Option<BodyId>,
+    // The value when the slider was bound, the slider covers a range around it
+    pub base: Option<f64>,
+}
+
+impl Default for Experiment {
+    fn default() -> Self {
+        Self {
+            kind: ExperimentKind::MassRatio,
+            a: None,
+            b: None,
+            base: None,
+        }
+    }
+}
+
+// Second burn of an applied transfer, it has to wait for the future to be regenerated
+#[derive(Debug, Clone, Copy)]
+pub struct Arrival {
+    pub state: usize,
+    pub body: BodyId,
+    pub parent: BodyId,
+}
+
+#[derive(Debug)]
+pub struct Transfer {
+    pub target_altitude: f64,
+    pub preview: bool,
+    pub arrival: Option<Arrival>,
+}
+
+impl Default for Transfer {
+    fn default() -> Self {
+        Self {
+            target_altitude: 100.0,
+            preview: false,
+            arrival: None,
+        }
+    }
+}
+
+// A Hohmann transfer for the selected body around the focused one, from where it is now
+pub struct TransferPlan {
+    pub body: BodyId,
+    pub parent: BodyId,
+    pub mu: f64,
+    pub from: f64,
+    pub to: f64,
+    // From the parent to the body
+    pub radial: Vector2<f64>,
+    pub clockwise: bool,
+    pub hohmann: conics::Hohmann,
+}
+
+// A body that appears once generation reaches its time, it keeps its id so a regenerated future
+// can tell whether it has been added yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedSpawn {
+    pub time: f64,
+    pub id: BodyId,
+    pub body: Body,
+}
+
+// An instantaneous burn on a body's predicted path
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ManeuverNode {
+    // Saves from before nodes had ids get fresh ones
+    #[serde(default = "ManeuverId::next_id")]
+    pub id: ManeuverId,
+    pub time: f64,
+    pub body: BodyId,
+    // Directions follow the velocity relative to this body, the absolute velocity when None
+    pub reference: Option<BodyId>,
+    pub prograde: f64,
+    // A quarter turn counterclockwise from prograde
+    pub normal: f64,
+}
+
+impl ManeuverNode {
+    pub fn delta_v(&self) -> f64 {
+        self.prograde.hypot(self.normal)
+    }
+
+    fn apply(&self, universe: &mut Universe) {
+        let reference_vel = self
+            .reference
+            .and_then(|reference| universe.bodies.get(reference))
+            .map_or(Vector3::zero(), |reference| reference.vel);
+        let Some(body) = universe.bodies.get_mut(self.body) else {
+            return;
+        };
+        let relative_vel = body.vel - reference_vel;
+        if relative_vel.magnitude2() == 0.0 {
+            return;
+        }
+        // Nodes are only placed in the plane, where normal is a quarter turn from prograde
+        let prograde = relative_vel.normalize();
+        let delta_v =
+            prograde * self.prograde + Vector3::new(-prograde.y, prograde.x, 0.0) * self.normal;
+        body.vel += delta_v;
+        body.record_burn(self.time, BurnKind::Maneuver, delta_v);
+    }
+}
+
+// Adds queued bodies and maneuvers to the earliest generated future state that is missing them and
+// drops everything after it, returning that state so generation can continue from there. Each
+// state records the nodes applied to it, editing a node regenerates the future from before it.
+// Headless runs call it after every step so they match what the editor generates
+pub fn apply_scheduled(
+    states: &mut Vec<Universe>,
+    queue: &[QueuedSpawn],
+    maneuvers: &[ManeuverNode],
+    step_size: f64,
+    current_state: usize,
+) -> Option<usize> {
+    let usable = |index: usize, states: &[Universe]| {
+        index > current_state
+            && index < states.len()
+            && !states[index].is_compact()
+            && !states[index].is_thinned()
+    };
+    let spawn_due = |spawn: &QueuedSpawn, states: &[Universe]| {
+        let index = (spawn.time / step_size).round() as usize;
+        (usable(index, states) && states[index].bodies.get(spawn.id).is_none()).then_some(index)
+    };
+    let maneuver_due = |node: &ManeuverNode, states: &[Universe]| {
+        let index = (node.time / step_size).round() as usize;
+        (usable(index, states) && !states[index].maneuvers.contains(&node.id)).then_some(index)
+    };
+    let index = queue
+        .iter()
+        .filter_map(|spawn| spawn_due(spawn, states))
+        .chain(
+            maneuvers
+                .iter()
+                .filter_map(|node| maneuver_due(node, states)),
+        )
+        .min()?;
+    let spawns: Vec<&QueuedSpawn> = queue
+        .iter()
+        .filter(|spawn| spawn_due(spawn, states) == Some(index))
+        .collect();
+    let nodes: Vec<&ManeuverNode> = maneuvers
+        .iter()
+        .filter(|node| maneuver_due(node, states) == Some(index))
+        .collect();
+    states.truncate(index + 1);
+    let universe = &mut states[index];
+    for spawn in spawns {
+        universe.bodies.insert(spawn.id, spawn.body.clone());
+    }
+    for node in nodes {
+        node.apply(universe);
+        universe.maneuvers.push(node.id);
+    }
+    universe.changed = true;
+    Some(index)
+}
+
+// A note placed in the world, it stays where it was put rather than following any body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub pos: Vector2<f64>,
+    pub text: String,
+}
+
+// A deleted body as it was when it was deleted
+pub struct TrashedBody {
+    pub id: BodyId,
+    pub body: Body,
+    pub time: f64,
+}
+
+// Closest or furthest point from the focus along a body's predicted path
+pub struct Apsis {
+    pub body: BodyId,
+    pub state: usize,
+    // Where it is drawn, relative to the focus like trails
+    pub pos: Vector2<f64>,
+    pub distance: f64,
+    pub periapsis: bool,
+}
+
+// Smallest separation between two bodies over the generated future
+pub struct Approach {
+    pub state: usize,
+    pub distance: f64,
+    pub relative_speed: f64,
+    // Where both bodies are at that moment, relative to the focus like trails
+    pub pos_a: Vector2<f64>,
+    pub pos_b: Vector2<f64>,
+}
+
+// The first time two discs start to overlap in the generated future
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionWarning {
+    pub state: usize,
+    pub a: BodyId,
+    pub b: BodyId,
+}
+
+pub struct PendingEdit {
+    pub index: usize,
+    pub last_edit: std::time::Instant,
+    pub original: Universe,
+    pub original_changed: bool,
+    pub sent: bool,
+}
+
+// Timeline operations that throw away states, applying one returns its inverse
+pub enum TimelineChange {
+    // Put these states back in front of the timeline along with the bookmarks that pointed at them
+    RestorePast {
+        states: Vec<Universe>,
+        bookmarks: Vec<usize>,
+    },
+    DeletePast {
+        count: usize,
+    },
+    // Swap out everything from index onwards
+    ReplaceFuture {
+        index: usize,
+        states: Vec<Universe>,
+    },
+}
+
+impl TimelineChange {
+    pub fn memory_estimate(&self) -> usize {
+        match self {
+            TimelineChange::RestorePast { states, .. }
+            | TimelineChange::ReplaceFuture { states, .. } => {
+                states.iter().map(Universe::memory_estimate).sum()
+            }
+            TimelineChange::DeletePast { .. } => 0,
+        }
+    }
 }
 
 pub struct World {
@@ -34,19 +479,108 @@ pub struct World {
     pub step_size: f64,
     pub speed: f64,
     pub playing: bool,
-    pub focused: Option<BodyId>,
+    pub focused: Option<Focus>,
     pub selected: Option<BodyId>,
     pub current_state_modified: bool,
-    pub auto_orbit: bool,
+    pub auto_orbit: AutoOrbit,
     pub accumulated_time: f64,
     pub save_path: Option<String>,
     pub modified_since_save_to_file: bool,
+    pub quick_prediction: bool,
+    pub barycenter_trail: bool,
+    pub barycenter_marker: bool,
+    pub group_barycenter_marker: bool,
+    pub apsis_markers: bool,
+    pub soi_rings: bool,
+    pub hill_sphere: bool,
+    pub spin_gauges: bool,
+    pub units: Units,
+    pub regen_target: Option<usize>,
+    pub generation_paused: bool,
+    pub pending_edit: Option<PendingEdit>,
+    pub edit_snapshot: Option<(Universe, bool)>,
+    pub ghost_states: Vec<Universe>,
+    pub apply_edits: bool,
+    pub revert_edits: bool,
+    pub edit_state: Option<usize>,
+    pub real_time_played: f64,
+    pub bookmarks: Vec<usize>,
+    pub event_ramp: EventRamp,
+    pub constants: Vec<Constant>,
+    pub camera_path: Vec<CameraKeyframe>,
+    pub alarms: Vec<Alarm>,
+    pub overlays: Vec<Overlay>,
+    pub observer: Option<Observer>,
+    pub annotations: Vec<Annotation>,
+    pub spawn_queue: Vec<QueuedSpawn>,
+    pub maneuver_nodes: Vec<ManeuverNode>,
+    pub collision_warning: Option<CollisionWarning>,
+    pub recording: Option<Recording>,
+    pub finished_recording: Option<Recording>,
+    pub replay: Option<Replay>,
+    // Every future state before this has been checked
+    pub collision_scan: usize,
+    pub editing_maneuver: Option<usize>,
+    // The last state the alarms were checked in
+    pub alarm_state: usize,
+    pub toasts: Vec<Toast>,
+    pub follow_camera_path: bool,
+    pub pip_camera: Camera,
+    pub pip_focused: Option<Focus>,
+    // Earlier focuses, most recent last, and the ones gone back from
+    pub focus_history: Vec<Option<Focus>>,
+    pub focus_future: Vec<Option<Focus>>,
+    pub ephemeris_center: ephemeris::Center,
+    pub ephemeris_interval: f64,
+    pub cross_check: Option<CrossCheck>,
+    pub cross_check_interval: f64,
+    pub cross_check_tolerance: f64,
+    pub undo_stack: Vec<(&'static str, TimelineChange)>,
+    pub redo_stack: Vec<(&'static str, TimelineChange)>,
+    // Held by the states in both stacks, measured whenever they change
+    pub undo_memory: usize,
+    pub undoable_edit: Option<&'static str>,
+    pub trash: Vec<TrashedBody>,
+    pub tutorial: Option<Tutorial>,
+    pub stopwatch: Option<Stopwatch>,
+    pub tool: Tool,
+    pub editing_annotation: Option<usize>,
+    pub experiment: Experiment,
+    pub approach: [Option<BodyId>; 2],
+    pub comparison: [Option<BodyId>; 2],
+    pub transfer: Transfer,
+    pub heat_map: Option<HeatMap>,
+    pub conservation: Option<Conservation>,
+    pub graph: Graph,
+    pub chaos: Chaos,
+    // Whether the rebuilt history matched the hash in the save, None when there was nothing to check
+    pub history_verified: Option<bool>,
+    pub time_bar_rect: egui::Rect,
+    pub selected_window_rect: Option<egui::Rect>,
+    pub selected_window_detached: bool,
 }
 
 impl World {
-    pub fn new(step_size: f64) -> Self {
+    pub fn new(step_size: f64, units: Units) -> Self {
+        Self::with_universe(
+            Universe::new(units.gravity().unwrap_or(1.0)),
+            step_size,
+            units,
+        )
+    }
+
+    // What a 3D world does and does not do, for the buttons that create one
+    pub const THREE_D_HINT: &str = "Bodies are balls that can leave the plane, right drag turns the view. Kepler parents, Barnes-Hut, tides, landings, the plane's markers and the Measure, Maneuver and Observe tools are not available";
+
+    pub fn new_3d(step_size: f64, units: Units) -> Self {
+        let mut universe = Universe::new(units.gravity().unwrap_or(1.0));
+        universe.three_d = true;
+        Self::with_universe(universe, step_size, units)
+    }
+
+    fn with_universe(universe: Universe, step_size: f64, units: Units) -> Self {
         let current_state = 0;
-        let states = vec![Universe::new(1.0)];
+        let states = vec![universe];
 
         let gen_future = 20000usize;
         let thread_state = Arc::new(ThreadState {
@@ -55,6 +589,11 @@ impl World {
                 new_states: vec![],
                 states_buffer_size: gen_future.saturating_sub(states.len() - current_state),
                 step_size,
+                cpu_budget: 1.0,
+                threads: 0,
+                step_cost: 0.0,
+                step_time: 0.0,
+                paused: false,
             }),
             wakeup: Condvar::new(),
         });
@@ -77,637 +616,4898 @@ impl World {
             focused: None,
             selected: None,
             current_state_modified: false,
-            auto_orbit: false,
+            auto_orbit: AutoOrbit::default(),
             accumulated_time: 0.0,
             save_path: None,
             modified_since_save_to_file: true,
+            quick_prediction: false,
+            barycenter_trail: false,
+            barycenter_marker: false,
+            group_barycenter_marker: false,
+            apsis_markers: false,
+            soi_rings: false,
+            hill_sphere: false,
+            spin_gauges: false,
+            units,
+            regen_target: None,
+            generation_paused: false,
+            pending_edit: None,
+            edit_snapshot: None,
+            ghost_states: vec![],
+            apply_edits: false,
+            revert_edits: false,
+            edit_state: Some(current_state),
+            real_time_played: 0.0,
+            bookmarks: vec![],
+            event_ramp: EventRamp::default(),
+            constants: default_constants(),
+            camera_path: vec![],
+            alarms: vec![],
+            overlays: vec![],
+            observer: None,
+            annotations: vec![],
+            spawn_queue: vec![],
+            maneuver_nodes: vec![],
+            editing_maneuver: None,
+            collision_warning: None,
+            collision_scan: 0,
+            recording: None,
+            finished_recording: None,
+            replay: None,
+            alarm_state: 0,
+            toasts: vec![],
+            follow_camera_path: false,
+            pip_camera: Camera::new(Vector2::zero(), Vector2::zero(), 100.0),
+            pip_focused: None,
+            focus_history: vec![],
+            focus_future: vec![],
+            ephemeris_center: ephemeris::Center::Focus,
+            ephemeris_interval: 1.0,
+            cross_check: None,
+            cross_check_interval: 1.0,
+            cross_check_tolerance: 1e-10,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            undo_memory: 0,
+            undoable_edit: None,
+            trash: vec![],
+            tutorial: None,
+            stopwatch: None,
+            tool: Tool::Select,
+            editing_annotation: None,
+            experiment: Experiment::default(),
+            approach: [None, None],
+            comparison: [None, None],
+            transfer: Transfer::default(),
+            heat_map: None,
+            conservation: None,
+            graph: Graph::default(),
+            chaos: Chaos::default(),
+            history_verified: None,
+            time_bar_rect: egui::Rect::NOTHING,
+            selected_window_rect: None,
+            selected_window_detached: false,
         }
     }
 
-    pub fn state(&self) -> &Universe {
-        &self.states[self.current_state]
+    fn builtin_variables(&self) -> BTreeMap<String, f64> {
+        [
+            ("pi", std::f64::consts::PI),
+            ("e", std::f64::consts::E),
+            ("G", self.state().gravity),
+            ("k", self.state().coulomb),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
     }
 
-    pub fn from_save(save: Save) -> World {
-        let states: Vec<Universe> = save.states.into();
-
-        let gen_future = 20000usize;
-        let thread_state = Arc::new(ThreadState {
-            generation_state: Mutex::new(GenerationState {
-                initial_state: Some(states.last().unwrap().clone()),
-                new_states: vec![],
-                states_buffer_size: gen_future
-                    .saturating_sub(states.len() - save.data.current_state),
-                step_size: save.data.step_size,
-            }),
-            wakeup: Condvar::new(),
-        });
-
-        Self::spawn_update_thread(thread_state.clone());
-
-        Self {
-            name: save.data.name.clone(),
-            camera: save.data.camera,
-            states,
-            gen_future,
-            show_future: save.data.show_future,
-            show_past: save.data.show_past,
-            path_quality: save.data.path_quality,
-            current_state: save.data.current_state,
-            thread_state,
-            step_size: save.data.step_size,
-            speed: save.data.speed,
-            playing: false,
-            focused: None,
-            selected: None,
-            current_state_modified: false,
-            auto_orbit: false,
-            accumulated_time: 0.0,
-            save_path: save.data.save_path,
-            modified_since_save_to_file: false,
+    pub fn expression_variables(&self) -> BTreeMap<String, f64> {
+        let mut variables = self.builtin_variables();
+        for constant in &self.constants {
+            if let Some(value) = expression::evaluate(&constant.expression, &variables) {
+                variables.insert(constant.name.clone(), value);
+            }
+        }
+        let [selected, focused] = [self.selected, self.focused_body()]
+            .map(|id| id.and_then(|id| self.state().bodies.get(id)));
+        if let Some(selected) = selected {
+            variables.insert("m".to_string(), selected.mass(self.state().three_d));
+        }
+        if let Some(focused) = focused {
+            variables.insert("M".to_string(), focused.mass(self.state().three_d));
+            variables.insert("R".to_string(), focused.radius);
+        }
+        if let (Some(selected), Some(focused)) = (selected, focused) {
+            variables.insert("r".to_string(), (selected.pos - focused.pos).magnitude());
         }
+        variables
     }
 
-    pub fn to_save(&self) -> Save {
-        Save {
-            data: Data {
-                name: self.name.clone(),
-                camera: self.camera,
-                gen_future: self.gen_future,
-                show_future: self.show_future,
-                show_past: self.show_past,
-                path_quality: self.path_quality,
-                current_state: self.current_state,
-                step_size: self.step_size,
-                speed: self.speed,
-                save_path: self.save_path.clone(),
-            },
-            states: self.states.as_slice().into(),
+    fn trash_body(&mut self, id: BodyId) {
+        if let Some(body) = self.states[self.current_state].bodies.remove(id) {
+            self.trash.push(TrashedBody {
+                id,
+                body,
+                time: self.current_state as f64 * self.step_size,
+            });
         }
     }
 
-    fn spawn_update_thread(thread_state: Arc<ThreadState>) {
-        std::thread::spawn(move || {
-            let mut state: Option<Universe> = None;
-            let mut lock = thread_state.generation_state.lock().unwrap();
-            loop {
-                if let Some(initial_state) = lock.initial_state.take() {
-                    lock.new_states.clear();
-                    state = Some(initial_state);
-                }
-
-                if lock.new_states.len() >= lock.states_buffer_size {
-                    lock = thread_state.wakeup.wait(lock).unwrap();
-                    continue;
+    pub fn alarms_ui(&mut self, ui: &mut egui::Ui) {
+        let body_names: Vec<(BodyId, String)> = self
+            .state()
+            .bodies
+            .iter()
+            .map(|(id, body)| (id, body.meta.name.clone()))
+            .collect();
+        let name = |id: BodyId| {
+            body_names
+                .iter()
+                .find(|(other, _)| *other == id)
+                .map_or("Missing", |(_, name)| name.as_str())
+        };
+        let mut changed = false;
+        let mut remove = None;
+        egui::Grid::new("Alarms").num_columns(6).show(ui, |ui| {
+            for (i, alarm) in self.alarms.iter_mut().enumerate() {
+                for (side, id) in [("A", &mut alarm.a), ("B", &mut alarm.b)] {
+                    egui::ComboBox::from_id_salt(("Alarm", i, side))
+                        .selected_text(name(*id))
+                        .show_ui(ui, |ui| {
+                            for (other, name) in &body_names {
+                                changed |= ui.selectable_value(id, *other, name).changed();
+                            }
+                        });
                 }
-                let step_size = lock.step_size;
-
-                if let Some(old_state) = &state {
-                    drop(lock);
-
-                    let mut new_state = old_state.clone();
-                    new_state.step(step_size);
-
-                    lock = thread_state.generation_state.lock().unwrap();
-                    if lock.new_states.len() >= lock.states_buffer_size {
-                        lock = thread_state.wakeup.wait(lock).unwrap();
-                        continue;
+                let label = ui.label("Within:");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut alarm.distance)
+                            .range(0.0..=f64::INFINITY)
+                            .speed(0.1),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Distance between the centers")
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut alarm.pause, "Pause")
+                    .on_hover_text("Pause playback when this goes off")
+                    .changed();
+                match alarm.separation(&self.states[self.current_state]) {
+                    Some(separation) if separation <= alarm.distance => {
+                        ui.colored_label(egui::Color32::YELLOW, format!("{separation:.3}"))
                     }
-                    lock.new_states.push(new_state.clone());
-                    state = Some(new_state);
-                } else {
-                    lock = thread_state.wakeup.wait(lock).unwrap();
+                    Some(separation) => ui.label(format!("{separation:.3}")),
+                    None => ui.label("-"),
+                };
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
                 }
+                ui.end_row();
             }
         });
+        if let Some(remove) = remove {
+            self.alarms.remove(remove);
+            changed = true;
+        }
+        let ids: Vec<BodyId> = body_names.iter().map(|(id, _)| *id).collect();
+        let a = self.selected.or(ids.first().copied());
+        let b = self
+            .focused_body()
+            .filter(|&b| Some(b) != a)
+            .or_else(|| ids.iter().copied().find(|&b| Some(b) != a));
+        if let (Some(a), Some(b)) = (a, b) {
+            if ui
+                .button("Add")
+                .on_hover_text("Watch the selected and focused bodies")
+                .clicked()
+            {
+                self.alarms.push(Alarm {
+                    a,
+                    b,
+                    distance: 1.0,
+                    pause: false,
+                    inside: false,
+                });
+                changed = true;
+            }
+        } else {
+            ui.label("Alarms need two bodies");
+        }
+        self.modified_since_save_to_file |= changed;
     }
 
-    pub fn ui(&mut self, ctx: &egui::Context, dt: f64) {
-        self.current_state_modified = false;
-        egui::TopBottomPanel::bottom("Time").show(ctx, |ui| {
-            ui.vertical_centered(|ui| {
-                ui.heading("Time");
-            });
-            ui.add(egui::Separator::default().horizontal());
-            //ui.group(|ui| {
-            egui::Grid::new("Time")
-                .num_columns(2)
-                .spacing([30.0, 2.0])
-                .show(ui, |ui| {
-                    ui.group(|ui| {
-                        ui.label("Time:");
-                        let mut seconds = self.current_state as f64 * self.step_size;
-                        if ui
-                            .add(egui::DragValue::new(&mut seconds).suffix("s").speed(1.0))
-                            .changed()
-                        {
-                            self.current_state = (seconds / self.step_size) as usize;
-                        }
-                        ui.label(format!(
-                            " /  {:.2}s",
-                            self.states.len() as f64 * self.step_size
-                        ));
-                    });
-                    ui.group(|ui| {
-                        ui.spacing_mut().slider_width = ui.available_width() - 75.0;
-                        ui.add(
-                            egui::Slider::new(&mut self.current_state, 0..=self.states.len() - 1)
-                                .suffix("t"),
-                        );
-                    });
-                    ui.end_row();
-
-                    let mut changed = false;
-                    let mut seconds = self.gen_future as f64 * self.step_size;
-                    ui.group(|ui| {
-                        ui.label("Gen Future: ");
-                        let drag_value =
-                            ui.add(egui::DragValue::new(&mut seconds).suffix("s").speed(1.0));
-                        changed |= drag_value.changed()
-                    });
-                    ui.group(|ui| {
-                        let mut gen_to = self.current_state + (seconds / self.step_size) as usize;
-                        ui.spacing_mut().slider_width = ui.available_width() - 75.0;
-                        let slider = ui.add(
-                            egui::Slider::new(&mut gen_to, 0..=self.states.len() - 1).suffix("t"),
-                        );
-                        if slider.changed() {
-                            seconds =
-                                (gen_to.saturating_sub(self.current_state)) as f64 * self.step_size;
-                            changed |= true;
-                        }
-                    });
-                    if changed {
-                        self.modified_since_save_to_file = true;
-                        self.gen_future = (seconds / self.step_size) as usize;
-                    }
-                    ui.end_row();
+    fn experiment_value(&self) -> Option<f64> {
+        let universe = self.state();
+        let (a, b) = (
+            universe.bodies.get(self.experiment.a?)?,
+            universe.bodies.get(self.experiment.b?)?,
+        );
+        let (a_mass, b_mass) = (a.mass(universe.three_d), b.mass(universe.three_d));
+        match self.experiment.kind {
+            ExperimentKind::MassRatio => (b_mass > 0.0).then(|| a_mass / b_mass),
+            ExperimentKind::SemiMajorAxis => {
+                let mu = universe.gravity * (a_mass + b_mass);
+                let energy = (a.vel - b.vel).magnitude2() / 2.0 - mu / (a.pos - b.pos).magnitude();
+                // Only bound orbits have one
+                (energy < 0.0).then(|| -mu / (2.0 * energy))
+            }
+        }
+    }
 
-                    ui.group(|ui| {
-                        ui.label("Show Future: ");
-                        ui.add(egui::DragValue::new(&mut self.show_future).suffix("s"))
-                    });
-                    ui.group(|ui| {
-                        let mut show_to =
-                            (self.show_future / self.step_size) as usize + self.current_state;
-                        ui.spacing_mut().slider_width = ui.available_width() - 75.0;
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut show_to, 0..=self.states.len() - 1)
-                                    .suffix("t")
-                                    .step_by(1.0),
-                            )
-                            .changed()
-                        {
-                            self.show_future = (show_to.saturating_sub(self.current_state)) as f64
-                                * self.step_size;
-                            self.modified_since_save_to_file = true;
-                        }
-                    });
-                    self.show_future = self.show_future.max(0.0);
-                    ui.end_row();
+    fn set_experiment_value(&mut self, value: f64) {
+        let Some(current) = self.experiment_value() else {
+            return;
+        };
+        let (Some(a), Some(b)) = (self.experiment.a, self.experiment.b) else {
+            return;
+        };
+        let bodies = &mut self.states[self.current_state].bodies;
+        let Some(b) = bodies.get(b).cloned() else {
+            return;
+        };
+        let Some(a) = bodies.get_mut(a) else {
+            return;
+        };
+        match self.experiment.kind {
+            ExperimentKind::MassRatio => {
+                a.meta_mut().density *= value / current;
+            }
+            // Scaling the distance by k and the relative speed by 1/sqrt(k) keeps the shape and
+            // orientation of the orbit around b
+            ExperimentKind::SemiMajorAxis => {
+                let scale = value / current;
+                a.pos = b.pos + (a.pos - b.pos) * scale;
+                a.vel = b.vel + (a.vel - b.vel) / scale.sqrt();
+            }
+        }
+        self.current_state_modified = true;
+    }
 
-                    ui.group(|ui| {
-                        ui.label("Show Past: ");
-                        ui.add(egui::DragValue::new(&mut self.show_past).suffix("s"))
-                    });
-                    ui.group(|ui| {
-                        let mut show_back = self
-                            .current_state
-                            .saturating_sub((self.show_past / self.step_size) as usize);
-                        ui.spacing_mut().slider_width = ui.available_width() - 75.0;
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut show_back, 0..=self.states.len() - 1)
-                                    .suffix("t")
-                                    .step_by(1.0),
-                            )
-                            .changed()
-                        {
-                            self.show_past = self.current_state.saturating_sub(show_back) as f64
-                                * self.step_size;
-                            self.modified_since_save_to_file = true;
-                        }
-                    });
-                    self.show_past = self.show_past.max(0.0);
-                });
-            //});
-            ui.add(egui::Separator::default());
+    pub fn experiment_ui(&mut self, ui: &mut egui::Ui) {
+        let body_names: Vec<(BodyId, String)> = self
+            .state()
+            .bodies
+            .iter()
+            .map(|(id, body)| (id, body.meta.name.clone()))
+            .collect();
+        let name = |id: Option<BodyId>| {
+            body_names
+                .iter()
+                .find(|(other, _)| Some(*other) == id)
+                .map_or("None", |(_, name)| name.as_str())
+        };
+        let mut rebind = false;
+        ui.horizontal(|ui| {
+            rebind |= ui
+                .radio_value(
+                    &mut self.experiment.kind,
+                    ExperimentKind::MassRatio,
+                    "Mass Ratio",
+                )
+                .changed();
+            rebind |= ui
+                .radio_value(
+                    &mut self.experiment.kind,
+                    ExperimentKind::SemiMajorAxis,
+                    "Semi-Major Axis",
+                )
+                .changed();
+        });
+        let labels = match self.experiment.kind {
+            ExperimentKind::MassRatio => ["Body:", "Relative To:"],
+            ExperimentKind::SemiMajorAxis => ["Body:", "Orbiting:"],
+        };
+        for (side, label) in labels.into_iter().enumerate() {
             ui.horizontal(|ui| {
-                ui.group(|ui| {
-                    ui.label("Path Quality: ");
-                    if ui
-                        .add(egui::Slider::new(&mut self.path_quality, 1..=128))
-                        .changed()
-                    {
-                        self.modified_since_save_to_file = true;
-                    };
-                });
+                let label = ui.label(label);
+                let id = if side == 0 {
+                    &mut self.experiment.a
+                } else {
+                    &mut self.experiment.b
+                };
+                egui::ComboBox::from_id_salt(("Experiment", side))
+                    .selected_text(name(*id))
+                    .show_ui(ui, |ui| {
+                        for (other, name) in &body_names {
+                            rebind |= ui.selectable_value(id, Some(*other), name).changed();
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            });
+        }
+        if rebind {
+            self.experiment.base = None;
+        }
+        let Some(mut value) = self.experiment_value() else {
+            ui.label(match self.experiment.kind {
+                ExperimentKind::MassRatio => "Pick two bodies",
+                ExperimentKind::SemiMajorAxis => "Pick a body on a bound orbit around another",
             });
+            return;
+        };
+        if self.experiment.a == self.experiment.b {
+            ui.label("Pick two different bodies");
+            return;
+        }
+        let base = *self.experiment.base.get_or_insert(value);
+        let slider = ui
+            .add_enabled(
+                self.can_edit(),
+                egui::Slider::new(&mut value, base * 0.01..=base * 100.0)
+                    .logarithmic(true)
+                    .suffix(match self.experiment.kind {
+                        ExperimentKind::MassRatio => "x",
+                        ExperimentKind::SemiMajorAxis => "m",
+                    }),
+            )
+            .on_disabled_hover_text("Press Edit Here to experiment");
+        if slider.changed() {
+            self.set_experiment_value(value);
+        }
+        if ui
+            .button("Recenter")
+            .on_hover_text("Center the slider on the current value")
+            .clicked()
+        {
+            self.experiment.base = None;
+        }
+    }
+
+    pub fn approach_ui(&mut self, ui: &mut egui::Ui) {
+        let body_names: Vec<(BodyId, String)> = self
+            .state()
+            .bodies
+            .iter()
+            .map(|(id, body)| (id, body.meta.name.clone()))
+            .collect();
+        let name = |id: Option<BodyId>| {
+            body_names
+                .iter()
+                .find(|(other, _)| Some(*other) == id)
+                .map_or("None", |(_, name)| name.as_str())
+        };
+        for (side, id) in self.approach.iter_mut().enumerate() {
             ui.horizontal(|ui| {
-                ui.group(|ui| {
-                    ui.label("Speed: ");
-                    if ui
-                        .add(egui::DragValue::new(&mut self.speed).speed(0.1))
-                        .changed()
-                    {
-                        self.modified_since_save_to_file = true;
-                    }
-                    if ui
-                        .button(if self.playing { "Pause" } else { "Play" })
-                        .clicked()
-                    {
-                        self.playing = !self.playing;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 0.1, "0.1x").clicked() {
-                        self.speed = 0.1;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 0.5, "0.5x").clicked() {
-                        self.speed = 0.5;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 1.0, "1x").clicked() {
-                        self.speed = 1.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 5.0, "5x").clicked() {
-                        self.speed = 5.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 10.0, "10x").clicked() {
-                        self.speed = 10.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 20.0, "20x").clicked() {
-                        self.speed = 20.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 50.0, "50x").clicked() {
-                        self.speed = 50.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 75.0, "75x").clicked() {
-                        self.speed = 75.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 100.0, "100x").clicked() {
-                        self.speed = 100.0;
-                        self.modified_since_save_to_file = true;
-                    }
-                    ui.add(egui::Separator::default().vertical());
-                    if ui.selectable_label(self.speed == 200.0, "200x").clicked() {
-                        self.speed = 200.0;
-                        self.modified_since_save_to_file = true;
+                let label = ui.label(if side == 0 { "Body:" } else { "And:" });
+                egui::ComboBox::from_id_salt(("Approach", side))
+                    .selected_text(name(*id))
+                    .show_ui(ui, |ui| {
+                        for (other, name) in &body_names {
+                            ui.selectable_value(id, Some(*other), name);
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            });
+        }
+        if ui.button("Use Focused and Selected").clicked() {
+            self.approach = [self.focused_body(), self.selected];
+        }
+        let [Some(a), Some(b)] = self.approach else {
+            ui.label("Pick two bodies");
+            return;
+        };
+        if a == b {
+            ui.label("Pick two different bodies");
+            return;
+        }
+        let Some(approach) = self.closest_approach(&self.view()) else {
+            ui.label("Neither body is in the generated future");
+            return;
+        };
+        let time = (approach.state - self.current_state) as f64 * self.step_size;
+        ui.label(format!("In: {time:.2}s"));
+        ui.label(format!("Distance: {:.2}m", approach.distance));
+        ui.label(format!("Relative Speed: {:.2}m/s", approach.relative_speed));
+        // Only what has been generated is scanned, a closer pass may come later
+        if approach.state + 1 == self.states.len() {
+            ui.label("Still closing in at the end of the generated future");
+        }
+    }
+
+    pub fn comparison_ui(&mut self, ui: &mut egui::Ui) {
+        let body_names: Vec<(BodyId, String)> = self
+            .state()
+            .bodies
+            .iter()
+            .map(|(id, body)| (id, body.meta.name.clone()))
+            .collect();
+        let name = |id: Option<BodyId>| {
+            body_names
+                .iter()
+                .find(|(other, _)| Some(*other) == id)
+                .map_or("None", |(_, name)| name.as_str())
+        };
+        ui.horizontal(|ui| {
+            for (side, id) in self.comparison.iter_mut().enumerate() {
+                let label = ui.label(if side == 0 { "A:" } else { "B:" });
+                egui::ComboBox::from_id_salt(("Comparison", side))
+                    .selected_text(name(*id))
+                    .show_ui(ui, |ui| {
+                        for (other, name) in &body_names {
+                            ui.selectable_value(id, Some(*other), name);
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            }
+        });
+        if ui.button("Use Focused and Selected").clicked() {
+            self.comparison = [self.focused_body(), self.selected];
+        }
+        let [Some(a), Some(b)] = self.comparison else {
+            ui.label("Pick two bodies");
+            return;
+        };
+        let universe = self.state();
+        let (Some(body_a), Some(body_b)) = (universe.bodies.get(a), universe.bodies.get(b)) else {
+            ui.label("Both bodies have to exist now");
+            return;
+        };
+        egui::Grid::new("Comparison")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.strong(&body_a.meta.name);
+                ui.strong(&body_b.meta.name);
+                ui.end_row();
+                let mut row = |label: &str, value: &dyn Fn(&Body) -> String| {
+                    ui.label(label);
+                    ui.label(value(body_a));
+                    ui.label(value(body_b));
+                    ui.end_row();
+                };
+                let three_d = universe.three_d;
+                let vector = |v: Vector3<f64>| {
+                    if three_d {
+                        format!("{:.2}, {:.2}, {:.2}", v.x, v.y, v.z)
+                    } else {
+                        format!("{:.2}, {:.2}", v.x, v.y)
                     }
-                    ui.add(egui::Separator::default().vertical());
+                };
+                row("Mass", &|body| format!("{:.3}kg", body.mass(three_d)));
+                row("Radius", &|body| format!("{:.3}m", body.radius));
+                row("Density", &|body| {
+                    format!("{:.3}{}", body.meta.density, density_unit(three_d))
                 });
-                self.speed = self.speed.max(0.0)
+                row("Position", &|body| vector(body.pos));
+                row("Velocity", &|body| vector(body.vel));
+                row("Speed", &|body| format!("{:.3}m/s", body.vel.magnitude()));
+                row("Spin", &|body| format!("{:.3}rad/s", body.spin));
+                row("Charge", &|body| format!("{:.3}", body.charge));
             });
-            ui.horizontal(|ui| {
-                ui.group(|ui| {
-                    if ui.button("Delete Past").clicked() {
-                        self.states.drain(..self.current_state);
-                        self.current_state = 0;
-                        self.states.shrink_to_fit();
-                        self.modified_since_save_to_file = true;
-                    }
-                    if ui.button("Delete Future").clicked() {
-                        self.current_state_modified = true;
-                        self.modified_since_save_to_file = true;
-                    }
+        ui.separator();
+        egui::Grid::new("Comparison Derived")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Mass Ratio");
+                let (mass_a, mass_b) =
+                    (body_a.mass(universe.three_d), body_b.mass(universe.three_d));
+                ui.label(if mass_b > 0.0 {
+                    format!("{:.4}", mass_a / mass_b)
+                } else {
+                    "Infinite".into()
                 });
+                ui.end_row();
+                ui.label("Distance");
+                ui.label(format!("{:.3}m", (body_a.pos - body_b.pos).magnitude()));
+                ui.end_row();
+                ui.label("Relative Speed");
+                ui.label(format!("{:.3}m/s", (body_a.vel - body_b.vel).magnitude()));
+                ui.end_row();
+                ui.label("Mutual Hill Radius");
+                ui.label(match conics::mutual_hill_radius(universe, a, b) {
+                    Some((radius, parent)) => format!(
+                        "{radius:.3}m relative to {}",
+                        universe
+                            .bodies
+                            .get(parent)
+                            .map_or("Unnamed", |parent| parent.meta.name.as_str())
+                    ),
+                    None => "Needs a third body to orbit".into(),
+                });
+                ui.end_row();
             });
+    }
+
+    // The estimate assumes tangential thrust under the inverse square law
+    fn spiral(&self) -> Option<conics::Spiral> {
+        let universe = self.state();
+        let body = universe.bodies.get(self.selected?)?;
+        let thrust = body.thrust?;
+        let ThrustDirection::Tangential { around } = thrust.direction else {
+            return None;
+        };
+        let center = universe.bodies.get(around)?;
+        if universe.force_exponent != 2.0 {
+            return None;
+        }
+        conics::spiral(
+            universe.active_gravity()
+                * (body.mass(universe.three_d) + center.mass(universe.three_d)),
+            (body.pos - center.pos).magnitude(),
+            thrust.acceleration,
+            thrust.fuel,
+        )
+    }
+
+    pub fn transfer_plan(&self) -> Option<TransferPlan> {
+        let (body, parent) = (self.selected?, self.focused_body()?);
+        if body == parent {
+            return None;
+        }
+        let universe = self.state();
+        let (body_state, parent_state) = (universe.bodies.get(body)?, universe.bodies.get(parent)?);
+        let offset = (body_state.pos - parent_state.pos).truncate();
+        let from = offset.magnitude();
+        let to = self.transfer.target_altitude + parent_state.radius;
+        let mu = universe.gravity
+            * (body_state.mass(universe.three_d) + parent_state.mass(universe.three_d));
+        let hohmann = conics::hohmann(mu, from, to)?;
+        Some(TransferPlan {
+            body,
+            parent,
+            mu,
+            from,
+            to,
+            radial: offset / from,
+            clockwise: offset.perp_dot((body_state.vel - parent_state.vel).truncate()) < 0.0,
+            hohmann,
+        })
+    }
+
+    // Both burns set the speed the ideal transfer needs at the body's actual distance, so a slightly
+    // eccentric starting orbit still ends up close to the target
+    fn apply_first_burn(&mut self, plan: &TransferPlan) {
+        let bodies = &mut self.states[self.current_state].bodies;
+        let Some(parent) = bodies.get(plan.parent).cloned() else {
+            return;
+        };
+        let Some(body) = bodies.get_mut(plan.body) else {
+            return;
+        };
+        let tangent = Vector2::new(-plan.radial.y, plan.radial.x);
+        let tangent = if plan.clockwise { -tangent } else { tangent };
+        let speed = (plan.mu * (2.0 / plan.from - 1.0 / plan.hohmann.semi_major_axis)).sqrt();
+        let vel = parent.vel + (tangent * speed).extend(0.0);
+        body.record_burn(
+            self.current_state as f64 * self.step_size,
+            BurnKind::Transfer,
+            vel - body.vel,
+        );
+        body.vel = vel;
+        self.undoable_edit.get_or_insert("Transfer Burn");
+        self.current_state_modified = true;
+        self.transfer.arrival = Some(Arrival {
+            state: self.current_state + (plan.hohmann.duration / self.step_size).round() as usize,
+            body: plan.body,
+            parent: plan.parent,
         });
+    }
 
-        {
-            let mut open = self.selected.is_some();
-            let name = self.selected.and_then(|selected| {
-                Some(
-                    self.states[self.current_state]
-                        .bodies
-                        .get(selected)?
-                        .name
-                        .as_str(),
-                )
+    // Jumps to the arrival and circularizes there
+    fn apply_second_burn(&mut self, arrival: Arrival) {
+        self.playing = false;
+        self.current_state = arrival.state;
+        self.accumulated_time = 0.0;
+        self.expand_current();
+        self.edit_snapshot = Some((self.state().clone(), self.state().changed));
+        self.edit_state = Some(self.current_state);
+        let (gravity, three_d) = (self.state().gravity, self.state().three_d);
+        let [Some(body), Some(parent)] = self.states[self.current_state]
+            .bodies
+            .maybe_get_disjoint_mut([Some(arrival.body), Some(arrival.parent)])
+        else {
+            return;
+        };
+        let offset = (body.pos - parent.pos).truncate();
+        let mu = gravity * (body.mass(three_d) + parent.mass(three_d));
+        let vel = parent.vel
+            + conics::periapsis_velocity(
+                mu,
+                offset,
+                0.0,
+                offset.perp_dot((body.vel - parent.vel).truncate()) < 0.0,
+            )
+            .extend(0.0);
+        body.record_burn(
+            arrival.state as f64 * self.step_size,
+            BurnKind::Transfer,
+            vel - body.vel,
+        );
+        body.vel = vel;
+        self.undoable_edit.get_or_insert("Transfer Burn");
+        self.current_state_modified = true;
+        self.transfer.arrival = None;
+    }
+
+    pub fn transfer_ui(&mut self, ui: &mut egui::Ui) {
+        if self.unavailable_in_3d(ui) {
+            return;
+        }
+        ui.horizontal(|ui| {
+            let label = ui.label("Target Altitude:");
+            ui.add(
+                egui::DragValue::new(&mut self.transfer.target_altitude)
+                    .range(0.0..=f64::INFINITY)
+                    .speed(1.0)
+                    .suffix("m"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("Above the surface of the focused body");
+        });
+        ui.checkbox(&mut self.transfer.preview, "Preview");
+        if let Some(arrival) = self.transfer.arrival {
+            let ready = self.pending_edit.is_none() && arrival.state < self.states.len();
+            if arrival.state > self.current_state {
+                ui.label(format!(
+                    "Second burn in {}",
+                    format_time((arrival.state - self.current_state) as f64 * self.step_size)
+                ));
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(ready, egui::Button::new("Apply Second Burn"))
+                    .on_hover_text("Jump to the arrival and circularize there")
+                    .on_disabled_hover_text("Waiting for the transfer to be generated")
+                    .clicked()
+                {
+                    self.apply_second_burn(arrival);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.transfer.arrival = None;
+                }
             });
-            egui::Window::new(name.unwrap_or("Selected Body"))
-                .id("Selected Body".into())
-                .open(&mut open)
-                .show(ctx, |ui| {
-                    let [selected, focused] = self.states[self.current_state]
-                        .bodies
-                        .maybe_get_disjoint_mut([self.selected, self.focused]);
-                    let Some(body) = selected else {
-                        ui.label("The selected body does not exist in this time :p");
-                        return;
-                    };
-                    let mut delete = false;
-                    ui.add_enabled_ui(!self.playing, |ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("Name:");
-                            self.current_state_modified |=
-                                ui.text_edit_singleline(&mut body.name).changed();
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Position:");
-                            self.current_state_modified |= ui
-                                .add(
-                                    egui::DragValue::new(&mut body.pos.x)
-                                        .speed(1.0)
-                                        .prefix("x:"),
-                                )
-                                .changed();
-                            self.current_state_modified |= ui
-                                .add(
-                                    egui::DragValue::new(&mut body.pos.y)
-                                        .speed(1.0)
-                                        .prefix("y:"),
-                                )
-                                .changed();
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Velocity:");
-                            self.current_state_modified |= ui
-                                .add(
-                                    egui::DragValue::new(&mut body.vel.x)
-                                        .speed(0.1)
-                                        .prefix("x:"),
-                                )
-                                .changed();
-                            self.current_state_modified |= ui
-                                .add(
-                                    egui::DragValue::new(&mut body.vel.y)
-                                        .speed(0.1)
-                                        .prefix("y:"),
-                                )
-                                .changed();
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Radius:");
-                            self.current_state_modified |= ui
-                                .add(
-                                    egui::DragValue::new(&mut body.radius)
-                                        .speed(0.1)
-                                        .suffix("m"),
-                                )
+            return;
+        }
+        let Some(plan) = self.transfer_plan() else {
+            ui.label("Select a body and focus the body it orbits");
+            return;
+        };
+        let hohmann = &plan.hohmann;
+        egui::Grid::new("Transfer").num_columns(2).show(ui, |ui| {
+            ui.label("First Burn");
+            ui.label(format!("{:+.3}m/s", hohmann.first_burn));
+            ui.end_row();
+            ui.label("Second Burn");
+            ui.label(format!("{:+.3}m/s", hohmann.second_burn));
+            ui.end_row();
+            ui.label("Total");
+            ui.label(format!(
+                "{:.3}m/s",
+                hohmann.first_burn.abs() + hohmann.second_burn.abs()
+            ));
+            ui.end_row();
+            ui.label("Transfer Time");
+            ui.label(format_time(hohmann.duration));
+            ui.end_row();
+        });
+        if let (Some(body), Some(parent)) = (
+            self.state().bodies.get(plan.body),
+            self.state().bodies.get(plan.parent),
+        ) {
+            let circular = (plan.mu / plan.from).sqrt();
+            if ((body.vel - parent.vel).magnitude() / circular - 1.0).abs() > 0.05 {
+                ui.label("The current orbit is not circular, the transfer will be off");
+            }
+        }
+        if ui
+            .add_enabled(self.can_edit(), egui::Button::new("Apply First Burn"))
+            .on_disabled_hover_text("Press Edit Here to plan a transfer")
+            .clicked()
+        {
+            self.apply_first_burn(&plan);
+        }
+    }
+
+    pub fn overlays_ui(&mut self, ui: &mut egui::Ui) {
+        if self.unavailable_in_3d(ui) {
+            return;
+        }
+        let body_names: Vec<(BodyId, String)> = self
+            .state()
+            .bodies
+            .iter()
+            .map(|(id, body)| (id, body.meta.name.clone()))
+            .collect();
+        let name = |id: BodyId| {
+            body_names
+                .iter()
+                .find(|(other, _)| *other == id)
+                .map_or("Missing", |(_, name)| name.as_str())
+        };
+        let mut changed = false;
+        let mut remove = None;
+        egui::Grid::new("Overlays").num_columns(6).show(ui, |ui| {
+            for (i, overlay) in self.overlays.iter_mut().enumerate() {
+                changed |= ui.checkbox(&mut overlay.visible, "").changed();
+                egui::ComboBox::from_id_salt(("Overlay", i))
+                    .selected_text(overlay.kind.name())
+                    .show_ui(ui, |ui| {
+                        for kind in OverlayKind::ALL {
+                            changed |= ui
+                                .selectable_value(&mut overlay.kind, kind, kind.name())
                                 .changed();
+                        }
+                    });
+                for (side, id) in [("A", &mut overlay.a), ("B", &mut overlay.b)] {
+                    egui::ComboBox::from_id_salt(("Overlay", i, side))
+                        .selected_text(name(*id))
+                        .show_ui(ui, |ui| {
+                            for (other, name) in &body_names {
+                                changed |= ui.selectable_value(id, *other, name).changed();
+                            }
                         });
-                        ui.horizontal(|ui| {
-                            ui.label("Density:");
-                            self.current_state_modified |= ui
-                                .add(
-                                    egui::DragValue::new(&mut body.density)
-                                        .speed(0.1)
-                                        .suffix("m^2/kg"),
-                                )
+                }
+                if overlay.kind == OverlayKind::ZeroVelocity {
+                    egui::ComboBox::from_id_salt(("Overlay", i, "Test"))
+                        .selected_text(overlay.test.map_or("Selected", name))
+                        .show_ui(ui, |ui| {
+                            changed |= ui
+                                .selectable_value(&mut overlay.test, None, "Selected")
                                 .changed();
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Mass:");
-                            ui.add_enabled(
-                                false,
-                                egui::DragValue::new(&mut body.mass()).suffix("kg"),
-                            );
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Color:");
-                            let color: Vector3<f32> = body.color.cast().unwrap();
-                            let mut color: [f32; 3] = color.into();
-                            if ui.color_edit_button_rgb(&mut color).changed() {
-                                self.current_state_modified = true;
-                                let color: Vector3<f32> = color.into();
-                                body.color = color.cast().unwrap();
+                            for (other, name) in &body_names {
+                                changed |= ui
+                                    .selectable_value(&mut overlay.test, Some(*other), name)
+                                    .changed();
                             }
+                        })
+                        .response
+                        .on_hover_text("The light body whose Jacobi constant bounds the regions");
+                } else {
+                    ui.label("");
+                }
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(remove) = remove {
+            self.overlays.remove(remove);
+            changed = true;
+        }
+        let ids: Vec<BodyId> = body_names.iter().map(|(id, _)| *id).collect();
+        let a = self.focused_body().or(ids.first().copied());
+        let b = self
+            .selected
+            .filter(|&b| Some(b) != a)
+            .or_else(|| ids.iter().copied().find(|&b| Some(b) != a));
+        if let (Some(a), Some(b)) = (a, b) {
+            if ui
+                .button("Add")
+                .on_hover_text("Add an overlay for the focused and selected bodies")
+                .clicked()
+            {
+                self.overlays.push(Overlay {
+                    kind: OverlayKind::Lagrange,
+                    a,
+                    b,
+                    visible: true,
+                    test: None,
+                });
+                changed = true;
+            }
+        } else {
+            ui.label("Overlays need two bodies");
+        }
+        self.modified_since_save_to_file |= changed;
+
+        let visible: Vec<&Overlay> = self
+            .overlays
+            .iter()
+            .filter(|overlay| overlay.visible)
+            .collect();
+        if visible.is_empty() {
+            return;
+        }
+        ui.separator();
+        if self.selected.is_none() && visible.iter().any(|overlay| overlay.test.is_none()) {
+            ui.label("Select a light body to see its Jacobi constant");
+        }
+        for overlay in visible {
+            let Some(test) = overlay.test.or(self.selected) else {
+                continue;
+            };
+            if test == overlay.a || test == overlay.b {
+                continue;
+            }
+            let Some(readout) = overlays::jacobi(self.state(), overlay.a, overlay.b, test) else {
+                continue;
+            };
+            ui.label(format!(
+                "Jacobi constant of {} around {} and {}: {:.5}",
+                name(test),
+                name(overlay.a),
+                name(overlay.b),
+                readout.constant
+            ))
+            .on_hover_text(
+                "Conserved in the frame turning with the pair, as long as the pair orbits in a circle and the body is too light to disturb it",
+            );
+            ui.horizontal(|ui| {
+                for (label, value) in ["L1", "L2", "L3", "L4", "L5"].iter().zip(readout.lagrange) {
+                    let color = if readout.constant > value {
+                        egui::Color32::LIGHT_RED
+                    } else {
+                        egui::Color32::LIGHT_GREEN
+                    };
+                    ui.colored_label(color, format!("{label}: {value:.4}"))
+                        .on_hover_text(if readout.constant > value {
+                            "Closed, the body can not get past this point"
+                        } else {
+                            "Open, the body has enough energy to pass this point"
                         });
-                        if ui.button("Delete").clicked() {
-                            self.current_state_modified = true;
-                            delete = true;
+                }
+            });
+        }
+    }
+
+    pub fn observer_ui(&mut self, ui: &mut egui::Ui) {
+        if self.unavailable_in_3d(ui) {
+            return;
+        }
+        let body_names: Vec<(BodyId, String)> = self
+            .state()
+            .bodies
+            .iter()
+            .map(|(id, body)| (id, body.meta.name.clone()))
+            .collect();
+        let name = |id: Option<BodyId>| {
+            body_names
+                .iter()
+                .find(|(other, _)| Some(*other) == id)
+                .map_or("None", |(_, name)| name.as_str())
+        };
+        let Some(mut observer) = self.observer else {
+            ui.label("Use the Observe tool to place an observer");
+            if ui.button("Place at View Center").clicked() {
+                self.observer = Some(Observer {
+                    pos: self.camera.pos - self.camera.offset,
+                    on: None,
+                    signal_speed: DEFAULT_SIGNAL_SPEED,
+                });
+                self.modified_since_save_to_file = true;
+            }
+            return;
+        };
+        ui.horizontal(|ui| {
+            let label = ui.label("On:");
+            egui::ComboBox::from_id_salt("Observer On")
+                .selected_text(name(observer.on))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut observer.on, None, "None");
+                    for (id, name) in &body_names {
+                        ui.selectable_value(&mut observer.on, Some(*id), name);
+                    }
+                })
+                .response
+                .labelled_by(label.id)
+                .on_hover_text("Ride along with a body, or stay put when None");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Signal Speed:");
+            ui.add(
+                egui::DragValue::new(&mut observer.signal_speed)
+                    .range(1e-6..=f64::INFINITY)
+                    .speed(0.1)
+                    .suffix("m/s"),
+            )
+            .labelled_by(label.id)
+            .on_hover_text("How fast light, or whatever the observer sees with, travels");
+        });
+        if ui.button("Remove").clicked() {
+            self.observer = None;
+            self.modified_since_save_to_file = true;
+            return;
+        }
+        if Some(observer) != self.observer {
+            // Leaving a body keeps the observer where it was
+            if observer.on != self.observer.and_then(|old| old.on) {
+                observer.pos = self.observer.unwrap().position(self.state());
+            }
+            self.observer = Some(observer);
+            self.modified_since_save_to_file = true;
+        }
+
+        ui.label("Faded copies show where each body appears to the observer");
+        egui::Grid::new("Observer")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Body");
+                ui.label("Delay");
+                ui.label("Apparent Offset");
+                ui.end_row();
+                for (id, body) in self.state().bodies.iter() {
+                    if Some(id) == observer.on {
+                        continue;
+                    }
+                    ui.label(&body.meta.name);
+                    match observer.apparent(&self.states, self.current_state, self.step_size, id) {
+                        Some((pos, delay)) => {
+                            ui.label(format_time(delay));
+                            ui.label(format!("{:.3}m", (body.pos.truncate() - pos).magnitude()));
                         }
-                        ui.checkbox(&mut self.auto_orbit, "Auto Orbit");
-                        if self.focused.is_none() && self.auto_orbit && !self.playing {
-                            ui.label("Focus a body for auto orbit");
+                        None => {
+                            ui.label("Not seen yet")
+                                .on_hover_text("Its signal left before the start of the history");
+                            ui.label("");
                         }
-                        if let Some(focus) = focused
-                            && self.auto_orbit
-                            && !self.playing
-                        {
-                            let focused_to_body = body.pos - focus.pos;
-                            let mut current_height = focused_to_body.magnitude();
-                            ui.horizontal(|ui| {
-                                ui.label("Current Height:");
-                                if ui
-                                    .add(egui::DragValue::new(&mut current_height).speed(0.1))
-                                    .changed()
-                                {
-                                    let new_focused_to_body =
-                                        focused_to_body.normalize_to(current_height);
-                                    body.pos = new_focused_to_body + focus.pos;
-                                    self.current_state_modified = true;
-                                }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+        if tool == Tool::Measure {
+            self.stopwatch.get_or_insert_with(Stopwatch::default);
+        } else {
+            self.stopwatch = None;
+        }
+    }
+
+    pub fn spawn_queue_ui(&mut self, ui: &mut egui::Ui) {
+        let now = self.current_state as f64 * self.step_size;
+        ui.horizontal(|ui| {
+            if ui
+                .button("Queue New Body")
+                .on_hover_text("A body at the center of the view 10s from now")
+                .clicked()
+            {
+                let mut body = self.default_body(self.camera.pos - self.camera.offset);
+                body.meta_mut().name = "Arrival".into();
+                self.spawn_queue.push(QueuedSpawn {
+                    time: now + 10.0,
+                    id: BodyId::next_id(),
+                    body,
+                });
+                self.modified_since_save_to_file = true;
+            }
+            if let Some(selected) = self.selected.and_then(|id| self.state().bodies.get(id))
+                && ui
+                    .button("Queue Copy of Selected")
+                    .on_hover_text("The selected body as it is now, appearing 10s from now")
+                    .clicked()
+            {
+                let body = selected.clone();
+                self.spawn_queue.push(QueuedSpawn {
+                    time: now + 10.0,
+                    id: BodyId::next_id(),
+                    body,
+                });
+                self.modified_since_save_to_file = true;
+            }
+        });
+        if self.spawn_queue.is_empty() {
+            return;
+        }
+        let mut changed = false;
+        let mut remove = None;
+        egui::Grid::new("Spawn Queue")
+            .num_columns(6)
+            .show(ui, |ui| {
+                ui.strong("Name");
+                ui.strong("Time");
+                ui.strong("Position");
+                ui.strong("Velocity");
+                ui.strong("Status");
+                ui.end_row();
+                for (i, spawn) in self.spawn_queue.iter_mut().enumerate() {
+                    let index = (spawn.time / self.step_size).round() as usize;
+                    let spawned = self
+                        .states
+                        .get(index)
+                        .is_some_and(|universe| universe.bodies.get(spawn.id).is_some());
+                    // Once a body is in the timeline it is edited like any other
+                    ui.add_enabled_ui(!spawned, |ui| {
+                        let mut name = spawn.body.meta.name.clone();
+                        if ui.text_edit_singleline(&mut name).changed() {
+                            spawn.body.meta_mut().name = name;
+                            changed = true;
+                        }
+                    });
+                    ui.add_enabled_ui(!spawned, |ui| {
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut spawn.time)
+                                    .range(0.0..=f64::INFINITY)
+                                    .suffix("s"),
+                            )
+                            .changed();
+                    });
+                    for vector in [&mut spawn.body.pos, &mut spawn.body.vel] {
+                        ui.add_enabled_ui(!spawned, |ui| {
+                            ui.horizontal(|ui| {
+                                changed |= ui
+                                    .add(egui::DragValue::new(&mut vector.x).speed(0.1))
+                                    .changed();
+                                changed |= ui
+                                    .add(egui::DragValue::new(&mut vector.y).speed(0.1))
+                                    .changed();
                             });
-                            ui.label("Not Finished");
+                        });
+                    }
+                    ui.label(if spawned {
+                        "Spawned"
+                    } else if index <= self.current_state {
+                        "Missed"
+                    } else {
+                        "Waiting"
+                    });
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(remove) = remove {
+            self.spawn_queue.remove(remove);
+            changed = true;
+        }
+        self.modified_since_save_to_file |= changed;
+    }
+
+    // Tools that work out positions in the plane say so instead of showing anything for 3D worlds
+    pub fn unavailable_in_3d(&self, ui: &mut egui::Ui) -> bool {
+        let three_d = self.state().three_d;
+        if three_d {
+            ui.label("Only available for worlds without 3D");
+        }
+        three_d
+    }
+
+    pub fn stopwatch_ui(&mut self, ui: &mut egui::Ui) {
+        if self.unavailable_in_3d(ui) {
+            return;
+        }
+        let mut active = self.tool == Tool::Measure;
+        if ui
+            .checkbox(&mut active, "Measure")
+            .on_hover_text("Switch to the Measure tool, clicks pick points on trails")
+            .changed()
+        {
+            self.set_tool(if active { Tool::Measure } else { Tool::Select });
+        }
+        let Some(stopwatch) = &self.stopwatch else {
+            return;
+        };
+        let (Some(start), Some(end)) = (stopwatch.start, stopwatch.end) else {
+            ui.label(match stopwatch.start {
+                None => "Click a point on a trail",
+                Some(_) => "Click another point on the same trail",
+            });
+            return;
+        };
+        let Some((elapsed, arc_length)) = self.stopwatch_measurement() else {
+            ui.label("The measured states no longer exist");
+            return;
+        };
+        let name = self
+            .states
+            .iter()
+            .skip(start.state)
+            .find_map(|universe| universe.bodies.get(start.body))
+            .map_or("Unnamed".to_string(), |body| body.meta.name.clone());
+        egui::Grid::new("Stopwatch").num_columns(2).show(ui, |ui| {
+            ui.label("Body:");
+            ui.label(name);
+            ui.end_row();
+            ui.label("From:");
+            ui.label(format_time(start.state as f64 * self.step_size));
+            ui.end_row();
+            ui.label("To:");
+            ui.label(format_time(end.state as f64 * self.step_size));
+            ui.end_row();
+            ui.label("Elapsed:");
+            ui.label(format!("{elapsed:.3}s"));
+            ui.end_row();
+            ui.label("Arc Length:");
+            ui.label(format!("{arc_length:.3}m"));
+            ui.end_row();
+            ui.label("Average Speed:");
+            ui.label(if elapsed > 0.0 {
+                format!("{:.3}m/s", arc_length / elapsed)
+            } else {
+                "-".to_string()
+            });
+            ui.end_row();
+        });
+        if ui.button("Clear").clicked() {
+            self.stopwatch = Some(Stopwatch::default());
+        }
+    }
+
+    fn trail_position(&self, universe: &Universe, id: BodyId) -> Option<Vector2<f64>> {
+        self.view().trail_position(universe, id)
+    }
+
+    // Nearest visible trail point, trails are drawn relative to the focused body so this is too
+    fn pick_trail(&self, pos: Vector2<f64>) -> Option<TrailPoint> {
+        let first = self
+            .current_state
+            .saturating_sub((self.show_past / self.step_size) as usize);
+        let last = (self.current_state + (self.show_future / self.step_size) as usize)
+            .min(self.states.len() - 1);
+        let tolerance = 0.01 * self.camera.view_height;
+        let mut best: Option<(f64, TrailPoint)> = None;
+        let mut previous: Option<usize> = None;
+        for index in first..=last {
+            let universe = &self.states[index];
+            if universe.is_thinned() {
+                continue;
+            }
+            if let Some(previous) = previous {
+                for id in universe.body_ids() {
+                    let (Some(a), Some(b)) = (
+                        self.trail_position(&self.states[previous], id),
+                        self.trail_position(universe, id),
+                    ) else {
+                        continue;
+                    };
+                    let distance = picking::distance_to_segment(pos, a, b);
+                    if distance <= tolerance && best.is_none_or(|(best, _)| distance < best) {
+                        let state = if (pos - a).magnitude2() < (pos - b).magnitude2() {
+                            previous
+                        } else {
+                            index
+                        };
+                        best = Some((distance, TrailPoint { body: id, state }));
+                    }
+                }
+            }
+            previous = Some(index);
+        }
+        best.map(|(_, point)| point)
+    }
+
+    fn stopwatch_click(&mut self, pos: Vector2<f64>) {
+        let Some(point) = self.pick_trail(pos) else {
+            return;
+        };
+        let Some(stopwatch) = &mut self.stopwatch else {
+            return;
+        };
+        match stopwatch.start {
+            Some(start) if stopwatch.end.is_none() && start.body == point.body => {
+                let (start, end) = if point.state < start.state {
+                    (point, start)
+                } else {
+                    (start, point)
+                };
+                stopwatch.start = Some(start);
+                stopwatch.end = Some(end);
+            }
+            _ => {
+                stopwatch.start = Some(point);
+                stopwatch.end = None;
+            }
+        }
+    }
+
+    // Elapsed time and distance travelled along the trail between the two points
+    pub fn stopwatch_measurement(&self) -> Option<(f64, f64)> {
+        let stopwatch = self.stopwatch.as_ref()?;
+        let (start, end) = (stopwatch.start?, stopwatch.end?);
+        let mut arc_length = 0.0;
+        let mut previous: Option<Vector2<f64>> = None;
+        for universe in self.states.get(start.state..=end.state)? {
+            if universe.is_thinned() {
+                continue;
+            }
+            let pos = self.trail_position(universe, start.body);
+            if let (Some(pos), Some(previous)) = (pos, previous) {
+                arc_length += (pos - previous).magnitude();
+            }
+            previous = pos;
+        }
+        Some((
+            (end.state - start.state) as f64 * self.step_size,
+            arc_length,
+        ))
+    }
+
+    pub fn heat_map_ui(&mut self, ui: &mut egui::Ui) {
+        if self.unavailable_in_3d(ui) {
+            return;
+        }
+        let mut enabled = self.heat_map.is_some();
+        if ui
+            .checkbox(&mut enabled, "Show Heat Map")
+            .on_hover_text("Shade the places where bodies come close to each other over the run")
+            .changed()
+        {
+            self.heat_map = enabled.then(|| HeatMap::new(self.camera.view_height / 64.0));
+        }
+        let states = self.states.len();
+        let Some(heat_map) = &mut self.heat_map else {
+            return;
+        };
+        let mut changed = false;
+        let cell_speed = heat_map.cell_size * 0.01;
+        ui.horizontal(|ui| {
+            let label = ui.label("Cell Size:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut heat_map.cell_size)
+                        .range(1e-6..=f64::INFINITY)
+                        .speed(cell_speed)
+                        .suffix("m"),
+                )
+                .labelled_by(label.id)
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Close Approach:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut heat_map.approach_factor)
+                        .range(1.0..=100.0)
+                        .speed(0.05)
+                        .suffix("x radii"),
+                )
+                .labelled_by(label.id)
+                .changed();
+        })
+        .response
+        .on_hover_text(
+            "Count a pair when they are closer than this many times their radii added up",
+        );
+        ui.label(format!("Scanned: {} / {states} states", heat_map.scanned));
+        if ui.button("Reset").clicked() || changed {
+            heat_map.reset();
+        }
+    }
+
+    pub fn conservation_ui(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.conservation.is_some();
+        if ui
+            .checkbox(&mut enabled, "Track Conservation")
+            .on_hover_text(
+                "Sum the energy and momentum of every state to see how the integrator drifts",
+            )
+            .changed()
+        {
+            self.conservation = enabled.then(Conservation::default);
+        }
+        if let Some(conservation) = &mut self.conservation {
+            conservation.ui(
+                ui,
+                self.step_size,
+                self.current_state,
+                self.states[self.current_state].integrator.name(),
+            );
+        }
+    }
+
+    pub fn graph_ui(&mut self, ui: &mut egui::Ui) {
+        let focused = self.focused_body();
+        self.graph.ui(
+            ui,
+            &self.states,
+            self.step_size,
+            self.current_state,
+            self.selected,
+            focused,
+        );
+    }
+
+    pub fn chaos_ui(&mut self, ui: &mut egui::Ui) {
+        self.chaos.ui(
+            ui,
+            &self.states[self.current_state],
+            self.current_state,
+            self.step_size,
+            self.selected,
+        );
+    }
+
+    pub fn trash_ui(&mut self, ui: &mut egui::Ui) {
+        if self.trash.is_empty() {
+            ui.label("Deleted bodies show up here");
+            return;
+        }
+        let can_edit = self.can_edit();
+        let mut restore = None;
+        let mut remove = None;
+        egui::Grid::new("Trash").num_columns(3).show(ui, |ui| {
+            for (i, trashed) in self.trash.iter().enumerate().rev() {
+                ui.label(trashed.body.meta.name.as_str());
+                ui.label(format!("Deleted at {}", format_time(trashed.time)));
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(can_edit, egui::Button::new("Restore"))
+                        .on_hover_text(
+                            "Put the body back at the current time, as it was when deleted",
+                        )
+                        .on_disabled_hover_text("Press Edit Here to restore bodies")
+                        .clicked()
+                    {
+                        restore = Some(i);
+                    }
+                    if ui.button("Forget").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if ui.button("Empty Trash").clicked() {
+            self.trash.clear();
+        }
+        if let Some(i) = remove {
+            self.trash.remove(i);
+        }
+        if let Some(i) = restore {
+            let TrashedBody { id, body, .. } = self.trash.remove(i);
+            let bodies = &mut self.states[self.current_state].bodies;
+            // The id may have been taken by a body restored earlier
+            let id = if bodies.get(id).is_none() {
+                bodies.insert(id, body);
+                id
+            } else {
+                bodies.push(body)
+            };
+            self.selected = Some(id);
+            self.current_state_modified = true;
+            self.modified_since_save_to_file = true;
+        }
+    }
+
+    pub fn statistics_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(warning) = self.collision_warning {
+            let name = |id: BodyId| {
+                self.states[warning.state]
+                    .bodies
+                    .get(id)
+                    .map_or("Unnamed", |body| body.meta.name.as_str())
+            };
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!(
+                        "Impact between {} and {} in {:.1}s",
+                        name(warning.a),
+                        name(warning.b),
+                        (warning.state - self.current_state) as f64 * self.step_size
+                    ),
+                );
+                if ui.button("Jump").clicked() {
+                    self.playing = false;
+                    self.accumulated_time = 0.0;
+                    self.current_state = warning.state.min(self.states.len() - 1);
+                }
+            });
+        }
+        let universe = self.state();
+        egui::Grid::new("Statistics").num_columns(2).show(ui, |ui| {
+            ui.label("Bodies:");
+            ui.label(universe.bodies.len().to_string());
+            ui.end_row();
+            ui.label("Total Mass:");
+            ui.label(format!("{:.3e}kg", universe.total_mass()));
+            ui.end_row();
+            ui.label("Kinetic Energy:");
+            ui.label(format!("{:.3e}J", universe.kinetic_energy()));
+            ui.end_row();
+            ui.label("Potential Energy:");
+            ui.label(format!("{:.3e}J", universe.potential_energy()));
+            ui.end_row();
+            ui.label("Total Energy:");
+            ui.label(format!("{:.3e}J", universe.total_energy()));
+            ui.end_row();
+            ui.label("Angular Momentum:");
+            ui.label(format!("{:.3e}kg m^2/s", universe.angular_momentum()));
+            ui.end_row();
+            if let Some((min, max)) = universe.pairwise_distance_range() {
+                ui.label("Closest Pair:");
+                ui.label(format!("{min:.3}m"));
+                ui.end_row();
+                ui.label("Farthest Pair:");
+                ui.label(format!("{max:.3}m"));
+                ui.end_row();
+            }
+            if let Some(dominant) = universe
+                .dominant_body()
+                .and_then(|id| universe.bodies.get(id))
+            {
+                ui.label("Dominant Attractor:");
+                ui.label(&dominant.meta.name);
+                ui.end_row();
+            }
+        });
+    }
+
+    pub fn ephemeris_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let Some(target) = self
+            .selected
+            .and_then(|selected| self.state().bodies.get(selected))
+        else {
+            ui.label("Select a body to export its ephemeris");
+            return false;
+        };
+        ui.label(format!("Target: {}", target.meta.name));
+        ui.horizontal(|ui| {
+            ui.label("Center:");
+            ui.radio_value(
+                &mut self.ephemeris_center,
+                ephemeris::Center::Focus,
+                "Focused Body",
+            );
+            ui.radio_value(
+                &mut self.ephemeris_center,
+                ephemeris::Center::Barycenter,
+                "Barycenter",
+            );
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Interval:");
+            ui.add(
+                egui::DragValue::new(&mut self.ephemeris_interval)
+                    .range(self.step_size..=f64::INFINITY)
+                    .speed(0.1)
+                    .suffix("s"),
+            )
+            .labelled_by(label.id);
+        });
+        ui.button("Export")
+            .on_hover_text("Write the past and future of the selected body as a table")
+            .clicked()
+    }
+
+    pub fn cross_check_ui(&mut self, ui: &mut egui::Ui) {
+        if self.unavailable_in_3d(ui) {
+            return;
+        }
+        ui.horizontal(|ui| {
+            let label = ui.label("Sample Every:");
+            ui.add(
+                egui::DragValue::new(&mut self.cross_check_interval)
+                    .range(self.step_size..=f64::INFINITY)
+                    .speed(0.1)
+                    .suffix("s"),
+            )
+            .labelled_by(label.id);
+            let label = ui.label("Tolerance:");
+            ui.add(
+                egui::DragValue::new(&mut self.cross_check_tolerance)
+                    .range(1e-15..=1e-3)
+                    .speed(1e-10)
+                    .custom_formatter(|value, _| format!("{value:.0e}")),
+            )
+            .labelled_by(label.id);
+        });
+        // The reference run only integrates forces that follow from the positions
+        let thrusting = self
+            .state()
+            .bodies
+            .iter()
+            .any(|(_, body)| body.thrust.is_some());
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!thrusting, egui::Button::new("Start Here"))
+                .on_hover_text("Compare the future from the current state against a reference run")
+                .on_disabled_hover_text("Engines are not part of the reference run")
+                .clicked()
+            {
+                let interval =
+                    ((self.cross_check_interval / self.step_size).round() as usize).max(1);
+                self.cross_check = Some(CrossCheck::spawn(
+                    self.state(),
+                    self.current_state,
+                    self.step_size,
+                    interval,
+                    self.cross_check_tolerance,
+                ));
+            }
+            if ui
+                .add_enabled(self.cross_check.is_some(), egui::Button::new("Stop"))
+                .clicked()
+            {
+                self.cross_check = None;
+            }
+        });
+        let Some(check) = &self.cross_check else {
+            return;
+        };
+
+        // Edits after the start change the main run, so comparing past them is meaningless
+        let end = self.states[check.start + 1..]
+            .iter()
+            .position(|universe| universe.changed)
+            .map_or(self.states.len(), |offset| check.start + 1 + offset);
+        let shared = check.shared.lock().unwrap();
+        let errors: Vec<(f64, f64)> = shared
+            .samples
+            .iter()
+            .filter(|sample| sample.index < end)
+            .filter_map(|sample| {
+                let universe = &self.states[sample.index];
+                let error = sample
+                    .positions
+                    .iter()
+                    .filter_map(|&(id, pos)| Some((universe.position(id)? - pos).magnitude()))
+                    .reduce(f64::max)?;
+                Some(((sample.index - check.start) as f64 * self.step_size, error))
+            })
+            .collect();
+        if let Some(failed) = shared.failed {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!(
+                    "The reference run stopped before {}, its step size collapsed",
+                    format_time((failed - check.start) as f64 * self.step_size)
+                ),
+            )
+            .on_hover_text("Usually a very close encounter, try a looser tolerance");
+        }
+        drop(shared);
+
+        let Some(&(last_time, last_error)) = errors.last() else {
+            ui.label("Waiting for the reference run");
+            return;
+        };
+        let max_error = errors.iter().map(|&(_, error)| error).fold(0.0, f64::max);
+        ui.label(format!(
+            "Position Error: {last_error:.3e}m after {} (max {max_error:.3e}m)",
+            format_time(last_time)
+        ));
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(320.0, 120.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+        let log_errors: Vec<(f64, f64)> = errors
+            .iter()
+            .map(|&(time, error)| (time, error.max(1e-300).log10()))
+            .collect();
+        let min_log = log_errors
+            .iter()
+            .map(|&(_, e)| e)
+            .fold(f64::INFINITY, f64::min);
+        let max_log = log_errors
+            .iter()
+            .map(|&(_, e)| e)
+            .fold(f64::NEG_INFINITY, f64::max)
+            .max(min_log + 1.0);
+        let points: Vec<egui::Pos2> = log_errors
+            .iter()
+            .map(|&(time, log_error)| {
+                egui::pos2(
+                    rect.left() + (time / last_time.max(self.step_size)) as f32 * rect.width(),
+                    rect.bottom()
+                        - ((log_error - min_log) / (max_log - min_log)) as f32 * rect.height(),
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.5, egui::Color32::LIGHT_RED),
+        ));
+        painter.text(
+            rect.left_top(),
+            egui::Align2::LEFT_TOP,
+            format!("1e{max_log:.0}m"),
+            egui::FontId::monospace(10.0),
+            egui::Color32::GRAY,
+        );
+        painter.text(
+            rect.left_bottom(),
+            egui::Align2::LEFT_BOTTOM,
+            format!("1e{min_log:.0}m"),
+            egui::FontId::monospace(10.0),
+            egui::Color32::GRAY,
+        );
+    }
+
+    pub fn universe_ui(&mut self, ui: &mut egui::Ui) {
+        // These settings only make sense for bodies that stay in the plane
+        let plane = !self.state().three_d;
+        ui.add_enabled_ui(self.can_edit(), |ui| {
+            let mut integrator = self.state().integrator;
+            ui.horizontal(|ui| {
+                ui.label("Integrator:");
+                for option in Integrator::ALL {
+                    ui.radio_value(&mut integrator, option, option.name());
+                }
+            })
+            .response
+            .on_hover_text("Regenerates the future from the current state with the new method");
+            if integrator != self.state().integrator {
+                self.undoable_edit.get_or_insert("Change Integrator");
+                self.states[self.current_state].integrator = integrator;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut gravity = self.state().gravity;
+            let mut units = self.units;
+            // Drags by a percent of the value so tiny SI values can be adjusted too
+            let gravity_speed = if gravity > 0.0 { gravity * 0.01 } else { 0.01 };
+            ui.horizontal(|ui| {
+                let label = ui.label("G:");
+                ui.add(
+                    egui::DragValue::new(&mut gravity)
+                        .range(0.0..=f64::INFINITY)
+                        .speed(gravity_speed)
+                        .custom_formatter(|value, _| {
+                            if value != 0.0 && value.abs() < 1e-3 {
+                                format!("{value:.4e}")
+                            } else {
+                                format!("{value}")
+                            }
+                        }),
+                )
+                .labelled_by(label.id)
+                .on_hover_text("Gravitational constant, regenerates the future when changed");
+                egui::ComboBox::from_id_salt("Units")
+                    .selected_text(units.name())
+                    .show_ui(ui, |ui| {
+                        for option in Units::ALL {
+                            ui.selectable_value(&mut units, option, option.name());
                         }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("Sandbox uses G = 1, SI uses the real value for meters, kilograms and seconds");
+            });
+            if units != self.units {
+                if let Some(preset) = units.gravity() {
+                    gravity = preset;
+                }
+            } else if gravity != self.state().gravity {
+                units = Units::ALL
+                    .into_iter()
+                    .find(|units| units.gravity() == Some(gravity))
+                    .unwrap_or(Units::Custom);
+            }
+            if units != self.units {
+                self.units = units;
+                self.modified_since_save_to_file = true;
+            }
+            if gravity != self.state().gravity {
+                self.states[self.current_state].gravity = gravity;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut gravity_off = self.state().gravity_off;
+            if ui
+                .checkbox(&mut gravity_off, "Gravity Off")
+                .on_hover_text(
+                    "Bodies keep their velocity and move in straight lines, handy for setting up formations",
+                )
+                .changed()
+            {
+                self.undoable_edit.get_or_insert(if gravity_off {
+                    "Turn Gravity Off"
+                } else {
+                    "Turn Gravity On"
+                });
+                self.states[self.current_state].gravity_off = gravity_off;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut force_exponent = self.state().force_exponent;
+            ui.horizontal(|ui| {
+                let label = ui.label("Force Law: 1/r^");
+                ui.add(
+                    egui::DragValue::new(&mut force_exponent)
+                        .range(0.5..=5.0)
+                        .speed(0.01),
+                )
+                .labelled_by(label.id)
+                .on_hover_text("Gravity falls off with distance to this power, only 2 gives closed ellipses. Hierarchical Kepler Drift and orbit previews assume 2");
+                if ui
+                    .add_enabled(force_exponent != 2.0, egui::Button::new("Inverse Square"))
+                    .clicked()
+                {
+                    force_exponent = 2.0;
+                }
+            });
+            if force_exponent != self.state().force_exponent {
+                self.undoable_edit.get_or_insert("Change Force Law");
+                self.states[self.current_state].force_exponent = force_exponent;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut kepler_drift = self.state().kepler_drift;
+            if ui
+                .add_enabled(
+                    plane,
+                    egui::Checkbox::new(&mut kepler_drift, "Hierarchical Kepler Drift"),
+                )
+                .on_hover_text(
+                    "Move bodies with a parent on Kepler orbits around it and only integrate the rest of gravity, greatly reducing drift of moons",
+                )
+                .changed()
+            {
+                self.states[self.current_state].kepler_drift = kepler_drift;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut adaptive_substeps = self.state().adaptive_substeps;
+            if ui
+                .checkbox(&mut adaptive_substeps, "Adaptive Substeps")
+                .on_hover_text(
+                    "Split steps into smaller ones during close encounters, the timeline still keeps one state per step",
+                )
+                .changed()
+            {
+                self.states[self.current_state].adaptive_substeps = adaptive_substeps;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut collision_mode = self.state().collision_mode;
+            ui.horizontal(|ui| {
+                let label = ui.label("Collisions:");
+                ui.radio_value(&mut collision_mode, CollisionMode::PassThrough, "Pass Through");
+                ui.radio_value(&mut collision_mode, CollisionMode::Merge, "Merge");
+                if ui
+                    .radio(
+                        matches!(collision_mode, CollisionMode::Bounce { .. }),
+                        "Bounce",
+                    )
+                    .clicked()
+                    && !matches!(collision_mode, CollisionMode::Bounce { .. })
+                {
+                    collision_mode = CollisionMode::Bounce {
+                        restitution: 1.0,
+                        friction: 0.0,
+                    };
+                }
+                if let CollisionMode::Bounce {
+                    restitution,
+                    friction,
+                } = &mut collision_mode
+                {
+                    ui.add(
+                        egui::DragValue::new(restitution)
+                            .range(0.0..=1.0)
+                            .speed(0.01),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Restitution, 1 keeps all energy and 0 stops the bodies");
+                    ui.add(
+                        egui::DragValue::new(friction)
+                            .range(0.0..=2.0)
+                            .speed(0.01)
+                            .prefix("friction "),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("How much grazing hits spin the bodies up, 0 keeps spin unchanged");
+                }
+            });
+            if collision_mode != self.state().collision_mode {
+                self.states[self.current_state].collision_mode = collision_mode;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut fragmentation = self.state().fragmentation;
+            ui.add_enabled_ui(collision_mode != CollisionMode::PassThrough, |ui| {
+                let mut enabled = fragmentation.is_some();
+                if ui
+                    .checkbox(&mut enabled, "Fragmentation")
+                    .on_hover_text("Hard enough impacts shatter both bodies into debris")
+                    .changed()
+                {
+                    fragmentation = enabled.then(Fragmentation::default);
+                }
+                if let Some(fragmentation) = &mut fragmentation {
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Impact Energy:");
+                        ui.add(
+                            egui::DragValue::new(&mut fragmentation.energy)
+                                .range(0.0..=f64::INFINITY)
+                                .speed(0.1),
+                        )
+                        .labelled_by(label.id)
+                        .on_hover_text("Collisions with less energy than this merge or bounce as usual");
                     });
-                    if delete {
-                        self.states[self.current_state]
-                            .bodies
-                            .remove(self.selected.unwrap());
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Pieces:");
+                        ui.add(egui::DragValue::new(&mut fragmentation.pieces).range(2..=64))
+                            .labelled_by(label.id);
+                        let label = ui.label("Dispersion:");
+                        ui.add(
+                            egui::DragValue::new(&mut fragmentation.dispersion)
+                                .range(0.0..=1.0)
+                                .speed(0.01),
+                        )
+                        .labelled_by(label.id)
+                        .on_hover_text("Share of the impact energy the debris flies apart with");
+                    });
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Smallest Piece:");
+                        ui.add(
+                            egui::DragValue::new(&mut fragmentation.min_radius)
+                                .range(0.0..=f64::INFINITY)
+                                .speed(0.01)
+                                .prefix("radius "),
+                        )
+                        .labelled_by(label.id)
+                        .on_hover_text("Impacts that would make smaller debris than this merge or bounce instead");
+                    });
+                }
+            });
+            if fragmentation != self.state().fragmentation {
+                self.states[self.current_state].fragmentation = fragmentation;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut landing_speed = self.state().landing_speed;
+            ui.horizontal(|ui| {
+                let mut landing = landing_speed > 0.0;
+                if ui
+                    .add_enabled(plane, egui::Checkbox::new(&mut landing, "Landing"))
+                    .on_hover_text(
+                        "Bodies touching a heavier one slowly come to rest on its surface and ride along with it",
+                    )
+                    .changed()
+                {
+                    landing_speed = if landing { 1.0 } else { 0.0 };
+                }
+                if landing {
+                    let label = ui.label("Below:");
+                    ui.add(
+                        egui::DragValue::new(&mut landing_speed)
+                            .range(1e-6..=f64::INFINITY)
+                            .speed(0.01)
+                            .suffix("m/s"),
+                    )
+                    .labelled_by(label.id)
+                    .on_hover_text("Faster arrivals collide as usual");
+                }
+            });
+            if landing_speed != self.state().landing_speed {
+                self.states[self.current_state].landing_speed = landing_speed;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut coulomb = self.state().coulomb;
+            ui.horizontal(|ui| {
+                let label = ui.label("Coulomb Constant:");
+                ui.add(
+                    egui::DragValue::new(&mut coulomb)
+                        .range(0.0..=f64::INFINITY)
+                        .speed(0.01),
+                )
+                .labelled_by(label.id)
+                .on_hover_text("Strength of the push and pull between charged bodies, k in expressions");
+            });
+            if coulomb != self.state().coulomb {
+                self.states[self.current_state].coulomb = coulomb;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut uniform_field = self.state().uniform_field;
+            ui.horizontal(|ui| {
+                let label = ui.label("Uniform Field:");
+                ui.add(
+                    egui::DragValue::new(&mut uniform_field.x)
+                        .speed(0.01)
+                        .prefix("x:"),
+                )
+                .labelled_by(label.id);
+                ui.add(
+                    egui::DragValue::new(&mut uniform_field.y)
+                        .speed(0.01)
+                        .prefix("y:"),
+                )
+                .labelled_by(label.id)
+                .on_hover_text("Acceleration given to every body, like wind or the pull near a planet's surface");
+                if ui
+                    .add_enabled(uniform_field != Vector2::zero(), egui::Button::new("Off"))
+                    .clicked()
+                {
+                    uniform_field = Vector2::zero();
+                }
+            });
+            if uniform_field != self.state().uniform_field {
+                self.states[self.current_state].uniform_field = uniform_field;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut background = self.state().background;
+            ui.horizontal(|ui| {
+                let label = ui.label("Background:");
+                egui::ComboBox::from_id_salt("Background")
+                    .selected_text(background.map_or("None", Background::name))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut background, None, "None");
+                        for option in Background::ALL {
+                            // Picking the kind already in use keeps its settings
+                            let current = background.map(Background::name) == Some(option.name());
+                            if ui.selectable_label(current, option.name()).clicked() && !current {
+                                background = Some(option);
+                            }
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id)
+                    .on_hover_text("A potential centered on the origin that every body moves in, like a dark matter halo");
+            });
+            match &mut background {
+                Some(Background::Harmonic { strength }) => {
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Trap Strength:");
+                        ui.add(
+                            egui::DragValue::new(strength)
+                                .range(0.0..=f64::INFINITY)
+                                .speed(0.001),
+                        )
+                        .labelled_by(label.id)
+                        .on_hover_text("Pull toward the origin per meter away from it");
+                    });
+                }
+                Some(Background::Halo { mass, scale }) => {
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Halo Mass:");
+                        let speed = (*mass * 0.01).max(0.01);
+                        ui.add(
+                            egui::DragValue::new(mass)
+                                .range(0.0..=f64::INFINITY)
+                                .speed(speed),
+                        )
+                        .labelled_by(label.id);
+                        let label = ui.label("Scale Radius:");
+                        let speed = (*scale * 0.01).max(0.01);
+                        ui.add(
+                            egui::DragValue::new(scale)
+                                .range(1e-6..=f64::INFINITY)
+                                .speed(speed)
+                                .suffix("m"),
+                        )
+                        .labelled_by(label.id)
+                        .on_hover_text("Where the density profile steepens, rotation curves flatten out past it");
+                    });
+                }
+                None => {}
+            }
+            if background != self.state().background {
+                self.states[self.current_state].background = background;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut tidal_lag = self.state().tidal_lag;
+            ui.horizontal(|ui| {
+                let label = ui.label("Tidal Lag:");
+                ui.add_enabled(
+                    plane,
+                    egui::DragValue::new(&mut tidal_lag)
+                        .range(0.0..=f64::INFINITY)
+                        .speed(0.01)
+                        .suffix("s"),
+                )
+                .labelled_by(label.id)
+                .on_hover_text(
+                    "How far tidal bulges lag behind, larger values spin bodies toward tidal lock faster, 0 turns tides off",
+                );
+            });
+            if tidal_lag != self.state().tidal_lag {
+                self.states[self.current_state].tidal_lag = tidal_lag;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+            let mut theta = self.state().barnes_hut_theta;
+            ui.horizontal(|ui| {
+                let label = ui.label("Barnes-Hut Theta:");
+                ui.add_enabled(
+                    plane,
+                    egui::DragValue::new(&mut theta).range(0.0..=2.0).speed(0.01),
+                )
+                .labelled_by(label.id)
+                .on_hover_text(
+                        "With many bodies, distant groups pull as one when they look smaller than this, 0 always sums every pair",
+                    );
+            });
+            if theta != self.state().barnes_hut_theta {
+                self.states[self.current_state].barnes_hut_theta = theta;
+                self.current_state_modified = true;
+                self.modified_since_save_to_file = true;
+            }
+        });
+    }
+
+    pub fn constants_ui(&mut self, ui: &mut egui::Ui) {
+        let mut variables = self.builtin_variables();
+        let mut remove = None;
+        egui::Grid::new("Constants").num_columns(4).show(ui, |ui| {
+            for (name, value) in &variables {
+                ui.label(name);
+                ui.label("built in");
+                ui.label(format!("{value:e}"));
+                ui.end_row();
+            }
+            for (i, constant) in self.constants.iter_mut().enumerate() {
+                let mut changed = ui
+                    .add(egui::TextEdit::singleline(&mut constant.name).desired_width(80.0))
+                    .changed();
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut constant.expression).desired_width(160.0))
+                    .changed();
+                match expression::evaluate(&constant.expression, &variables) {
+                    Some(value) => {
+                        ui.label(format!("{value:e}"));
+                        variables.insert(constant.name.clone(), value);
+                    }
+                    None => {
+                        ui.colored_label(egui::Color32::RED, "Invalid");
                     }
+                }
+                if ui.button("-").clicked() {
+                    remove = Some(i);
+                }
+                self.modified_since_save_to_file |= changed;
+                ui.end_row();
+            }
+        });
+        if let Some(remove) = remove {
+            self.constants.remove(remove);
+            self.modified_since_save_to_file = true;
+        }
+        if ui.button("Add").clicked() {
+            self.constants.push(Constant {
+                name: format!("x{}", self.constants.len()),
+                expression: "0".to_string(),
+            });
+            self.modified_since_save_to_file = true;
+        }
+    }
+
+    pub fn can_edit(&self) -> bool {
+        !self.playing && self.edit_state == Some(self.current_state)
+    }
+
+    pub fn state(&self) -> &Universe {
+        &self.states[self.current_state]
+    }
+
+    pub fn from_save(save: Save) -> World {
+        let states: Vec<Universe> = save.states.into();
+        let history_verified = save.data.history_hash.map(|hash| {
+            hash == history_hash(&states[..=save.data.current_state.min(states.len() - 1)])
+        });
+
+        let gen_future = 20000usize;
+        let thread_state = Arc::new(ThreadState {
+            generation_state: Mutex::new(GenerationState {
+                initial_state: Some(states.last().unwrap().clone()),
+                new_states: vec![],
+                states_buffer_size: gen_future
+                    .saturating_sub(states.len() - save.data.current_state),
+                step_size: save.data.step_size,
+                cpu_budget: 1.0,
+                threads: 0,
+                step_cost: 0.0,
+                step_time: 0.0,
+                paused: false,
+            }),
+            wakeup: Condvar::new(),
+        });
+
+        Self::spawn_update_thread(thread_state.clone());
+
+        Self {
+            name: save.data.name.clone(),
+            camera: save.data.camera,
+            states,
+            gen_future,
+            show_future: save.data.show_future,
+            show_past: save.data.show_past,
+            path_quality: save.data.path_quality,
+            current_state: save.data.current_state,
+            thread_state,
+            step_size: save.data.step_size,
+            speed: save.data.speed,
+            playing: false,
+            focused: None,
+            selected: None,
+            current_state_modified: false,
+            auto_orbit: AutoOrbit::default(),
+            accumulated_time: 0.0,
+            save_path: save.data.save_path,
+            modified_since_save_to_file: false,
+            quick_prediction: save.data.quick_prediction,
+            barycenter_trail: save.data.barycenter_trail,
+            barycenter_marker: save.data.barycenter_marker,
+            group_barycenter_marker: save.data.group_barycenter_marker,
+            apsis_markers: save.data.apsis_markers,
+            soi_rings: save.data.soi_rings,
+            hill_sphere: save.data.hill_sphere,
+            spin_gauges: save.data.spin_gauges,
+            units: save.data.units,
+            regen_target: None,
+            generation_paused: false,
+            pending_edit: None,
+            edit_snapshot: None,
+            ghost_states: vec![],
+            apply_edits: false,
+            revert_edits: false,
+            edit_state: None,
+            real_time_played: 0.0,
+            bookmarks: save.data.bookmarks,
+            event_ramp: save.data.event_ramp,
+            constants: save.data.constants,
+            camera_path: save.data.camera_path,
+            alarms: save.data.alarms,
+            overlays: save.data.overlays,
+            observer: save.data.observer,
+            annotations: save.data.annotations,
+            spawn_queue: save.data.spawn_queue,
+            maneuver_nodes: save.data.maneuver_nodes,
+            editing_maneuver: None,
+            collision_warning: None,
+            collision_scan: 0,
+            recording: None,
+            finished_recording: None,
+            replay: None,
+            alarm_state: save.data.current_state,
+            toasts: vec![],
+            follow_camera_path: save.data.follow_camera_path,
+            pip_camera: Camera::new(Vector2::zero(), Vector2::zero(), 100.0),
+            pip_focused: None,
+            focus_history: vec![],
+            focus_future: vec![],
+            ephemeris_center: ephemeris::Center::Focus,
+            ephemeris_interval: 1.0,
+            cross_check: None,
+            cross_check_interval: 1.0,
+            cross_check_tolerance: 1e-10,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            undo_memory: 0,
+            undoable_edit: None,
+            trash: vec![],
+            tutorial: None,
+            stopwatch: None,
+            tool: Tool::Select,
+            editing_annotation: None,
+            experiment: Experiment::default(),
+            approach: [None, None],
+            comparison: [None, None],
+            transfer: Transfer::default(),
+            heat_map: None,
+            conservation: None,
+            graph: Graph::default(),
+            chaos: Chaos::default(),
+            history_verified,
+            time_bar_rect: egui::Rect::NOTHING,
+            selected_window_rect: None,
+            selected_window_detached: false,
+        }
+    }
+
+    pub fn to_save(&self) -> Save<'_> {
+        Save {
+            data: Data {
+                name: self.name.clone(),
+                camera: self.camera,
+                gen_future: self.gen_future,
+                show_future: self.show_future,
+                show_past: self.show_past,
+                path_quality: self.path_quality,
+                current_state: self.current_state,
+                step_size: self.step_size,
+                speed: self.speed,
+                save_path: self.save_path.clone(),
+                quick_prediction: self.quick_prediction,
+                barycenter_trail: self.barycenter_trail,
+                barycenter_marker: self.barycenter_marker,
+                group_barycenter_marker: self.group_barycenter_marker,
+                apsis_markers: self.apsis_markers,
+                soi_rings: self.soi_rings,
+                hill_sphere: self.hill_sphere,
+                spin_gauges: self.spin_gauges,
+                units: self.units,
+                bookmarks: self.bookmarks.clone(),
+                event_ramp: self.event_ramp,
+                constants: self.constants.clone(),
+                camera_path: self.camera_path.clone(),
+                alarms: self.alarms.clone(),
+                overlays: self.overlays.clone(),
+                observer: self.observer,
+                annotations: self.annotations.clone(),
+                spawn_queue: self.spawn_queue.clone(),
+                maneuver_nodes: self.maneuver_nodes.clone(),
+                follow_camera_path: self.follow_camera_path,
+                integrator: self.state().integrator,
+                history_hash: Some(history_hash(&self.states[..=self.current_state])),
+                version: SAVE_VERSION,
+            },
+            states: self.states.as_slice().into(),
+        }
+    }
+
+    fn spawn_update_thread(thread_state: Arc<ThreadState>) {
+        std::thread::spawn(move || {
+            let mut state: Option<Universe> = None;
+            let mut pool: Option<(usize, rayon::ThreadPool)> = None;
+            let mut lock = thread_state.generation_state.lock().unwrap();
+            loop {
+                if let Some(initial_state) = lock.initial_state.take() {
+                    lock.new_states.clear();
+                    state = Some(initial_state);
+                }
+
+                if lock.paused || lock.new_states.len() >= lock.states_buffer_size {
+                    lock = thread_state.wakeup.wait(lock).unwrap();
+                    continue;
+                }
+                let step_size = lock.step_size;
+                let cpu_budget = lock.cpu_budget;
+                let threads = lock.threads;
+
+                if let Some(old_state) = &state {
+                    drop(lock);
+
+                    let start = std::time::Instant::now();
+                    if pool.as_ref().is_none_or(|(count, _)| *count != threads) {
+                        pool = rayon::ThreadPoolBuilder::new()
+                            .num_threads(threads)
+                            .build()
+                            .inspect_err(|error| {
+                                tracing::warn!("Stepping on the global pool instead: {error}")
+                            })
+                            .ok()
+                            .map(|pool| (threads, pool));
+                    }
+                    let mut new_state = old_state.clone();
+                    match &pool {
+                        Some((_, pool)) => pool.install(|| new_state.step(step_size)),
+                        None => new_state.step(step_size),
+                    }
+                    let cost = start.elapsed();
+                    if cpu_budget < 1.0 {
+                        std::thread::sleep(cost.mul_f64(1.0 / cpu_budget - 1.0));
+                    }
+
+                    let time = start.elapsed();
+
+                    lock = thread_state.generation_state.lock().unwrap();
+                    lock.step_cost = if lock.step_cost == 0.0 {
+                        cost.as_secs_f64()
+                    } else {
+                        lock.step_cost * 0.99 + cost.as_secs_f64() * 0.01
+                    };
+                    lock.step_time = if lock.step_time == 0.0 {
+                        time.as_secs_f64()
+                    } else {
+                        lock.step_time * 0.99 + time.as_secs_f64() * 0.01
+                    };
+                    if lock.new_states.len() >= lock.states_buffer_size {
+                        lock = thread_state.wakeup.wait(lock).unwrap();
+                        continue;
+                    }
+                    lock.new_states.push(new_state.clone());
+                    state = Some(new_state);
+                } else {
+                    lock = thread_state.wakeup.wait(lock).unwrap();
+                }
+            }
+        });
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context, dt: f64, settings: &Settings) {
+        self.current_state_modified = false;
+        self.expand_current();
+        if self.pending_edit.is_none() {
+            self.edit_snapshot = Some((self.state().clone(), self.state().changed));
+        }
+        self.time_bar_rect = egui::TopBottomPanel::bottom("Time")
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Time");
+                });
+                ui.add(egui::Separator::default().horizontal());
+                //ui.group(|ui| {
+                egui::Grid::new("Time")
+                    .num_columns(2)
+                    .spacing([30.0, 2.0])
+                    .show(ui, |ui| {
+                        let time_label = ui.group(|ui| {
+                            let label = ui.label("Time:");
+                            let mut seconds = self.current_state as f64 * self.step_size;
+                            if ui
+                                .add(egui::DragValue::new(&mut seconds).suffix("s").speed(1.0))
+                                .labelled_by(label.id)
+                                .changed()
+                            {
+                                self.current_state = (seconds / self.step_size) as usize;
+                            }
+                            ui.label(format!(
+                                " /  {:.2}s",
+                                self.states.len() as f64 * self.step_size
+                            ));
+                            label.id
+                        });
+                        ui.group(|ui| {
+                            ui.spacing_mut().slider_width = ui.available_width() - 75.0;
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut self.current_state,
+                                    0..=self.states.len() - 1,
+                                )
+                                .suffix("t"),
+                            )
+                            .labelled_by(time_label.inner);
+                        });
+                        ui.end_row();
+
+                        let mut changed = false;
+                        let mut seconds = self.gen_future as f64 * self.step_size;
+                        let gen_label = ui.group(|ui| {
+                            let label = ui.label("Gen Future: ");
+                            let drag_value = ui
+                                .add_enabled(
+                                    !settings.auto_gen_future,
+                                    egui::DragValue::new(&mut seconds)
+                                        .range(0.0..=MAX_GEN_FUTURE as f64 * self.step_size)
+                                        .suffix("s")
+                                        .speed(1.0),
+                                )
+                                .labelled_by(label.id)
+                                .on_disabled_hover_text("Auto Gen Future in Settings sets this");
+                            changed |= drag_value.changed();
+                            let estimate =
+                                self.memory_estimate((seconds / self.step_size) as usize, settings);
+                            let text = format!("~{:.1}mb", estimate as f64 / 1_000_000.0);
+                            if estimate as f64 > settings.memory_budget * 1_000_000.0 {
+                                ui.colored_label(egui::Color32::RED, text)
+                                    .on_hover_text("This is over the memory budget in Settings");
+                            } else {
+                                ui.label(text).on_hover_text(
+                                    "Estimated memory used by the past, the future and undo history",
+                                );
+                            }
+                            label.id
+                        });
+                        ui.group(|ui| {
+                            let mut gen_to =
+                                self.current_state + (seconds / self.step_size) as usize;
+                            ui.spacing_mut().slider_width = ui.available_width() - 75.0;
+                            let slider = ui
+                                .add_enabled(
+                                    !settings.auto_gen_future,
+                                    egui::Slider::new(&mut gen_to, 0..=self.states.len() - 1)
+                                        .suffix("t"),
+                                )
+                                .labelled_by(gen_label.inner);
+                            if slider.changed() {
+                                seconds = (gen_to.saturating_sub(self.current_state)) as f64
+                                    * self.step_size;
+                                changed |= true;
+                            }
+                        });
+                        if changed {
+                            self.modified_since_save_to_file = true;
+                            self.gen_future = (seconds / self.step_size) as usize;
+                        }
+                        ui.end_row();
+
+                        let show_future_label = ui.group(|ui| {
+                            let label = ui.label("Show Future: ");
+                            ui.add(egui::DragValue::new(&mut self.show_future).suffix("s"))
+                                .labelled_by(label.id);
+                            label.id
+                        });
+                        ui.group(|ui| {
+                            let mut show_to =
+                                (self.show_future / self.step_size) as usize + self.current_state;
+                            ui.spacing_mut().slider_width = ui.available_width() - 75.0;
+                            if ui
+                                .add(
+                                    egui::Slider::new(&mut show_to, 0..=self.states.len() - 1)
+                                        .suffix("t")
+                                        .step_by(1.0),
+                                )
+                                .labelled_by(show_future_label.inner)
+                                .changed()
+                            {
+                                self.show_future = (show_to.saturating_sub(self.current_state))
+                                    as f64
+                                    * self.step_size;
+                                self.modified_since_save_to_file = true;
+                            }
+                        });
+                        self.show_future = self.show_future.max(0.0);
+                        ui.end_row();
+
+                        let show_past_label = ui.group(|ui| {
+                            let label = ui.label("Show Past: ");
+                            ui.add(egui::DragValue::new(&mut self.show_past).suffix("s"))
+                                .labelled_by(label.id);
+                            label.id
+                        });
+                        ui.group(|ui| {
+                            let mut show_back = self
+                                .current_state
+                                .saturating_sub((self.show_past / self.step_size) as usize);
+                            ui.spacing_mut().slider_width = ui.available_width() - 75.0;
+                            if ui
+                                .add(
+                                    egui::Slider::new(&mut show_back, 0..=self.states.len() - 1)
+                                        .suffix("t")
+                                        .step_by(1.0),
+                                )
+                                .labelled_by(show_past_label.inner)
+                                .changed()
+                            {
+                                self.show_past = self.current_state.saturating_sub(show_back)
+                                    as f64
+                                    * self.step_size;
+                                self.modified_since_save_to_file = true;
+                            }
+                        });
+                        self.show_past = self.show_past.max(0.0);
+                    });
+                //});
+                ui.add(egui::Separator::default());
+                ui.horizontal(|ui| {
+                    ui.group(|ui| {
+                        let label = ui.label("Path Quality: ");
+                        if ui
+                            .add(egui::Slider::new(&mut self.path_quality, 1..=128))
+                            .labelled_by(label.id)
+                            .changed()
+                        {
+                            self.modified_since_save_to_file = true;
+                        };
+                    });
+                    ui.group(|ui| {
+                        if ui
+                        .add_enabled(
+                            !self.states[self.current_state].three_d,
+                            egui::Checkbox::new(&mut self.quick_prediction, "Quick Prediction"),
+                        )
+                        .on_hover_text(
+                            "Draw the future path with patched conics instead of the simulation",
+                        )
+                        .changed()
+                    {
+                        self.modified_since_save_to_file = true;
+                    }
+                    });
+                    ui.group(|ui| {
+                        if ui
+                            .checkbox(&mut self.barycenter_trail, "Barycenter Trail")
+                            .on_hover_text(
+                                "Draw the path of the center of mass, any drift means momentum is not conserved",
+                            )
+                            .changed()
+                        {
+                            self.modified_since_save_to_file = true;
+                        }
+                        if ui
+                            .checkbox(&mut self.apsis_markers, "Apsis Markers")
+                            .on_hover_text(
+                                "Mark where predicted paths come closest to and furthest from the focus",
+                            )
+                            .changed()
+                        {
+                            self.modified_since_save_to_file = true;
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.group(|ui| {
+                        let label = ui.label("Speed: ");
+                        if ui
+                            .add(egui::DragValue::new(&mut self.speed).speed(0.1))
+                            .labelled_by(label.id)
+                            .changed()
+                        {
+                            self.modified_since_save_to_file = true;
+                        }
+                        if ui
+                            .button(if self.playing { "Pause" } else { "Play" })
+                            .clicked()
+                        {
+                            self.playing = !self.playing;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 0.1, "0.1x").clicked() {
+                            self.speed = 0.1;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 0.5, "0.5x").clicked() {
+                            self.speed = 0.5;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 1.0, "1x").clicked() {
+                            self.speed = 1.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 5.0, "5x").clicked() {
+                            self.speed = 5.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 10.0, "10x").clicked() {
+                            self.speed = 10.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 20.0, "20x").clicked() {
+                            self.speed = 20.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 50.0, "50x").clicked() {
+                            self.speed = 50.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 75.0, "75x").clicked() {
+                            self.speed = 75.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 100.0, "100x").clicked() {
+                            self.speed = 100.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                        if ui.selectable_label(self.speed == 200.0, "200x").clicked() {
+                            self.speed = 200.0;
+                            self.modified_since_save_to_file = true;
+                        }
+                        ui.add(egui::Separator::default().vertical());
+                    });
+                    self.speed = self.speed.max(0.0)
+                });
+                ui.horizontal(|ui| {
+                    ui.group(|ui| {
+                        if ui.button("Delete Past").clicked() && self.current_state > 0 {
+                            let change = self.apply_timeline_change(TimelineChange::DeletePast {
+                                count: self.current_state,
+                            });
+                            self.record_timeline_change("Delete Past", change, settings);
+                        }
+                        if ui
+                            .add_enabled(
+                                !self.playing,
+                                egui::SelectableLabel::new(self.can_edit(), "Edit Here"),
+                            )
+                            .on_hover_text(
+                                "Allow editing this state, the future after it will be regenerated",
+                            )
+                            .clicked()
+                        {
+                            self.edit_state = match self.can_edit() {
+                                true => None,
+                                false => Some(self.current_state),
+                            };
+                        }
+                        if ui.button("Delete Future").clicked() {
+                            self.undoable_edit
+                                .get_or_insert("Delete Future");
+                            self.current_state_modified = true;
+                            self.modified_since_save_to_file = true;
+                        }
+                        if ui
+                            .button(if self.generation_paused {
+                                "Resume Generation"
+                            } else {
+                                "Pause Generation"
+                            })
+                            .clicked()
+                        {
+                            self.generation_paused = !self.generation_paused;
+                        }
+                        ui.add_enabled_ui(self.pending_edit.is_none(), |ui| {
+                            let undo = self.undo_stack.last().map(|(name, _)| *name);
+                            if ui
+                                .add_enabled(undo.is_some(), egui::Button::new("Undo"))
+                                .on_hover_text(format!("Undo {}", undo.unwrap_or("")))
+                                .clicked()
+                            {
+                                self.undo();
+                            }
+                            let redo = self.redo_stack.last().map(|(name, _)| *name);
+                            if ui
+                                .add_enabled(redo.is_some(), egui::Button::new("Redo"))
+                                .on_hover_text(format!("Redo {}", redo.unwrap_or("")))
+                                .clicked()
+                            {
+                                self.redo();
+                            }
+                        });
+                        ui.add_enabled_ui(self.pending_edit.is_some(), |ui| {
+                            if ui
+                                .button("Apply Edits")
+                                .on_hover_text("Regenerate the future from the edited state now")
+                                .clicked()
+                            {
+                                self.apply_edits = true;
+                            }
+                            if ui
+                                .button("Revert Edits")
+                                .on_hover_text("Restore the state from before the current edits")
+                                .clicked()
+                            {
+                                self.revert_edits = true;
+                            }
+                        });
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.group(|ui| {
+                        ui.label("Bookmarks:");
+                        if ui.button("Add").clicked()
+                            && let Err(index) = self.bookmarks.binary_search(&self.current_state)
+                        {
+                            self.bookmarks.insert(index, self.current_state);
+                            self.modified_since_save_to_file = true;
+                        }
+                        let mut remove = None;
+                        for (i, &bookmark) in self.bookmarks.iter().enumerate() {
+                            let label = ui
+                                .selectable_label(
+                                    bookmark == self.current_state,
+                                    format_time(bookmark as f64 * self.step_size),
+                                )
+                                .on_hover_text("Click to jump, middle click to remove");
+                            if label.clicked() {
+                                self.current_state = bookmark.min(self.states.len() - 1);
+                            }
+                            if label.clicked_by(egui::PointerButton::Middle) {
+                                remove = Some(i);
+                            }
+                        }
+                        if let Some(remove) = remove {
+                            self.bookmarks.remove(remove);
+                            self.modified_since_save_to_file = true;
+                        }
+                    });
+                    ui.group(|ui| camera_path::ui(ui, self));
+                    ui.group(|ui| {
+                        self.modified_since_save_to_file |= self.event_ramp.ui(ui);
+                    });
+                });
+            })
+            .response
+            .rect;
+        self.expand_current();
+        camera_path::apply(self);
+        alarms::show_toasts(ctx, &self.toasts);
+        self.annotation_ui(ctx);
+        self.maneuver_ui(ctx);
+
+        {
+            let impact = self.predict_impact(&self.view());
+            let can_edit = self.can_edit();
+            let three_d = self.state().three_d;
+            let variables = self.expression_variables();
+            let parser = |text: &str| expression::evaluate(text, &variables);
+            let mut open = self.selected.is_some();
+            let body_names: Vec<(BodyId, String)> = self
+                .state()
+                .bodies
+                .iter()
+                .map(|(id, body)| (id, body.meta.name.clone()))
+                .collect();
+            let name = self.selected.and_then(|selected| {
+                Some(
+                    self.states[self.current_state]
+                        .bodies
+                        .get(selected)?
+                        .meta
+                        .name
+                        .as_str(),
+                )
+            });
+            let title = name.unwrap_or("Selected Body").to_string();
+            let mut detached = self.selected_window_detached;
+            self.selected_window_rect = detachable::show(
+                ctx,
+                &title,
+                "Selected Body",
+                &mut open,
+                &mut detached,
+                |ui| {
+                    let focused_body = self.focused_body();
+                    let gravity = self.state().gravity;
+                    let spiral = self.spiral();
+                    let [selected, focused] = self.states[self.current_state]
+                        .bodies
+                        .maybe_get_disjoint_mut([self.selected, focused_body]);
+                    let Some(body) = selected else {
+                        ui.label("The selected body does not exist in this time :p");
+                        return;
+                    };
+                    let vel_before = body.vel;
+                    let mut delete = false;
+                    if let Some(landing) = body.landed {
+                        let surface = body_names
+                            .iter()
+                            .find(|(id, _)| *id == landing.on)
+                            .map_or("Unknown", |(_, name)| name.as_str());
+                        ui.label(format!("Landed on {surface}"))
+                            .on_hover_text("Push it away from the surface to lift off");
+                    }
+                    if !can_edit && !self.playing {
+                        ui.label("Press Edit Here to change this state");
+                    }
+                    ui.add_enabled_ui(can_edit, |ui| {
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Name:");
+                            let mut name = body.meta.name.clone();
+                            if ui
+                                .text_edit_singleline(&mut name)
+                                .labelled_by(label.id)
+                                .changed()
+                            {
+                                self.current_state_modified = true;
+                                body.meta_mut().name = name;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Position:");
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.pos.x)
+                                        .speed(1.0)
+                                        .custom_parser(parser)
+                                        .prefix("x:"),
+                                )
+                                .labelled_by(label.id)
+                                .changed();
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.pos.y)
+                                        .speed(1.0)
+                                        .custom_parser(parser)
+                                        .prefix("y:"),
+                                )
+                                .labelled_by(label.id)
+                                .changed();
+                            if three_d {
+                                self.current_state_modified |= ui
+                                    .add(
+                                        egui::DragValue::new(&mut body.pos.z)
+                                            .speed(1.0)
+                                            .custom_parser(parser)
+                                            .prefix("z:"),
+                                    )
+                                    .labelled_by(label.id)
+                                    .changed();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Velocity:");
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.vel.x)
+                                        .speed(0.1)
+                                        .custom_parser(parser)
+                                        .prefix("x:"),
+                                )
+                                .labelled_by(label.id)
+                                .changed();
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.vel.y)
+                                        .speed(0.1)
+                                        .custom_parser(parser)
+                                        .prefix("y:"),
+                                )
+                                .labelled_by(label.id)
+                                .changed();
+                            if three_d {
+                                self.current_state_modified |= ui
+                                    .add(
+                                        egui::DragValue::new(&mut body.vel.z)
+                                            .speed(0.1)
+                                            .custom_parser(parser)
+                                            .prefix("z:"),
+                                    )
+                                    .labelled_by(label.id)
+                                    .changed();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Spin:");
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.spin)
+                                        .speed(0.01)
+                                        .custom_parser(parser)
+                                        .suffix("rad/s"),
+                                )
+                                .labelled_by(label.id)
+                                .on_hover_text("Counterclockwise is positive")
+                                .changed();
+                            let mut degrees = body.angle.to_degrees();
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut degrees)
+                                        .speed(1.0)
+                                        .custom_parser(parser)
+                                        .suffix("°"),
+                                )
+                                .labelled_by(label.id)
+                                .changed()
+                            {
+                                self.current_state_modified = true;
+                                body.angle = degrees.to_radians().rem_euclid(std::f64::consts::TAU);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Charge:");
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.charge)
+                                        .speed(0.01)
+                                        .custom_parser(parser),
+                                )
+                                .labelled_by(label.id)
+                                .on_hover_text("Like charges repel and opposite charges attract")
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Radius:");
+                            self.current_state_modified |= ui
+                                .add(
+                                    egui::DragValue::new(&mut body.radius)
+                                        .speed(0.1)
+                                        .custom_parser(parser)
+                                        .suffix("m"),
+                                )
+                                .labelled_by(label.id)
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Density:");
+                            let mut density = body.meta.density;
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut density)
+                                        .speed(0.1)
+                                        .custom_parser(parser)
+                                        .suffix(density_unit(three_d)),
+                                )
+                                .labelled_by(label.id)
+                                .changed()
+                            {
+                                self.current_state_modified = true;
+                                body.meta_mut().density = density;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Mass:");
+                            ui.add_enabled(
+                                false,
+                                egui::DragValue::new(&mut body.mass(three_d)).suffix("kg"),
+                            )
+                            .labelled_by(label.id);
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Color:");
+                            let color: Vector3<f32> = body.meta.color.cast().unwrap();
+                            let mut color: [f32; 3] = color.into();
+                            if ui
+                                .color_edit_button_rgb(&mut color)
+                                .labelled_by(label.id)
+                                .changed()
+                            {
+                                self.current_state_modified = true;
+                                let color: Vector3<f32> = color.into();
+                                body.meta_mut().color = color.cast().unwrap();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Parent:");
+                            let mut parent = body.meta.parent;
+                            let parent_name = body_names
+                                .iter()
+                                .find(|(id, _)| Some(*id) == parent)
+                                .map_or("None", |(_, name)| name.as_str());
+                            egui::ComboBox::from_id_salt("Parent")
+                                .selected_text(parent_name)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut parent, None, "None");
+                                    for (id, name) in &body_names {
+                                        if Some(*id) != self.selected {
+                                            ui.selectable_value(&mut parent, Some(*id), name);
+                                        }
+                                    }
+                                })
+                                .response
+                                .labelled_by(label.id)
+                                .on_hover_text("The body this one orbits, used by Hierarchical Kepler Drift");
+                            if parent != body.meta.parent {
+                                self.current_state_modified = true;
+                                body.meta_mut().parent = parent;
+                            }
+                        });
+                        let mut high_accuracy = body.meta.high_accuracy;
+                        if ui
+                            .checkbox(&mut high_accuracy, "High Accuracy")
+                            .on_hover_text(
+                                "Substep the interactions between high accuracy bodies, useful for close binaries",
+                            )
+                            .changed()
+                        {
+                            self.current_state_modified = true;
+                            body.meta_mut().high_accuracy = high_accuracy;
+                        }
+                        let mut pinned = body.meta.pinned;
+                        if ui
+                            .checkbox(&mut pinned, "Pinned")
+                            .on_hover_text(
+                                "Keep this body fixed in place, it still pulls on everything else",
+                            )
+                            .changed()
+                        {
+                            self.current_state_modified = true;
+                            body.meta_mut().pinned = pinned;
+                            if pinned {
+                                body.vel = Vector3::zero();
+                            }
+                        }
+                        self.current_state_modified |= thrust::ui(
+                            ui,
+                            self.selected.unwrap(),
+                            body,
+                            focused_body,
+                            &body_names,
+                            parser,
+                            spiral.as_ref(),
+                        );
+                        if ui.button("Delete").clicked() {
+                            self.current_state_modified = true;
+                            delete = true;
+                        }
+                        self.current_state_modified |= self.auto_orbit.ui(
+                            ui,
+                            body,
+                            focused.as_deref(),
+                            gravity,
+                            three_d,
+                            parser,
+                        );
+                    });
+                    body.record_burn(
+                        self.current_state as f64 * self.step_size,
+                        BurnKind::Edit,
+                        body.vel - vel_before,
+                    );
+                    self.current_state_modified |= ledger::ui(ui, body, can_edit);
+                    if let Some(focus) = &focused {
+                        impact::altitude_ui(ui, body, focus);
+                        orbit_info::ui(ui, body, focus, gravity, three_d);
+                    }
+                    if let Some(impact) = &impact {
+                        impact::warning_ui(ui, impact.time(self.current_state, self.step_size));
+                    }
+                    if delete {
+                        self.trash_body(self.selected.unwrap());
+                    }
+                },
+            );
+            self.selected_window_detached = detached;
+            if self.selected.is_some() && !open {
+                self.selected = None;
+            }
+            if let Some(impact) = &impact {
+                impact::window(ctx, impact, self.current_state, self.step_size);
+            }
+        }
+        if !ctx.wants_keyboard_input() {
+            let keys = &settings.keybindings;
+            // Space would also press a focused button, so playback only takes it when nothing has focus
+            let nothing_focused = ctx.memory(|m| m.focused()).is_none();
+            ctx.input(|i| {
+                let move_speed = settings.camera_move_speed * settings.camera_modifier(i.modifiers);
+                let pan = Vector2 {
+                    x: keys.down(i, Action::PanRight) as u8 as f64
+                        - keys.down(i, Action::PanLeft) as u8 as f64,
+                    y: keys.down(i, Action::PanUp) as u8 as f64
+                        - keys.down(i, Action::PanDown) as u8 as f64,
+                };
+                self.camera.pos += pan * dt * move_speed * self.camera.view_height;
+
+                if keys.pressed(i, Action::DeleteBody)
+                    && self.can_edit()
+                    && let Some(selected) = self.selected
+                {
+                    self.selected = None;
+                    self.trash_body(selected);
+                    self.current_state_modified = true
+                }
+                let nudge = Vector2 {
+                    x: keys.pressed(i, Action::NudgeRight) as u8 as f64
+                        - keys.pressed(i, Action::NudgeLeft) as u8 as f64,
+                    y: keys.pressed(i, Action::NudgeUp) as u8 as f64
+                        - keys.pressed(i, Action::NudgeDown) as u8 as f64,
+                };
+                if nudge != Vector2::zero()
+                    && self.can_edit()
+                    && let Some(selected) = self.selected
+                {
+                    let (pos_step, vel_step) = self.nudge_steps();
+                    if let Some(body) = self.states[self.current_state].bodies.get_mut(selected) {
+                        if i.modifiers.shift {
+                            body.vel += (nudge * vel_step).extend(0.0);
+                            body.record_burn(
+                                self.current_state as f64 * self.step_size,
+                                BurnKind::Nudge,
+                                (nudge * vel_step).extend(0.0),
+                            );
+                        } else {
+                            body.pos += (nudge * pos_step).extend(0.0);
+                        }
+                        self.current_state_modified = true;
+                    }
+                }
+                if keys.pressed(i, Action::NewBody) && self.can_edit() {
+                    self.new_body(self.camera.pos);
+                }
+                let select = keys.pressed(i, Action::SelectNext) as isize
+                    - keys.pressed(i, Action::SelectPrevious) as isize;
+                if select != 0 {
+                    let ids: Vec<BodyId> = self.states[self.current_state]
+                        .bodies
+                        .iter()
+                        .map(|(id, _)| id)
+                        .collect();
+                    if !ids.is_empty() {
+                        let index = match self
+                            .selected
+                            .and_then(|s| ids.iter().position(|&id| id == s))
+                        {
+                            Some(index) => (index as isize + select).rem_euclid(ids.len() as isize),
+                            None if select > 0 => 0,
+                            None => ids.len() as isize - 1,
+                        };
+                        self.selected = Some(ids[index as usize]);
+                    }
+                }
+                if keys.pressed(i, Action::FocusSelected) {
+                    self.set_focus(self.selected.map(Focus::Body));
+                }
+                if nothing_focused && keys.pressed(i, Action::PlayPause) {
+                    self.playing = !self.playing;
+                }
+                let step = keys.pressed(i, Action::StepForward) as isize
+                    - keys.pressed(i, Action::StepBack) as isize;
+                if step != 0 && self.pending_edit.is_none() {
+                    self.playing = false;
+                    self.current_state = (self.current_state as isize + step)
+                        .clamp(0, self.states.len() as isize - 1)
+                        as usize;
+                }
+                if self.pending_edit.is_none() && i.modifiers.command {
+                    if keys.pressed(i, Action::Redo)
+                        || (i.modifiers.shift && keys.pressed(i, Action::Undo))
+                    {
+                        self.redo();
+                    } else if keys.pressed(i, Action::Undo) {
+                        self.undo();
+                    }
+                }
+            });
+        }
+        if !ctx.wants_pointer_input() {
+            let (back, forward) = ctx.input(|i| {
+                self.camera.view_height *= settings.zoom_factor(i);
+                self.camera.view_height = self.camera.view_height.max(0.1);
+                (
+                    i.pointer.button_pressed(egui::PointerButton::Extra1),
+                    i.pointer.button_pressed(egui::PointerButton::Extra2),
+                )
+            });
+            if back {
+                self.focus_back();
+            }
+            if forward {
+                self.focus_forward();
+            }
+        }
+        self.modified_since_save_to_file |= self.current_state_modified;
+    }
+
+    pub fn world_input(&mut self, response: &egui::Response, rect: egui::Rect, ui: &mut egui::Ui) {
+        self.camera.width = rect.width() as f64;
+        self.camera.height = rect.height() as f64;
+
+        self.camera.offset = -self
+            .focus_position(&self.states[self.current_state])
+            .unwrap_or(Vector2::zero());
+        let mouse_pos = if let Some(hover_pos) = ui.ctx().pointer_hover_pos() {
+            Vector2 {
+                x: hover_pos.x - rect.left_top().x,
+                y: hover_pos.y - rect.left_top().y,
+            }
+        } else {
+            Vector2::zero()
+        }
+        .cast()
+        .unwrap();
+
+        let world_mouse_pos = self.camera.screen_to_world(mouse_pos);
+
+        // Every click goes to exactly one place, egui already keeps clicks on windows from
+        // reaching the viewport
+        if response.clicked_by(egui::PointerButton::Secondary) {
+            self.attempt_focus(world_mouse_pos);
+        }
+        // New bodies go in the plane, under the mouse however the view is turned
+        let plane_mouse_pos = self.camera.unproject(world_mouse_pos);
+        if response.clicked_by(egui::PointerButton::Middle)
+            && self.can_edit()
+            && let Some(pos) = plane_mouse_pos
+        {
+            self.new_body(pos);
+        }
+        if response.clicked() {
+            match self.tool {
+                Tool::Select => self.attempt_select(world_mouse_pos),
+                Tool::Pan => {}
+                Tool::Add => {
+                    if self.can_edit()
+                        && let Some(pos) = plane_mouse_pos
+                    {
+                        self.new_body(pos);
+                    }
+                }
+                Tool::Measure => self.stopwatch_click(world_mouse_pos),
+                Tool::Annotate => self.annotate_click(mouse_pos, world_mouse_pos),
+                Tool::Maneuver => self.maneuver_click(mouse_pos, world_mouse_pos),
+                Tool::Observe => self.observe_click(world_mouse_pos),
+            }
+        }
+        if self.tool == Tool::Pan && response.dragged_by(egui::PointerButton::Primary) {
+            let delta = response.drag_delta();
+            self.camera.pos.x -= delta.x as f64 * self.camera.view_height / self.camera.height;
+            self.camera.pos.y += delta.y as f64 * self.camera.view_height / self.camera.height;
+        }
+        if self.state().three_d && response.dragged_by(egui::PointerButton::Secondary) {
+            let delta = response.drag_delta();
+            self.camera
+                .orbit(Vector2::new(delta.x, delta.y).cast().unwrap());
+        }
+    }
+
+    fn maneuver_click(&mut self, mouse_pos: Vector2<f64>, world_mouse_pos: Vector2<f64>) {
+        let clicked = self
+            .maneuver_markers(&self.view())
+            .into_iter()
+            .find_map(|(index, pos)| {
+                picking::point_in_circle(
+                    mouse_pos,
+                    self.camera.world_to_screen(pos),
+                    ANNOTATION_PICK_RADIUS,
+                )
+                .then_some(index)
+            });
+        if let Some(index) = clicked {
+            self.editing_maneuver = Some(index);
+            return;
+        }
+        let Some(point) = self.pick_trail(world_mouse_pos) else {
+            return;
+        };
+        if Some(point.body) != self.selected || point.state <= self.current_state {
+            return;
+        }
+        self.maneuver_nodes.push(ManeuverNode {
+            id: ManeuverId::next_id(),
+            time: point.state as f64 * self.step_size,
+            body: point.body,
+            reference: self.focused_body().filter(|&focus| focus != point.body),
+            prograde: 0.0,
+            normal: 0.0,
+        });
+        self.modified_since_save_to_file = true;
+        self.editing_maneuver = Some(self.maneuver_nodes.len() - 1);
+    }
+
+    // Nodes whose state has been generated, placed on the trail of their body
+    fn maneuver_markers(&self, view: &View) -> Vec<(usize, Vector2<f64>)> {
+        self.maneuver_nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| {
+                let universe = self
+                    .states
+                    .get((node.time / self.step_size).round() as usize)?;
+                Some((i, view.trail_position(universe, node.body)?))
+            })
+            .collect()
+    }
+
+    // Throws away the future from a node's state so it is generated again with the node as it is now.
+    // An edit in progress replaces the future anyway
+    fn regenerate_from(&mut self, time: f64) {
+        let index = (time / self.step_size).round() as usize;
+        if index <= self.current_state || index >= self.states.len() || self.pending_edit.is_some()
+        {
+            return;
+        }
+        let keep = (self.current_state..index)
+            .rev()
+            .find(|&i| !self.states[i].is_thinned() && !self.states[i].is_compact())
+            .unwrap_or(self.current_state);
+        self.states.truncate(keep + 1);
+        self.ghost_states.clear();
+        self.regen_target = None;
+        self.reset_collision_scan();
+        if let Some(conservation) = &mut self.conservation {
+            conservation.truncate(keep + 1);
+        }
+        self.graph.truncate(keep + 1);
+        self.restart_generation();
+    }
+
+    fn maneuver_ui(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.editing_maneuver else {
+            return;
+        };
+        let Some(&original) = self.maneuver_nodes.get(index) else {
+            self.editing_maneuver = None;
+            return;
+        };
+        let mut node = original;
+        let body_name = self
+            .state()
+            .bodies
+            .get(node.body)
+            .map_or("Missing".to_string(), |body| body.meta.name.clone());
+        let reference_name = node
+            .reference
+            .and_then(|reference| self.state().bodies.get(reference))
+            .map_or("Nothing".to_string(), |body| body.meta.name.clone());
+        let past = (node.time / self.step_size).round() as usize <= self.current_state;
+        let mut open = true;
+        let mut delete = false;
+        egui::Window::new("Maneuver Node")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{body_name} relative to {reference_name}"));
+                if past {
+                    ui.label("This burn has already happened");
+                }
+                ui.add_enabled_ui(!past && self.pending_edit.is_none(), |ui| {
+                    egui::Grid::new("Maneuver Node")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            let label = ui.label("Time:");
+                            // A node dragged into the past would never be applied. Past nodes keep
+                            // their time, they can not be edited anyway
+                            let next = if past {
+                                0.0
+                            } else {
+                                (self.current_state as f64 + 0.5) * self.step_size
+                            };
+                            ui.add(
+                                egui::DragValue::new(&mut node.time)
+                                    .range(next..=f64::INFINITY)
+                                    .speed(self.step_size)
+                                    .suffix("s"),
+                            )
+                            .labelled_by(label.id);
+                            ui.end_row();
+                            let label = ui.label("Prograde:");
+                            ui.add(
+                                egui::DragValue::new(&mut node.prograde)
+                                    .speed(0.01)
+                                    .suffix("m/s"),
+                            )
+                            .labelled_by(label.id);
+                            ui.end_row();
+                            let label = ui.label("Normal:");
+                            ui.add(
+                                egui::DragValue::new(&mut node.normal)
+                                    .speed(0.01)
+                                    .suffix("m/s"),
+                            )
+                            .labelled_by(label.id)
+                            .on_hover_text("A quarter turn counterclockwise from prograde");
+                            ui.end_row();
+                        });
+                    ui.label(format!("Total: {:.3}m/s", node.delta_v()));
+                    delete = ui.button("Delete").clicked();
+                });
+            });
+        // The state a node sits on can not be edited in place, so the future from the earlier of
+        // the old and new times is generated again
+        let changed = node.time != original.time
+            || node.prograde != original.prograde
+            || node.normal != original.normal;
+        if changed || delete {
+            self.regenerate_from(node.time.min(original.time));
+            self.modified_since_save_to_file = true;
+        }
+        if delete {
+            self.maneuver_nodes.remove(index);
+        } else {
+            self.maneuver_nodes[index] = node;
+        }
+        if delete || !open {
+            self.editing_maneuver = None;
+        }
+    }
+
+    fn annotate_click(&mut self, mouse_pos: Vector2<f64>, world_mouse_pos: Vector2<f64>) {
+        let clicked = self.annotations.iter().position(|annotation| {
+            picking::point_in_circle(
+                mouse_pos,
+                self.camera.world_to_screen(annotation.pos),
+                ANNOTATION_PICK_RADIUS,
+            )
+        });
+        self.editing_annotation = Some(clicked.unwrap_or_else(|| {
+            self.annotations.push(Annotation {
+                pos: world_mouse_pos,
+                text: "Note".to_string(),
+            });
+            self.modified_since_save_to_file = true;
+            self.annotations.len() - 1
+        }));
+    }
+
+    fn annotation_ui(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.editing_annotation else {
+            return;
+        };
+        let Some(annotation) = self.annotations.get_mut(index) else {
+            self.editing_annotation = None;
+            return;
+        };
+        let mut open = true;
+        let mut delete = false;
+        egui::Window::new("Annotation")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.modified_since_save_to_file |=
+                    ui.text_edit_multiline(&mut annotation.text).changed();
+                delete = ui.button("Delete").clicked();
+            });
+        if delete {
+            self.annotations.remove(index);
+            self.modified_since_save_to_file = true;
+        }
+        if delete || !open {
+            self.editing_annotation = None;
+        }
+    }
+
+    // The picture in picture only moves its own camera and focus, tools and focus history belong to
+    // the main view
+    pub fn pip_input(&mut self, response: &egui::Response, rect: egui::Rect, settings: &Settings) {
+        let mut view = self.pip_view();
+        view.camera.width = rect.width() as f64;
+        view.camera.height = rect.height() as f64;
+        view.camera.offset = -view.focus_position(self.state()).unwrap_or(Vector2::zero());
+        if response.hovered() {
+            view.camera.view_height *= response.ctx.input(|i| settings.zoom_factor(i));
+            view.camera.view_height = view.camera.view_height.max(0.1);
+        }
+        if response.dragged_by(egui::PointerButton::Primary) {
+            let delta = response.drag_delta();
+            view.camera.pos.x -= delta.x as f64 * view.camera.view_height / view.camera.height;
+            view.camera.pos.y += delta.y as f64 * view.camera.view_height / view.camera.height;
+        }
+        if self.state().three_d && response.dragged_by(egui::PointerButton::Secondary) {
+            let delta = response.drag_delta();
+            view.camera
+                .orbit(Vector2::new(delta.x, delta.y).cast().unwrap());
+        }
+        if response.clicked_by(egui::PointerButton::Secondary)
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            let mouse_pos = Vector2::new(pointer.x - rect.left(), pointer.y - rect.top())
+                .cast()
+                .unwrap();
+            let clicked = self.body_at(&view.camera, view.camera.screen_to_world(mouse_pos));
+            view.move_focus(self.state(), clicked.map(Focus::Body));
+        }
+        (self.pip_camera, self.pip_focused) = (view.camera, view.focused);
+    }
+
+    // Clicking a body puts the observer on it, the signal speed is kept when moving it
+    fn observe_click(&mut self, world_mouse_pos: Vector2<f64>) {
+        let on = self
+            .state()
+            .bodies
+            .iter()
+            .filter(|(_, body)| {
+                picking::point_in_circle(world_mouse_pos, body.pos.truncate(), body.radius)
+            })
+            .map(|(id, _)| id)
+            .last();
+        self.observer = Some(Observer {
+            pos: world_mouse_pos,
+            on,
+            signal_speed: self
+                .observer
+                .map_or(DEFAULT_SIGNAL_SPEED, |observer| observer.signal_speed),
+        });
+        self.modified_since_save_to_file = true;
+    }
+
+    fn attempt_select(&mut self, pos: Vector2<f64>) {
+        if let Some(id) = self.body_at(&self.camera, pos) {
+            self.selected = Some(id);
+        }
+    }
+
+    // The one drawn on top, which is the nearest in a turned 3D view
+    fn body_at(&self, camera: &Camera, pos: Vector2<f64>) -> Option<BodyId> {
+        self.states[self.current_state]
+            .bodies
+            .iter()
+            .filter(|(_, body)| {
+                picking::point_in_circle(pos, camera.project(body.pos), body.radius)
+            })
+            .max_by(|(_, a), (_, b)| camera.depth(a.pos).total_cmp(&camera.depth(b.pos)))
+            .map(|(key, _)| key)
+    }
+
+    fn attempt_focus(&mut self, pos: Vector2<f64>) {
+        self.set_focus(self.body_at(&self.camera, pos).map(Focus::Body));
+    }
+
+    // Like browser navigation, focusing something new forgets what was gone back from
+    pub fn set_focus(&mut self, focus: Option<Focus>) {
+        let previous = self.focused;
+        self.move_focus(focus);
+        if self.focused != previous {
+            if self.focus_history.len() >= FOCUS_HISTORY_LIMIT {
+                self.focus_history.remove(0);
+            }
+            self.focus_history.push(previous);
+            self.focus_future.clear();
+        }
+    }
+
+    pub fn focus_back(&mut self) {
+        if let Some(focus) = self.focus_history.pop() {
+            self.focus_future.push(self.focused);
+            self.move_focus(focus);
+        }
+    }
+
+    pub fn focus_forward(&mut self) {
+        if let Some(focus) = self.focus_future.pop() {
+            self.focus_history.push(self.focused);
+            self.move_focus(focus);
+        }
+    }
+
+    pub fn focus_name(&self, focus: Option<Focus>) -> String {
+        match focus {
+            Some(Focus::Body(id)) => self
+                .state()
+                .bodies
+                .get(id)
+                .map_or("Deleted Body".to_string(), |body| body.meta.name.clone()),
+            Some(Focus::Barycenter) => "Barycenter".to_string(),
+            None => "Nothing".to_string(),
+        }
+    }
+
+    fn move_focus(&mut self, focus: Option<Focus>) {
+        let mut view = self.view();
+        view.move_focus(self.state(), focus);
+        (self.camera, self.focused) = (view.camera, view.focused);
+    }
+
+    pub fn view(&self) -> View {
+        View {
+            camera: self.camera,
+            focused: self.focused,
+        }
+    }
+
+    pub fn pip_view(&self) -> View {
+        View {
+            camera: self.pip_camera,
+            focused: self.pip_focused,
+        }
+    }
+
+    pub fn focused_body(&self) -> Option<BodyId> {
+        self.view().focused_body()
+    }
+
+    pub fn focus_position(&self, universe: &Universe) -> Option<Vector2<f64>> {
+        self.view().focus_position(universe)
+    }
+
+    // The selected body together with everything whose parent chain leads back to it, like a
+    // planet and its moons
+    pub fn group_barycenter(&self, universe: &Universe, root: BodyId) -> Option<Vector2<f64>> {
+        let in_group = |mut id: BodyId| {
+            for _ in 0..universe.bodies.len() {
+                if id == root {
+                    return true;
+                }
+                match universe.bodies.get(id).and_then(|body| body.meta.parent) {
+                    Some(parent) => id = parent,
+                    None => return false,
+                }
+            }
+            false
+        };
+        let (mass, weighted) = universe.bodies.iter().filter(|&(id, _)| in_group(id)).fold(
+            (0.0, Vector3::zero()),
+            |(mass, weighted), (_, body)| {
+                let body_mass = body.mass(universe.three_d);
+                (mass + body_mass, weighted + body.pos * body_mass)
+            },
+        );
+        (mass > 0.0).then(|| (weighted / mass).truncate())
+    }
+
+    fn new_body(&mut self, pos: Vector2<f64>) {
+        self.current_state_modified = true;
+        let body = self.default_body(pos);
+        self.selected = Some(self.states[self.current_state].bodies.push(body));
+    }
+
+    fn default_body(&self, pos: Vector2<f64>) -> Body {
+        Body {
+            meta: Arc::new(BodyMeta {
+                name: "Unnamed".into(),
+                density: 1.0,
+                color: Vector3 {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                },
+                high_accuracy: false,
+                pinned: false,
+                parent: None,
+            }),
+            pos: pos.extend(0.0),
+            vel: Vector3::zero(),
+            radius: 1.0,
+            thrust: None,
+            spin: 0.0,
+            angle: 0.0,
+            charge: 0.0,
+            landed: None,
+            burns: vec![],
+        }
+    }
+
+    pub fn near_event(&self) -> bool {
+        let window = (self.event_ramp.window / self.step_size) as usize;
+        self.bookmarks
+            .iter()
+            .copied()
+            .chain(self.predict_impact(&self.view()).map(|impact| impact.state))
+            .any(|state| state.abs_diff(self.current_state) <= window)
+    }
+
+    pub fn effective_speed(&self) -> f64 {
+        if self.event_ramp.enabled && self.near_event() {
+            self.speed.min(self.event_ramp.speed)
+        } else {
+            self.speed
+        }
+    }
+
+    pub fn move_time(&mut self, dt: f64) {
+        self.record_actions(dt);
+        self.run_replay(dt);
+        self.real_time_played += dt * self.playing as u8 as f64;
+        self.accumulated_time += (dt * self.playing as u8 as f64 * self.effective_speed()).max(0.0);
+        while self.accumulated_time >= self.step_size {
+            if self.current_state + 1 < self.states.len() {
+                self.current_state += 1;
+            } else {
+                break;
+            }
+            self.accumulated_time -= self.step_size;
+        }
+        self.check_alarms();
+        self.toasts.retain_mut(|toast| {
+            toast.remaining -= dt;
+            toast.remaining > 0.0
+        });
+        if let Some(recording) = &mut self.recording {
+            recording.last_state = self.current_state;
+        }
+    }
+
+    // Whatever changed since last frame that playback does not explain was done by the user. Edits
+    // are recorded when they are committed
+    fn record_actions(&mut self, dt: f64) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+        recording.elapsed += dt;
+        if self.playing != recording.last_playing {
+            let action = if self.playing {
+                replay::Action::Play
+            } else {
+                replay::Action::Pause
+            };
+            recording.push(self.current_state, action);
+            recording.last_playing = self.playing;
+        } else if self.current_state != recording.last_state {
+            recording.push(self.current_state, replay::Action::Jump);
+        }
+        if self.speed != recording.last_speed {
+            recording.push(self.current_state, replay::Action::Speed(self.speed));
+            recording.last_speed = self.speed;
+        }
+    }
+
+    // Actions that need a state that has not been generated yet wait for it
+    fn run_replay(&mut self, dt: f64) {
+        let Some(mut replay) = self.replay.take() else {
+            return;
+        };
+        replay.clock += dt;
+        while let Some(recorded) = replay.actions.get(replay.next)
+            && recorded.time <= replay.clock
+        {
+            if recorded.state >= self.states.len() || self.pending_edit.is_some() {
+                break;
+            }
+            match &recorded.action {
+                replay::Action::Play => self.playing = true,
+                replay::Action::Pause => self.playing = false,
+                replay::Action::Jump => {}
+                replay::Action::Speed(speed) => self.speed = *speed,
+                replay::Action::Edit(universe) => {
+                    self.playing = false;
+                    self.current_state = recorded.state;
+                    self.expand_current();
+                    self.edit_snapshot = Some((self.state().clone(), self.state().changed));
+                    self.states[recorded.state] = (**universe).clone();
+                    self.current_state_modified = true;
+                    self.apply_edits = true;
+                }
+            }
+            // Real time playback drifts, so every action also puts time back where it was
+            self.current_state = recorded.state;
+            self.accumulated_time = 0.0;
+            replay.next += 1;
+        }
+        if replay.next < replay.actions.len() {
+            self.replay = Some(replay);
+        }
+    }
+
+    // Every state played through is checked so fast playback does not skip past an approach,
+    // scrubbing only updates which pairs are close without going off
+    fn check_alarms(&mut self) {
+        let from = if self.playing
+            && self.current_state > self.alarm_state
+            && self.current_state - self.alarm_state <= alarms::SCAN_LIMIT
+        {
+            self.alarm_state + 1
+        } else {
+            self.current_state
+        };
+        self.alarm_state = self.current_state;
+        for index in from..=self.current_state {
+            let triggered = alarms::check(&mut self.alarms, &self.states[index]);
+            if !self.playing {
+                continue;
+            }
+            let mut pause = false;
+            for alarm in triggered.into_iter().map(|i| self.alarms[i]) {
+                let name = |id: BodyId| {
+                    self.state()
+                        .bodies
+                        .get(id)
+                        .map_or("Unnamed".to_string(), |body| body.meta.name.clone())
+                };
+                let message = format!(
+                    "{} is within {} of {} at {}",
+                    name(alarm.a),
+                    alarm.distance,
+                    name(alarm.b),
+                    format_time(index as f64 * self.step_size)
+                );
+                tracing::info!("{}: {message}", self.name);
+                self.toasts.push(Toast::new(message));
+                pause |= alarm.pause;
+            }
+            if pause {
+                self.playing = false;
+                self.current_state = index;
+                self.alarm_state = index;
+                self.accumulated_time = 0.0;
+                break;
+            }
+        }
+    }
+
+    pub fn gen_future(&mut self, settings: &Settings) {
+        let mut lock = self.thread_state.generation_state.lock().unwrap();
+        lock.cpu_budget = settings.cpu_budget;
+        lock.threads = settings.simulation_threads;
+        lock.paused = self.generation_paused;
+        if settings.auto_gen_future && lock.step_cost > 0.0 {
+            let state_size = self.state().memory_estimate().max(1) as f64;
+            let memory_limit = ((settings.memory_budget * 1_000_000.0 - self.undo_memory as f64)
+                / state_size) as usize;
+            self.gen_future = ((settings.cpu_budget / lock.step_cost * AUTO_GEN_FUTURE_WINDOW)
+                as usize)
+                .min(memory_limit.saturating_sub(self.current_state))
+                .max(1);
+        }
+        if self.current_state_modified {
+            self.states[self.current_state].changed = true;
+            match &mut self.pending_edit {
+                Some(pending) => {
+                    pending.last_edit = std::time::Instant::now();
+                    pending.sent = false;
+                }
+                None => {
+                    let (original, original_changed) = self
+                        .edit_snapshot
+                        .take()
+                        .unwrap_or_else(|| (self.state().clone(), true));
+                    self.pending_edit = Some(PendingEdit {
+                        index: self.current_state,
+                        last_edit: std::time::Instant::now(),
+                        original,
+                        original_changed,
+                        sent: false,
+                    });
+                }
+            }
+        }
+        if self.revert_edits {
+            self.revert_edits = false;
+            self.undoable_edit = None;
+            if let Some(pending) = self.pending_edit.take() {
+                let mut original = pending.original;
+                original.changed = pending.original_changed;
+                self.states[pending.index] = original;
+                self.ghost_states.clear();
+                if pending.sent {
+                    // Whatever was generated from the edit belongs to the ghost preview
+                    lock.new_states.clear();
+                    lock.step_size = self.step_size;
+                    lock.initial_state = Some(self.states.last().unwrap().clone());
+                }
+            }
+        }
+        let mut committed = None;
+        if let Some(pending) = &mut self.pending_edit {
+            if settings.edit_transactions {
+                self.current_state = pending.index;
+            }
+            if !pending.sent
+                && (self.apply_edits
+                    || self.playing
+                    || pending.index != self.current_state
+                    || pending.last_edit.elapsed().as_secs_f64() >= settings.edit_debounce)
+            {
+                pending.sent = true;
+                self.ghost_states.clear();
+                lock.new_states.clear();
+                lock.step_size = self.step_size;
+                lock.initial_state = Some(self.states[pending.index].clone());
+            }
+            if pending.sent && (!settings.edit_transactions || self.apply_edits || self.playing) {
+                let index = pending.index;
+                let pending = self.pending_edit.take().unwrap();
+                if self
+                    .cross_check
+                    .as_ref()
+                    .is_some_and(|check| index <= check.start)
+                {
+                    self.cross_check = None;
+                }
+                self.regen_target = Some(self.regen_target.unwrap_or(0).max(self.states.len()));
+                if let Some(heat_map) = &mut self.heat_map {
+                    heat_map.reset();
+                }
+                if let Some(conservation) = &mut self.conservation {
+                    conservation.truncate(index);
+                }
+                self.graph.truncate(index);
+                let mut replaced = self.states.split_off(index + 1);
+                let undo_name = self
+                    .undoable_edit
+                    .take()
+                    .or(self.apply_edits.then_some("Apply Edits"));
+                if let Some(name) = undo_name {
+                    let mut original = pending.original;
+                    original.changed = pending.original_changed;
+                    replaced.insert(0, original);
+                    let change = TimelineChange::ReplaceFuture {
+                        index,
+                        states: replaced,
+                    };
+                    committed = Some((name, change));
+                }
+                self.states.append(&mut self.ghost_states);
+                self.states.shrink_to_fit();
+                self.current_state = self.current_state.min(self.states.len() - 1);
+                self.collision_scan = 0;
+                self.collision_warning = None;
+                if let Some(recording) = &mut self.recording {
+                    recording.push(
+                        index,
+                        replay::Action::Edit(Box::new(self.states[index].clone())),
+                    );
+                }
+            }
+        }
+        self.apply_edits = false;
+        if self
+            .pending_edit
+            .as_ref()
+            .is_some_and(|pending| pending.sent)
+        {
+            self.ghost_states.append(&mut lock.new_states);
+            lock.states_buffer_size = self.gen_future.saturating_sub(self.ghost_states.len() + 1);
+        } else {
+            self.states.append(&mut lock.new_states);
+            if let Some(index) = apply_scheduled(
+                &mut self.states,
+                &self.spawn_queue,
+                &self.maneuver_nodes,
+                self.step_size,
+                self.current_state,
+            ) {
+                lock.new_states.clear();
+                lock.step_size = self.step_size;
+                lock.initial_state = Some(self.states[index].clone());
+                self.collision_scan = 0;
+                self.collision_warning = None;
+                if let Some(conservation) = &mut self.conservation {
+                    conservation.truncate(index);
+                }
+                self.graph.truncate(index);
+            }
+            lock.states_buffer_size = self
+                .gen_future
+                .saturating_sub((self.states.len()) - self.current_state);
+            if self
+                .regen_target
+                .is_some_and(|target| self.states.len() >= target.min(self.gen_horizon()))
+            {
+                self.regen_target = None;
+            }
+        }
+        drop(lock);
+        self.thread_state.wakeup.notify_one();
+        if let Some((name, change)) = committed {
+            self.record_timeline_change(name, change, settings);
+        }
+        if let Some(check) = &self.cross_check {
+            check.shared.lock().unwrap().horizon = self.states.len() - 1;
+        }
+        if let Some(heat_map) = &mut self.heat_map {
+            heat_map.scan(&self.states);
+        }
+        if let Some(conservation) = &mut self.conservation {
+            conservation.scan(&self.states);
+        }
+        self.scan_collisions();
+
+        if settings.compact_history {
+            let end = self.current_state.saturating_sub(COMPACT_HISTORY_MARGIN);
+            self.states[..end]
+                .iter_mut()
+                .filter(|universe| !universe.changed)
+                .for_each(Universe::compact);
+        }
+        if settings.multi_rate_history {
+            let end = self.current_state.saturating_sub(MULTI_RATE_HISTORY_MARGIN);
+            for (index, universe) in self.states[..end].iter_mut().enumerate() {
+                let stride =
+                    history_stride(self.current_state - index, settings.history_max_stride);
+                if !universe.changed && !index.is_multiple_of(stride) {
+                    universe.thin();
+                }
+            }
+        }
+        self.expand_current();
+    }
+
+    pub fn expand_current(&mut self) {
+        if self.state().is_thinned() {
+            self.regenerate_thinned();
+        }
+        if !self.state().is_compact() {
+            return;
+        }
+        self.states[self.current_state].expand();
+    }
+
+    // Throws away whatever was generated so far and continues from the last state
+    pub fn restart_generation(&self) {
+        let mut lock = self.thread_state.generation_state.lock().unwrap();
+        lock.new_states.clear();
+        lock.step_size = self.step_size;
+        lock.initial_state = Some(self.states.last().unwrap().clone());
+        drop(lock);
+        self.thread_state.wakeup.notify_one();
+    }
+
+    fn record_timeline_change(
+        &mut self,
+        name: &'static str,
+        change: TimelineChange,
+        settings: &Settings,
+    ) {
+        self.undo_stack.push((name, change));
+        self.redo_stack.clear();
+        let budget = settings.memory_budget * 1_000_000.0 * TIMELINE_UNDO_MEMORY_SHARE;
+        let mut sizes: Vec<usize> = self
+            .undo_stack
+            .iter()
+            .map(|(_, change)| change.memory_estimate())
+            .collect();
+        while !sizes.is_empty()
+            && (sizes.len() > TIMELINE_UNDO_LIMIT || sizes.iter().sum::<usize>() as f64 > budget)
+        {
+            sizes.remove(0);
+            let (forgotten, _) = self.undo_stack.remove(0);
+            if sizes.is_empty() {
+                tracing::info!(
+                    "{forgotten} holds more than the undo history may keep, it can not be undone"
+                );
+            }
+        }
+        self.undo_memory = sizes.iter().sum();
+    }
+
+    fn measure_undo_memory(&mut self) {
+        self.undo_memory = self
+            .undo_stack
+            .iter()
+            .chain(&self.redo_stack)
+            .map(|(_, change)| change.memory_estimate())
+            .sum();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some((name, change)) = self.undo_stack.pop() {
+            let inverse = self.apply_timeline_change(change);
+            self.redo_stack.push((name, inverse));
+            self.measure_undo_memory();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some((name, change)) = self.redo_stack.pop() {
+            let inverse = self.apply_timeline_change(change);
+            self.undo_stack.push((name, inverse));
+            self.measure_undo_memory();
+        }
+    }
+
+    fn apply_timeline_change(&mut self, change: TimelineChange) -> TimelineChange {
+        self.cross_check = None;
+        self.reset_collision_scan();
+        if let Some(stopwatch) = &mut self.stopwatch {
+            *stopwatch = Stopwatch::default();
+        }
+        if let Some(heat_map) = &mut self.heat_map {
+            heat_map.reset();
+        }
+        if let Some(conservation) = &mut self.conservation {
+            conservation.truncate(0);
+        }
+        self.graph.truncate(0);
+        self.modified_since_save_to_file = true;
+        match change {
+            TimelineChange::DeletePast { count } => {
+                if let Some(pending) = &mut self.pending_edit {
+                    pending.index = pending.index.saturating_sub(count);
+                }
+                self.edit_state = self.edit_state.map(|index| index.saturating_sub(count));
+                let (bookmarks, kept): (Vec<usize>, Vec<usize>) = self
+                    .bookmarks
+                    .iter()
+                    .partition(|&&bookmark| bookmark < count);
+                self.bookmarks = kept.into_iter().map(|bookmark| bookmark - count).collect();
+                let states: Vec<Universe> = self.states.drain(..count).collect();
+                self.current_state = self.current_state.saturating_sub(count);
+                self.states.shrink_to_fit();
+                TimelineChange::RestorePast { states, bookmarks }
+            }
+            TimelineChange::RestorePast { states, bookmarks } => {
+                let count = states.len();
+                self.states.splice(0..0, states);
+                self.current_state += count;
+                self.edit_state = self.edit_state.map(|index| index + count);
+                self.bookmarks
+                    .iter_mut()
+                    .for_each(|bookmark| *bookmark += count);
+                self.bookmarks.splice(0..0, bookmarks);
+                TimelineChange::DeletePast { count }
+            }
+            TimelineChange::ReplaceFuture { index, states } => {
+                let replaced = self.states.split_off(index);
+                self.states.extend(states);
+                self.current_state = self.current_state.min(self.states.len() - 1);
+                self.edit_state = self.edit_state.filter(|&edit| edit < self.states.len());
+                self.ghost_states.clear();
+                self.regen_target = None;
+                self.restart_generation();
+
+                TimelineChange::ReplaceFuture {
+                    index,
+                    states: replaced,
+                }
+            }
+        }
+    }
+
+    fn regenerate_thinned(&mut self) {
+        let Some(start) = self.states[..self.current_state]
+            .iter()
+            .rposition(|universe| !universe.is_thinned())
+        else {
+            return;
+        };
+        let mut universe = self.states[start].clone();
+        universe.expand();
+        for index in start + 1..self.states.len() {
+            if !self.states[index].is_thinned() {
+                break;
+            }
+            universe.step(self.step_size);
+            self.states[index] = universe.clone();
+        }
+    }
+
+    pub fn reset_collision_scan(&mut self) {
+        self.collision_scan = 0;
+        self.collision_warning = None;
+    }
+
+    // Looks through newly generated states a budget at a time for the first pair of discs that
+    // start overlapping, pairs that already overlapped in the state before are not new impacts
+    pub fn scan_collisions(&mut self) {
+        if self
+            .collision_warning
+            .is_some_and(|warning| warning.state <= self.current_state)
+        {
+            self.reset_collision_scan();
+        }
+        if self.collision_warning.is_some() {
+            return;
+        }
+        let mut index = self.collision_scan.max(self.current_state + 1);
+        let mut budget = COLLISION_SCAN_BUDGET;
+        while index < self.states.len() && budget > 0 {
+            let universe = &self.states[index];
+            budget = budget.saturating_sub(universe.bodies.len().max(1));
+            let previous = &self.states[index - 1];
+            if let Some((a, b)) = universe
+                .overlapping_pairs()
+                .into_iter()
+                .find(|&(a, b)| !previous.overlaps(a, b))
+            {
+                self.collision_warning = Some(CollisionWarning { state: index, a, b });
+                break;
+            }
+            index += 1;
+        }
+        self.collision_scan = index;
+    }
+
+    pub fn predict_impact(&self, view: &View) -> Option<Impact> {
+        impact::predict(
+            &self.states,
+            self.current_state,
+            self.selected?,
+            view.focused_body()?,
+        )
+    }
+
+    // Scans every generated state from now on, thinned states have nothing to check
+    pub fn closest_approach(&self, view: &View) -> Option<Approach> {
+        let [Some(a), Some(b)] = self.approach else {
+            return None;
+        };
+        if a == b {
+            return None;
+        }
+        let mut best: Option<(usize, f64)> = None;
+        for (index, universe) in self.states.iter().enumerate().skip(self.current_state) {
+            let (Some(pos_a), Some(pos_b)) = (universe.position3(a), universe.position3(b)) else {
+                continue;
+            };
+            let distance = (pos_a - pos_b).magnitude();
+            if best.is_none_or(|(_, best)| distance < best) {
+                best = Some((index, distance));
+            }
+        }
+        let (state, distance) = best?;
+        let universe = &self.states[state];
+        Some(Approach {
+            state,
+            distance,
+            relative_speed: (universe.velocity3(a)? - universe.velocity3(b)?).magnitude(),
+            pos_a: view.trail_position(universe, a)?,
+            pos_b: view.trail_position(universe, b)?,
+        })
+    }
+
+    // Overlays follow the bodies in the current state, placed relative to the focus like trails
+    pub fn overlay_markers(&self, view: &View) -> Vec<Marker> {
+        let offset = view.path_offset(self.state());
+        self.overlays
+            .iter()
+            .filter(|overlay| overlay.visible)
+            .flat_map(|overlay| overlay.markers(self.state()))
+            .map(|marker| Marker {
+                pos: marker.pos - offset,
+                ..marker
+            })
+            .collect()
+    }
+
+    pub fn overlay_regions(&self, view: &View) -> Vec<ForbiddenCell> {
+        let offset = view.path_offset(self.state());
+        self.overlays
+            .iter()
+            .filter(|overlay| overlay.visible && overlay.kind == OverlayKind::ZeroVelocity)
+            .filter_map(|overlay| Some((overlay, overlay.test.or(self.selected)?)))
+            .filter(|(overlay, test)| *test != overlay.a && *test != overlay.b)
+            .flat_map(|(overlay, test)| {
+                overlays::forbidden_cells(self.state(), overlay.a, overlay.b, test)
+            })
+            .map(|cell| ForbiddenCell {
+                pos: cell.pos - offset,
+                ..cell
+            })
+            .collect()
+    }
+
+    // Turning points of the distance to the focus, sampled at the same spacing as the drawn trails
+    pub fn apsides(&self, view: &View) -> Vec<Apsis> {
+        let Some(focused) = view.focused else {
+            return vec![];
+        };
+        let end = (self.current_state + (self.show_future / self.step_size) as usize)
+            .min(self.states.len() - 1);
+        let samples: Vec<usize> = (self.current_state..=end)
+            .step_by(self.path_quality.max(1))
+            .filter(|&index| !self.states[index].is_thinned())
+            .collect();
+        let mut apsides = vec![];
+        for (id, _) in self.state().bodies.iter() {
+            if focused == Focus::Body(id) {
+                continue;
+            }
+            let distance = |index: usize| {
+                let universe = &self.states[index];
+                Some((universe.position(id)? - view.focus_position(universe)?).magnitude())
+            };
+            let distances: Vec<Option<f64>> =
+                samples.iter().map(|&index| distance(index)).collect();
+            for (i, window) in distances.windows(3).enumerate() {
+                let [Some(before), Some(here), Some(after)] = *window else {
+                    continue;
+                };
+                let periapsis = here < before && here <= after;
+                let apoapsis = here > before && here >= after;
+                if !periapsis && !apoapsis {
+                    continue;
+                }
+                let state = samples[i + 1];
+                let Some(pos) = view.trail_position(&self.states[state], id) else {
+                    continue;
+                };
+                apsides.push(Apsis {
+                    body: id,
+                    state,
+                    pos,
+                    distance: here,
+                    periapsis,
+                });
+            }
+        }
+        apsides
+    }
+
+    pub fn nudge_steps(&self) -> (f64, f64) {
+        let pos_step = self.camera.view_height * 0.01;
+        (pos_step, pos_step * 0.1)
+    }
+
+    // Spin as a multiple of the tidal partner's orbital rate and how long tides take to lock it
+    fn draw_spin_labels(&self, painter: &egui::Painter, rect: egui::Rect) {
+        let universe = self.state();
+        for (id, body) in universe.bodies.iter() {
+            let Some((partner, orbit_rate)) = universe.tidal_partner(id) else {
+                continue;
+            };
+            let torque = universe.tidal_torque(id);
+            if orbit_rate == 0.0 || (body.spin == 0.0 && torque == 0.0) {
+                continue;
+            }
+            let partner_name = universe
+                .bodies
+                .get(partner)
+                .map_or("Unnamed", |partner| partner.meta.name.as_str());
+            let mut text = format!(
+                "Spin {:.2}x orbit of {partner_name}",
+                body.spin / orbit_rate
+            );
+            let difference = orbit_rate - body.spin;
+            if (difference * torque) > 0.0 {
+                let time =
+                    difference.abs() * body.moment_of_inertia(universe.three_d) / torque.abs();
+                text += &format!(", locks in {}", format_time(time));
+            }
+            let edge =
+                body.pos.truncate() + Vector2::new(0.0, -spin_gauge_radius(&self.view(), body));
+            let screen_pos = self.camera.world_to_screen(edge).cast().unwrap();
+            painter.text(
+                rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                egui::Align2::CENTER_TOP,
+                text,
+                egui::FontId::default(),
+                egui::Color32::LIGHT_GRAY,
+            );
+        }
+    }
+
+    pub fn draw_labels(&self, painter: &egui::Painter, rect: egui::Rect) {
+        if self.selected.is_some() && self.can_edit() {
+            let (pos_step, vel_step) = self.nudge_steps();
+            painter.text(
+                rect.left_bottom() + egui::vec2(8.0, -8.0),
+                egui::Align2::LEFT_BOTTOM,
+                format!("Arrows: {pos_step:.3}m  Shift+Arrows: {vel_step:.4}m/s"),
+                egui::FontId::default(),
+                egui::Color32::LIGHT_GRAY,
+            );
+        }
+        for annotation in &self.annotations {
+            let screen_pos = self.camera.world_to_screen(annotation.pos).cast().unwrap();
+            let screen_pos = rect.min + egui::vec2(screen_pos.x, screen_pos.y);
+            painter.circle_filled(screen_pos, 3.0, egui::Color32::YELLOW);
+            painter.text(
+                screen_pos + egui::vec2(6.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                &annotation.text,
+                egui::FontId::default(),
+                egui::Color32::YELLOW,
+            );
+        }
+        // The rest label markers that are placed in the plane
+        if self.state().three_d {
+            return;
+        }
+        if self.hill_sphere
+            && let Some(focused) = self.focused_body()
+            && let Some(body) = self.state().bodies.get(focused)
+            && let Some((radius, parent)) = conics::hill_radius(self.state(), focused)
+        {
+            let edge = body.pos.truncate() + Vector2::new(0.0, radius);
+            let screen_pos = self.camera.world_to_screen(edge).cast().unwrap();
+            let parent_name = self
+                .state()
+                .bodies
+                .get(parent)
+                .map_or("Unnamed", |parent| parent.meta.name.as_str());
+            painter.text(
+                rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                egui::Align2::CENTER_BOTTOM,
+                format!("Hill Sphere {radius:.2}m relative to {parent_name}"),
+                egui::FontId::default(),
+                egui::Color32::LIGHT_GRAY,
+            );
+        }
+        if self.spin_gauges {
+            self.draw_spin_labels(painter, rect);
+        }
+        for marker in self.overlay_markers(&self.view()) {
+            let screen_pos = self.camera.world_to_screen(marker.pos).cast().unwrap();
+            painter.text(
+                rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                egui::Align2::LEFT_BOTTOM,
+                marker.label,
+                egui::FontId::default(),
+                egui::Color32::LIGHT_GRAY,
+            );
+        }
+        if self.apsis_markers {
+            for apsis in self.apsides(&self.view()) {
+                let screen_pos = self.camera.world_to_screen(apsis.pos).cast().unwrap();
+                painter.text(
+                    rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                    egui::Align2::LEFT_BOTTOM,
+                    format!(
+                        "{} {:.2}m  {:.2}s",
+                        if apsis.periapsis { "Pe" } else { "Ap" },
+                        apsis.distance,
+                        (apsis.state - self.current_state) as f64 * self.step_size
+                    ),
+                    egui::FontId::default(),
+                    egui::Color32::LIGHT_GRAY,
+                );
+            }
+        }
+        for (index, pos) in self.maneuver_markers(&self.view()) {
+            let screen_pos = self.camera.world_to_screen(pos).cast().unwrap();
+            painter.text(
+                rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                egui::Align2::LEFT_BOTTOM,
+                format!("Burn {:.2}m/s", self.maneuver_nodes[index].delta_v()),
+                egui::FontId::default(),
+                egui::Color32::LIGHT_BLUE,
+            );
+        }
+        if let Some(approach) = self.closest_approach(&self.view()) {
+            let screen_pos = self.camera.world_to_screen(approach.pos_a).cast().unwrap();
+            painter.text(
+                rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                egui::Align2::LEFT_BOTTOM,
+                format!(
+                    "Closest {:.2}m  {:.2}s",
+                    approach.distance,
+                    (approach.state - self.current_state) as f64 * self.step_size
+                ),
+                egui::FontId::default(),
+                egui::Color32::LIGHT_GRAY,
+            );
+        }
+        if let Some(impact) = self.predict_impact(&self.view()) {
+            let screen_pos = self
+                .camera
+                .world_to_screen(impact.pos.truncate() - self.camera.offset)
+                .cast()
+                .unwrap();
+            painter.text(
+                rect.min + egui::vec2(screen_pos.x, screen_pos.y),
+                egui::Align2::LEFT_BOTTOM,
+                format!(
+                    "Impact {:.2}s",
+                    impact.time(self.current_state, self.step_size)
+                ),
+                egui::FontId::default(),
+                egui::Color32::RED,
+            );
+        }
+    }
+
+    // Counted a kind of state at a time rather than state by state since it is shown every frame
+    pub fn memory_estimate(&self, gen_future: usize, settings: &Settings) -> usize {
+        let state_size = self.state().memory_estimate();
+        let compact_size = self.state().compact_memory_estimate();
+        let total = self
+            .current_state
+            .saturating_add(gen_future)
+            .saturating_add(1);
+        let thin_end = if settings.multi_rate_history {
+            self.current_state.saturating_sub(MULTI_RATE_HISTORY_MARGIN)
+        } else {
+            0
+        };
+        let compact_end = if settings.compact_history {
+            self.current_state.saturating_sub(COMPACT_HISTORY_MARGIN)
+        } else {
+            0
+        };
+        let max_stride = settings.history_max_stride;
+        let thinned = thinned_states(self.current_state, max_stride, thin_end);
+        let compacted =
+            compact_end - thinned_states(self.current_state, max_stride, thin_end.min(compact_end));
+        let full = total - thinned - compacted;
+        full.saturating_mul(state_size)
+            .saturating_add(compacted * compact_size)
+            .saturating_add(thinned * size_of::<Universe>())
+            .saturating_add(self.undo_memory)
+    }
+
+    fn gen_horizon(&self) -> usize {
+        self.current_state + self.gen_future
+    }
+
+    pub fn steps_per_second(&self) -> f64 {
+        let step_time = self.thread_state.generation_state.lock().unwrap().step_time;
+        if step_time > 0.0 {
+            1.0 / step_time
+        } else {
+            0.0
+        }
+    }
+
+    pub fn regen_eta(&self) -> Option<f64> {
+        let target = self.regen_target?.min(self.gen_horizon());
+        let steps_per_second = self.steps_per_second();
+        (steps_per_second > 0.0)
+            .then(|| target.saturating_sub(self.states.len()) as f64 / steps_per_second)
+    }
+
+    pub fn draw_clock(&self, painter: &egui::Painter, rect: egui::Rect) {
+        painter.text(
+            rect.right_top() + egui::vec2(-16.0, 16.0),
+            egui::Align2::RIGHT_TOP,
+            format!(
+                "T+ {}\n{}x\nReal {}",
+                format_time(self.current_state as f64 * self.step_size),
+                self.effective_speed(),
+                format_time(self.real_time_played)
+            ),
+            egui::FontId::monospace(32.0),
+            egui::Color32::WHITE,
+        );
+    }
+
+    pub fn draw_states(&self, view: &View, d: &mut DrawHandler) {
+        // Overlays work out where things are in the plane, which bodies in a 3D world leave
+        let plane = !self.state().three_d;
+        if plane {
+            self.draw_underlays(view, d);
+        }
+        self.state().draw(&view.camera, d);
+        if plane && self.spin_gauges {
+            self.draw_spin_gauges(view, d);
+        }
+        if let Some(selected) = self.selected
+            && let Some(selected) = self.state().bodies.get(selected)
+        {
+            d.circle(
+                view.camera.project(selected.pos).cast().unwrap(),
+                selected.radius as f32 * 1.3,
+                selected.meta.color.cast().unwrap() * 2.0,
+                0.05,
+            );
+        }
+        if plane && let Some(impact) = self.predict_impact(view) {
+            d.circle(
+                (impact.pos.truncate() - view.camera.offset).cast().unwrap(),
+                0.01 * view.camera.view_height as f32,
+                Vector3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                0.2,
+            );
+        }
+
+        if self.quick_prediction && plane {
+            self.draw_quick_prediction(view, d);
+        } else {
+            self.draw_future(view, d);
+        }
+        if plane {
+            self.draw_ghost(view, d);
+            self.draw_stopwatch(view, d);
+        }
+        // Show Past
+        let mut old_index = self.current_state;
+        for i in 0..(self.show_past / self.step_size) as usize {
+            let past_index = self.current_state - i;
+            if past_index == 0 {
+                let universe = &self.states[0];
+                universe.bodies.iter().for_each(|(_, body)| {
+                    let offset = view.path_offset(universe);
+                    d.circle(
+                        (view.camera.project(body.pos) - offset).cast().unwrap(),
+                        0.005 * view.camera.view_height as f32,
+                        Vector3 {
+                            x: 0.75,
+                            y: 0.75,
+                            z: 0.75,
+                        },
+                        0.1,
+                    );
                 });
-            if self.selected.is_some() && !open {
-                self.selected = None;
+                break;
+            }
+            let universe = &self.states[old_index];
+            let new_universe = &self.states[past_index - 1];
+            if (i + self.current_state).is_multiple_of(self.path_quality)
+                && !new_universe.is_thinned()
+            {
+                universe.body_ids().into_iter().for_each(|id| {
+                    let Some(current) = universe.position3(id) else {
+                        return;
+                    };
+                    let Some(future) = new_universe.position3(id) else {
+                        return;
+                    };
+                    let current_offset = view.path_offset(universe);
+                    let future_offset = view.path_offset(new_universe);
+                    let color = universe
+                        .bodies
+                        .get(id)
+                        .or_else(|| self.state().bodies.get(id))
+                        .map_or(Vector3::new(0.75, 0.75, 0.75), |body| body.meta.color);
+
+                    d.line(
+                        (view.camera.project(current) - current_offset)
+                            .cast()
+                            .unwrap(),
+                        (view.camera.project(future) - future_offset)
+                            .cast()
+                            .unwrap(),
+                        0.005 * view.camera.view_height as f32,
+                        (color * 0.5).cast().unwrap(),
+                        0.0,
+                    );
+                });
+                old_index = past_index - 1
             }
         }
-        if !ctx.wants_keyboard_input() {
-            ctx.input(|i| {
-                let move_speed = 1.0;
-                self.camera.pos.y += i.key_down(egui::Key::W) as u8 as f64
-                    * dt
-                    * move_speed
-                    * self.camera.view_height;
-                self.camera.pos.y -= i.key_down(egui::Key::S) as u8 as f64
-                    * dt
-                    * move_speed
-                    * self.camera.view_height;
-                self.camera.pos.x += i.key_down(egui::Key::D) as u8 as f64
-                    * dt
-                    * move_speed
-                    * self.camera.view_height;
-                self.camera.pos.x -= i.key_down(egui::Key::A) as u8 as f64
-                    * dt
-                    * move_speed
-                    * self.camera.view_height;
-
-                if i.key_pressed(egui::Key::Delete)
-                    && let Some(selected) = self.selected
-                {
-                    self.selected = None;
-                    self.states[self.current_state].bodies.remove(selected);
-                    self.current_state_modified = true
-                }
-                if i.key_pressed(egui::Key::N) {
-                    self.new_body(self.camera.pos);
-                }
-            });
-        }
-        if !ctx.wants_pointer_input() {
-            ctx.input(|i| {
-                self.camera.view_height -=
-                    i.raw_scroll_delta.y as f64 * self.camera.view_height * 0.005;
-                self.camera.view_height = self.camera.view_height.max(0.1);
-            });
+        if plane {
+            self.draw_overlays(view, d);
         }
-        self.modified_since_save_to_file |= self.current_state_modified;
     }
 
-    pub fn world_input(&mut self, response: &egui::Response, rect: egui::Rect, ui: &mut egui::Ui) {
-        self.camera.width = rect.width() as f64;
-        self.camera.height = rect.height() as f64;
-
-        if let Some(focused) = self.focused
-            && let Some(body) = self.states[self.current_state].bodies.get(focused)
+    fn draw_underlays(&self, view: &View, d: &mut DrawHandler) {
+        // Drawn first so trails at the same depth end up on top
+        if let Some(heat_map) = &self.heat_map {
+            heat_map.draw(d);
+        }
+        for cell in self.overlay_regions(view) {
+            d.rect(
+                cell.pos.cast().unwrap(),
+                Vector2::new(cell.size, cell.size).cast().unwrap(),
+                cell.angle as f32,
+                Vector3::new(0.3, 0.16, 0.16),
+                0.0,
+            );
+        }
+        if self.soi_rings {
+            self.draw_soi_rings(view, d);
+        }
+        if self.hill_sphere
+            && let Some(focused) = view.focused_body()
+            && let Some(body) = self.state().bodies.get(focused)
+            && let Some((radius, _)) = conics::hill_radius(self.state(), focused)
         {
-            self.camera.offset = -body.pos;
-        } else {
-            self.camera.offset = Vector2::zero()
-        };
-        let mouse_pos = if let Some(hover_pos) = ui.ctx().pointer_hover_pos() {
-            Vector2 {
-                x: hover_pos.x - rect.left_top().x,
-                y: hover_pos.y - rect.left_top().y,
-            }
-        } else {
-            Vector2::zero()
+            self.draw_ring(
+                view,
+                d,
+                body.pos.truncate(),
+                radius,
+                Vector3::new(0.4, 1.0, 0.6),
+            );
         }
-        .cast()
-        .unwrap();
-
-        let world_mouse_pos = self.camera.screen_to_world(mouse_pos);
+    }
 
-        if response.clicked_by(egui::PointerButton::Secondary) {
-            self.attempt_focus(world_mouse_pos);
+    fn draw_overlays(&self, view: &View, d: &mut DrawHandler) {
+        if self.barycenter_trail {
+            self.draw_barycenter_trail(view, d);
         }
-
-        if response.clicked() {
-            self.attempt_select(world_mouse_pos);
+        for marker in self.overlay_markers(view) {
+            d.circle(
+                marker.pos.cast().unwrap(),
+                0.006 * view.camera.view_height as f32,
+                marker.color.cast().unwrap(),
+                0.2,
+            );
+        }
+        if self.apsis_markers {
+            for apsis in self.apsides(view) {
+                d.circle(
+                    apsis.pos.cast().unwrap(),
+                    0.008 * view.camera.view_height as f32,
+                    if apsis.periapsis {
+                        Vector3::new(1.0, 0.6, 0.2)
+                    } else {
+                        Vector3::new(0.3, 0.6, 1.0)
+                    },
+                    0.2,
+                );
+            }
+        }
+        for (_, pos) in self.maneuver_markers(view) {
+            d.circle(
+                pos.cast().unwrap(),
+                0.008 * view.camera.view_height as f32,
+                Vector3::new(0.4, 0.7, 1.0),
+                0.2,
+            );
+        }
+        if self.transfer.preview && self.transfer.arrival.is_none() {
+            self.draw_transfer(view, d);
+        }
+        if let Some(approach) = self.closest_approach(view) {
+            let color = Vector3::new(1.0, 1.0, 0.4);
+            let thickness = 0.003 * view.camera.view_height as f32;
+            d.line(
+                approach.pos_a.cast().unwrap(),
+                approach.pos_b.cast().unwrap(),
+                thickness,
+                color,
+                0.2,
+            );
+            for pos in [approach.pos_a, approach.pos_b] {
+                d.circle(
+                    pos.cast().unwrap(),
+                    0.008 * view.camera.view_height as f32,
+                    color,
+                    0.2,
+                );
+            }
+        }
+        if self.barycenter_marker
+            && let Some(barycenter) = self.state().barycenter()
+        {
+            self.draw_crosshair(view, d, barycenter, Vector3::new(1.0, 0.3, 1.0));
+        }
+        if self.group_barycenter_marker
+            && let Some(selected) = self.selected
+            && let Some(barycenter) = self.group_barycenter(self.state(), selected)
+        {
+            self.draw_crosshair(view, d, barycenter, Vector3::new(0.3, 1.0, 1.0));
+        }
+        if let Some(observer) = &self.observer {
+            self.draw_observer(view, d, observer);
         }
+    }
 
-        if response.clicked_by(egui::PointerButton::Middle) && !self.playing {
-            self.new_body(world_mouse_pos);
+    // Each body's apparent position as a faded copy, tied to the true one by a thin line
+    fn draw_observer(&self, view: &View, d: &mut DrawHandler, observer: &Observer) {
+        self.draw_crosshair(
+            view,
+            d,
+            observer.position(self.state()),
+            Vector3::new(1.0, 0.85, 0.3),
+        );
+        for (id, body) in self.state().bodies.iter() {
+            if Some(id) == observer.on {
+                continue;
+            }
+            let Some((pos, _)) =
+                observer.apparent(&self.states, self.current_state, self.step_size, id)
+            else {
+                continue;
+            };
+            let color = (body.meta.color * 0.4 + Vector3::new(0.2, 0.2, 0.2))
+                .cast()
+                .unwrap();
+            d.line(
+                body.pos.truncate().cast().unwrap(),
+                pos.cast().unwrap(),
+                0.002 * view.camera.view_height as f32,
+                color,
+                0.01,
+            );
+            d.circle(pos.cast().unwrap(), body.radius as f32, color, 0.01);
         }
+    }
 
+    fn draw_soi_rings(&self, view: &View, d: &mut DrawHandler) {
+        for influence in conics::hierarchy(self.state()) {
+            let Some(body) = self.state().bodies.get(influence.id) else {
+                continue;
+            };
+            if !influence.soi.is_finite() || influence.soi <= body.radius {
+                continue;
+            }
+            self.draw_ring(
+                view,
+                d,
+                body.pos.truncate(),
+                influence.soi,
+                body.meta.color * 0.4,
+            );
+        }
     }
 
-    fn attempt_select(&mut self, pos: Vector2<f64>) {
-        self.states[self.current_state]
-            .bodies
-            .iter()
-            .for_each(|(key, body)| {
-                let mouse_to_body = body.pos - pos;
-                if mouse_to_body.magnitude() < body.radius {
-                    self.selected = Some(key);
+    // The arc starts at the body's orientation and sweeps a quarter turn for each multiple of the
+    // rate its tidal partner goes around it, so it reaches the tick once the body is tidally locked.
+    // The arrow at its end shows which way tides are turning the spin
+    fn draw_spin_gauges(&self, view: &View, d: &mut DrawHandler) {
+        let universe = self.state();
+        let thickness = 0.003 * view.camera.view_height;
+        for (id, body) in universe.bodies.iter() {
+            let Some((_, orbit_rate)) = universe.tidal_partner(id) else {
+                continue;
+            };
+            let torque = universe.tidal_torque(id);
+            if orbit_rate == 0.0 || (body.spin == 0.0 && torque == 0.0) {
+                continue;
+            }
+            let radius = spin_gauge_radius(view, body);
+            let color = (body.meta.color * 0.6 + Vector3::new(0.4, 0.4, 0.4))
+                .cast()
+                .unwrap();
+            let point = |angle: f64, radius: f64| {
+                body.pos.truncate() + Vector2::new(angle.cos(), angle.sin()) * radius
+            };
+            let locked = body.angle + FRAC_PI_2 * orbit_rate.signum();
+            d.line(
+                point(locked, radius * 0.85).cast().unwrap(),
+                point(locked, radius * 1.15).cast().unwrap(),
+                thickness as f32,
+                color,
+                0.25,
+            );
+            let sweep = (FRAC_PI_2 * body.spin / orbit_rate.abs()).clamp(-TAU, TAU);
+            let segments = ((sweep.abs() / TAU * 64.0).ceil() as usize).max(1);
+            let angle = |i: usize| body.angle + sweep * i as f64 / segments as f64;
+            for i in 0..segments {
+                d.line(
+                    point(angle(i), radius).cast().unwrap(),
+                    point(angle(i + 1), radius).cast().unwrap(),
+                    thickness as f32,
+                    color,
+                    0.25,
+                );
+            }
+            if torque != 0.0 {
+                let end = body.angle + sweep;
+                let tangent = Vector2::new(-end.sin(), end.cos()) * torque.signum();
+                let tip = point(end, radius) + tangent * 6.0 * thickness;
+                let back = tip - tangent * 2.0 * thickness;
+                let side = Vector2::new(end.cos(), end.sin()) * 1.5 * thickness;
+                let arrow = Vector3::new(1.0, 0.6, 0.2);
+                for from in [point(end, radius), back + side, back - side] {
+                    d.line(
+                        from.cast().unwrap(),
+                        tip.cast().unwrap(),
+                        thickness as f32,
+                        arrow,
+                        0.25,
+                    );
                 }
-            });
+            }
+        }
     }
 
-    fn attempt_focus(&mut self, pos: Vector2<f64>) {
-        let mut clicked_on_body = false;
-        self.states[self.current_state]
-            .bodies
-            .iter()
-            .for_each(|(key, body)| {
-                let mouse_to_body = body.pos - pos;
-                if mouse_to_body.magnitude() < body.radius {
-                    if let Some(_focused) = self.focused {
-                        self.camera.pos -= self.camera.offset
-                    }
-                    self.focused = Some(key);
-                    self.camera.pos -= body.pos;
-                    self.camera.offset = -body.pos;
-                    clicked_on_body = true
-                }
-            });
-        self.focused = if !clicked_on_body && let Some(_) = self.focused {
-            self.camera.pos -= self.camera.offset;
-            self.camera.offset = Vector2::zero();
-            None
-        } else {
-            self.focused
+    fn draw_ring(
+        &self,
+        view: &View,
+        d: &mut DrawHandler,
+        center: Vector2<f64>,
+        radius: f64,
+        color: Vector3<f64>,
+    ) {
+        const SEGMENTS: usize = 64;
+        let point = |i: usize| {
+            let angle = std::f64::consts::TAU * i as f64 / SEGMENTS as f64;
+            center + Vector2::new(angle.cos(), angle.sin()) * radius
+        };
+        for i in 0..SEGMENTS {
+            d.line(
+                point(i).cast().unwrap(),
+                point(i + 1).cast().unwrap(),
+                0.002 * view.camera.view_height as f32,
+                color.cast().unwrap(),
+                0.0,
+            );
         }
     }
 
-    fn new_body(&mut self, pos: Vector2<f64>) {
-        self.current_state_modified = true;
-        let new_body = self.states[self.current_state].bodies.push(Body {
-            name: "Unnamed".into(),
-            pos: pos,
-            vel: Vector2::zero(),
-            radius: 1.0,
-            density: 1.0,
-            color: Vector3 {
-                x: 1.0,
-                y: 1.0,
-                z: 1.0,
-            },
-        });
-        self.selected = Some(new_body)
+    // The half ellipse from the body to the target orbit, and the target orbit itself
+    fn draw_transfer(&self, view: &View, d: &mut DrawHandler) {
+        let Some(plan) = self.transfer_plan() else {
+            return;
+        };
+        let Some(center) = view.trail_position(self.state(), plan.parent) else {
+            return;
+        };
+        let color = Vector3::new(1.0, 0.5, 1.0);
+        self.draw_ring(view, d, center, plan.to, color);
+        let hohmann = &plan.hohmann;
+        let semi_latus_rectum =
+            hohmann.semi_major_axis * (1.0 - hohmann.eccentricity * hohmann.eccentricity);
+        let turn = if plan.clockwise { -1.0 } else { 1.0 };
+        // Raising starts at periapsis, lowering starts at apoapsis
+        let (start, periapsis) = if plan.to >= plan.from {
+            (0.0, plan.radial.y.atan2(plan.radial.x))
+        } else {
+            (
+                std::f64::consts::PI,
+                plan.radial.y.atan2(plan.radial.x) + std::f64::consts::PI,
+            )
+        };
+        const SEGMENTS: usize = 64;
+        let point = |i: usize| {
+            let anomaly = start + std::f64::consts::PI * i as f64 / SEGMENTS as f64;
+            let distance = semi_latus_rectum / (1.0 + hohmann.eccentricity * anomaly.cos());
+            let angle = periapsis + turn * anomaly;
+            center + Vector2::new(angle.cos(), angle.sin()) * distance
+        };
+        for i in 0..SEGMENTS {
+            d.line(
+                point(i).cast().unwrap(),
+                point(i + 1).cast().unwrap(),
+                0.003 * view.camera.view_height as f32,
+                color.cast().unwrap(),
+                0.2,
+            );
+        }
     }
 
-    pub fn move_time(&mut self, dt: f64) {
-        self.accumulated_time += (dt * self.playing as u8 as f64 * self.speed).max(0.0);
-        while self.accumulated_time >= self.step_size {
-            if self.current_state + 1 < self.states.len() {
-                self.current_state += 1;
-            } else {
-                break;
-            }
-            self.accumulated_time -= self.step_size;
+    fn draw_crosshair(
+        &self,
+        view: &View,
+        d: &mut DrawHandler,
+        pos: Vector2<f64>,
+        color: Vector3<f32>,
+    ) {
+        let pos = pos - view.path_offset(self.state());
+        let size = 0.02 * view.camera.view_height;
+        for arm in [Vector2::new(size, 0.0), Vector2::new(0.0, size)] {
+            d.line(
+                (pos - arm).cast().unwrap(),
+                (pos + arm).cast().unwrap(),
+                0.003 * view.camera.view_height as f32,
+                color,
+                0.2,
+            );
         }
     }
 
-    pub fn gen_future(&mut self) {
-        let mut lock = self.thread_state.generation_state.lock().unwrap();
-        if self.current_state_modified {
-            self.states[self.current_state].changed = true;
-            self.states.truncate(self.current_state + 1);
-            self.states.shrink_to_fit();
-            lock.step_size = self.step_size;
-            lock.states_buffer_size = self
-                .gen_future
-                .saturating_sub((self.states.len()) - self.current_state);
-            lock.initial_state = Some(self.states.last().unwrap().clone());
-        } else {
-            self.states.append(&mut lock.new_states);
-            lock.states_buffer_size = self
-                .gen_future
-                .saturating_sub((self.states.len()) - self.current_state);
+    fn draw_barycenter_trail(&self, view: &View, d: &mut DrawHandler) {
+        let start = self
+            .current_state
+            .saturating_sub((self.show_past / self.step_size) as usize);
+        let end = (self.current_state + (self.show_future / self.step_size) as usize)
+            .min(self.states.len() - 1);
+        let mut previous: Option<Vector2<f64>> = None;
+        for index in start..=end {
+            if !index.is_multiple_of(self.path_quality) && index != start && index != end {
+                continue;
+            }
+            let universe = &self.states[index];
+            if universe.is_thinned() {
+                previous = None;
+                continue;
+            }
+            let Some(barycenter) = universe.barycenter() else {
+                previous = None;
+                continue;
+            };
+            let pos = barycenter - view.path_offset(universe);
+            if let Some(previous) = previous {
+                d.line(
+                    previous.cast().unwrap(),
+                    pos.cast().unwrap(),
+                    0.004 * view.camera.view_height as f32,
+                    Vector3::new(1.0, 0.3, 1.0),
+                    0.0,
+                );
+            }
+            previous = Some(pos);
         }
-        self.thread_state.wakeup.notify_one();
     }
 
-    pub fn draw_states(&self, d: &mut DrawHandler) {
-        self.state().draw(d);
-        if let Some(selected) = self.selected
-            && let Some(selected) = self.state().bodies.get(selected)
-        {
-            d.circle(
-                selected.pos.cast().unwrap(),
-                selected.radius as f32 * 1.3,
-                selected.color.cast().unwrap() * 2.0,
-                0.05,
-            );
+    fn draw_stopwatch(&self, view: &View, d: &mut DrawHandler) {
+        let Some(stopwatch) = &self.stopwatch else {
+            return;
+        };
+        let marker = |d: &mut DrawHandler, point: TrailPoint| {
+            if let Some(pos) = self
+                .states
+                .get(point.state)
+                .and_then(|universe| view.trail_position(universe, point.body))
+            {
+                d.circle(
+                    pos.cast().unwrap(),
+                    0.01 * view.camera.view_height as f32,
+                    Vector3::new(1.0, 1.0, 0.3),
+                    0.3,
+                );
+            }
+        };
+        if let Some(start) = stopwatch.start {
+            marker(d, start);
+        }
+        if let Some(end) = stopwatch.end {
+            marker(d, end);
         }
+    }
 
+    fn draw_future(&self, view: &View, d: &mut DrawHandler) {
         d.quads.reserve(
             ((self.show_future / self.step_size) as usize)
                 .min((self.states.len() as i32 - 2_i32).max(0) as usize)
@@ -720,16 +5520,10 @@ impl World {
             if future_index + 2 > self.states.len() {
                 let universe = &self.states.last().unwrap();
                 universe.bodies.iter().for_each(|(_, body)| {
-                    let offset = if let Some(focused) = self.focused
-                        && let Some(body) = universe.bodies.get(focused)
-                    {
-                        body.pos + self.camera.offset
-                    } else {
-                        self.camera.offset
-                    };
+                    let offset = view.path_offset(universe);
                     d.circle(
-                        (body.pos - offset).cast().unwrap(),
-                        0.005 * self.camera.view_height as f32,
+                        (view.camera.project(body.pos) - offset).cast().unwrap(),
+                        0.005 * view.camera.view_height as f32,
                         Vector3 {
                             x: 0.75,
                             y: 0.75,
@@ -742,7 +5536,9 @@ impl World {
             }
             let universe = &self.states[old_index];
             let new_universe = &self.states[future_index + 1];
-            if (i + self.current_state) % self.path_quality == 0 {
+            if (i + self.current_state).is_multiple_of(self.path_quality)
+                && !new_universe.is_thinned()
+            {
                 universe.bodies.iter().for_each(|(id, _)| {
                     let Some(current) = universe.bodies.get(id) else {
                         return;
@@ -750,94 +5546,283 @@ impl World {
                     let Some(future) = new_universe.bodies.get(id) else {
                         return;
                     };
-                    let current_offset = if let Some(focused) = self.focused
-                        && let Some(body) = universe.bodies.get(focused)
-                    {
-                        body.pos + self.camera.offset
-                    } else {
-                        self.camera.offset
-                    };
-                    let future_offset = if let Some(focused) = self.focused
-                        && let Some(body) = new_universe.bodies.get(focused)
-                    {
-                        body.pos + self.camera.offset
-                    } else {
-                        self.camera.offset
-                    };
+                    let current_offset = view.path_offset(universe);
+                    let future_offset = view.path_offset(new_universe);
 
                     d.line(
-                        (current.pos - current_offset).cast().unwrap(),
-                        (future.pos - future_offset).cast().unwrap(),
-                        0.005 * self.camera.view_height as f32,
-                        current.color.cast().unwrap(),
+                        (view.camera.project(current.pos) - current_offset)
+                            .cast()
+                            .unwrap(),
+                        (view.camera.project(future.pos) - future_offset)
+                            .cast()
+                            .unwrap(),
+                        0.005 * view.camera.view_height as f32,
+                        current.meta.color.cast().unwrap(),
                         0.0,
                     );
                 });
-                old_index = future_index
+                old_index = future_index + 1
             }
         }
-        // Show Past
-        let mut old_index = self.current_state;
-        for i in 0..(self.show_past / self.step_size) as usize {
-            let past_index = self.current_state - i;
-            if past_index == 0 {
-                let universe = &self.states[0];
-                universe.bodies.iter().for_each(|(_, body)| {
-                    let offset = if let Some(focused) = self.focused
-                        && let Some(body) = universe.bodies.get(focused)
-                    {
-                        body.pos + self.camera.offset
-                    } else {
-                        self.camera.offset
-                    };
-                    d.circle(
-                        (body.pos - offset).cast().unwrap(),
-                        0.005 * self.camera.view_height as f32,
-                        Vector3 {
-                            x: 0.75,
-                            y: 0.75,
-                            z: 0.75,
+    }
+
+    fn draw_quick_prediction(&self, view: &View, d: &mut DrawHandler) {
+        let count =
+            ((self.show_future / (self.step_size * self.path_quality as f64)) as usize).min(1024);
+        if count == 0 {
+            return;
+        }
+        let samples = conics::predict(self.state(), self.show_future / count as f64, count);
+        let offset =
+            |sample: &[(BodyId, Vector2<f64>)]| match view.focused.and_then(|focus| match focus {
+                Focus::Body(focused) => sample
+                    .iter()
+                    .find_map(|&(id, pos)| (id == focused).then_some(pos)),
+                Focus::Barycenter => {
+                    let (mass, weighted) = sample.iter().fold(
+                        (0.0, Vector2::zero()),
+                        |(mass, weighted), &(id, pos)| {
+                            let body_mass = self
+                                .state()
+                                .bodies
+                                .get(id)
+                                .map_or(0.0, |body| body.mass(self.state().three_d));
+                            (mass + body_mass, weighted + pos * body_mass)
                         },
-                        0.1,
                     );
-                });
-                break;
+                    (mass > 0.0).then(|| weighted / mass)
+                }
+            }) {
+                Some(pos) => pos + view.camera.offset,
+                None => view.camera.offset,
+            };
+        d.quads.reserve(count * self.state().bodies.len());
+        for pair in samples.windows(2) {
+            let current_offset = offset(&pair[0]);
+            let future_offset = offset(&pair[1]);
+            for ((&(_, current), &(_, future)), (_, body)) in
+                pair[0].iter().zip(&pair[1]).zip(self.state().bodies.iter())
+            {
+                d.line(
+                    (current - current_offset).cast().unwrap(),
+                    (future - future_offset).cast().unwrap(),
+                    0.005 * view.camera.view_height as f32,
+                    body.meta.color.cast().unwrap(),
+                    0.0,
+                );
             }
-            let universe = &self.states[old_index];
-            let new_universe = &self.states[past_index - 1];
-            if (i + self.current_state) % self.path_quality == 0 {
-                universe.bodies.iter().for_each(|(id, _)| {
-                    let Some(current) = universe.bodies.get(id) else {
-                        return;
-                    };
-                    let Some(future) = new_universe.bodies.get(id) else {
-                        return;
-                    };
-                    let current_offset = if let Some(focused) = self.focused
-                        && let Some(body) = universe.bodies.get(focused)
-                    {
-                        body.pos + self.camera.offset
-                    } else {
-                        self.camera.offset
-                    };
-                    let future_offset = if let Some(focused) = self.focused
-                        && let Some(body) = new_universe.bodies.get(focused)
-                    {
-                        body.pos + self.camera.offset
-                    } else {
-                        self.camera.offset
-                    };
+        }
+    }
 
-                    d.line(
-                        (current.pos - current_offset).cast().unwrap(),
-                        (future.pos - future_offset).cast().unwrap(),
-                        0.005 * self.camera.view_height as f32,
-                        (current.color * 0.5).cast().unwrap(),
-                        0.0,
-                    );
-                });
-                old_index = past_index
+    fn draw_ghost(&self, view: &View, d: &mut DrawHandler) {
+        let Some(pending) = &self.pending_edit else {
+            return;
+        };
+        let count = ((self.show_future / self.step_size) as usize).min(self.ghost_states.len());
+        let mut old_universe = &self.states[pending.index];
+        for (i, universe) in self.ghost_states[..count].iter().enumerate() {
+            if !(i + 1).is_multiple_of(self.path_quality) && i + 1 != count {
+                continue;
+            }
+            let current_offset = view.path_offset(old_universe);
+            let future_offset = view.path_offset(universe);
+            old_universe.bodies.iter().for_each(|(id, current)| {
+                let Some(future) = universe.bodies.get(id) else {
+                    return;
+                };
+                d.line(
+                    (current.pos.truncate() - current_offset).cast().unwrap(),
+                    (future.pos.truncate() - future_offset).cast().unwrap(),
+                    0.005 * view.camera.view_height as f32,
+                    (current.meta.color * 0.5 + Vector3::new(0.5, 0.5, 0.5))
+                        .cast()
+                        .unwrap(),
+                    0.01,
+                );
+            });
+            old_universe = universe;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(x: f64) -> Body {
+        Body {
+            meta: Arc::new(BodyMeta {
+                name: "Body".into(),
+                density: 1.0,
+                color: Vector3::new(1.0, 1.0, 1.0),
+                high_accuracy: false,
+                pinned: false,
+                parent: None,
+            }),
+            pos: Vector3::new(x, 0.0, 0.0),
+            vel: Vector3::zero(),
+            radius: 1.0,
+            thrust: None,
+            spin: 0.0,
+            angle: 0.0,
+            charge: 0.0,
+            landed: None,
+            burns: vec![],
+        }
+    }
+
+    fn timeline(len: usize) -> Vec<Universe> {
+        let mut first = Universe::new(1.0);
+        first.bodies.push(Body {
+            vel: Vector3::new(1.0, 0.0, 0.0),
+            ..body(0.0)
+        });
+        vec![first; len]
+    }
+
+    #[test]
+    fn history_stride_doubles_with_each_band() {
+        let margin = MULTI_RATE_HISTORY_MARGIN;
+        assert_eq!(history_stride(0, 64), 1);
+        assert_eq!(history_stride(margin - 1, 64), 1);
+        assert_eq!(history_stride(margin, 64), 2);
+        assert_eq!(history_stride(2 * margin - 1, 64), 2);
+        assert_eq!(history_stride(2 * margin, 64), 4);
+        assert_eq!(history_stride(4 * margin - 1, 64), 4);
+        assert_eq!(history_stride(4 * margin, 64), 8);
+        assert_eq!(history_stride(1000 * margin, 4), 4);
+        // A max stride of 0 keeps every state like 1 does
+        assert_eq!(history_stride(1000 * margin, 0), 1);
+    }
+
+    #[test]
+    fn thinned_count_matches_what_thinning_drops() {
+        for current_state in [0usize, 500, 3000, 20_000, 100_003] {
+            for max_stride in [0, 1, 2, 8, 64] {
+                let end = current_state.saturating_sub(MULTI_RATE_HISTORY_MARGIN);
+                let dropped = (0..end)
+                    .filter(|&index| {
+                        !index.is_multiple_of(history_stride(current_state - index, max_stride))
+                    })
+                    .count();
+                assert_eq!(
+                    thinned_states(current_state, max_stride, end),
+                    dropped,
+                    "current state {current_state}, max stride {max_stride}"
+                );
             }
         }
     }
+
+    #[test]
+    fn scheduled_changes_land_on_the_earliest_state_once() {
+        let mut states = timeline(10);
+        let spawn = QueuedSpawn {
+            time: 6.0,
+            id: BodyId::next_id(),
+            body: body(10.0),
+        };
+        let node = ManeuverNode {
+            id: ManeuverId::next_id(),
+            time: 3.0,
+            body: states[0].bodies.iter().next().unwrap().0,
+            reference: None,
+            prograde: 0.0,
+            normal: 1.0,
+        };
+        let (queue, maneuvers) = ([spawn.clone()], [node]);
+
+        // The maneuver comes first, everything after it is dropped to be generated again
+        assert_eq!(
+            apply_scheduled(&mut states, &queue, &maneuvers, 1.0, 0),
+            Some(3)
+        );
+        assert_eq!(states.len(), 4);
+        assert!(states[3].changed);
+        assert_eq!(states[3].maneuvers, [node.id]);
+        let (_, moved) = states[3].bodies.iter().next().unwrap();
+        assert_eq!(moved.vel, Vector3::new(1.0, 1.0, 0.0));
+
+        // Nothing is due again until generation reaches the spawn
+        assert_eq!(
+            apply_scheduled(&mut states, &queue, &maneuvers, 1.0, 0),
+            None
+        );
+        let next = states[3].clone();
+        states.extend(std::iter::repeat_n(next, 6));
+        assert_eq!(
+            apply_scheduled(&mut states, &queue, &maneuvers, 1.0, 0),
+            Some(6)
+        );
+        assert!(states[6].bodies.get(spawn.id).is_some());
+        assert_eq!(
+            apply_scheduled(&mut states, &queue, &maneuvers, 1.0, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn scheduled_changes_skip_the_past() {
+        let mut states = timeline(10);
+        let spawn = QueuedSpawn {
+            time: 2.0,
+            id: BodyId::next_id(),
+            body: body(10.0),
+        };
+        assert_eq!(apply_scheduled(&mut states, &[spawn], &[], 1.0, 5), None);
+        assert_eq!(states.len(), 10);
+    }
+
+    #[test]
+    fn delete_past_can_be_undone_and_redone() {
+        let settings = Settings::default();
+        let mut world = World::new(1.0, Units::Sandbox);
+        world.states = timeline(10);
+        world.current_state = 6;
+        world.bookmarks = vec![2, 8];
+
+        let change = world.apply_timeline_change(TimelineChange::DeletePast { count: 6 });
+        world.record_timeline_change("Delete Past", change, &settings);
+        assert_eq!((world.states.len(), world.current_state), (4, 0));
+        assert_eq!(world.bookmarks, [2]);
+        assert!(world.undo_memory > 0);
+
+        world.undo();
+        assert_eq!((world.states.len(), world.current_state), (10, 6));
+        assert_eq!(world.bookmarks, [2, 8]);
+        assert!(world.undo_stack.is_empty());
+
+        world.redo();
+        assert_eq!((world.states.len(), world.current_state), (4, 0));
+        assert_eq!(world.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn timeline_undo_forgets_the_oldest_changes() {
+        let mut settings = Settings::default();
+        let mut world = World::new(1.0, Units::Sandbox);
+        world.states = timeline(2 * TIMELINE_UNDO_LIMIT + 2);
+        for _ in 0..TIMELINE_UNDO_LIMIT + 2 {
+            world.current_state = 1;
+            let change = world.apply_timeline_change(TimelineChange::DeletePast { count: 1 });
+            world.record_timeline_change("Delete Past", change, &settings);
+        }
+        assert_eq!(world.undo_stack.len(), TIMELINE_UNDO_LIMIT);
+
+        // A new change clears what could be redone
+        world.undo();
+        assert_eq!(world.redo_stack.len(), 1);
+        world.current_state = 1;
+        let change = world.apply_timeline_change(TimelineChange::DeletePast { count: 1 });
+        world.record_timeline_change("Delete Past", change, &settings);
+        assert!(world.redo_stack.is_empty());
+
+        // Past the memory budget even the newest change is forgotten
+        settings.memory_budget = 0.0;
+        world.current_state = 1;
+        let change = world.apply_timeline_change(TimelineChange::DeletePast { count: 1 });
+        world.record_timeline_change("Delete Past", change, &settings);
+        assert!(world.undo_stack.is_empty());
+        assert_eq!(world.undo_memory, 0);
+    }
 }